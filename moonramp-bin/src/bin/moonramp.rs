@@ -4,13 +4,19 @@ use log::trace;
 use serde::{Deserialize, Serialize};
 
 use moonramp::node_ctl::NodeCtl;
-use moonramp_core::{anyhow, log, serde, tokio, Hash};
+use moonramp_core::{anyhow, chrono, log, serde, tokio, Hash};
+use moonramp_program_rpc::{
+    ClnRestBackend, LightningRpcConfig, LndRestBackend, MockLightningBackend,
+};
+use moonramp_sale_rpc::{FileRateProvider, HttpWebhookDelivery, RateProviderConfig, WebhookConfig};
 
 #[derive(clap::ArgEnum, Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum NetworkOpt {
     Regtest,
     Testnet,
+    Signet,
+    Stagenet,
     Mainnet,
 }
 
@@ -19,11 +25,22 @@ impl From<NetworkOpt> for moonramp_wallet_rpc::Network {
         match val {
             NetworkOpt::Regtest => moonramp_wallet_rpc::Network::Regtest,
             NetworkOpt::Testnet => moonramp_wallet_rpc::Network::Testnet,
+            NetworkOpt::Signet => moonramp_wallet_rpc::Network::Signet,
+            NetworkOpt::Stagenet => moonramp_wallet_rpc::Network::Stagenet,
             NetworkOpt::Mainnet => moonramp_wallet_rpc::Network::Mainnet,
         }
     }
 }
 
+#[derive(clap::ArgEnum, Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum LightningBackendOpt {
+    None,
+    Lnd,
+    Cln,
+    Mock,
+}
+
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
@@ -52,6 +69,45 @@ enum Commands {
         #[clap(short = 'B', long, default_value_t = String::from("bW9vbnJhbXA6bW9vbnJhbXA="))]
         bitcoin_rpc_auth: String,
 
+        #[clap(short = 'c', long, default_value_t = String::from("http://127.0.0.1:28443/"))]
+        bitcoin_cash_rpc_endpoint: String,
+
+        #[clap(short = 'C', long, default_value_t = String::from("bW9vbnJhbXA6bW9vbnJhbXA="))]
+        bitcoin_cash_rpc_auth: String,
+
+        #[clap(short, long, default_value_t = String::from("http://127.0.0.1:8545/"))]
+        ethereum_rpc_endpoint: String,
+
+        #[clap(short = 'x', long, default_value_t = String::from("http://127.0.0.1:18082/json_rpc"))]
+        monero_rpc_endpoint: String,
+
+        #[clap(long, arg_enum, default_value_t = LightningBackendOpt::None)]
+        lightning_backend: LightningBackendOpt,
+
+        #[clap(long, default_value_t = String::from("http://127.0.0.1:8080/"))]
+        lightning_rpc_endpoint: String,
+
+        /// Hex encoded LND macaroon or CLN rune
+        #[clap(long, default_value_t = String::from(""))]
+        lightning_rpc_auth: String,
+
+        /// JSON file of fiat rates, `{"BTC": {"USD": 20000.0}}`. Fiat invoices fail without one
+        #[clap(long)]
+        rate_file: Option<String>,
+
+        /// Seconds between reads of `--rate-file`
+        #[clap(long, default_value_t = 60)]
+        rate_reload_secs: u64,
+
+        /// Fiat invoices fail once `--rate-file` was last written longer ago than this
+        #[clap(long, default_value_t = 3600)]
+        rate_max_age_secs: i64,
+
+        /// Public base url of the sale HTTP server, `sale.checkout` payment urls point here.
+        /// Defaults to `http://<sale-http-addr>`
+        #[clap(long)]
+        checkout_url: Option<String>,
+
         #[clap(short, long)]
         master_merchant_hash: Hash,
 
@@ -83,11 +139,46 @@ async fn main() -> anyhow::Result<()> {
             wallet_http_addr,
             bitcoin_rpc_endpoint,
             bitcoin_rpc_auth,
+            bitcoin_cash_rpc_endpoint,
+            bitcoin_cash_rpc_auth,
+            ethereum_rpc_endpoint,
+            monero_rpc_endpoint,
+            lightning_backend,
+            lightning_rpc_endpoint,
+            lightning_rpc_auth,
+            rate_file,
+            rate_reload_secs,
+            rate_max_age_secs,
+            checkout_url,
             master_merchant_hash,
             master_key_encryption_key,
             db_url,
             network,
         } => {
+            let lightning = match lightning_backend {
+                LightningBackendOpt::None => LightningRpcConfig::disabled(),
+                LightningBackendOpt::Lnd => LightningRpcConfig::new(LndRestBackend::new(
+                    lightning_rpc_endpoint,
+                    lightning_rpc_auth,
+                )),
+                LightningBackendOpt::Cln => LightningRpcConfig::new(ClnRestBackend::new(
+                    lightning_rpc_endpoint,
+                    lightning_rpc_auth,
+                )),
+                LightningBackendOpt::Mock => {
+                    LightningRpcConfig::new(MockLightningBackend::default())
+                }
+            };
+            let rates = match rate_file {
+                Some(path) => RateProviderConfig::new(FileRateProvider::new(
+                    path,
+                    std::time::Duration::from_secs(rate_reload_secs),
+                    chrono::Duration::seconds(rate_max_age_secs),
+                )?),
+                None => RateProviderConfig::disabled(),
+            };
+            let webhooks = WebhookConfig::new(HttpWebhookDelivery::default());
+            let checkout_url = checkout_url.unwrap_or(format!("http://{}", sale_http_addr));
             let mut node = NodeCtl::new(
                 node_id.into(),
                 program_http_addr,
@@ -95,6 +186,14 @@ async fn main() -> anyhow::Result<()> {
                 wallet_http_addr,
                 bitcoin_rpc_endpoint,
                 bitcoin_rpc_auth,
+                bitcoin_cash_rpc_endpoint,
+                bitcoin_cash_rpc_auth,
+                ethereum_rpc_endpoint,
+                monero_rpc_endpoint,
+                lightning,
+                rates,
+                webhooks,
+                checkout_url,
                 master_merchant_hash,
                 master_key_encryption_key.as_bytes().to_vec(),
                 db_url,
@@ -6,13 +6,23 @@ use uuid::Uuid;
 
 use moonramp::program_ctl::{ProgramCtl, ProgramSubcommand};
 use moonramp::sale_ctl::{SaleCtl, SaleSubcommand};
-use moonramp::wallet_ctl::{Ticker, WalletCtl, WalletSubcommand, WalletType};
+use moonramp::wallet_ctl::{
+    read_import_secret, Ticker, WalletColdType, WalletCtl, WalletSubcommand, WalletType,
+};
 use moonramp_core::{actix_rt, anyhow, log, tokio, uuid};
 use moonramp_program_rpc::{ProgramCreateRequest, ProgramLookupRequest, ProgramUpdateRequest};
 use moonramp_sale_rpc::{
-    SaleCaptureRequest, SaleInvoiceLookupRequest, SaleInvoiceRequest, SaleLookupRequest,
+    FiatAmount, SaleCaptureAsyncRequest, SaleCaptureRequest, SaleCheckoutRequest,
+    SaleInvoiceCancelRequest, SaleInvoiceLookupRequest, SaleInvoiceRequest, SaleLookupRequest,
+    SaleWebhookCreateRequest, SaleWebhookDeleteRequest, SaleWebhookDeliveriesRequest,
+    SaleWebhookReplayRequest, WebhookEventType,
+};
+use moonramp_wallet_rpc::{
+    SweepDestination, WalletBalanceRequest, WalletCreatePsbtRequest, WalletCreateRequest,
+    WalletDeleteRequest, WalletExportRequest, WalletFinalizePsbtRequest, WalletImportRequest,
+    WalletLookupRequest, WalletRescanRequest, WalletRotateKeyRequest, WalletScriptRequest,
+    WalletSweepRequest, WalletUpdateRequest, WalletUtxosRequest,
 };
-use moonramp_wallet_rpc::{WalletCreateRequest, WalletLookupRequest};
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -118,6 +128,8 @@ async fn main() -> anyhow::Result<()> {
                     hash,
                     currency,
                     amount,
+                    fiat_amount,
+                    fiat_currency,
                     expires_in,
                     program,
                 } => {
@@ -126,6 +138,9 @@ async fn main() -> anyhow::Result<()> {
                         uuid: Uuid::new_v4().to_simple().to_string(),
                         currency: currency.into(),
                         amount,
+                        fiat: fiat_amount
+                            .zip(fiat_currency)
+                            .map(|(amount, currency)| FiatAmount { amount, currency }),
                         expires_in,
                         user_data: None,
                         program,
@@ -136,6 +151,10 @@ async fn main() -> anyhow::Result<()> {
                     sale.invoice_lookup(SaleInvoiceLookupRequest::Hash { hash })
                         .await?;
                 }
+                SaleSubcommand::Cancel { hash, reason } => {
+                    sale.cancel(SaleInvoiceCancelRequest { hash, reason })
+                        .await?;
+                }
                 SaleSubcommand::Capture {
                     hash,
                     confirmations,
@@ -150,6 +169,55 @@ async fn main() -> anyhow::Result<()> {
                     })
                     .await?;
                 }
+                SaleSubcommand::CaptureAsync {
+                    hash,
+                    webhook_id,
+                    uuid,
+                    confirmations,
+                    program,
+                } => {
+                    sale.capture_async(SaleCaptureAsyncRequest {
+                        hash,
+                        uuid: uuid.unwrap_or_else(|| Uuid::new_v4().to_simple().to_string()),
+                        webhook_id,
+                        confirmations,
+                        user_data: None,
+                        program,
+                    })
+                    .await?;
+                }
+                SaleSubcommand::Checkout {
+                    hash,
+                    currency,
+                    amount,
+                    fiat_amount,
+                    fiat_currency,
+                    expires_in,
+                    webhook_id,
+                    uuid,
+                    confirmations,
+                    success_url,
+                    cancel_url,
+                    program,
+                } => {
+                    sale.checkout(SaleCheckoutRequest {
+                        hash,
+                        uuid: uuid.unwrap_or_else(|| Uuid::new_v4().to_simple().to_string()),
+                        currency: currency.into(),
+                        amount,
+                        fiat: fiat_amount
+                            .zip(fiat_currency)
+                            .map(|(amount, currency)| FiatAmount { amount, currency }),
+                        expires_in,
+                        webhook_id,
+                        confirmations,
+                        cancel_url,
+                        success_url,
+                        user_data: None,
+                        program,
+                    })
+                    .await?;
+                }
                 SaleSubcommand::Lookup { hash, invoice_hash } => match (hash, invoice_hash) {
                     (Some(hash), None) => sale.lookup(SaleLookupRequest::Hash { hash }).await?,
                     (None, Some(invoice_hash)) => {
@@ -158,6 +226,45 @@ async fn main() -> anyhow::Result<()> {
                     }
                     _ => unreachable!(),
                 },
+                SaleSubcommand::WebhookCreate { url, event_type } => {
+                    let event_types = event_type
+                        .iter()
+                        .map(|event_type| event_type.parse())
+                        .collect::<anyhow::Result<Vec<WebhookEventType>>>()?;
+                    sale.webhook_create(SaleWebhookCreateRequest {
+                        url,
+                        event_types: Some(event_types),
+                    })
+                    .await?;
+                }
+                SaleSubcommand::WebhookList {} => {
+                    sale.webhook_list().await?;
+                }
+                SaleSubcommand::WebhookDelete { hash } => {
+                    sale.webhook_delete(SaleWebhookDeleteRequest { hash })
+                        .await?;
+                }
+                SaleSubcommand::WebhookDeliveries { hash, event_hash } => {
+                    match (hash, event_hash) {
+                        (Some(webhook_hash), None) => {
+                            sale.webhook_deliveries(SaleWebhookDeliveriesRequest::WebhookHash {
+                                webhook_hash,
+                            })
+                            .await?
+                        }
+                        (None, Some(event_hash)) => {
+                            sale.webhook_deliveries(SaleWebhookDeliveriesRequest::EventHash {
+                                event_hash,
+                            })
+                            .await?
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+                SaleSubcommand::WebhookReplay { hash } => {
+                    sale.webhook_replay(SaleWebhookReplayRequest { hash })
+                        .await?;
+                }
                 SaleSubcommand::Version {} => {
                     sale.version().await?;
                 }
@@ -171,22 +278,60 @@ async fn main() -> anyhow::Result<()> {
                     wallet_type,
                     pubkey,
                     cold_type,
+                    derivation,
+                    account,
+                    threshold,
                 } => {
                     let req = match (ticker, wallet_type, pubkey, cold_type) {
-                        (Ticker::BTC, WalletType::Hot, None, None) => WalletCreateRequest::BtcHot,
+                        (Ticker::BTC, WalletType::Hot, None, None) => match derivation {
+                            Some(derivation) => WalletCreateRequest::BtcHotAccount {
+                                derivation: derivation.into(),
+                                account: account.unwrap_or(0),
+                            },
+                            None => WalletCreateRequest::BtcHot,
+                        },
+                        (
+                            Ticker::BTC,
+                            WalletType::Cold,
+                            Some(pubkey),
+                            Some(WalletColdType::BtcMultisig),
+                        ) => WalletCreateRequest::BtcMultisig {
+                            threshold: threshold.unwrap_or_default(),
+                            pubkeys: pubkey.split(',').map(|p| p.trim().to_string()).collect(),
+                        },
                         (Ticker::BTC, WalletType::Cold, Some(pubkey), Some(cold_type)) => {
                             WalletCreateRequest::BtcCold {
                                 pubkey,
                                 cold_type: cold_type.try_into()?,
                             }
                         }
-                        (Ticker::BCH, WalletType::Hot, None, None) => WalletCreateRequest::BchHot,
+                        (Ticker::BCH, WalletType::Hot, None, None) => match derivation {
+                            Some(derivation) => WalletCreateRequest::BchHotAccount {
+                                derivation: derivation.into(),
+                                account: account.unwrap_or(0),
+                            },
+                            None => WalletCreateRequest::BchHot,
+                        },
                         (Ticker::BCH, WalletType::Cold, Some(pubkey), Some(cold_type)) => {
                             WalletCreateRequest::BchCold {
                                 pubkey,
                                 cold_type: cold_type.try_into()?,
                             }
                         }
+                        (Ticker::ETH, WalletType::Hot, None, None) => WalletCreateRequest::EthHot,
+                        (Ticker::ETH, WalletType::Cold, Some(pubkey), Some(cold_type)) => {
+                            WalletCreateRequest::EthCold {
+                                pubkey,
+                                cold_type: cold_type.try_into()?,
+                            }
+                        }
+                        (Ticker::ETC, WalletType::Hot, None, None) => WalletCreateRequest::EtcHot,
+                        (Ticker::ETC, WalletType::Cold, Some(pubkey), Some(cold_type)) => {
+                            WalletCreateRequest::EtcCold {
+                                pubkey,
+                                cold_type: cold_type.try_into()?,
+                            }
+                        }
                         (Ticker::XMR, WalletType::Hot, None, None) => WalletCreateRequest::XmrHot,
                         _ => todo!(),
                     };
@@ -203,6 +348,120 @@ async fn main() -> anyhow::Result<()> {
                     }
                     _ => unreachable!(),
                 },
+                WalletSubcommand::Script { hash, index } => {
+                    wallet.script(WalletScriptRequest { hash, index }).await?;
+                }
+                WalletSubcommand::Balance { hash } => {
+                    wallet.balance(WalletBalanceRequest { hash }).await?;
+                }
+                WalletSubcommand::Utxos { hash } => {
+                    wallet.utxos(WalletUtxosRequest { hash }).await?;
+                }
+                WalletSubcommand::Sweep {
+                    hash,
+                    address,
+                    wallet: destination_wallet,
+                    fee_rate,
+                    dry_run,
+                } => {
+                    let destination = match (address, destination_wallet) {
+                        (Some(address), None) => SweepDestination::Address(address),
+                        (None, Some(hash)) => SweepDestination::Wallet(hash),
+                        _ => unreachable!(),
+                    };
+                    wallet
+                        .sweep(WalletSweepRequest {
+                            hash,
+                            destination,
+                            fee_rate,
+                            dry_run,
+                        })
+                        .await?;
+                }
+                WalletSubcommand::CreatePsbt {
+                    hash,
+                    address,
+                    wallet: destination_wallet,
+                    fee_rate,
+                } => {
+                    let destination = match (address, destination_wallet) {
+                        (Some(address), None) => SweepDestination::Address(address),
+                        (None, Some(hash)) => SweepDestination::Wallet(hash),
+                        _ => unreachable!(),
+                    };
+                    wallet
+                        .create_psbt(WalletCreatePsbtRequest {
+                            hash,
+                            destination,
+                            fee_rate,
+                        })
+                        .await?;
+                }
+                WalletSubcommand::FinalizePsbt { hash, psbt } => {
+                    wallet
+                        .finalize_psbt(WalletFinalizePsbtRequest { hash, psbt })
+                        .await?;
+                }
+                WalletSubcommand::Update { hash, status } => {
+                    wallet
+                        .update(WalletUpdateRequest {
+                            hash,
+                            wallet_status: status.into(),
+                        })
+                        .await?;
+                }
+                WalletSubcommand::Delete { hash } => {
+                    wallet.delete(WalletDeleteRequest { hash }).await?;
+                }
+                WalletSubcommand::RotateKey { hash } => {
+                    wallet.rotate_key(WalletRotateKeyRequest { hash }).await?;
+                }
+                WalletSubcommand::Rescan { hash, gap_limit } => {
+                    wallet
+                        .rescan(WalletRescanRequest { hash, gap_limit })
+                        .await?;
+                }
+                WalletSubcommand::Export { hash } => {
+                    wallet.export(WalletExportRequest { hash }).await?;
+                }
+                WalletSubcommand::Import {
+                    ticker,
+                    derivation,
+                    account,
+                } => {
+                    let (mnemonic, passphrase) = read_import_secret()?;
+                    let req = match ticker {
+                        Ticker::BTC => WalletImportRequest::BtcHot {
+                            mnemonic,
+                            passphrase,
+                            derivation: derivation.map(|d| d.into()),
+                            account: account.unwrap_or(0),
+                        },
+                        Ticker::BCH => WalletImportRequest::BchHot {
+                            mnemonic,
+                            passphrase,
+                            derivation: derivation.map(|d| d.into()),
+                            account: account.unwrap_or(0),
+                        },
+                        Ticker::ETH => WalletImportRequest::EthHot {
+                            mnemonic,
+                            passphrase,
+                        },
+                        Ticker::ETC => WalletImportRequest::EtcHot {
+                            mnemonic,
+                            passphrase,
+                        },
+                        Ticker::XMR if passphrase.is_empty() => {
+                            WalletImportRequest::XmrHot { mnemonic }
+                        }
+                        Ticker::XMR => {
+                            return Err(anyhow::anyhow!(
+                                "XMR seeds with a passphrase are not supported"
+                            ))
+                        }
+                    };
+                    wallet.import(req).await?;
+                }
                 WalletSubcommand::Version {} => {
                     wallet.version().await?;
                 }
@@ -17,7 +17,10 @@ use moonramp_encryption::{
 };
 use moonramp_entity::{key_encryption_key, merchant};
 use moonramp_migration::{Migrator, MigratorTrait};
-use moonramp_program_rpc::{BitcoinRpcConfig, Runtime};
+use moonramp_program_rpc::{
+    BitcoinCashRpcConfig, BitcoinRpcConfig, EthereumRpcConfig, GatewayConfig, LightningRpcConfig,
+    MockLightningBackend, MoneroRpcConfig, Runtime,
+};
 use moonramp_wallet_rpc::{BitcoinWallet, Currency, Network, Ticker, Wallet};
 
 #[derive(Parser)]
@@ -92,9 +95,20 @@ async fn main() -> anyhow::Result<()> {
                 )?)
             };
 
-            let bitcoin_gateway_config = BitcoinRpcConfig {
-                endpoint: "http://localhost:18443".to_string(),
-                basic_auth: None,
+            let gateway_config = GatewayConfig {
+                bitcoin: BitcoinRpcConfig {
+                    endpoint: "http://localhost:18443".to_string(),
+                    basic_auth: None,
+                },
+                bitcoin_cash: BitcoinCashRpcConfig {
+                    endpoint: "http://localhost:28443".to_string(),
+                    basic_auth: None,
+                },
+                ethereum: EthereumRpcConfig {
+                    endpoint: "http://localhost:8545".to_string(),
+                },
+                lightning: LightningRpcConfig::new(MockLightningBackend::default()),
+                monero: MoneroRpcConfig::new("http://localhost:18082/json_rpc".to_string()),
             };
             info!("Loading program data...");
             let data = fs::read(program_path).await?;
@@ -111,11 +125,13 @@ async fn main() -> anyhow::Result<()> {
                     moonramp_lunar::EntryData::Invoice {
                         wallet: w,
                         currency: Currency::BTC,
-                        amount: 0.00001000,
+                        amount: Currency::BTC.amount(1000)?,
+                        contract: None,
+                        expires_in: 15 * 60,
                         user_data: None,
                     },
                     Duration::from_secs(1),
-                    bitcoin_gateway_config.clone(),
+                    gateway_config.clone(),
                 )
                 .await
                 {
@@ -8,7 +8,12 @@ use moonramp_encryption::{
     KeyCustodian, KeyEncryptionKeyCustodian, MasterKeyEncryptionKeyCustodian,
 };
 use moonramp_entity::key_encryption_key;
+use moonramp_program_rpc::{
+    BitcoinCashRpcConfig, BitcoinRpcConfig, EthereumRpcConfig, GatewayConfig, LightningRpcConfig,
+    MoneroRpcConfig,
+};
 use moonramp_rpc::RpcService;
+use moonramp_sale_rpc::{RateProviderConfig, WebhookConfig};
 
 pub struct NodeCtl {
     node_id: NodeId,
@@ -19,6 +24,14 @@ pub struct NodeCtl {
     wallet_http_addr: String,
     bitcoin_rpc_endpoint: String,
     bitcoin_rpc_auth: String,
+    bitcoin_cash_rpc_endpoint: String,
+    bitcoin_cash_rpc_auth: String,
+    ethereum_rpc_endpoint: String,
+    monero_rpc_endpoint: String,
+    lightning: LightningRpcConfig,
+    rates: RateProviderConfig,
+    webhooks: WebhookConfig,
+    checkout_url: String,
     master_merchant_hash: Arc<Hash>,
     network: moonramp_wallet_rpc::Network,
 }
@@ -31,6 +44,14 @@ impl NodeCtl {
         wallet_http_addr: String,
         bitcoin_rpc_endpoint: String,
         bitcoin_rpc_auth: String,
+        bitcoin_cash_rpc_endpoint: String,
+        bitcoin_cash_rpc_auth: String,
+        ethereum_rpc_endpoint: String,
+        monero_rpc_endpoint: String,
+        lightning: LightningRpcConfig,
+        rates: RateProviderConfig,
+        webhooks: WebhookConfig,
+        checkout_url: String,
         master_merchant_hash: Hash,
         master_key_encryption_key: Vec<u8>,
         db_url: String,
@@ -68,6 +89,14 @@ impl NodeCtl {
             wallet_http_addr,
             bitcoin_rpc_endpoint,
             bitcoin_rpc_auth,
+            bitcoin_cash_rpc_endpoint,
+            bitcoin_cash_rpc_auth,
+            ethereum_rpc_endpoint,
+            monero_rpc_endpoint,
+            lightning,
+            rates,
+            webhooks,
+            checkout_url,
             master_merchant_hash: Arc::new(master_merchant_hash),
             network,
         })
@@ -103,8 +132,24 @@ impl NodeCtl {
             self.master_merchant_hash.clone(),
             self.kek_custodian.clone(),
             self.database.clone(),
-            self.bitcoin_rpc_endpoint.clone(),
-            self.bitcoin_rpc_auth.clone(),
+            GatewayConfig {
+                bitcoin: BitcoinRpcConfig {
+                    endpoint: self.bitcoin_rpc_endpoint.clone(),
+                    basic_auth: Some(self.bitcoin_rpc_auth.clone()),
+                },
+                bitcoin_cash: BitcoinCashRpcConfig {
+                    endpoint: self.bitcoin_cash_rpc_endpoint.clone(),
+                    basic_auth: Some(self.bitcoin_cash_rpc_auth.clone()),
+                },
+                ethereum: EthereumRpcConfig {
+                    endpoint: self.ethereum_rpc_endpoint.clone(),
+                },
+                lightning: self.lightning.clone(),
+                monero: MoneroRpcConfig::new(self.monero_rpc_endpoint.clone()),
+            },
+            self.rates.clone(),
+            self.webhooks.clone(),
+            self.checkout_url.clone(),
             self.network.clone(),
         )?;
         registry.register(TunnelName::Sale, sale_public_tx);
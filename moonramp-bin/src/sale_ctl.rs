@@ -6,15 +6,18 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
 
-use moonramp_core::{anyhow, awc, serde, serde_json, uuid, Hash};
+use moonramp_core::{anyhow, awc, serde, serde_json, uuid, Amount, Hash};
 use moonramp_sale_rpc::{
-    SaleCaptureRequest, SaleInvoiceLookupRequest, SaleInvoiceRequest, SaleLookupRequest,
+    SaleCaptureAsyncRequest, SaleCaptureRequest, SaleCheckoutRequest, SaleInvoiceCancelRequest,
+    SaleInvoiceLookupRequest, SaleInvoiceRequest, SaleLookupRequest, SaleWebhookCreateRequest,
+    SaleWebhookDeleteRequest, SaleWebhookDeliveriesRequest, SaleWebhookReplayRequest,
 };
 
 #[derive(clap::ArgEnum, Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum Currency {
     BTC,
+    BTCLN,
     BCH,
     ETH,
     USDT,
@@ -26,6 +29,7 @@ impl From<Currency> for moonramp_wallet_rpc::Currency {
     fn from(c: Currency) -> moonramp_wallet_rpc::Currency {
         match c {
             Currency::BTC => moonramp_wallet_rpc::Currency::BTC,
+            Currency::BTCLN => moonramp_wallet_rpc::Currency::BTCLN,
             Currency::BCH => moonramp_wallet_rpc::Currency::BCH,
             Currency::ETH => moonramp_wallet_rpc::Currency::ETH,
             Currency::USDT => moonramp_wallet_rpc::Currency::USDT,
@@ -44,8 +48,16 @@ pub enum SaleSubcommand {
         #[clap(short, long, arg_enum)]
         currency: Currency,
 
-        #[clap(short, long)]
-        amount: f64,
+        #[clap(short, long, required_unless_present("fiat-amount"))]
+        amount: Option<Amount>,
+
+        /// Price the invoice in fiat, converted at the node's quoted rate
+        #[clap(short = 'f', long, conflicts_with("amount"), requires("fiat-currency"))]
+        fiat_amount: Option<Amount>,
+
+        /// ISO 4217 code of the fiat amount, e.g. USD
+        #[clap(short = 'F', long)]
+        fiat_currency: Option<String>,
 
         #[clap(short, long)]
         expires_in: Option<i64>,
@@ -57,6 +69,14 @@ pub enum SaleSubcommand {
         #[clap(short = 'H', long)]
         hash: Hash,
     },
+    /// Cancels a pending invoice, canceling it again keeps the first reason
+    Cancel {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        #[clap(short, long)]
+        reason: String,
+    },
     Capture {
         #[clap(short = 'H', long)]
         hash: Hash,
@@ -67,12 +87,99 @@ pub enum SaleSubcommand {
         #[clap(short, long)]
         program: Option<Hash>,
     },
+    /// Queues a capture and returns immediately, the result is sent to `webhook-id`
+    CaptureAsync {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        /// Hash of a webhook from `webhook-create`
+        #[clap(short, long)]
+        webhook_id: String,
+
+        /// Reusing a uuid returns the existing capture instead of queueing another
+        #[clap(short, long)]
+        uuid: Option<String>,
+
+        #[clap(short, long)]
+        confirmations: Option<i64>,
+
+        #[clap(short, long)]
+        program: Option<Hash>,
+    },
+    /// Creates an invoice with a hosted payment page, send the customer to its `paymentUrl`
+    Checkout {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        #[clap(short, long, arg_enum)]
+        currency: Currency,
+
+        #[clap(short, long, required_unless_present("fiat-amount"))]
+        amount: Option<Amount>,
+
+        #[clap(short = 'f', long, conflicts_with("amount"), requires("fiat-currency"))]
+        fiat_amount: Option<Amount>,
+
+        #[clap(short = 'F', long)]
+        fiat_currency: Option<String>,
+
+        #[clap(short, long)]
+        expires_in: Option<i64>,
+
+        /// Hash of a webhook from `webhook-create`, the capture is delivered there.
+        /// Without one the invoice can only be polled
+        #[clap(short, long)]
+        webhook_id: Option<String>,
+
+        #[clap(short, long)]
+        uuid: Option<String>,
+
+        #[clap(long)]
+        confirmations: Option<i64>,
+
+        /// Where the page sends the customer once the invoice is funded
+        #[clap(long)]
+        success_url: String,
+
+        /// Where the page sends the customer once the invoice expires or is canceled
+        #[clap(long)]
+        cancel_url: String,
+
+        #[clap(short, long)]
+        program: Option<Hash>,
+    },
     Lookup {
         #[clap(short = 'H', long, required_unless_present("invoice-hash"))]
         hash: Option<Hash>,
         #[clap(short = 'I', long, required_unless_present("hash"))]
         invoice_hash: Option<Hash>,
     },
+    /// Registers a webhook, its signing secret is only shown once
+    WebhookCreate {
+        #[clap(short = 'U', long)]
+        url: String,
+
+        /// Event types to deliver, e.g. sale.funded. Every event when omitted
+        #[clap(short, long)]
+        event_type: Vec<String>,
+    },
+    WebhookList {},
+    WebhookDelete {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+    },
+    /// Delivery attempts of a webhook or an event, newest first
+    WebhookDeliveries {
+        #[clap(short = 'H', long, required_unless_present("event-hash"))]
+        hash: Option<Hash>,
+        #[clap(short = 'E', long, required_unless_present("hash"))]
+        event_hash: Option<Hash>,
+    },
+    /// Sends the event of a delivery attempt again
+    WebhookReplay {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+    },
     Version {},
 }
 
@@ -180,6 +287,50 @@ impl SaleCtl {
         Ok(())
     }
 
+    pub async fn cancel(&self, req: SaleInvoiceCancelRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "sale.invoiceCancel",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
     pub async fn capture(&self, req: SaleCaptureRequest) -> anyhow::Result<()> {
         let id = Uuid::new_v4().to_simple().to_string();
         let json_rpc = json!({
@@ -225,6 +376,94 @@ impl SaleCtl {
         Ok(())
     }
 
+    pub async fn capture_async(&self, req: SaleCaptureAsyncRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "sale.captureAsync",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn checkout(&self, req: SaleCheckoutRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "sale.checkout",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
     pub async fn lookup(&self, req: SaleLookupRequest) -> anyhow::Result<()> {
         let id = Uuid::new_v4().to_simple().to_string();
         let json_rpc = json!({
@@ -269,6 +508,75 @@ impl SaleCtl {
         Ok(())
     }
 
+    pub async fn webhook_create(&self, req: SaleWebhookCreateRequest) -> anyhow::Result<()> {
+        self.webhook_request("sale.webhookCreate", json!({ "request": req }))
+            .await
+    }
+
+    pub async fn webhook_list(&self) -> anyhow::Result<()> {
+        self.webhook_request("sale.webhookList", json!({})).await
+    }
+
+    pub async fn webhook_delete(&self, req: SaleWebhookDeleteRequest) -> anyhow::Result<()> {
+        self.webhook_request("sale.webhookDelete", json!({ "request": req }))
+            .await
+    }
+
+    pub async fn webhook_deliveries(
+        &self,
+        req: SaleWebhookDeliveriesRequest,
+    ) -> anyhow::Result<()> {
+        self.webhook_request("sale.webhookDeliveries", json!({ "request": req }))
+            .await
+    }
+
+    pub async fn webhook_replay(&self, req: SaleWebhookReplayRequest) -> anyhow::Result<()> {
+        self.webhook_request("sale.webhookReplay", json!({ "request": req }))
+            .await
+    }
+
+    async fn webhook_request(&self, method: &str, params: serde_json::Value) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
     pub async fn version(&self) -> anyhow::Result<()> {
         let id = Uuid::new_v4().to_simple().to_string();
         let json_rpc = json!({
@@ -1,4 +1,8 @@
-use std::convert::TryFrom;
+use std::{
+    convert::TryFrom,
+    io::{self, BufRead, IsTerminal},
+    time::Duration,
+};
 
 use anyhow::anyhow;
 use clap::Subcommand;
@@ -6,9 +10,14 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
 
-use moonramp_core::{anyhow, awc, serde, serde_json, uuid, Hash};
+use moonramp_core::{anyhow, awc, serde, serde_json, tokio, uuid, Hash};
+use moonramp_entity::wallet;
 use moonramp_wallet_rpc::{
-    BitcoinColdWalletType, MoneroColdWalletType, WalletCreateRequest, WalletLookupRequest,
+    BitcoinColdWalletType, BitcoinDerivation, EthereumColdWalletType, MoneroColdWalletType,
+    WalletBalanceRequest, WalletCreatePsbtRequest, WalletCreateRequest, WalletDeleteRequest,
+    WalletExportRequest, WalletFinalizePsbtRequest, WalletImportRequest, WalletLookupRequest,
+    WalletRescanRequest, WalletRescanStatusRequest, WalletRotateKeyRequest, WalletScriptRequest,
+    WalletSweepRequest, WalletUpdateRequest, WalletUtxosRequest,
 };
 
 #[derive(clap::ArgEnum, Clone, Debug, Deserialize, Serialize)]
@@ -33,24 +42,86 @@ pub enum WalletType {
 pub enum WalletColdType {
     BtcXPub,
     BchXPub,
-    //BtcP2PKH,
-    //BtcP2SHWPKH,
-    //BtcP2WPKH,
-    //BthP2PKH,
+    BtcP2PKH,
+    BtcP2SHWPKH,
+    BtcP2WPKH,
+    BtcP2TR,
+    BtcDescriptor,
+    BtcMultisig,
+    BchP2PKH,
+    EthXPub,
+    EtcXPub,
     XmrView,
 }
 
+#[derive(clap::ArgEnum, Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum Derivation {
+    Legacy,
+    Bip44,
+    Bip49,
+    Bip84,
+    Bip86,
+}
+
+#[derive(clap::ArgEnum, Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum Status {
+    Active,
+    ReceiveDisabled,
+    Archived,
+}
+
+impl From<Status> for wallet::WalletStatus {
+    fn from(status: Status) -> wallet::WalletStatus {
+        match status {
+            Status::Active => wallet::WalletStatus::Active,
+            Status::ReceiveDisabled => wallet::WalletStatus::ReceiveDisabled,
+            Status::Archived => wallet::WalletStatus::Archived,
+        }
+    }
+}
+
+impl From<Derivation> for BitcoinDerivation {
+    fn from(derivation: Derivation) -> BitcoinDerivation {
+        match derivation {
+            Derivation::Legacy => BitcoinDerivation::Legacy,
+            Derivation::Bip44 => BitcoinDerivation::Bip44,
+            Derivation::Bip49 => BitcoinDerivation::Bip49,
+            Derivation::Bip84 => BitcoinDerivation::Bip84,
+            Derivation::Bip86 => BitcoinDerivation::Bip86,
+        }
+    }
+}
+
 impl TryFrom<WalletColdType> for BitcoinColdWalletType {
     type Error = anyhow::Error;
     fn try_from(cold_type: WalletColdType) -> anyhow::Result<BitcoinColdWalletType> {
         match cold_type {
             WalletColdType::BtcXPub => Ok(BitcoinColdWalletType::XPubkey),
             WalletColdType::BchXPub => Ok(BitcoinColdWalletType::XPubkey),
+            WalletColdType::BtcP2PKH => Ok(BitcoinColdWalletType::P2PKH),
+            WalletColdType::BtcP2SHWPKH => Ok(BitcoinColdWalletType::P2SHWPKH),
+            WalletColdType::BtcP2WPKH => Ok(BitcoinColdWalletType::P2WPKH),
+            WalletColdType::BtcP2TR => Ok(BitcoinColdWalletType::P2TR),
+            WalletColdType::BtcDescriptor => Ok(BitcoinColdWalletType::Descriptor),
+            WalletColdType::BchP2PKH => Ok(BitcoinColdWalletType::P2PKH),
             w => Err(anyhow!("{:?} is not a BitcoinColdWalletType", w)),
         }
     }
 }
 
+impl TryFrom<WalletColdType> for EthereumColdWalletType {
+    type Error = anyhow::Error;
+    fn try_from(cold_type: WalletColdType) -> anyhow::Result<EthereumColdWalletType> {
+        match cold_type {
+            WalletColdType::EthXPub => Ok(EthereumColdWalletType::XPubkey),
+            WalletColdType::EtcXPub => Ok(EthereumColdWalletType::XPubkey),
+            w => Err(anyhow!("{:?} is not a EthereumColdWalletType", w)),
+        }
+    }
+}
+
 impl TryFrom<WalletColdType> for MoneroColdWalletType {
     type Error = anyhow::Error;
     fn try_from(cold_type: WalletColdType) -> anyhow::Result<MoneroColdWalletType> {
@@ -75,6 +146,17 @@ pub enum WalletSubcommand {
 
         #[clap(short, long, arg_enum, required_if_eq("wallet-type", "cold"))]
         cold_type: Option<WalletColdType>,
+
+        #[clap(long, arg_enum, conflicts_with("cold-type"))]
+        derivation: Option<Derivation>,
+
+        #[clap(long, requires("derivation"))]
+        account: Option<u32>,
+
+        /// Signatures required by a `btc-multisig` wallet, `pubkey` is then a comma separated
+        /// list of the cosigner xpubs
+        #[clap(long, required_if_eq("cold-type", "btc-multisig"))]
+        threshold: Option<usize>,
     },
     Lookup {
         #[clap(
@@ -88,9 +170,158 @@ pub enum WalletSubcommand {
         #[clap(short, long, conflicts_with("hash"), required_unless_present("hash"))]
         pubkey: Option<String>,
     },
+    Script {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        #[clap(short, long, default_value_t = 0)]
+        index: u64,
+    },
+    Balance {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+    },
+    Utxos {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+    },
+    Sweep {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        /// External address to sweep to
+        #[clap(
+            short,
+            long,
+            conflicts_with("wallet"),
+            required_unless_present("wallet")
+        )]
+        address: Option<String>,
+
+        /// Wallet to sweep to, swept funds land on its next receive address
+        #[clap(
+            short,
+            long,
+            conflicts_with("address"),
+            required_unless_present("address")
+        )]
+        wallet: Option<Hash>,
+
+        /// sat/vB
+        #[clap(short, long)]
+        fee_rate: u64,
+
+        /// Return the unsigned PSBT instead of broadcasting
+        #[clap(long)]
+        dry_run: bool,
+    },
+    CreatePsbt {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        /// External address to spend to
+        #[clap(
+            short,
+            long,
+            conflicts_with("wallet"),
+            required_unless_present("wallet")
+        )]
+        address: Option<String>,
+
+        /// Wallet to spend to, funds land on its next receive address
+        #[clap(
+            short,
+            long,
+            conflicts_with("address"),
+            required_unless_present("address")
+        )]
+        wallet: Option<Hash>,
+
+        /// sat/vB
+        #[clap(short, long)]
+        fee_rate: u64,
+    },
+    FinalizePsbt {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        /// Signed base64 PSBT
+        #[clap(short, long)]
+        psbt: String,
+    },
+    /// Only `active` wallets issue new invoices, existing invoices settle in every status
+    Update {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        #[clap(short, long, arg_enum)]
+        status: Status,
+    },
+    /// Archives a wallet, `update` makes it `active` again
+    Delete {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+    },
+    /// Re-encrypts a wallet under a fresh encryption key
+    RotateKey {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+    },
+    /// Moves the receive index past the highest address used on chain
+    Rescan {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+
+        /// Unused addresses in a row that end the scan, defaults to 20
+        #[clap(short, long)]
+        gap_limit: Option<u64>,
+    },
+    /// Mnemonic and passphrase (25 word seed for XMR) of a hot wallet, needs a token granted
+    /// `WalletSecret` `Write`
+    Export {
+        #[clap(short = 'H', long)]
+        hash: Hash,
+    },
+    /// Restores a hot wallet from its mnemonic and passphrase (25 word seed for XMR), read from
+    /// stdin: the mnemonic on the first line, the passphrase on the optional second line
+    Import {
+        #[clap(short, long, arg_enum)]
+        ticker: Ticker,
+
+        #[clap(long, arg_enum)]
+        derivation: Option<Derivation>,
+
+        #[clap(long)]
+        account: Option<u32>,
+    },
     Version {},
 }
 
+/// Reads the mnemonic and passphrase `wallet import` restores from stdin, prompting when it is
+/// a terminal. On the command line they would show up in `ps` and the shell history
+pub fn read_import_secret() -> anyhow::Result<(String, String)> {
+    let stdin = io::stdin();
+    let prompt = stdin.is_terminal();
+    let mut lines = stdin.lock().lines();
+    if prompt {
+        eprint!("Mnemonic: ");
+    }
+    let mnemonic = lines
+        .next()
+        .transpose()?
+        .map(|line| line.trim().to_string())
+        .filter(|mnemonic| !mnemonic.is_empty())
+        .ok_or(anyhow!("No mnemonic on stdin"))?;
+    if prompt {
+        eprint!("Passphrase (empty for none): ");
+    }
+    let passphrase = lines.next().transpose()?.unwrap_or_default();
+    Ok((mnemonic, passphrase))
+}
+
+/// Delay between polls of a running rescan
+const RESCAN_POLL_SECS: u64 = 2;
+
 pub struct WalletCtl {
     verbose: bool,
     endpoint: String,
@@ -194,6 +425,554 @@ impl WalletCtl {
         Ok(())
     }
 
+    pub async fn script(&self, req: WalletScriptRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.script",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn balance(&self, req: WalletBalanceRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.balance",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn utxos(&self, req: WalletUtxosRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.utxos",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn sweep(&self, req: WalletSweepRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.sweep",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn create_psbt(&self, req: WalletCreatePsbtRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.createPsbt",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn finalize_psbt(&self, req: WalletFinalizePsbtRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.finalizePsbt",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn update(&self, req: WalletUpdateRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.update",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn delete(&self, req: WalletDeleteRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.delete",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn rotate_key(&self, req: WalletRotateKeyRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.rotateKey",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    /// Starts the rescan and polls it until the node finished, rescans outlive a request
+    pub async fn rescan(&self, req: WalletRescanRequest) -> anyhow::Result<()> {
+        let status_req = WalletRescanStatusRequest {
+            hash: req.hash.clone(),
+        };
+        let mut response_json = self
+            .rescan_request("wallet.rescan", json!({ "request": req }))
+            .await?;
+        while response_json["result"]["status"] == json!("running") {
+            tokio::time::sleep(Duration::from_secs(RESCAN_POLL_SECS)).await;
+            response_json = self
+                .rescan_request("wallet.rescanStatus", json!({ "request": status_req }))
+                .await?;
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    async fn rescan_request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+            println!("{}", serde_json::to_string_pretty(&response_json)?);
+        }
+        Ok(response_json)
+    }
+
+    pub async fn export(&self, req: WalletExportRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.export",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
+    pub async fn import(&self, req: WalletImportRequest) -> anyhow::Result<()> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let json_rpc = json!({
+            "jsonrpc": "2.0",
+            "method": "wallet.import",
+            "params": {
+                "request": req,
+            },
+            "id": id,
+        });
+
+        let url = format!("{}/jsonrpc", self.endpoint);
+
+        if self.verbose {
+            println!("*****************************");
+            println!("********** REQUEST **********");
+            println!("*****************************");
+            println!("{}", url);
+            println!("{}", serde_json::to_string_pretty(&json_rpc)?);
+        }
+
+        let client = awc::Client::default();
+        let mut response = client
+            .post(&url)
+            .insert_header((
+                "User-Agent",
+                format!("moonramp-cli/v{}", env!("CARGO_PKG_VERSION")),
+            ))
+            .bearer_auth(self.api_token.clone())
+            .send_json(&json_rpc)
+            .await
+            .map_err(|err| anyhow!("{}", err))?;
+
+        let response_json: serde_json::Value = response.json().await?;
+        if self.verbose {
+            println!("******************************");
+            println!("********** RESPONSE **********");
+            println!("******************************");
+            println!("{:?}", response);
+        }
+        println!("{}", serde_json::to_string_pretty(&response_json)?);
+        Ok(())
+    }
+
     pub async fn version(&self) -> anyhow::Result<()> {
         let id = Uuid::new_v4().to_simple().to_string();
         let json_rpc = json!({
@@ -20,6 +20,8 @@ pub use async_trait;
 #[cfg(feature = "http")]
 pub use awc;
 #[cfg(feature = "crypto-currency-bitcoin")]
+pub use base64;
+#[cfg(feature = "crypto-currency-bitcoin")]
 pub use bip39;
 #[cfg(feature = "crypto-currency-bitcoin")]
 pub use bitcoin;
@@ -47,6 +49,8 @@ pub use log;
 pub use lz4_flex;
 #[cfg(feature = "crypto-currency-monero")]
 pub use monero;
+#[cfg(feature = "http")]
+pub use qrcode;
 #[cfg(feature = "random")]
 pub use rand;
 #[cfg(feature = "serialization")]
@@ -57,18 +61,22 @@ pub use sea_orm;
 pub use serde;
 #[cfg(feature = "serialization")]
 pub use serde_json;
-#[cfg(feature = "crypto")]
+#[cfg(any(feature = "crypto", feature = "crypto-currency-ethereum"))]
 pub use sha3;
 //#[cfg(feature = "sql")]
 //pub use sqlx;
 #[cfg(feature = "async-core")]
 pub use tokio;
+#[cfg(feature = "http")]
+pub use tokio_rustls;
 #[cfg(feature = "random")]
 pub use uuid;
 #[cfg(feature = "wasm")]
 pub use wasmtime;
 #[cfg(feature = "wasm")]
 pub use wasmtime_wasi;
+#[cfg(feature = "http")]
+pub use webpki_roots;
 
 //#[cfg(feature = "async-core")]
 //pub use async_stream;
@@ -342,3 +342,257 @@ impl From<Hash> for sea_orm::Value {
         sea_orm::Value::from(h.to_string())
     }
 }
+
+/// An exact decimal amount of `units` * 10^-`decimals`, e.g. satoshis with 8 decimals
+///
+/// Serialized as a decimal string (`"0.00001000"`) so no precision is lost to f64
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Amount {
+    units: u128,
+    decimals: u32,
+}
+
+/// The most decimals an `Amount` can carry, 10^38 is the largest power of ten in a u128
+pub const AMOUNT_MAX_DECIMALS: u32 = 38;
+
+impl Amount {
+    /// An amount of `units` of the smallest unit of a currency with `decimals` places, fails
+    /// above `AMOUNT_MAX_DECIMALS`
+    pub fn new(units: u128, decimals: u32) -> anyhow::Result<Self> {
+        if decimals > AMOUNT_MAX_DECIMALS {
+            return Err(anyhow::anyhow!(
+                "Amounts have at most {} decimals",
+                AMOUNT_MAX_DECIMALS
+            ));
+        }
+        Ok(Amount { units, decimals })
+    }
+
+    /// Integer units of 10^-`decimals`
+    pub fn units(&self) -> u128 {
+        self.units
+    }
+
+    /// Decimal places of `units`
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    /// True if the amount is zero
+    pub fn is_zero(&self) -> bool {
+        self.units == 0
+    }
+
+    /// The same amount expressed with `decimals` places, fails if that would lose precision
+    pub fn rescale(&self, decimals: u32) -> anyhow::Result<Amount> {
+        if decimals > AMOUNT_MAX_DECIMALS {
+            return Err(anyhow::anyhow!(
+                "Amounts have at most {} decimals",
+                AMOUNT_MAX_DECIMALS
+            ));
+        }
+        let units = if decimals >= self.decimals {
+            self.units
+                .checked_mul(10u128.pow(decimals - self.decimals))
+                .ok_or(anyhow::anyhow!("Amount {} is too large", self))?
+        } else {
+            let scale = 10u128.pow(self.decimals - decimals);
+            if self.units % scale != 0 {
+                return Err(anyhow::anyhow!(
+                    "Amount {} has more than {} decimals",
+                    self,
+                    decimals
+                ));
+            }
+            self.units / scale
+        };
+        Ok(Amount { units, decimals })
+    }
+
+    /// Integer units of a currency with `decimals` places, e.g. wei for 18
+    pub fn to_base_units(&self, decimals: u32) -> anyhow::Result<u128> {
+        Ok(self.rescale(decimals)?.units)
+    }
+
+    fn split(&self) -> (u128, u128) {
+        let scale = 10u128.pow(self.decimals);
+        (self.units / scale, self.units % scale)
+    }
+}
+
+impl PartialEq for Amount {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Amount {}
+
+impl PartialOrd for Amount {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Amount {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Whole parts first, the fractions then fit a u128 at the larger scale
+        let ((whole, frac), (other_whole, other_frac)) = (self.split(), other.split());
+        let decimals = self.decimals.max(other.decimals);
+        whole.cmp(&other_whole).then_with(|| {
+            (frac * 10u128.pow(decimals - self.decimals))
+                .cmp(&(other_frac * 10u128.pow(decimals - other.decimals)))
+        })
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (whole, frac) = self.split();
+        if self.decimals == 0 {
+            write!(f, "{}", whole)
+        } else {
+            write!(
+                f,
+                "{}.{:0width$}",
+                whole,
+                frac,
+                width = self.decimals as usize
+            )
+        }
+    }
+}
+
+impl std::str::FromStr for Amount {
+    type Err = anyhow::Error;
+    fn from_str(val: &str) -> anyhow::Result<Amount> {
+        let (whole, frac) = val.split_once('.').unwrap_or((val, ""));
+        if whole.is_empty()
+            || (val.contains('.') && frac.is_empty())
+            || !whole
+                .chars()
+                .chain(frac.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(anyhow::anyhow!("Invalid amount {:?}", val));
+        }
+        if frac.len() > AMOUNT_MAX_DECIMALS as usize {
+            return Err(anyhow::anyhow!(
+                "Amount {} has more than {} decimals",
+                val,
+                AMOUNT_MAX_DECIMALS
+            ));
+        }
+        let units = format!("{}{}", whole, frac)
+            .parse::<u128>()
+            .map_err(|_| anyhow::anyhow!("Amount {} is too large", val))?;
+        Amount::new(units, frac.len() as u32)
+    }
+}
+
+#[cfg(feature = "serialization")]
+impl serde::Serialize for Amount {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_string().serialize(s)
+    }
+}
+
+#[cfg(feature = "serialization")]
+impl<'de> serde::Deserialize<'de> for Amount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+#[cfg(feature = "serialization")]
+struct AmountVisitor;
+
+#[cfg(feature = "serialization")]
+impl<'de> serde::de::Visitor<'de> for AmountVisitor {
+    type Value = Amount;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a non-negative decimal amount")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, val: &str) -> Result<Amount, E> {
+        val.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, val: u64) -> Result<Amount, E> {
+        Amount::new(val as u128, 0).map_err(E::custom)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, val: i64) -> Result<Amount, E> {
+        u64::try_from(val)
+            .map_err(|_| E::custom(format!("Invalid amount {}", val)))
+            .and_then(|val| self.visit_u64(val))
+    }
+
+    // JSON numbers from older clients, f64 Display is the shortest exact round trip
+    // of the number that was sent, e.g. `0.00001`
+    fn visit_f64<E: serde::de::Error>(self, val: f64) -> Result<Amount, E> {
+        if !val.is_finite() {
+            return Err(E::custom(format!("Invalid amount {}", val)));
+        }
+        self.visit_str(&val.to_string())
+    }
+}
+
+#[cfg(feature = "sql")]
+impl sea_orm::TryGetable for Amount {
+    fn try_get(
+        res: &sea_orm::QueryResult,
+        pre: &str,
+        col: &str,
+    ) -> Result<Self, sea_orm::TryGetError> {
+        let opt: Option<String> = res.try_get(pre, col).map_err(sea_orm::TryGetError::DbErr)?;
+        match opt {
+            Some(val) => Ok(val
+                .parse()
+                .map_err(|_| sea_orm::DbErr::Exec("Invalid Amount".to_string()))
+                .map_err(sea_orm::TryGetError::DbErr)?),
+            None => Err(sea_orm::TryGetError::Null),
+        }
+    }
+}
+
+#[cfg(feature = "sql")]
+impl sea_orm::sea_query::Nullable for Amount {
+    fn null() -> sea_orm::Value {
+        sea_orm::Value::String(None)
+    }
+}
+
+#[cfg(feature = "sql")]
+impl sea_orm::sea_query::ValueType for Amount {
+    fn try_from(v: sea_orm::Value) -> Result<Self, sea_orm::sea_query::ValueTypeErr> {
+        match v {
+            sea_orm::Value::String(Some(x)) => {
+                Ok(x.parse().map_err(|_| sea_orm::sea_query::ValueTypeErr)?)
+            }
+            _ => Err(sea_orm::sea_query::ValueTypeErr),
+        }
+    }
+
+    fn type_name() -> String {
+        "Amount".to_string()
+    }
+
+    fn column_type() -> sea_orm::sea_query::ColumnType {
+        sea_orm::sea_query::ColumnType::Text
+    }
+}
+
+#[cfg(feature = "sql")]
+impl From<Amount> for sea_orm::Value {
+    fn from(a: Amount) -> sea_orm::Value {
+        sea_orm::Value::from(a.to_string())
+    }
+}
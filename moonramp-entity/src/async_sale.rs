@@ -4,8 +4,10 @@ use serde::{Deserialize, Serialize};
 
 use moonramp_core::{chrono, sea_orm, serde, Hash};
 
+/// A durable `sale.captureAsync` job, polled by the sale workers until its invoice is funded
+/// or closed and then reported to `webhook_id` if it has one
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
-#[sea_orm(table_name = "sales")]
+#[sea_orm(table_name = "async_sales")]
 #[serde(crate = "moonramp_core::serde")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
@@ -13,22 +15,43 @@ pub struct Model {
     #[sea_orm(indexed, column_type = "Text")]
     pub merchant_hash: Hash,
     #[sea_orm(indexed, column_type = "Text")]
-    pub wallet_hash: Hash,
-    #[sea_orm(indexed, column_type = "Text")]
     pub invoice_hash: Hash,
-    pub ticker: super::ticker::Ticker,
-    pub currency: super::currency::Currency,
-    pub network: super::network::Network,
-    pub pubkey: String,
-    pub address: String,
-    pub amount: i64,
+    pub uuid: String,
+    pub webhook_id: Option<String>,
     pub confirmations: i64,
+    #[sea_orm(column_type = "Text")]
+    pub program_hash: Option<Hash>,
+    pub async_sale_status: AsyncSaleStatus,
+    #[sea_orm(column_type = "Text")]
+    pub sale_hash: Option<Hash>,
+    /// Failed captures in a row, drives the retry backoff
+    pub attempts: i64,
+    pub last_error: Option<String>,
     pub cipher: super::cipher::Cipher,
     #[sea_orm(indexed, column_type = "Text")]
     pub encryption_key_hash: Hash,
     pub blob: Vec<u8>,
     pub nonce: Vec<u8>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Pending jobs are captured and finished jobs are delivered once this has passed
+    #[sea_orm(indexed)]
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, EnumIter, DeriveActiveEnum, Deserialize, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "Text", enum_name = "async_sale_status")]
+#[serde(crate = "moonramp_core::serde")]
+pub enum AsyncSaleStatus {
+    #[sea_orm(string_value = "Canceled")]
+    Canceled,
+    #[sea_orm(string_value = "Expired")]
+    Expired,
+    #[sea_orm(string_value = "Funded")]
+    Funded,
+    #[sea_orm(string_value = "Pending")]
+    Pending,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use moonramp_core::{chrono, sea_orm, serde, Hash};
+
+/// A hosted `sale.checkout` page. The hash is random since it is the only thing guarding
+/// the public page
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "checkouts")]
+#[serde(crate = "moonramp_core::serde")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
+    pub hash: Hash,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub merchant_hash: Hash,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub invoice_hash: Hash,
+    /// The capture job that funds the invoice while the page polls
+    #[sea_orm(indexed, column_type = "Text")]
+    pub async_sale_hash: Hash,
+    pub success_url: String,
+    pub cancel_url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::async_sale::Entity",
+        from = "Column::AsyncSaleHash",
+        to = "super::async_sale::Column::Hash"
+    )]
+    AsyncSale,
+    #[sea_orm(
+        belongs_to = "super::invoice::Entity",
+        from = "Column::InvoiceHash",
+        to = "super::invoice::Column::Hash"
+    )]
+    Invoice,
+    #[sea_orm(
+        belongs_to = "super::merchant::Entity",
+        from = "Column::MerchantHash",
+        to = "super::merchant::Column::Hash"
+    )]
+    Merchant,
+}
+
+impl Related<super::async_sale::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AsyncSale.def()
+    }
+}
+
+impl Related<super::invoice::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invoice.def()
+    }
+}
+
+impl Related<super::merchant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Merchant.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
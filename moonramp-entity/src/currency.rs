@@ -11,6 +11,8 @@ pub enum Currency {
     BCH,
     #[sea_orm(string_value = "Bitcoin")]
     BTC,
+    #[sea_orm(string_value = "Bitcoin Lightning")]
+    BTCLN,
     #[sea_orm(string_value = "Ethereum Classic")]
     ETC,
     #[sea_orm(string_value = "Ethereum")]
@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use moonramp_core::{chrono, sea_orm, serde, Hash};
+use moonramp_core::{chrono, sea_orm, serde, Amount, Hash};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
 #[sea_orm(table_name = "invoices")]
@@ -20,8 +20,27 @@ pub struct Model {
     pub invoice_status: InvoiceStatus,
     pub pubkey: String,
     pub address: String,
-    pub amount: f64,
+    #[sea_orm(column_type = "Text")]
+    pub amount: Amount,
     pub uri: String,
+    pub contract: Option<String>,
+    /// Lightning payment hash, hex encoded
+    pub payment_hash: Option<String>,
+    /// Chain height when the invoice was created, ERC-20 captures scan transfer logs from it
+    pub start_height: Option<i64>,
+    /// Set when the invoice was priced in fiat, `amount` is locked at `rate` until `expires_at`
+    #[sea_orm(column_type = "Text")]
+    pub fiat_amount: Option<Amount>,
+    /// ISO 4217 code of `fiat_amount`
+    pub fiat_currency: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    pub rate: Option<Amount>,
+    pub rate_source: Option<String>,
+    pub quoted_at: Option<DateTime<Utc>>,
+    /// Set when a capture found the invoice funded after it had already expired
+    pub late_paid_at: Option<DateTime<Utc>>,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub cancel_reason: Option<String>,
     pub cipher: super::cipher::Cipher,
     #[sea_orm(indexed, column_type = "Text")]
     pub encryption_key_hash: Hash,
@@ -1,4 +1,6 @@
 pub mod api_token;
+pub mod async_sale;
+pub mod checkout;
 pub mod cipher;
 pub mod currency;
 pub mod encryption_key;
@@ -11,6 +13,8 @@ pub mod role;
 pub mod sale;
 pub mod ticker;
 pub mod wallet;
+pub mod webhook;
+pub mod webhook_delivery;
 
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 
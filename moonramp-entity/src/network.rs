@@ -11,6 +11,10 @@ pub enum Network {
     Mainnet,
     #[sea_orm(string_value = "Testnet")]
     Testnet,
+    #[sea_orm(string_value = "Signet")]
+    Signet,
+    #[sea_orm(string_value = "Stagenet")]
+    Stagenet,
     #[sea_orm(string_value = "Regtest")]
     Regtest,
 }
@@ -47,6 +47,9 @@ pub enum Resource {
     Sale,
     #[sea_orm(string_value = "Wallet")]
     Wallet,
+    /// Mnemonics and private keys of hot wallets, never granted by default
+    #[sea_orm(string_value = "WalletSecret")]
+    WalletSecret,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
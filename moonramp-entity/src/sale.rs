@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use moonramp_core::{chrono, sea_orm, serde, Hash};
+use moonramp_core::{chrono, sea_orm, serde, Amount, Hash};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
 #[sea_orm(table_name = "sales")]
@@ -21,7 +21,8 @@ pub struct Model {
     pub network: super::network::Network,
     pub pubkey: String,
     pub address: String,
-    pub amount: f64,
+    #[sea_orm(column_type = "Text")]
+    pub amount: Amount,
     pub confirmations: i64,
     pub cipher: super::cipher::Cipher,
     #[sea_orm(indexed, column_type = "Text")]
@@ -17,6 +17,7 @@ pub struct Model {
     pub ticker: super::ticker::Ticker,
     pub network: super::network::Network,
     pub wallet_type: WalletType,
+    pub wallet_status: WalletStatus,
     pub cipher: super::cipher::Cipher,
     #[sea_orm(indexed, column_type = "Text")]
     pub encryption_key_hash: Hash,
@@ -35,6 +36,20 @@ pub enum WalletType {
     Cold,
 }
 
+#[derive(Clone, Debug, PartialEq, EnumIter, DeriveActiveEnum, Deserialize, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "Text", enum_name = "wallet_status")]
+#[serde(crate = "moonramp_core::serde")]
+pub enum WalletStatus {
+    #[sea_orm(string_value = "Active")]
+    Active,
+    /// Existing invoices settle but no new invoices are issued
+    #[sea_orm(string_value = "ReceiveDisabled")]
+    ReceiveDisabled,
+    /// Soft deleted, receives like `ReceiveDisabled` and can be made `Active` again
+    #[sea_orm(string_value = "Archived")]
+    Archived,
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use moonramp_core::{chrono, sea_orm, serde, Hash};
+
+/// An endpoint a merchant registered for signed event deliveries, the blob is its
+/// signing secret
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "webhooks")]
+#[serde(crate = "moonramp_core::serde")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
+    pub hash: Hash,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub merchant_hash: Hash,
+    pub url: String,
+    /// Comma separated event types, empty subscribes to every event
+    pub event_types: String,
+    pub cipher: super::cipher::Cipher,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub encryption_key_hash: Hash,
+    pub blob: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Deleted webhooks are kept for their delivery log but receive nothing
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::encryption_key::Entity",
+        from = "Column::EncryptionKeyHash",
+        to = "super::encryption_key::Column::Hash"
+    )]
+    EncryptionKey,
+    #[sea_orm(
+        belongs_to = "super::merchant::Entity",
+        from = "Column::MerchantHash",
+        to = "super::merchant::Column::Hash"
+    )]
+    Merchant,
+}
+
+impl Related<super::encryption_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EncryptionKey.def()
+    }
+}
+
+impl Related<super::merchant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Merchant.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
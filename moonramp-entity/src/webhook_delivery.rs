@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use moonramp_core::{chrono, sea_orm, serde, Hash};
+
+/// One attempt to deliver an event to a webhook. Retries and replays add attempts, the
+/// blob is the signed body encrypted with the webhook's key
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "webhook_deliveries")]
+#[serde(crate = "moonramp_core::serde")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
+    pub hash: Hash,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub merchant_hash: Hash,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub webhook_hash: Hash,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub event_hash: Hash,
+    pub event_type: String,
+    pub attempt: i64,
+    pub delivery_status: DeliveryStatus,
+    pub response_status: Option<i64>,
+    pub error: Option<String>,
+    pub cipher: super::cipher::Cipher,
+    #[sea_orm(indexed, column_type = "Text")]
+    pub encryption_key_hash: Hash,
+    pub blob: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    /// Pending attempts are sent once this has passed
+    #[sea_orm(indexed)]
+    pub next_attempt_at: DateTime<Utc>,
+    pub attempted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, EnumIter, DeriveActiveEnum, Deserialize, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "Text", enum_name = "delivery_status")]
+#[serde(crate = "moonramp_core::serde")]
+pub enum DeliveryStatus {
+    #[sea_orm(string_value = "Failed")]
+    Failed,
+    #[sea_orm(string_value = "Pending")]
+    Pending,
+    #[sea_orm(string_value = "Succeeded")]
+    Succeeded,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::encryption_key::Entity",
+        from = "Column::EncryptionKeyHash",
+        to = "super::encryption_key::Column::Hash"
+    )]
+    EncryptionKey,
+    #[sea_orm(
+        belongs_to = "super::merchant::Entity",
+        from = "Column::MerchantHash",
+        to = "super::merchant::Column::Hash"
+    )]
+    Merchant,
+    #[sea_orm(
+        belongs_to = "super::webhook::Entity",
+        from = "Column::WebhookHash",
+        to = "super::webhook::Column::Hash"
+    )]
+    Webhook,
+}
+
+impl Related<super::encryption_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EncryptionKey.def()
+    }
+}
+
+impl Related<super::merchant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Merchant.def()
+    }
+}
+
+impl Related<super::webhook::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webhook.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,21 +1,25 @@
-use std::io::Write;
-
 use anyhow::anyhow;
-use bitcoincore_rpc_json::ScanTxOutResult;
+use bitcoincore_rpc_json::{
+    GetTxOutResult, ListReceivedByAddressResult, ListUnspentResultEntry, ScanTxOutRequest,
+    ScanTxOutResult,
+};
 use hyper::{
     http::header::{AUTHORIZATION, CONTENT_TYPE},
     Body, Client, Method, Request,
 };
-use log::{debug, trace};
+use log::trace;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
-use wasmtime::{Extern, FuncType, Linker, Trap, Val, ValType};
+use wasmtime::Linker;
 use wasmtime_wasi::WasiCtx;
 
 use moonramp_core::{
     anyhow, bitcoincore_rpc_json, hyper, log, serde, serde_json, uuid, wasmtime, wasmtime_wasi,
 };
+use moonramp_lunar::gateway::{BitcoinGatewayRequest, BitcoinGatewayResponse};
+
+use crate::linker::add_gateway_func;
 
 #[derive(Debug, Clone)]
 pub struct BitcoinRpcConfig {
@@ -39,108 +43,240 @@ impl<T> JsonRpcOneDotZeroResult<T> {
             _ => Err(anyhow!("Invalid Response")),
         }
     }
+
+    fn error_code(&self) -> Option<i64> {
+        self.error.as_ref().and_then(|err| err["code"].as_i64())
+    }
 }
 
 pub fn add_to_linker(config: BitcoinRpcConfig, linker: &mut Linker<WasiCtx>) -> anyhow::Result<()> {
-    linker.func_new_async(
-        "env",
+    add_gateway_func(
+        linker,
         "bitcoin_gateway",
-        FuncType::new([ValType::I32, ValType::I32], [ValType::I32]),
-        move |mut caller, params, returns| {
-            let config = config.clone();
-            Box::new(async move {
-                if let (Some(Val::I32(req_ptr)), Some(Val::I32(req_len))) =
-                    (params.get(0), params.get(1))
-                {
-                    debug!("BitcoinGatewayRequest: 0x{:02X} {} bytes", req_ptr, req_len);
-                    let memory = match caller.get_export("memory") {
-                        Some(Extern::Memory(mem)) => mem,
-                        _ => return Err(Trap::new("failed to find memory")),
-                    };
-                    let data = memory
-                        .data(&caller)
-                        .get(*req_ptr as usize..)
-                        .and_then(|arr| arr.get(..*req_len as usize));
-                    let req: moonramp_lunar::gateway::BitcoinGatewayRequest = match data {
-                        Some(data) => match serde_json::from_slice(&data) {
-                            Ok(exit_data) => exit_data,
-                            Err(err) => {
-                                return Err(Trap::new(format!(
-                                    "Invalid BitcoinGatewayRequest: {}",
-                                    err
-                                )))
-                            }
-                        },
-                        None => return Err(Trap::new("pointer/length out of bounds")),
-                    };
-
-                    let res = match &req {
-                        moonramp_lunar::gateway::BitcoinGatewayRequest::ScanTxOut(req) => {
-                            let res: anyhow::Result<JsonRpcOneDotZeroResult<ScanTxOutResult>> =
-                                json_rpc_request(
-                                    &config,
-                                    "scantxoutset",
-                                    json!({
-                                        "action": "start",
-                                        "scanobjects": req,
-                                    }),
-                                )
-                                .await;
-                            trace!("REQUEST {:?}", res);
-                            moonramp_lunar::gateway::BitcoinGatewayResponse::ScanTxOut(
-                                res?.inner().map_err(|err| {
-                                    debug!("REQUEST ERROR {:?}", err);
-                                    Trap::new(err.to_string())
-                                })?,
-                            )
-                        }
-                    };
-
-                    debug!("RESPONSE {:?}", res);
-
-                    let lunar_alloc_fn = caller
-                        .get_export("lunar_allocate")
-                        .ok_or(anyhow!("lunar_allocate not found"))?
-                        .into_func()
-                        .ok_or(anyhow!("lunar_allocate not func"))?
-                        .typed::<i32, i32, _>(&mut caller)?;
-
-                    let res_json =
-                        serde_json::to_vec(&res).map_err(|err| Trap::new(err.to_string()))?;
-                    let res_json_ptr = lunar_alloc_fn
-                        .call_async(&mut caller, res_json.len() as i32)
-                        .await?;
-
-                    let data = memory
-                        .data_mut(&mut caller)
-                        .get_mut(res_json_ptr as usize..)
-                        .and_then(|arr| arr.get_mut(..res_json.len()));
-                    match data {
-                        Some(mut data) => {
-                            data.write(&res_json)
-                                .map_err(|err| Trap::new(err.to_string()))?;
-                        }
-                        None => return Err(Trap::new("pointer/length out of bounds")),
-                    }
-
-                    debug!("Res JSON Ptr 0x{:02X}", res_json_ptr);
-                    returns[0] = Val::I32(res_json_ptr);
-                    let wasi: &mut WasiCtx = caller.data_mut();
-                    wasi.table()
-                        .insert_at(res_json_ptr as u32, Box::new(res_json.len() as i32));
-                }
-                Ok(())
-            })
-        },
-    )?;
+        config,
+        |config, req| async move { gateway_request(&config, req).await },
+    )
+}
+
+async fn gateway_request(
+    config: &BitcoinRpcConfig,
+    req: BitcoinGatewayRequest,
+) -> anyhow::Result<BitcoinGatewayResponse> {
+    match req {
+        BitcoinGatewayRequest::ScanTxOut(req) => Ok(BitcoinGatewayResponse::ScanTxOut(
+            scan_tx_out(config, &req).await?,
+        )),
+    }
+}
 
+/// Scans the UTXO set for outputs matching `scanobjects`
+pub async fn scan_tx_out(
+    config: &BitcoinRpcConfig,
+    scanobjects: &[ScanTxOutRequest],
+) -> anyhow::Result<ScanTxOutResult> {
+    let res: JsonRpcOneDotZeroResult<ScanTxOutResult> = json_rpc_request(
+        config,
+        "scantxoutset",
+        json!({
+            "action": "start",
+            "scanobjects": scanobjects,
+        }),
+    )
+    .await?;
+    trace!("REQUEST {:?}", res);
+    res.inner()
+}
+
+/// Broadcasts a hex encoded signed transaction, returning its txid
+pub async fn send_raw_transaction(
+    config: &BitcoinRpcConfig,
+    tx_hex: &str,
+) -> anyhow::Result<String> {
+    let res: JsonRpcOneDotZeroResult<String> =
+        json_rpc_request(config, "sendrawtransaction", json!([tx_hex])).await?;
+    trace!("REQUEST {:?}", res);
+    res.inner()
+}
+
+/// Hex encoded transaction `txid`, needs `-txindex` once it is confirmed unless the node's
+/// wallet knows it
+pub async fn get_raw_transaction(config: &BitcoinRpcConfig, txid: &str) -> anyhow::Result<String> {
+    let res: JsonRpcOneDotZeroResult<String> =
+        json_rpc_request(config, "getrawtransaction", json!([txid])).await?;
+    trace!("REQUEST {:?}", res);
+    res.inner()
+}
+
+/// Unspent outputs `(txid, vout)`, `None` for those that don't exist or are spent, including
+/// by a mempool transaction
+pub async fn get_tx_outs(
+    config: &BitcoinRpcConfig,
+    outpoints: &[(String, u32)],
+) -> anyhow::Result<Vec<Option<GetTxOutResult>>> {
+    let mut txouts = Vec::with_capacity(outpoints.len());
+    for chunk in outpoints.chunks(JSON_RPC_BATCH_SIZE) {
+        let res: Vec<JsonRpcOneDotZeroResult<GetTxOutResult>> = json_rpc_batch_request(
+            config,
+            "gettxout",
+            chunk
+                .iter()
+                .map(|(txid, vout)| json!([txid, vout, true]))
+                .collect(),
+        )
+        .await?;
+        for res in res {
+            // gettxout answers null for spent outputs
+            match (res.result, res.error) {
+                (_, Some(err)) => return Err(anyhow!(err.to_string())),
+                (txout, None) => txouts.push(txout),
+            }
+        }
+    }
+    Ok(txouts)
+}
+
+/// Error code of wallet RPCs for a wallet that doesn't exist or isn't loaded
+const RPC_WALLET_NOT_FOUND: i64 = -18;
+/// Error code of `loadwallet` for a wallet that is already loaded
+const RPC_WALLET_ALREADY_LOADED: i64 = -35;
+
+/// Loads the node's watch-only descriptor wallet `wallet`, creating it the first time. Created
+/// wallets load on startup so the node keeps following their addresses
+pub async fn load_watch_only_wallet(config: &BitcoinRpcConfig, wallet: &str) -> anyhow::Result<()> {
+    let res: JsonRpcOneDotZeroResult<serde_json::Value> =
+        json_rpc_request(config, "loadwallet", json!([wallet, true])).await?;
+    match res.error_code() {
+        None | Some(RPC_WALLET_ALREADY_LOADED) => return Ok(()),
+        Some(RPC_WALLET_NOT_FOUND) => {}
+        Some(_) => return res.inner().map(|_| ()),
+    }
+    let res: JsonRpcOneDotZeroResult<serde_json::Value> = json_rpc_request(
+        config,
+        "createwallet",
+        create_watch_only_wallet_params(wallet),
+    )
+    .await?;
+    trace!("REQUEST {:?}", res);
+    res.inner().map(|_| ())
+}
+
+fn create_watch_only_wallet_params(wallet: &str) -> serde_json::Value {
+    json!({
+        "wallet_name": wallet,
+        "disable_private_keys": true,
+        "blank": true,
+        "descriptors": true,
+        "load_on_startup": true,
+    })
+}
+
+/// Imports ranged `descriptor` into `wallet` over indexes `0..=range_end`, the node rescans
+/// blocks from unix time `timestamp` for it
+pub async fn import_descriptor(
+    config: &BitcoinRpcConfig,
+    wallet: &str,
+    descriptor: &str,
+    range_end: u64,
+    timestamp: i64,
+) -> anyhow::Result<()> {
+    let res: JsonRpcOneDotZeroResult<Vec<serde_json::Value>> = json_rpc_wallet_request(
+        config,
+        wallet,
+        "importdescriptors",
+        import_descriptor_params(descriptor, range_end, timestamp),
+    )
+    .await?;
+    for import in res.inner()? {
+        if import["success"] != json!(true) {
+            return Err(anyhow!("Failed to import descriptor: {}", import["error"]));
+        }
+    }
     Ok(())
 }
 
+fn import_descriptor_params(descriptor: &str, range_end: u64, timestamp: i64) -> serde_json::Value {
+    json!([[{
+        "desc": descriptor,
+        "range": [0, range_end],
+        "timestamp": timestamp,
+    }]])
+}
+
+/// Highest index of the ranged descriptors imported into `wallet`, `None` before any import
+pub async fn imported_range_end(
+    config: &BitcoinRpcConfig,
+    wallet: &str,
+) -> anyhow::Result<Option<u64>> {
+    let res: JsonRpcOneDotZeroResult<serde_json::Value> =
+        json_rpc_wallet_request(config, wallet, "listdescriptors", json!([])).await?;
+    Ok(res.inner()?["descriptors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|descriptor| descriptor["range"][1].as_u64())
+        .max())
+}
+
+/// Outputs of `wallet` with at least `minconf` confirmations that neither the chain nor the
+/// mempool spends
+pub async fn list_unspent(
+    config: &BitcoinRpcConfig,
+    wallet: &str,
+    minconf: u32,
+) -> anyhow::Result<Vec<ListUnspentResultEntry>> {
+    let res: JsonRpcOneDotZeroResult<Vec<ListUnspentResultEntry>> =
+        json_rpc_wallet_request(config, wallet, "listunspent", json!([minconf])).await?;
+    res.inner()
+}
+
+/// Addresses of `wallet` that ever received funds, with the total they received and the
+/// transactions paying them. Spent funds and mempool transactions are included
+pub async fn list_received_by_address(
+    config: &BitcoinRpcConfig,
+    wallet: &str,
+) -> anyhow::Result<Vec<ListReceivedByAddressResult>> {
+    let res: JsonRpcOneDotZeroResult<Vec<ListReceivedByAddressResult>> = json_rpc_wallet_request(
+        config,
+        wallet,
+        "listreceivedbyaddress",
+        json!([0, false, true]),
+    )
+    .await?;
+    res.inner()
+}
+
+/// Height of the node's best block
+pub async fn get_block_count(config: &BitcoinRpcConfig) -> anyhow::Result<u64> {
+    let res: JsonRpcOneDotZeroResult<u64> =
+        json_rpc_request(config, "getblockcount", json!([])).await?;
+    res.inner()
+}
+
+const JSON_RPC_BATCH_SIZE: usize = 500;
+
 async fn json_rpc_request<T: for<'a> serde::de::Deserialize<'a>>(
     config: &BitcoinRpcConfig,
     method: &str,
     params: serde_json::Value,
+) -> anyhow::Result<T> {
+    json_rpc_request_to(config, &config.endpoint, method, params).await
+}
+
+/// `method` of the node's wallet `wallet`, served under `/wallet/<name>`
+async fn json_rpc_wallet_request<T: for<'a> serde::de::Deserialize<'a>>(
+    config: &BitcoinRpcConfig,
+    wallet: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> anyhow::Result<T> {
+    json_rpc_request_to(config, &wallet_endpoint(config, wallet), method, params).await
+}
+
+async fn json_rpc_request_to<T: for<'a> serde::de::Deserialize<'a>>(
+    config: &BitcoinRpcConfig,
+    uri: &str,
+    method: &str,
+    params: serde_json::Value,
 ) -> anyhow::Result<T> {
     let id = Uuid::new_v4().to_simple().to_string();
     let json_rpc = json!({
@@ -150,11 +286,65 @@ async fn json_rpc_request<T: for<'a> serde::de::Deserialize<'a>>(
         "id": id,
     });
     trace!("REQUEST {}", json_rpc);
+    json_rpc_post(config, uri, &json_rpc).await
+}
 
-    let json_bytes = serde_json::to_vec(&json_rpc)?;
+fn wallet_endpoint(config: &BitcoinRpcConfig, wallet: &str) -> String {
+    format!(
+        "{}/wallet/{}",
+        config.endpoint.trim_end_matches('/'),
+        wallet
+    )
+}
+
+/// Results of `method` called with each of `params`, in the same order
+async fn json_rpc_batch_request<T: for<'a> serde::de::Deserialize<'a>>(
+    config: &BitcoinRpcConfig,
+    method: &str,
+    params: Vec<serde_json::Value>,
+) -> anyhow::Result<Vec<JsonRpcOneDotZeroResult<T>>> {
+    let ids: Vec<String> = params
+        .iter()
+        .map(|_| Uuid::new_v4().to_simple().to_string())
+        .collect();
+    let json_rpc = serde_json::Value::Array(
+        params
+            .into_iter()
+            .zip(&ids)
+            .map(|(params, id)| {
+                json!({
+                    "jsonrpc": "1.0",
+                    "method": method,
+                    "params": params,
+                    "id": id,
+                })
+            })
+            .collect(),
+    );
+    trace!("REQUEST batch of {} {}", ids.len(), method);
+
+    let mut res: Vec<JsonRpcOneDotZeroResult<T>> =
+        json_rpc_post(config, &config.endpoint, &json_rpc).await?;
+    let mut ordered = Vec::with_capacity(ids.len());
+    for id in ids {
+        let i = res
+            .iter()
+            .position(|res| res.id == id)
+            .ok_or_else(|| anyhow!("Missing batch response {}", id))?;
+        ordered.push(res.swap_remove(i));
+    }
+    Ok(ordered)
+}
+
+async fn json_rpc_post<T: for<'a> serde::de::Deserialize<'a>>(
+    config: &BitcoinRpcConfig,
+    uri: &str,
+    json_rpc: &serde_json::Value,
+) -> anyhow::Result<T> {
+    let json_bytes = serde_json::to_vec(json_rpc)?;
     let req = Request::builder()
         .method(Method::POST)
-        .uri(&config.endpoint)
+        .uri(uri)
         .header(
             AUTHORIZATION,
             format!(
@@ -171,3 +361,58 @@ async fn json_rpc_request<T: for<'a> serde::de::Deserialize<'a>>(
         &hyper::body::to_bytes(res.into_body()).await?,
     )?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wallet_endpoint() {
+        for endpoint in ["http://127.0.0.1:18443", "http://127.0.0.1:18443/"] {
+            let config = BitcoinRpcConfig {
+                endpoint: endpoint.to_string(),
+                basic_auth: None,
+            };
+            assert_eq!(
+                wallet_endpoint(&config, "moonramp-abc"),
+                "http://127.0.0.1:18443/wallet/moonramp-abc"
+            );
+        }
+    }
+
+    #[test]
+    fn test_watch_only_wallet_params() {
+        let params = create_watch_only_wallet_params("moonramp-abc");
+        assert_eq!(params["wallet_name"], "moonramp-abc");
+        assert_eq!(params["disable_private_keys"], true);
+        assert_eq!(params["descriptors"], true);
+
+        let params = import_descriptor_params("wpkh(tpub.../0/*)#checksum", 1019, 1_660_000_000);
+        assert_eq!(
+            params,
+            json!([[{
+                "desc": "wpkh(tpub.../0/*)#checksum",
+                "range": [0, 1019],
+                "timestamp": 1_660_000_000,
+            }]])
+        );
+    }
+
+    #[test]
+    fn test_error_code() {
+        let res: JsonRpcOneDotZeroResult<serde_json::Value> = serde_json::from_value(json!({
+            "id": "1",
+            "result": null,
+            "error": { "code": -35, "message": "Wallet already loaded" },
+        }))
+        .expect("Invalid response");
+        assert_eq!(res.error_code(), Some(RPC_WALLET_ALREADY_LOADED));
+        let res: JsonRpcOneDotZeroResult<serde_json::Value> = serde_json::from_value(json!({
+            "id": "1",
+            "result": { "name": "moonramp-abc" },
+            "error": null,
+        }))
+        .expect("Invalid response");
+        assert_eq!(res.error_code(), None);
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::anyhow;
+use bitcoin::Amount;
+use hyper::{
+    http::header::{AUTHORIZATION, CONTENT_TYPE},
+    Body, Client, Method, Request,
+};
+use log::trace;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use wasmtime::Linker;
+use wasmtime_wasi::WasiCtx;
+
+use moonramp_core::{
+    anyhow, bitcoin, hyper, log, serde, serde_json, uuid, wasmtime, wasmtime_wasi,
+};
+use moonramp_lunar::gateway::{
+    BitcoinCashGatewayRequest, BitcoinCashGatewayResponse, BitcoinCashUnspent,
+};
+
+use crate::linker::add_gateway_func;
+
+/// BCHN (or any node exposing `scantxoutset`) JSON-RPC endpoint
+#[derive(Debug, Clone)]
+pub struct BitcoinCashRpcConfig {
+    pub endpoint: String,
+    pub basic_auth: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct JsonRpcOneDotZeroResult<T> {
+    id: String,
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+impl<T> JsonRpcOneDotZeroResult<T> {
+    fn inner(self) -> anyhow::Result<T> {
+        match (self.result, self.error) {
+            (Some(res), None) => Ok(res),
+            (None, Some(err)) => Err(anyhow!(err.to_string())),
+            _ => Err(anyhow!("Invalid Response")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct ScanTxOutUnspent {
+    txid: String,
+    vout: u32,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    amount: Amount,
+    height: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct ScanTxOutResult {
+    height: u64,
+    unspents: Vec<ScanTxOutUnspent>,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    total_amount: Amount,
+}
+
+impl From<ScanTxOutResult> for BitcoinCashGatewayResponse {
+    fn from(res: ScanTxOutResult) -> BitcoinCashGatewayResponse {
+        BitcoinCashGatewayResponse::ScanAddr {
+            height: res.height,
+            total_amount: res.total_amount.as_sat(),
+            unspents: res
+                .unspents
+                .into_iter()
+                .map(|unspent| BitcoinCashUnspent {
+                    txid: unspent.txid,
+                    vout: unspent.vout,
+                    amount: unspent.amount.as_sat(),
+                    height: unspent.height,
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn add_to_linker(
+    config: BitcoinCashRpcConfig,
+    linker: &mut Linker<WasiCtx>,
+) -> anyhow::Result<()> {
+    add_gateway_func(
+        linker,
+        "bitcoin_cash_gateway",
+        config,
+        |config, req| async move { gateway_request(&config, req).await },
+    )
+}
+
+async fn gateway_request(
+    config: &BitcoinCashRpcConfig,
+    req: BitcoinCashGatewayRequest,
+) -> anyhow::Result<BitcoinCashGatewayResponse> {
+    match req {
+        BitcoinCashGatewayRequest::ScanAddr { address } => {
+            let res: JsonRpcOneDotZeroResult<ScanTxOutResult> = json_rpc_request(
+                config,
+                "scantxoutset",
+                json!({
+                    "action": "start",
+                    "scanobjects": [format!("addr({})", address)],
+                }),
+            )
+            .await?;
+            Ok(res.inner()?.into())
+        }
+    }
+}
+
+async fn json_rpc_request<T: for<'a> serde::de::Deserialize<'a>>(
+    config: &BitcoinCashRpcConfig,
+    method: &str,
+    params: serde_json::Value,
+) -> anyhow::Result<T> {
+    let id = Uuid::new_v4().to_simple().to_string();
+    let json_rpc = json!({
+        "jsonrpc": "1.0",
+        "method": method,
+        "params": params,
+        "id": id,
+    });
+    trace!("REQUEST {}", json_rpc);
+
+    let json_bytes = serde_json::to_vec(&json_rpc)?;
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(&config.endpoint)
+        .header(
+            AUTHORIZATION,
+            format!(
+                "Basic {}",
+                config.basic_auth.as_ref().unwrap_or(&"".to_string())
+            ),
+        )
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(json_bytes))?;
+
+    let res = Client::new().request(req).await?;
+    trace!("RESPONSE {}", res.status());
+    Ok(serde_json::from_slice(
+        &hyper::body::to_bytes(res.into_body()).await?,
+    )?)
+}
@@ -0,0 +1,253 @@
+use anyhow::anyhow;
+use hyper::{http::header::CONTENT_TYPE, Body, Client, Method, Request};
+use log::trace;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use wasmtime::Linker;
+use wasmtime_wasi::WasiCtx;
+
+use moonramp_core::{anyhow, hyper, log, serde, serde_json, uuid, wasmtime, wasmtime_wasi};
+use moonramp_lunar::gateway::{
+    parse_quantity, EthereumGatewayRequest, EthereumGatewayResponse, TransferLog,
+};
+
+use crate::linker::add_gateway_func;
+
+/// keccak256("Transfer(address,address,uint256)")
+pub const ERC20_TRANSFER_TOPIC: &str =
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+#[derive(Debug, Clone)]
+pub struct EthereumRpcConfig {
+    pub endpoint: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct JsonRpcTwoDotZeroResult<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+impl<T> JsonRpcTwoDotZeroResult<T> {
+    fn inner(self) -> anyhow::Result<T> {
+        match (self.result, self.error) {
+            (Some(res), None) => Ok(res),
+            (None, Some(err)) => Err(anyhow!(err.to_string())),
+            _ => Err(anyhow!("Invalid Response")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+struct Log {
+    transaction_hash: String,
+    block_number: String,
+    topics: Vec<String>,
+    data: String,
+}
+
+impl TryFrom<Log> for TransferLog {
+    type Error = anyhow::Error;
+
+    fn try_from(log: Log) -> anyhow::Result<TransferLog> {
+        match (log.topics.get(1), log.topics.get(2)) {
+            (Some(from), Some(to)) => Ok(TransferLog {
+                transaction_hash: log.transaction_hash,
+                block_number: parse_quantity(&log.block_number)
+                    .map_err(|err| anyhow!(err.to_string()))? as u64,
+                from: topic_to_address(from),
+                to: topic_to_address(to),
+                value: log.data,
+            }),
+            _ => Err(anyhow!("Invalid Transfer log")),
+        }
+    }
+}
+
+fn topic_to_address(topic: &str) -> String {
+    let topic = topic.trim_start_matches("0x");
+    format!("0x{}", &topic[topic.len().saturating_sub(40)..])
+}
+
+fn address_to_topic(address: &str) -> String {
+    format!("0x{:0>64}", address.trim_start_matches("0x").to_lowercase())
+}
+
+fn block_tag(block: Option<u64>, default: &str) -> String {
+    block
+        .map(|block| format!("0x{:x}", block))
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// `eth_getLogs` filter for ERC-20 transfers of `contract` tokens to `to` since `from_block`
+fn transfer_logs_filter(contract: &str, to: &str, from_block: Option<u64>) -> serde_json::Value {
+    json!({
+        "address": contract,
+        "fromBlock": block_tag(from_block, "earliest"),
+        "toBlock": "latest",
+        "topics": [ERC20_TRANSFER_TOPIC, null, address_to_topic(to)],
+    })
+}
+
+pub fn add_to_linker(
+    config: EthereumRpcConfig,
+    linker: &mut Linker<WasiCtx>,
+) -> anyhow::Result<()> {
+    add_gateway_func(
+        linker,
+        "ethereum_gateway",
+        config,
+        |config, req| async move { gateway_request(&config, req).await },
+    )
+}
+
+async fn gateway_request(
+    config: &EthereumRpcConfig,
+    req: EthereumGatewayRequest,
+) -> anyhow::Result<EthereumGatewayResponse> {
+    match req {
+        EthereumGatewayRequest::BlockNumber => {
+            let res: JsonRpcTwoDotZeroResult<String> =
+                json_rpc_request(config, "eth_blockNumber", json!([])).await?;
+            let block_number = parse_quantity(&res.inner()?).map_err(|err| anyhow!(err))?;
+            Ok(EthereumGatewayResponse::BlockNumber(block_number as u64))
+        }
+        EthereumGatewayRequest::Balance { address, block } => {
+            let res: JsonRpcTwoDotZeroResult<String> = json_rpc_request(
+                config,
+                "eth_getBalance",
+                json!([address, block_tag(block, "latest")]),
+            )
+            .await?;
+            Ok(EthereumGatewayResponse::Balance(res.inner()?))
+        }
+        EthereumGatewayRequest::TransferLogs {
+            contract,
+            to,
+            from_block,
+        } => {
+            let res: JsonRpcTwoDotZeroResult<Vec<Log>> = json_rpc_request(
+                config,
+                "eth_getLogs",
+                json!([transfer_logs_filter(&contract, &to, from_block)]),
+            )
+            .await?;
+            Ok(EthereumGatewayResponse::TransferLogs(
+                res.inner()?
+                    .into_iter()
+                    .map(TransferLog::try_from)
+                    .collect::<anyhow::Result<Vec<TransferLog>>>()?,
+            ))
+        }
+    }
+}
+
+async fn json_rpc_request<T: for<'a> serde::de::Deserialize<'a>>(
+    config: &EthereumRpcConfig,
+    method: &str,
+    params: serde_json::Value,
+) -> anyhow::Result<T> {
+    let id = Uuid::new_v4().to_simple().to_string();
+    let json_rpc = json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": id,
+    });
+    trace!("REQUEST {}", json_rpc);
+
+    let json_bytes = serde_json::to_vec(&json_rpc)?;
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(&config.endpoint)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(json_bytes))?;
+
+    let res = Client::new().request(req).await?;
+    trace!("RESPONSE {}", res.status());
+    Ok(serde_json::from_slice(
+        &hyper::body::to_bytes(res.into_body()).await?,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const TO: &str = "0x52908400098527886E0F7030069857D2E4169EE7";
+
+    #[test]
+    fn test_address_topic_round_trip() {
+        let topic = address_to_topic(TO);
+        assert_eq!(
+            topic,
+            "0x00000000000000000000000052908400098527886e0f7030069857d2e4169ee7"
+        );
+        assert_eq!(topic_to_address(&topic), TO.to_lowercase());
+    }
+
+    #[test]
+    fn test_block_tag() {
+        assert_eq!(block_tag(Some(16_000_000), "earliest"), "0xf42400");
+        assert_eq!(block_tag(Some(0), "latest"), "0x0");
+        assert_eq!(block_tag(None, "earliest"), "earliest");
+    }
+
+    #[test]
+    fn test_transfer_logs_filter() {
+        assert_eq!(
+            transfer_logs_filter(USDC, TO, Some(16_000_000)),
+            json!({
+                "address": USDC,
+                "fromBlock": "0xf42400",
+                "toBlock": "latest",
+                "topics": [
+                    ERC20_TRANSFER_TOPIC,
+                    null,
+                    "0x00000000000000000000000052908400098527886e0f7030069857d2e4169ee7",
+                ],
+            })
+        );
+        assert_eq!(
+            transfer_logs_filter(USDC, TO, None)["fromBlock"],
+            json!("earliest")
+        );
+    }
+
+    #[test]
+    fn test_transfer_log_try_from() {
+        let log: Log = serde_json::from_value(json!({
+            "transactionHash": "0x9a8d2b7c",
+            "blockNumber": "0xf42401",
+            "topics": [
+                ERC20_TRANSFER_TOPIC,
+                "0x000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43",
+                "0x00000000000000000000000052908400098527886e0f7030069857d2e4169ee7",
+            ],
+            "data": "0x00000000000000000000000000000000000000000000000000000000017d7840",
+        }))
+        .expect("Invalid Log");
+
+        let transfer = TransferLog::try_from(log).expect("Invalid TransferLog");
+        assert_eq!(transfer.transaction_hash, "0x9a8d2b7c");
+        assert_eq!(transfer.block_number, 16_000_001);
+        assert_eq!(transfer.from, "0xa9d1e08c7793af67e9d92fe308d5697fb81d3e43");
+        assert_eq!(transfer.to, TO.to_lowercase());
+        assert_eq!(transfer.value().expect("Invalid value"), 25_000_000);
+    }
+
+    #[test]
+    fn test_transfer_log_try_from_not_ok() {
+        let log = Log {
+            transaction_hash: "0x9a8d2b7c".to_string(),
+            block_number: "0xf42401".to_string(),
+            topics: vec![ERC20_TRANSFER_TOPIC.to_string()],
+            data: "0x0".to_string(),
+        };
+        assert!(TransferLog::try_from(log).is_err());
+    }
+}
@@ -1,4 +1,44 @@
+use wasmtime::Linker;
+use wasmtime_wasi::WasiCtx;
+
+use moonramp_core::{anyhow, wasmtime, wasmtime_wasi};
+
 #[cfg(feature = "bitcoin")]
 pub mod bitcoin;
+#[cfg(feature = "bitcoin-cash")]
+pub mod bitcoin_cash;
+#[cfg(feature = "ethereum")]
+pub mod ethereum;
+#[cfg(feature = "lightning")]
+pub mod lightning;
+mod linker;
 #[cfg(feature = "monero")]
 pub mod monero;
+
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    #[cfg(feature = "bitcoin")]
+    pub bitcoin: bitcoin::BitcoinRpcConfig,
+    #[cfg(feature = "bitcoin-cash")]
+    pub bitcoin_cash: bitcoin_cash::BitcoinCashRpcConfig,
+    #[cfg(feature = "ethereum")]
+    pub ethereum: ethereum::EthereumRpcConfig,
+    #[cfg(feature = "lightning")]
+    pub lightning: lightning::LightningRpcConfig,
+    #[cfg(feature = "monero")]
+    pub monero: monero::MoneroRpcConfig,
+}
+
+pub fn add_to_linker(config: GatewayConfig, linker: &mut Linker<WasiCtx>) -> anyhow::Result<()> {
+    #[cfg(feature = "bitcoin")]
+    bitcoin::add_to_linker(config.bitcoin, linker)?;
+    #[cfg(feature = "bitcoin-cash")]
+    bitcoin_cash::add_to_linker(config.bitcoin_cash, linker)?;
+    #[cfg(feature = "ethereum")]
+    ethereum::add_to_linker(config.ethereum, linker)?;
+    #[cfg(feature = "lightning")]
+    lightning::add_to_linker(config.lightning, linker)?;
+    #[cfg(feature = "monero")]
+    monero::add_to_linker(config.monero, linker)?;
+    Ok(())
+}
@@ -0,0 +1,606 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bitcoin::hashes::{hex::FromHex, sha256, Hash};
+use hyper::{http::header::CONTENT_TYPE, Body, Client, Method, Request};
+use log::trace;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use wasmtime::Linker;
+use wasmtime_wasi::WasiCtx;
+
+use moonramp_core::{
+    anyhow, async_trait, base64, bitcoin, hyper, log, serde, serde_json, uuid, wasmtime,
+    wasmtime_wasi,
+};
+pub use moonramp_lunar::gateway::{
+    LightningGatewayRequest, LightningGatewayResponse, LightningInvoice, LightningInvoiceState,
+    LightningInvoiceStatus,
+};
+
+use crate::linker::add_gateway_func;
+
+/// A Lightning node that issues BOLT11 invoices and reports their settlement
+#[async_trait]
+pub trait LightningBackend: fmt::Debug + Send + Sync {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        memo: String,
+        expiry: u64,
+    ) -> anyhow::Result<LightningInvoice>;
+
+    async fn lookup_invoice(&self, payment_hash: &str) -> anyhow::Result<LightningInvoiceStatus>;
+
+    /// Stops the node from accepting payments for an open invoice, canceling an invoice that
+    /// is already canceled or expired succeeds and a settled one is an error
+    async fn cancel_invoice(&self, payment_hash: &str) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct LightningRpcConfig {
+    pub backend: Arc<dyn LightningBackend>,
+}
+
+impl LightningRpcConfig {
+    pub fn new<B: LightningBackend + 'static>(backend: B) -> Self {
+        LightningRpcConfig {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Config for nodes without a Lightning backend, every request fails
+    pub fn disabled() -> Self {
+        LightningRpcConfig::new(DisabledBackend)
+    }
+}
+
+#[derive(Debug)]
+struct DisabledBackend;
+
+#[async_trait]
+impl LightningBackend for DisabledBackend {
+    async fn create_invoice(&self, _: u64, _: String, _: u64) -> anyhow::Result<LightningInvoice> {
+        Err(anyhow!("Lightning backend not configured"))
+    }
+
+    async fn lookup_invoice(&self, _: &str) -> anyhow::Result<LightningInvoiceStatus> {
+        Err(anyhow!("Lightning backend not configured"))
+    }
+
+    async fn cancel_invoice(&self, _: &str) -> anyhow::Result<()> {
+        Err(anyhow!("Lightning backend not configured"))
+    }
+}
+
+/// LND REST API (`lnd --no-rest-tls` or a local TLS terminating proxy)
+pub struct LndRestBackend {
+    endpoint: String,
+    macaroon: String,
+}
+
+impl LndRestBackend {
+    /// `macaroon` is the hex encoded invoice macaroon
+    pub fn new(endpoint: String, macaroon: String) -> Self {
+        LndRestBackend { endpoint, macaroon }
+    }
+}
+
+impl fmt::Debug for LndRestBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LndRestBackend")
+            .field("endpoint", &self.endpoint)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct LndAddInvoiceResponse {
+    r_hash: String,
+    payment_request: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct LndInvoice {
+    state: String,
+    #[serde(default, deserialize_with = "u64_from_str_or_num")]
+    amt_paid_msat: u64,
+}
+
+impl TryFrom<LndInvoice> for LightningInvoiceStatus {
+    type Error = anyhow::Error;
+    fn try_from(invoice: LndInvoice) -> anyhow::Result<LightningInvoiceStatus> {
+        let state = match invoice.state.as_str() {
+            "OPEN" => LightningInvoiceState::Open,
+            "ACCEPTED" => LightningInvoiceState::Accepted,
+            "SETTLED" => LightningInvoiceState::Settled,
+            "CANCELED" => LightningInvoiceState::Canceled,
+            state => return Err(anyhow!("Unknown LND invoice state {}", state)),
+        };
+        Ok(LightningInvoiceStatus {
+            state,
+            amount_paid_msat: invoice.amt_paid_msat,
+        })
+    }
+}
+
+#[async_trait]
+impl LightningBackend for LndRestBackend {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        memo: String,
+        expiry: u64,
+    ) -> anyhow::Result<LightningInvoice> {
+        let res: LndAddInvoiceResponse = rest_request(
+            Method::POST,
+            &format!("{}/v1/invoices", self.endpoint.trim_end_matches('/')),
+            ("Grpc-Metadata-macaroon", &self.macaroon),
+            Some(json!({
+                // int64 fields are strings in LND's JSON mapping
+                "value_msat": amount_msat.to_string(),
+                "memo": memo,
+                "expiry": expiry.to_string(),
+            })),
+        )
+        .await?;
+        Ok(LightningInvoice {
+            payment_hash: to_hex(&base64::decode(&res.r_hash)?),
+            payment_request: res.payment_request,
+        })
+    }
+
+    async fn lookup_invoice(&self, payment_hash: &str) -> anyhow::Result<LightningInvoiceStatus> {
+        let res: LndInvoice = rest_request(
+            Method::GET,
+            &format!(
+                "{}/v1/invoice/{}",
+                self.endpoint.trim_end_matches('/'),
+                payment_hash
+            ),
+            ("Grpc-Metadata-macaroon", &self.macaroon),
+            None,
+        )
+        .await?;
+        res.try_into()
+    }
+
+    async fn cancel_invoice(&self, payment_hash: &str) -> anyhow::Result<()> {
+        let _: serde_json::Value = rest_request(
+            Method::POST,
+            &format!("{}/v2/invoices/cancel", self.endpoint.trim_end_matches('/')),
+            ("Grpc-Metadata-macaroon", &self.macaroon),
+            Some(lnd_cancel_invoice_body(payment_hash)?),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Bytes fields are base64 in LND's JSON mapping
+fn lnd_cancel_invoice_body(payment_hash: &str) -> anyhow::Result<serde_json::Value> {
+    Ok(json!({
+        "payment_hash": base64::encode(Vec::<u8>::from_hex(payment_hash)?),
+    }))
+}
+
+/// Core Lightning `clnrest` API (`clnrest-protocol=http` or a local TLS terminating proxy)
+pub struct ClnRestBackend {
+    endpoint: String,
+    rune: String,
+}
+
+impl ClnRestBackend {
+    pub fn new(endpoint: String, rune: String) -> Self {
+        ClnRestBackend { endpoint, rune }
+    }
+}
+
+impl fmt::Debug for ClnRestBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClnRestBackend")
+            .field("endpoint", &self.endpoint)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct ClnInvoiceResponse {
+    payment_hash: String,
+    bolt11: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct ClnListInvoicesResponse {
+    invoices: Vec<ClnInvoice>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct ClnInvoice {
+    #[serde(default)]
+    label: String,
+    status: String,
+    #[serde(default, deserialize_with = "u64_from_str_or_num")]
+    amount_received_msat: u64,
+}
+
+impl TryFrom<ClnInvoice> for LightningInvoiceStatus {
+    type Error = anyhow::Error;
+    fn try_from(invoice: ClnInvoice) -> anyhow::Result<LightningInvoiceStatus> {
+        let state = match invoice.status.as_str() {
+            "unpaid" => LightningInvoiceState::Open,
+            "paid" => LightningInvoiceState::Settled,
+            "expired" => LightningInvoiceState::Canceled,
+            status => return Err(anyhow!("Unknown CLN invoice status {}", status)),
+        };
+        Ok(LightningInvoiceStatus {
+            state,
+            amount_paid_msat: invoice.amount_received_msat,
+        })
+    }
+}
+
+#[async_trait]
+impl LightningBackend for ClnRestBackend {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        memo: String,
+        expiry: u64,
+    ) -> anyhow::Result<LightningInvoice> {
+        let res: ClnInvoiceResponse = rest_request(
+            Method::POST,
+            &format!("{}/v1/invoice", self.endpoint.trim_end_matches('/')),
+            ("Rune", &self.rune),
+            Some(json!({
+                "amount_msat": amount_msat,
+                "label": format!("moonramp-{}", Uuid::new_v4().to_simple()),
+                "description": memo,
+                "expiry": expiry,
+            })),
+        )
+        .await?;
+        Ok(LightningInvoice {
+            payment_hash: res.payment_hash,
+            payment_request: res.bolt11,
+        })
+    }
+
+    async fn lookup_invoice(&self, payment_hash: &str) -> anyhow::Result<LightningInvoiceStatus> {
+        let res: ClnListInvoicesResponse = rest_request(
+            Method::POST,
+            &format!("{}/v1/listinvoices", self.endpoint.trim_end_matches('/')),
+            ("Rune", &self.rune),
+            Some(json!({ "payment_hash": payment_hash })),
+        )
+        .await?;
+        res.invoices
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("Lightning invoice {} not found", payment_hash))?
+            .try_into()
+    }
+
+    async fn cancel_invoice(&self, payment_hash: &str) -> anyhow::Result<()> {
+        let res: ClnListInvoicesResponse = rest_request(
+            Method::POST,
+            &format!("{}/v1/listinvoices", self.endpoint.trim_end_matches('/')),
+            ("Rune", &self.rune),
+            Some(json!({ "payment_hash": payment_hash })),
+        )
+        .await?;
+        let invoice = res
+            .invoices
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("Lightning invoice {} not found", payment_hash))?;
+        match invoice.status.as_str() {
+            // CLN has no canceled state, deleting the unpaid invoice makes it reject payments
+            "unpaid" => {
+                let _: serde_json::Value = rest_request(
+                    Method::POST,
+                    &format!("{}/v1/delinvoice", self.endpoint.trim_end_matches('/')),
+                    ("Rune", &self.rune),
+                    Some(json!({ "label": invoice.label, "status": "unpaid" })),
+                )
+                .await?;
+                Ok(())
+            }
+            "expired" => Ok(()),
+            status => Err(anyhow!(
+                "Lightning invoice {} is {} and can't be canceled",
+                payment_hash,
+                status
+            )),
+        }
+    }
+}
+
+/// In memory backend for tests and local development, invoices settle only through `settle`
+#[derive(Debug, Default, Clone)]
+pub struct MockLightningBackend {
+    invoices: Arc<Mutex<HashMap<String, (u64, LightningInvoiceStatus)>>>,
+}
+
+impl MockLightningBackend {
+    pub fn settle(&self, payment_hash: &str) -> anyhow::Result<()> {
+        self.set_state(payment_hash, LightningInvoiceState::Settled)
+    }
+
+    pub fn cancel(&self, payment_hash: &str) -> anyhow::Result<()> {
+        self.set_state(payment_hash, LightningInvoiceState::Canceled)
+    }
+
+    fn set_state(&self, payment_hash: &str, state: LightningInvoiceState) -> anyhow::Result<()> {
+        let mut invoices = self
+            .invoices
+            .lock()
+            .map_err(|_| anyhow!("Mock lightning backend poisoned"))?;
+        let (amount_msat, status) = invoices
+            .get_mut(payment_hash)
+            .ok_or(anyhow!("Lightning invoice {} not found", payment_hash))?;
+        if state == LightningInvoiceState::Settled {
+            status.amount_paid_msat = *amount_msat;
+        }
+        status.state = state;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LightningBackend for MockLightningBackend {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        _memo: String,
+        _expiry: u64,
+    ) -> anyhow::Result<LightningInvoice> {
+        let preimage = Uuid::new_v4();
+        let payment_hash = sha256::Hash::hash(preimage.as_bytes()).to_string();
+        self.invoices
+            .lock()
+            .map_err(|_| anyhow!("Mock lightning backend poisoned"))?
+            .insert(
+                payment_hash.clone(),
+                (
+                    amount_msat,
+                    LightningInvoiceStatus {
+                        state: LightningInvoiceState::Open,
+                        amount_paid_msat: 0,
+                    },
+                ),
+            );
+        Ok(LightningInvoice {
+            payment_request: format!("lnmock{}n1{}", amount_msat, payment_hash),
+            payment_hash,
+        })
+    }
+
+    async fn lookup_invoice(&self, payment_hash: &str) -> anyhow::Result<LightningInvoiceStatus> {
+        self.invoices
+            .lock()
+            .map_err(|_| anyhow!("Mock lightning backend poisoned"))?
+            .get(payment_hash)
+            .map(|(_, status)| status.clone())
+            .ok_or(anyhow!("Lightning invoice {} not found", payment_hash))
+    }
+
+    async fn cancel_invoice(&self, payment_hash: &str) -> anyhow::Result<()> {
+        match self.lookup_invoice(payment_hash).await?.state {
+            LightningInvoiceState::Settled => Err(anyhow!(
+                "Lightning invoice {} is settled and can't be canceled",
+                payment_hash
+            )),
+            _ => self.cancel(payment_hash),
+        }
+    }
+}
+
+pub fn add_to_linker(
+    config: LightningRpcConfig,
+    linker: &mut Linker<WasiCtx>,
+) -> anyhow::Result<()> {
+    add_gateway_func(
+        linker,
+        "lightning_gateway",
+        config,
+        |config, req| async move { gateway_request(&config, req).await },
+    )
+}
+
+async fn gateway_request(
+    config: &LightningRpcConfig,
+    req: LightningGatewayRequest,
+) -> anyhow::Result<LightningGatewayResponse> {
+    match req {
+        LightningGatewayRequest::CreateInvoice {
+            amount_msat,
+            memo,
+            expiry,
+        } => Ok(LightningGatewayResponse::CreateInvoice(
+            config
+                .backend
+                .create_invoice(amount_msat, memo, expiry)
+                .await?,
+        )),
+        LightningGatewayRequest::LookupInvoice { payment_hash } => {
+            Ok(LightningGatewayResponse::LookupInvoice(
+                config.backend.lookup_invoice(&payment_hash).await?,
+            ))
+        }
+    }
+}
+
+async fn rest_request<T: for<'a> serde::de::Deserialize<'a>>(
+    method: Method,
+    url: &str,
+    (auth_header, auth): (&str, &str),
+    body: Option<serde_json::Value>,
+) -> anyhow::Result<T> {
+    trace!("REQUEST {} {}", method, url);
+    let req = Request::builder()
+        .method(method)
+        .uri(url)
+        .header(auth_header, auth)
+        .header(CONTENT_TYPE, "application/json");
+    let req = match body {
+        Some(body) => req.body(Body::from(serde_json::to_vec(&body)?))?,
+        None => req.body(Body::empty())?,
+    };
+
+    let res = Client::new().request(req).await?;
+    let status = res.status();
+    trace!("RESPONSE {}", status);
+    let bytes = hyper::body::to_bytes(res.into_body()).await?;
+    if !status.is_success() {
+        return Err(anyhow!(
+            "Lightning backend returned {} {}",
+            status,
+            String::from_utf8_lossy(&bytes)
+        ));
+    }
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn u64_from_str_or_num<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        serde_json::Value::Number(n) => n
+            .as_u64()
+            .ok_or_else(|| serde::de::Error::custom("Invalid msat amount")),
+        _ => Err(serde::de::Error::custom("Invalid msat amount")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use moonramp_core::tokio;
+
+    #[tokio::test]
+    async fn test_mock_backend() {
+        let mock = MockLightningBackend::default();
+        let config = LightningRpcConfig::new(mock.clone());
+
+        let invoice = match gateway_request(
+            &config,
+            LightningGatewayRequest::CreateInvoice {
+                amount_msat: 25_000_000,
+                memo: "test".to_string(),
+                expiry: 900,
+            },
+        )
+        .await
+        .expect("Invalid create")
+        {
+            LightningGatewayResponse::CreateInvoice(invoice) => invoice,
+            res => panic!("Unexpected response {:?}", res),
+        };
+        assert_eq!(invoice.payment_hash.len(), 64);
+
+        let status = config
+            .backend
+            .lookup_invoice(&invoice.payment_hash)
+            .await
+            .expect("Invalid lookup");
+        assert_eq!(status.state, LightningInvoiceState::Open);
+        assert_eq!(status.amount_paid_msat, 0);
+
+        mock.settle(&invoice.payment_hash).expect("Invalid settle");
+        let status = config
+            .backend
+            .lookup_invoice(&invoice.payment_hash)
+            .await
+            .expect("Invalid lookup");
+        assert_eq!(status.state, LightningInvoiceState::Settled);
+        assert_eq!(status.amount_paid_msat, 25_000_000);
+
+        assert!(config
+            .backend
+            .cancel_invoice(&invoice.payment_hash)
+            .await
+            .is_err());
+
+        let invoice = config
+            .backend
+            .create_invoice(1_000, "test".to_string(), 900)
+            .await
+            .expect("Invalid create");
+        for _ in 0..2 {
+            config
+                .backend
+                .cancel_invoice(&invoice.payment_hash)
+                .await
+                .expect("Invalid cancel");
+        }
+        let status = config
+            .backend
+            .lookup_invoice(&invoice.payment_hash)
+            .await
+            .expect("Invalid lookup");
+        assert_eq!(status.state, LightningInvoiceState::Canceled);
+
+        assert!(config.backend.lookup_invoice("00").await.is_err());
+        assert!(LightningRpcConfig::disabled()
+            .backend
+            .lookup_invoice(&invoice.payment_hash)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_invoice_status_parse() {
+        let lnd: LndInvoice =
+            serde_json::from_str(r#"{"state": "SETTLED", "amt_paid_msat": "25000000"}"#)
+                .expect("Invalid LND invoice");
+        assert_eq!(
+            LightningInvoiceStatus::try_from(lnd).ok(),
+            Some(LightningInvoiceStatus {
+                state: LightningInvoiceState::Settled,
+                amount_paid_msat: 25_000_000,
+            })
+        );
+
+        let cln: ClnInvoice =
+            serde_json::from_str(r#"{"status": "expired"}"#).expect("Invalid CLN invoice");
+        assert_eq!(
+            LightningInvoiceStatus::try_from(cln).ok(),
+            Some(LightningInvoiceStatus {
+                state: LightningInvoiceState::Canceled,
+                amount_paid_msat: 0,
+            })
+        );
+
+        let cln: ClnInvoice =
+            serde_json::from_str(r#"{"status": "refunded"}"#).expect("Invalid CLN invoice");
+        assert!(LightningInvoiceStatus::try_from(cln).is_err());
+    }
+
+    #[test]
+    fn test_lnd_cancel_invoice_body() {
+        assert_eq!(
+            lnd_cancel_invoice_body(
+                "0001020304050607080900010203040506070809000102030405060708090001"
+            )
+            .expect("Invalid body"),
+            json!({ "payment_hash": "AAECAwQFBgcICQABAgMEBQYHCAkAAQIDBAUGBwgJAAE=" })
+        );
+        assert!(lnd_cancel_invoice_body("not hex").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fmt, future::Future, io::Write};
+
+use anyhow::anyhow;
+use log::debug;
+use serde::{de::DeserializeOwned, Serialize};
+use wasmtime::{Extern, FuncType, Linker, Trap, Val, ValType};
+use wasmtime_wasi::WasiCtx;
+
+use moonramp_core::{anyhow, log, serde, serde_json, wasmtime, wasmtime_wasi};
+
+/// Defines the host function `env::<name>(req_ptr, req_len) -> res_ptr`. The program's JSON
+/// request is read from its memory and passed to `handler` with a clone of `config`, the JSON
+/// response is copied into a buffer allocated with the program's `lunar_allocate`
+pub fn add_gateway_func<C, Req, Res, F, Fut>(
+    linker: &mut Linker<WasiCtx>,
+    name: &'static str,
+    config: C,
+    handler: F,
+) -> anyhow::Result<()>
+where
+    C: Clone + Send + Sync + 'static,
+    Req: DeserializeOwned + Send + 'static,
+    Res: Serialize + fmt::Debug + Send + 'static,
+    F: Fn(C, Req) -> Fut + Copy + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<Res>> + Send + 'static,
+{
+    linker.func_new_async(
+        "env",
+        name,
+        FuncType::new([ValType::I32, ValType::I32], [ValType::I32]),
+        move |mut caller, params, returns| {
+            let config = config.clone();
+            Box::new(async move {
+                if let (Some(Val::I32(req_ptr)), Some(Val::I32(req_len))) =
+                    (params.first(), params.get(1))
+                {
+                    debug!("{}: 0x{:02X} {} bytes", name, req_ptr, req_len);
+                    let memory = match caller.get_export("memory") {
+                        Some(Extern::Memory(mem)) => mem,
+                        _ => return Err(Trap::new("failed to find memory")),
+                    };
+                    let data = memory
+                        .data(&caller)
+                        .get(*req_ptr as usize..)
+                        .and_then(|arr| arr.get(..*req_len as usize));
+                    let req: Req = match data {
+                        Some(data) => match serde_json::from_slice(data) {
+                            Ok(req) => req,
+                            Err(err) => {
+                                return Err(Trap::new(format!("Invalid {} request: {}", name, err)))
+                            }
+                        },
+                        None => return Err(Trap::new("pointer/length out of bounds")),
+                    };
+
+                    let res = handler(config, req).await.map_err(|err| {
+                        debug!("REQUEST ERROR {:?}", err);
+                        Trap::new(err.to_string())
+                    })?;
+
+                    debug!("RESPONSE {:?}", res);
+
+                    let lunar_alloc_fn = caller
+                        .get_export("lunar_allocate")
+                        .ok_or(anyhow!("lunar_allocate not found"))?
+                        .into_func()
+                        .ok_or(anyhow!("lunar_allocate not func"))?
+                        .typed::<i32, i32, _>(&mut caller)?;
+
+                    let res_json =
+                        serde_json::to_vec(&res).map_err(|err| Trap::new(err.to_string()))?;
+                    let res_json_ptr = lunar_alloc_fn
+                        .call_async(&mut caller, res_json.len() as i32)
+                        .await?;
+
+                    let data = memory
+                        .data_mut(&mut caller)
+                        .get_mut(res_json_ptr as usize..)
+                        .and_then(|arr| arr.get_mut(..res_json.len()));
+                    match data {
+                        Some(mut data) => {
+                            data.write(&res_json)
+                                .map_err(|err| Trap::new(err.to_string()))?;
+                        }
+                        None => return Err(Trap::new("pointer/length out of bounds")),
+                    }
+
+                    debug!("Res JSON Ptr 0x{:02X}", res_json_ptr);
+                    returns[0] = Val::I32(res_json_ptr);
+                    let wasi: &mut WasiCtx = caller.data_mut();
+                    wasi.table()
+                        .insert_at(res_json_ptr as u32, Box::new(res_json.len() as i32));
+                }
+                Ok(())
+            })
+        },
+    )?;
+
+    Ok(())
+}
@@ -1 +1,367 @@
+use std::sync::Arc;
 
+use anyhow::anyhow;
+use hyper::{http::header::CONTENT_TYPE, Body, Client, Method, Request};
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use wasmtime::Linker;
+use wasmtime_wasi::WasiCtx;
+
+use moonramp_core::{anyhow, hyper, log, serde, serde_json, tokio, uuid, wasmtime, wasmtime_wasi};
+use moonramp_lunar::gateway::{
+    MoneroGatewayRequest, MoneroGatewayResponse, MoneroTransfer, MoneroViewKeys,
+};
+
+use crate::linker::add_gateway_func;
+
+/// monero-wallet-rpc `json_rpc` endpoint, started with `--wallet-dir`. Every wallet gets its
+/// own view-only wallet file so merchants never see each other's transfers
+#[derive(Debug, Clone)]
+pub struct MoneroRpcConfig {
+    pub endpoint: String,
+    /// monero-wallet-rpc serves one open wallet at a time, requests hold this while they use it
+    open_wallet: Arc<Mutex<Option<String>>>,
+}
+
+impl MoneroRpcConfig {
+    pub fn new(endpoint: String) -> Self {
+        MoneroRpcConfig {
+            endpoint,
+            open_wallet: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct JsonRpcTwoDotZeroResult<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+impl<T> JsonRpcTwoDotZeroResult<T> {
+    fn inner(self) -> anyhow::Result<T> {
+        match (self.result, self.error) {
+            (Some(res), None) => Ok(res),
+            (None, Some(err)) => Err(anyhow!(err.to_string())),
+            _ => Err(anyhow!("Invalid Response")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct SubaddressIndex {
+    major: u32,
+    minor: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct GetAddressIndexResult {
+    index: SubaddressIndex,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct GetAccountsResult {
+    subaddress_accounts: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct GetAddressResult {
+    addresses: Vec<serde_json::Value>,
+}
+
+/// monero-wallet-rpc creates at most this many subaddresses per `create_address`
+const MAX_CREATE_ADDRESS_COUNT: u32 = 64;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct Transfer {
+    txid: String,
+    amount: u64,
+    #[serde(default)]
+    confirmations: u64,
+    height: u64,
+}
+
+impl From<Transfer> for MoneroTransfer {
+    fn from(transfer: Transfer) -> MoneroTransfer {
+        MoneroTransfer {
+            txid: transfer.txid,
+            amount: transfer.amount,
+            confirmations: transfer.confirmations,
+            height: transfer.height,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+struct GetTransfersResult {
+    #[serde(default, rename = "in")]
+    incoming: Vec<Transfer>,
+    #[serde(default)]
+    pool: Vec<Transfer>,
+}
+
+pub fn add_to_linker(config: MoneroRpcConfig, linker: &mut Linker<WasiCtx>) -> anyhow::Result<()> {
+    add_gateway_func(linker, "monero_gateway", config, |config, req| async move {
+        gateway_request(&config, req).await
+    })
+}
+
+async fn gateway_request(
+    config: &MoneroRpcConfig,
+    req: MoneroGatewayRequest,
+) -> anyhow::Result<MoneroGatewayResponse> {
+    match req {
+        MoneroGatewayRequest::IncomingTransfers { wallet, address } => {
+            let mut open_wallet = config.open_wallet.lock().await;
+            let filename = wallet_filename(&wallet.address);
+            if open_wallet.as_ref() != Some(&filename) {
+                *open_wallet = None;
+                use_wallet(config, &filename, &wallet).await?;
+                *open_wallet = Some(filename);
+            }
+            create_subaddresses(config, &wallet).await?;
+            let res: JsonRpcTwoDotZeroResult<serde_json::Value> =
+                json_rpc_request(config, "refresh", json!({})).await?;
+            res.inner()?;
+
+            // Fails for subaddresses of any other wallet
+            let res: JsonRpcTwoDotZeroResult<GetAddressIndexResult> =
+                json_rpc_request(config, "get_address_index", json!({ "address": address }))
+                    .await?;
+            let index = res.inner()?.index;
+
+            let res: JsonRpcTwoDotZeroResult<GetTransfersResult> =
+                json_rpc_request(config, "get_transfers", get_transfers_params(&index)).await?;
+            let transfers = res.inner()?;
+            Ok(MoneroGatewayResponse::IncomingTransfers(
+                transfers
+                    .incoming
+                    .into_iter()
+                    .chain(transfers.pool)
+                    .map(MoneroTransfer::from)
+                    .collect(),
+            ))
+        }
+    }
+}
+
+/// Wallet file of a wallet in monero-wallet-rpc's `--wallet-dir`
+fn wallet_filename(address: &str) -> String {
+    format!("moonramp-{}", address)
+}
+
+/// Opens the wallet file, restoring it from its view keys the first time the wallet is used
+async fn use_wallet(
+    config: &MoneroRpcConfig,
+    filename: &str,
+    wallet: &MoneroViewKeys,
+) -> anyhow::Result<()> {
+    let res: JsonRpcTwoDotZeroResult<serde_json::Value> = json_rpc_request(
+        config,
+        "open_wallet",
+        json!({ "filename": filename, "password": "" }),
+    )
+    .await?;
+    if let Err(open_err) = res.inner() {
+        debug!("Restoring {} ({})", filename, open_err);
+        let res: JsonRpcTwoDotZeroResult<serde_json::Value> = json_rpc_request(
+            config,
+            "generate_from_keys",
+            generate_from_keys_params(filename, wallet),
+        )
+        .await?;
+        res.inner()
+            .map_err(|err| anyhow!("Failed to restore {}: {}", filename, err))?;
+    }
+    Ok(())
+}
+
+/// monero-wallet-rpc only scans subaddresses within its lookahead (200 indexes) of the ones
+/// the wallet file created, creates them up to the last one MoonRamp handed out
+async fn create_subaddresses(
+    config: &MoneroRpcConfig,
+    wallet: &MoneroViewKeys,
+) -> anyhow::Result<()> {
+    let res: JsonRpcTwoDotZeroResult<GetAccountsResult> =
+        json_rpc_request(config, "get_accounts", json!({})).await?;
+    let accounts = res.inner()?.subaddress_accounts.len() as u32;
+    for _ in accounts..=wallet.account {
+        let res: JsonRpcTwoDotZeroResult<serde_json::Value> =
+            json_rpc_request(config, "create_account", json!({})).await?;
+        res.inner()?;
+    }
+
+    let res: JsonRpcTwoDotZeroResult<GetAddressResult> = json_rpc_request(
+        config,
+        "get_address",
+        json!({ "account_index": wallet.account }),
+    )
+    .await?;
+    let mut created = res.inner()?.addresses.len() as u32;
+    while let Some(count) = create_address_count(created, wallet.index) {
+        debug!("Creating {} subaddresses of {}", count, wallet.address);
+        let res: JsonRpcTwoDotZeroResult<serde_json::Value> = json_rpc_request(
+            config,
+            "create_address",
+            json!({ "account_index": wallet.account, "count": count }),
+        )
+        .await?;
+        res.inner()?;
+        created += count;
+    }
+    Ok(())
+}
+
+/// Subaddresses the next `create_address` creates so `index` is among the `created` ones
+fn create_address_count(created: u32, index: u32) -> Option<u32> {
+    if created > index {
+        None
+    } else {
+        Some((index - created + 1).min(MAX_CREATE_ADDRESS_COUNT))
+    }
+}
+
+fn generate_from_keys_params(filename: &str, wallet: &MoneroViewKeys) -> serde_json::Value {
+    json!({
+        "filename": filename,
+        "address": wallet.address,
+        "viewkey": wallet.view_key,
+        "password": "",
+        "restore_height": wallet.restore_height,
+        "autosave_current": true,
+    })
+}
+
+fn get_transfers_params(index: &SubaddressIndex) -> serde_json::Value {
+    json!({
+        "in": true,
+        "pool": true,
+        "account_index": index.major,
+        "subaddr_indices": [index.minor],
+    })
+}
+
+async fn json_rpc_request<T: for<'a> serde::de::Deserialize<'a>>(
+    config: &MoneroRpcConfig,
+    method: &str,
+    params: serde_json::Value,
+) -> anyhow::Result<T> {
+    let id = Uuid::new_v4().to_simple().to_string();
+    let json_rpc = json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": id,
+    });
+    trace!("REQUEST {}", json_rpc);
+
+    let json_bytes = serde_json::to_vec(&json_rpc)?;
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(&config.endpoint)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(json_bytes))?;
+
+    let res = Client::new().request(req).await?;
+    trace!("RESPONSE {}", res.status());
+    Ok(serde_json::from_slice(
+        &hyper::body::to_bytes(res.into_body()).await?,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "436cmaKNNvJQMBqWf3EaUCKJ7sJW4RkhJR1paum66s2ac8e8TrGYLwpiFsG66UAWkARupVhNkuiTweNehKn1x4Wa3zo7oFr";
+
+    #[test]
+    fn test_wallet_filename() {
+        assert_eq!(wallet_filename(ADDRESS), format!("moonramp-{}", ADDRESS));
+        assert_ne!(
+            wallet_filename(ADDRESS),
+            wallet_filename("89pMNxzcCo5LAPZDX4qaTeanA6ZiS3VRdUbeKHzbDZkD1Q3YsDDfmXbT2zyjLeHWuuN4vxKne8kNpjH3cMk7nmhwSALCxsd")
+        );
+    }
+
+    #[test]
+    fn test_generate_from_keys_params() {
+        let wallet = MoneroViewKeys {
+            address: ADDRESS.to_string(),
+            view_key: "e065c2bb784345ff500807bea4eda8a9512d974f3e7695d120d73c54045f6704"
+                .to_string(),
+            restore_height: 2750000,
+            account: 0,
+            index: 3,
+        };
+        let params = generate_from_keys_params(&wallet_filename(ADDRESS), &wallet);
+        assert_eq!(params["restore_height"], json!(2750000));
+        assert_eq!(params["filename"], json!(wallet_filename(ADDRESS)));
+        assert_eq!(params["address"], json!(ADDRESS));
+        assert_eq!(params["viewkey"], json!(wallet.view_key));
+        // View-only, the spend key never leaves MoonRamp
+        assert!(params.get("spendkey").is_none());
+    }
+
+    #[test]
+    fn test_create_address_count() {
+        // The primary address is index 0
+        assert_eq!(create_address_count(1, 0), None);
+        assert_eq!(create_address_count(5, 4), None);
+        assert_eq!(create_address_count(1, 1), Some(1));
+        assert_eq!(create_address_count(1, 300), Some(64));
+        assert_eq!(create_address_count(257, 300), Some(44));
+        assert_eq!(create_address_count(301, 300), None);
+    }
+
+    #[test]
+    fn test_get_transfers() {
+        assert_eq!(
+            get_transfers_params(&SubaddressIndex {
+                major: 2,
+                minor: 18
+            }),
+            json!({
+                "in": true,
+                "pool": true,
+                "account_index": 2,
+                "subaddr_indices": [18],
+            })
+        );
+
+        let res: JsonRpcTwoDotZeroResult<GetTransfersResult> = serde_json::from_value(json!({
+            "id": "0",
+            "jsonrpc": "2.0",
+            "result": {
+                "in": [{"txid": "aa", "amount": 250000000000u64, "confirmations": 12, "height": 2750000}],
+                "pool": [{"txid": "bb", "amount": 1000000000000u64, "height": 0}],
+            },
+        }))
+        .expect("Invalid GetTransfersResult");
+        let transfers = res.inner().expect("Invalid GetTransfersResult");
+        assert_eq!(transfers.incoming.len(), 1);
+        assert_eq!(transfers.pool.len(), 1);
+        let pool = MoneroTransfer::from(transfers.pool.into_iter().next().expect("No transfer"));
+        assert_eq!(pool.txid, "bb");
+        assert_eq!(pool.amount, 1_000_000_000_000);
+        assert_eq!(pool.confirmations, 0);
+
+        let res: JsonRpcTwoDotZeroResult<GetTransfersResult> = serde_json::from_value(json!({
+            "id": "0",
+            "jsonrpc": "2.0",
+            "error": {"code": -2, "message": "No wallet file"},
+        }))
+        .expect("Invalid error");
+        assert!(res.inner().is_err());
+    }
+}
@@ -9,6 +9,20 @@ mod m20220330_000006_create_programs_table;
 mod m20220330_000007_create_wallets_table;
 mod m20220504_000008_create_invoices_table;
 mod m20220504_000009_create_sales_table;
+mod m20221017_000010_add_invoices_contract_column;
+mod m20221101_000011_add_wallets_status_column;
+mod m20221108_000012_add_invoices_address_unique_index;
+mod m20221115_000013_add_invoices_payment_hash_column;
+mod m20221122_000014_update_xmr_wallets_network;
+mod m20221129_000015_add_invoices_rate_quote_columns;
+mod m20221206_000016_convert_amount_columns_to_exact;
+mod m20221213_000017_add_invoices_late_paid_at_column;
+mod m20221220_000018_add_invoices_cancel_columns;
+mod m20221227_000019_create_async_sales_table;
+mod m20230103_000020_create_webhooks_table;
+mod m20230103_000021_create_webhook_deliveries_table;
+mod m20230103_000022_create_checkouts_table;
+mod m20230110_000023_add_invoices_start_height_column;
 
 pub struct Migrator;
 
@@ -25,6 +39,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20220330_000007_create_wallets_table::Migration),
             Box::new(m20220504_000008_create_invoices_table::Migration),
             Box::new(m20220504_000009_create_sales_table::Migration),
+            Box::new(m20221017_000010_add_invoices_contract_column::Migration),
+            Box::new(m20221101_000011_add_wallets_status_column::Migration),
+            Box::new(m20221108_000012_add_invoices_address_unique_index::Migration),
+            Box::new(m20221115_000013_add_invoices_payment_hash_column::Migration),
+            Box::new(m20221122_000014_update_xmr_wallets_network::Migration),
+            Box::new(m20221129_000015_add_invoices_rate_quote_columns::Migration),
+            Box::new(m20221206_000016_convert_amount_columns_to_exact::Migration),
+            Box::new(m20221213_000017_add_invoices_late_paid_at_column::Migration),
+            Box::new(m20221220_000018_add_invoices_cancel_columns::Migration),
+            Box::new(m20221227_000019_create_async_sales_table::Migration),
+            Box::new(m20230103_000020_create_webhooks_table::Migration),
+            Box::new(m20230103_000021_create_webhook_deliveries_table::Migration),
+            Box::new(m20230103_000022_create_checkouts_table::Migration),
+            Box::new(m20230110_000023_add_invoices_start_height_column::Migration),
         ]
     }
 }
@@ -53,7 +81,7 @@ pub mod testing {
     };
     use moonramp_entity::{api_token, cipher::Cipher, key_encryption_key, merchant, role};
 
-    async fn add_role(
+    pub async fn add_role(
         database: &DatabaseConnection,
         merchant_hash: Hash,
         token_hash: Hash,
@@ -0,0 +1,44 @@
+use moonramp_core::sea_orm;
+use moonramp_entity::invoice::*;
+use sea_orm::EntityName;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221017_000010_add_invoices_contract_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fresh databases create the column from the entity in m20220504_000008
+        if manager
+            .has_column(Entity.table_name(), Column::Contract.to_string())
+            .await?
+        {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .add_column(ColumnDef::new(Column::Contract).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .drop_column(Column::Contract)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,49 @@
+use moonramp_core::sea_orm;
+use moonramp_entity::wallet::*;
+use sea_orm::EntityName;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221101_000011_add_wallets_status_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fresh databases create the column from the entity in m20220330_000007
+        if manager
+            .has_column(Entity.table_name(), Column::WalletStatus.to_string())
+            .await?
+        {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .add_column(
+                        ColumnDef::new(Column::WalletStatus)
+                            .string()
+                            .not_null()
+                            .default("Active"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .drop_column(Column::WalletStatus)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,88 @@
+use moonramp_core::sea_orm;
+use sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+const INDEX_NAME: &str = "idx-invoices-wallet_hash-address-open";
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221108_000012_add_invoices_address_unique_index"
+    }
+}
+
+/// Wallet and address pairs shared by more than one open invoice, the index can't be created
+/// while any exist
+async fn open_duplicates(manager: &SchemaManager<'_>) -> Result<Vec<String>, DbErr> {
+    let backend = manager.get_database_backend();
+    let rows = manager
+        .get_connection()
+        .query_all(Statement::from_string(
+            backend,
+            r#"SELECT "wallet_hash", "address", COUNT(*) AS "open_invoices" FROM "invoices" WHERE "invoice_status" IN ('Pending', 'Funded') GROUP BY "wallet_hash", "address" HAVING COUNT(*) > 1"#
+                .to_string(),
+        ))
+        .await?;
+    rows.into_iter()
+        .map(|row| {
+            let wallet_hash: String = row.try_get("", "wallet_hash")?;
+            let address: String = row.try_get("", "address")?;
+            let open_invoices: i64 = row.try_get("", "open_invoices")?;
+            Ok(format!(
+                "wallet {} address {} ({} invoices)",
+                wallet_hash, address, open_invoices
+            ))
+        })
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // An address may only belong to one Pending or Funded invoice per wallet.
+        // MySQL has no partial indexes and a unique index over every invoice would also stop
+        // the address of an expired or canceled invoice from being handed out again. There
+        // sale.invoice's own check, made while it holds the wallet's row lock, is the only guard
+        let backend = manager.get_database_backend();
+        if backend == DbBackend::MySql {
+            return Ok(());
+        }
+
+        // Invoices created before sale.invoice checked addresses may already share one. Which
+        // of them the payment belongs to is for the operator to decide, not the migration
+        let duplicates = open_duplicates(manager).await?;
+        if !duplicates.is_empty() {
+            return Err(DbErr::Migration(format!(
+                "Open invoices share addresses: {}. Capture or cancel (`moonrampctl sale cancel`) the extra ones until one open invoice is left per address and run the migration again",
+                duplicates.join(", ")
+            )));
+        }
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                backend,
+                format!(
+                    r#"CREATE UNIQUE INDEX IF NOT EXISTS "{}" ON "invoices" ("wallet_hash", "address") WHERE "invoice_status" IN ('Pending', 'Funded')"#,
+                    INDEX_NAME
+                ),
+            ))
+            .await
+            .map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let backend = manager.get_database_backend();
+        if backend == DbBackend::MySql {
+            return Ok(());
+        }
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                backend,
+                format!(r#"DROP INDEX IF EXISTS "{}""#, INDEX_NAME),
+            ))
+            .await
+            .map(|_| ())
+    }
+}
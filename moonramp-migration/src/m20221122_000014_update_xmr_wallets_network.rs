@@ -0,0 +1,75 @@
+use moonramp_core::sea_orm;
+use moonramp_entity::{invoice, sale, wallet};
+use sea_orm::EntityTrait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221122_000014_update_xmr_wallets_network"
+    }
+}
+
+fn update_xmr_network<E: EntityTrait>(
+    entity: E,
+    ticker: E::Column,
+    network: E::Column,
+    from: &str,
+    to: &str,
+) -> UpdateStatement {
+    Query::update()
+        .table(entity)
+        .value(network, to.into())
+        .and_where(Expr::col(ticker).eq("Monero"))
+        .and_where(Expr::col(network).eq(from))
+        .to_owned()
+}
+
+/// Relabels the wallets along with the invoices and sales made with them
+async fn update_xmr_networks(
+    manager: &SchemaManager<'_>,
+    from: &str,
+    to: &str,
+) -> Result<(), DbErr> {
+    for stmt in [
+        update_xmr_network(
+            wallet::Entity,
+            wallet::Column::Ticker,
+            wallet::Column::Network,
+            from,
+            to,
+        ),
+        update_xmr_network(
+            invoice::Entity,
+            invoice::Column::Ticker,
+            invoice::Column::Network,
+            from,
+            to,
+        ),
+        update_xmr_network(
+            sale::Entity,
+            sale::Column::Ticker,
+            sale::Column::Network,
+            from,
+            to,
+        ),
+    ] {
+        manager.exec_stmt(stmt).await?;
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Monero Testnet wallets were derived on stagenet before Network::Stagenet existed.
+        // Their encrypted blobs still say Testnet, MoneroWallet reads blobs without a
+        // network_version as stagenet and they are rewritten the next time they are saved
+        update_xmr_networks(manager, "Testnet", "Stagenet").await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        update_xmr_networks(manager, "Stagenet", "Testnet").await
+    }
+}
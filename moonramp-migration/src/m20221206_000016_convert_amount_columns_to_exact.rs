@@ -0,0 +1,266 @@
+use moonramp_core::{sea_orm, Amount};
+use sea_orm::{ConnectionTrait, DbBackend, QueryResult, Statement, Value};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221206_000016_convert_amount_columns_to_exact"
+    }
+}
+
+/// Decimals of the `currency` column values, frozen at the time of this migration
+fn currency_decimals(currency: &str) -> Result<u32, DbErr> {
+    match currency {
+        "Bitcoin Cash" | "Bitcoin" => Ok(8),
+        "Bitcoin Lightning" => Ok(11),
+        "Ethereum Classic" | "Ethereum" | "Pax Dollar" => Ok(18),
+        "USD Tether" | "USD Coin" => Ok(6),
+        "Monero" => Ok(12),
+        _ => Err(DbErr::Migration(format!("Unknown currency {}", currency))),
+    }
+}
+
+/// `val` as an exact decimal string. The shortest representation of the f64 is what was
+/// originally sent, it is padded to `decimals` or rounded if float noise exceeds them
+fn exact_amount(val: f64, decimals: Option<u32>) -> Result<String, DbErr> {
+    let amount: Amount = val
+        .to_string()
+        .parse()
+        .map_err(|_| DbErr::Migration(format!("Invalid amount {}", val)))?;
+    Ok(match decimals {
+        Some(decimals) => amount
+            .rescale(decimals)
+            .map(|amount| amount.to_string())
+            .unwrap_or_else(|_| format!("{:.*}", decimals as usize, val)),
+        None => amount.to_string(),
+    })
+}
+
+/// The old columns are dropped with `ALTER TABLE .. DROP COLUMN`, which SQLite only has
+/// since 3.35. Checked up front so an older SQLite fails before any column is renamed
+async fn check_sqlite_version(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    let backend = manager.get_database_backend();
+    if backend != DbBackend::Sqlite {
+        return Ok(());
+    }
+    let version: String = manager
+        .get_connection()
+        .query_one(Statement::from_string(
+            backend,
+            "SELECT sqlite_version() AS version".to_string(),
+        ))
+        .await?
+        .ok_or(DbErr::Migration("Unknown SQLite version".to_string()))?
+        .try_get("", "version")?;
+    let mut parts = version
+        .split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let (major, minor) = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+    if (major, minor) < (3, 35) {
+        return Err(DbErr::Migration(format!(
+            "SQLite {} can't drop columns, 3.35 or newer is required",
+            version
+        )));
+    }
+    Ok(())
+}
+
+struct AmountColumn {
+    table: &'static str,
+    column: &'static str,
+    /// Crypto amounts are padded to the decimals of the row's currency, fiat amounts and
+    /// rates keep their shortest form
+    per_currency: bool,
+    nullable: bool,
+}
+
+const AMOUNT_COLUMNS: [AmountColumn; 4] = [
+    AmountColumn {
+        table: "invoices",
+        column: "amount",
+        per_currency: true,
+        nullable: false,
+    },
+    AmountColumn {
+        table: "invoices",
+        column: "fiat_amount",
+        per_currency: false,
+        nullable: true,
+    },
+    AmountColumn {
+        table: "invoices",
+        column: "rate",
+        per_currency: false,
+        nullable: true,
+    },
+    AmountColumn {
+        table: "sales",
+        column: "amount",
+        per_currency: true,
+        nullable: false,
+    },
+];
+
+impl AmountColumn {
+    fn column_def(&self, text: bool) -> ColumnDef {
+        let mut def = ColumnDef::new(Alias::new(self.column));
+        if text {
+            def.text();
+        } else {
+            def.double();
+        }
+        if !self.nullable {
+            // SQLite can only add NOT NULL columns with a default, rows are rewritten below
+            def.not_null();
+            if text {
+                def.default("0");
+            } else {
+                def.default(0.0);
+            }
+        }
+        def
+    }
+
+    /// Swaps the column for `new_column`, rewriting every row with `convert`.
+    /// The old column is renamed aside, SQLite can't modify a column's type
+    async fn replace<F>(
+        &self,
+        manager: &SchemaManager<'_>,
+        mut new_column: ColumnDef,
+        convert: F,
+    ) -> Result<(), DbErr>
+    where
+        F: Fn(&QueryResult, &str, &str) -> Result<Value, DbErr>,
+    {
+        let old_column = format!("{}_old", self.column);
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new(self.table))
+                    .rename_column(Alias::new(self.column), Alias::new(&old_column))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new(self.table))
+                    .add_column(&mut new_column)
+                    .to_owned(),
+            )
+            .await?;
+
+        let backend = manager.get_database_backend();
+        let rows = manager
+            .get_connection()
+            .query_all(
+                backend.build(
+                    &Query::select()
+                        .columns([
+                            Alias::new("hash"),
+                            Alias::new("currency"),
+                            Alias::new(&old_column),
+                        ])
+                        .from(Alias::new(self.table))
+                        .to_owned(),
+                ),
+            )
+            .await?;
+        for row in rows {
+            let hash: String = row.try_get("", "hash")?;
+            let currency: String = row.try_get("", "currency")?;
+            manager
+                .exec_stmt(
+                    Query::update()
+                        .table(Alias::new(self.table))
+                        .value(
+                            Alias::new(self.column),
+                            convert(&row, &old_column, &currency)?,
+                        )
+                        .and_where(Expr::col(Alias::new("hash")).eq(hash))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // sea-query's SQLite backend can't drop columns, SQLite itself can since 3.35
+        if backend == DbBackend::Sqlite {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    backend,
+                    format!(
+                        r#"ALTER TABLE "{}" DROP COLUMN "{}""#,
+                        self.table, old_column
+                    ),
+                ))
+                .await
+                .map(|_| ())
+        } else {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new(self.table))
+                        .drop_column(Alias::new(&old_column))
+                        .to_owned(),
+                )
+                .await
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // f64 amounts lose satoshis and piconeros, store them as exact decimal strings.
+        // `Amount` holds integer base units in memory, but the columns are TEXT rather than
+        // BIGINT: wei overflow an i64 past ~9.2 ETH, and fiat amounts and rates have no fixed
+        // decimals to count units in
+        check_sqlite_version(manager).await?;
+        for amount_column in AMOUNT_COLUMNS {
+            amount_column
+                .replace(
+                    manager,
+                    amount_column.column_def(true),
+                    |row, old_column, currency| {
+                        let val: Option<f64> = row.try_get("", old_column)?;
+                        let decimals = if amount_column.per_currency {
+                            Some(currency_decimals(currency)?)
+                        } else {
+                            None
+                        };
+                        Ok(match val {
+                            Some(val) => Value::from(exact_amount(val, decimals)?),
+                            None => Value::String(None),
+                        })
+                    },
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        check_sqlite_version(manager).await?;
+        for amount_column in AMOUNT_COLUMNS {
+            amount_column
+                .replace(
+                    manager,
+                    amount_column.column_def(false),
+                    |row, old_column, _| {
+                        let val: Option<String> = row.try_get("", old_column)?;
+                        Ok(match val {
+                            Some(val) => Value::from(val.parse::<f64>().map_err(|_| {
+                                DbErr::Migration(format!("Invalid amount {}", val))
+                            })?),
+                            None => Value::Double(None),
+                        })
+                    },
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
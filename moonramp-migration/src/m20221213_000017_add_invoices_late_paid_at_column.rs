@@ -0,0 +1,44 @@
+use moonramp_core::sea_orm;
+use moonramp_entity::invoice::*;
+use sea_orm::EntityName;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221213_000017_add_invoices_late_paid_at_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fresh databases create the column from the entity in m20220504_000008
+        if manager
+            .has_column(Entity.table_name(), Column::LatePaidAt.to_string())
+            .await?
+        {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .add_column(ColumnDef::new(Column::LatePaidAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .drop_column(Column::LatePaidAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,55 @@
+use moonramp_core::sea_orm;
+use moonramp_entity::invoice::*;
+use sea_orm::EntityName;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221220_000018_add_invoices_cancel_columns"
+    }
+}
+
+fn cancel_columns() -> Vec<ColumnDef> {
+    vec![
+        ColumnDef::new(Column::CanceledAt)
+            .timestamp_with_time_zone()
+            .to_owned(),
+        ColumnDef::new(Column::CancelReason).string().to_owned(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fresh databases create the columns from the entity in m20220504_000008.
+        // One column per statement, SQLite can't add several in one ALTER TABLE
+        for mut column in cancel_columns() {
+            if manager
+                .has_column(Entity.table_name(), column.get_column_name())
+                .await?
+            {
+                continue;
+            }
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Entity)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Column::CanceledAt, Column::CancelReason] {
+            manager
+                .alter_table(Table::alter().table(Entity).drop_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
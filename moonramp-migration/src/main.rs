@@ -67,6 +67,10 @@ enum Commands {
 
         #[clap(short = 'M', long)]
         master_key_encryption_key: String,
+
+        /// Also grants `WalletSecret` `Write`, allowing `wallet.export` of hot wallet secrets
+        #[clap(long)]
+        wallet_secret: bool,
     },
 }
 
@@ -125,6 +129,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Commands::CreateApiToken {
             merchant_hash,
             master_key_encryption_key,
+            wallet_secret,
         } => {
             let kek_custodian = {
                 let master_custodian = MasterKeyEncryptionKeyCustodian::new(
@@ -198,6 +203,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 role::Resource::Sale,
                 role::Resource::Wallet,
             ];
+            let mut roles = vec![];
             for r in resources {
                 let scopes = vec![
                     role::Scope::Execute,
@@ -206,24 +212,30 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     role::Scope::Write,
                 ];
                 for s in scopes {
-                    let mut hasher = Sha3_256::new();
-                    hasher.update(&merchant_hash);
-                    hasher.update(&t.hash);
-                    hasher.update(format!("{:?}", r).as_bytes());
-                    hasher.update(format!("{:?}", s).as_bytes());
-                    let role_hash = Hash::try_from(hasher.finalize().to_vec())?;
-                    role::ActiveModel {
-                        hash: Set(role_hash),
-                        merchant_hash: Set(merchant_hash.clone()),
-                        token_hash: Set(t.hash.clone()),
-                        resource: Set(r.clone()),
-                        scope: Set(s),
-                        api_group: Set(None),
-                        created_at: Set(Utc::now()),
-                    }
-                    .insert(&database)
-                    .await?;
+                    roles.push((r.clone(), s));
+                }
+            }
+            if wallet_secret {
+                roles.push((role::Resource::WalletSecret, role::Scope::Write));
+            }
+            for (r, s) in roles {
+                let mut hasher = Sha3_256::new();
+                hasher.update(&merchant_hash);
+                hasher.update(&t.hash);
+                hasher.update(format!("{:?}", r).as_bytes());
+                hasher.update(format!("{:?}", s).as_bytes());
+                let role_hash = Hash::try_from(hasher.finalize().to_vec())?;
+                role::ActiveModel {
+                    hash: Set(role_hash),
+                    merchant_hash: Set(merchant_hash.clone()),
+                    token_hash: Set(t.hash.clone()),
+                    resource: Set(r.clone()),
+                    scope: Set(s),
+                    api_group: Set(None),
+                    created_at: Set(Utc::now()),
                 }
+                .insert(&database)
+                .await?;
             }
             println!(
                 "{}",
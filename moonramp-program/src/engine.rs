@@ -7,7 +7,17 @@ use wasmtime::{Config, Engine, Extern, FuncType, Linker, Module, Store, Trap, Va
 use wasmtime_wasi::{tokio::WasiCtxBuilder, WasiCtx};
 
 use moonramp_core::{anyhow, log, serde_json, tokio, wasmtime, wasmtime_wasi};
-pub use moonramp_gateway::bitcoin::BitcoinRpcConfig;
+pub use moonramp_gateway::{
+    bitcoin::BitcoinRpcConfig,
+    bitcoin_cash::BitcoinCashRpcConfig,
+    ethereum::EthereumRpcConfig,
+    lightning::{
+        ClnRestBackend, LightningBackend, LightningInvoiceState, LightningRpcConfig,
+        LndRestBackend, MockLightningBackend,
+    },
+    monero::MoneroRpcConfig,
+    GatewayConfig,
+};
 
 const TABLE_EXIT_DATA: u32 = 10;
 
@@ -18,10 +28,7 @@ struct State {
 }
 
 impl State {
-    pub fn new(
-        wasm_mod_bytes: &[u8],
-        bitcoin_gateway_config: BitcoinRpcConfig,
-    ) -> anyhow::Result<Self> {
+    pub fn new(wasm_mod_bytes: &[u8], gateway_config: GatewayConfig) -> anyhow::Result<Self> {
         let config = State::config();
         let engine = Engine::new(&config)?;
         let module = unsafe { Module::deserialize(&engine, &wasm_mod_bytes)? };
@@ -89,7 +96,7 @@ impl State {
             },
         )?;
 
-        moonramp_gateway::bitcoin::add_to_linker(bitcoin_gateway_config, &mut linker)?;
+        moonramp_gateway::add_to_linker(gateway_config, &mut linker)?;
 
         Ok(State {
             engine,
@@ -120,9 +127,9 @@ impl Runtime {
         wasm_mod_bytes: &[u8],
         entry_data: moonramp_lunar::EntryData,
         timeout: Duration,
-        bitcoin_gateway_config: BitcoinRpcConfig,
+        gateway_config: GatewayConfig,
     ) -> anyhow::Result<moonramp_lunar::ExitData> {
-        let state = State::new(wasm_mod_bytes, bitcoin_gateway_config)?;
+        let state = State::new(wasm_mod_bytes, gateway_config)?;
 
         let wasi = WasiCtxBuilder::new().inherit_stdout().build();
         let mut store = Store::new(&state.engine, wasi);
@@ -48,6 +48,23 @@ pub struct RpcServiceState<R: 'static + Clone + Send + Sync> {
     rpc: RpcModule<R>,
 }
 
+impl<R: 'static + Clone + Send + Sync> RpcServiceState<R> {
+    /// Drops closed response handlers and nodes that have not pinged in 10s
+    pub async fn prune(&self) {
+        self.response_handlers
+            .write()
+            .await
+            .retain(|_, chan| match chan {
+                NetworkTunnelChannel::Oneshot(tx) => !tx.is_closed(),
+                NetworkTunnelChannel::Mpsc(tx) => !tx.is_closed(),
+            });
+        self.liveliness
+            .write()
+            .await
+            .retain(|_, last_ping| last_ping.elapsed() <= Duration::from_secs(10));
+    }
+}
+
 #[async_trait]
 pub trait RpcService<R: 'static + Clone + Send + Sync>: 'static + Send + Sync {
     fn log_target(&self) -> String;
@@ -69,19 +86,7 @@ pub trait RpcService<R: 'static + Clone + Send + Sync>: 'static + Send + Sync {
     }
 
     async fn housekeeping_behavior(&self, state: RpcServiceState<R>) -> anyhow::Result<()> {
-        state
-            .response_handlers
-            .write()
-            .await
-            .retain(|_, chan| match chan {
-                NetworkTunnelChannel::Oneshot(tx) => !tx.is_closed(),
-                NetworkTunnelChannel::Mpsc(tx) => !tx.is_closed(),
-            });
-        state
-            .liveliness
-            .write()
-            .await
-            .retain(|_, last_ping| last_ping.elapsed() <= Duration::from_secs(10));
+        state.prune().await;
         Ok(())
     }
 
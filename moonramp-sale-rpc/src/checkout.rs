@@ -0,0 +1,194 @@
+use qrcode::{render::svg, QrCode};
+
+use moonramp_core::qrcode;
+
+use crate::params::SaleCheckoutResponse;
+
+/// Seconds between status polls of an open checkout page
+pub const CHECKOUT_POLL_SECS: u64 = 5;
+
+/// Escapes text and attribute values, program output and merchant urls end up in the page
+pub fn html_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Inline SVG QR code of a payment uri, `None` if it is too long to encode
+pub fn checkout_qr_code(uri: &str) -> Option<String> {
+    let code = QrCode::new(uri.as_bytes()).ok()?;
+    let svg = code
+        .render::<svg::Color>()
+        .min_dimensions(240, 240)
+        .quiet_zone(true)
+        .build();
+    // The XML declaration isn't valid inside an HTML document
+    Some(svg[svg.find("<svg")?..].to_string())
+}
+
+/// The hosted payment page. It polls `<hash>/status` and sends the customer to the success
+/// url once the invoice is funded, or the cancel url once it expires or is canceled
+pub fn checkout_page(c: &SaleCheckoutResponse) -> String {
+    let amount = format!("{} {:?}", c.amount, c.currency);
+    let fiat = match (&c.fiat_amount, &c.fiat_currency) {
+        (Some(fiat_amount), Some(fiat_currency)) => format!(
+            "<p class=\"fiat\">{} {}</p>",
+            html_escape(&fiat_amount.to_string()),
+            html_escape(fiat_currency)
+        ),
+        _ => String::new(),
+    };
+    let qr_code = checkout_qr_code(&c.uri).unwrap_or_default();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="referrer" content="no-referrer">
+<title>Pay {amount}</title>
+<style>
+body {{ font-family: sans-serif; margin: 0; background: #f4f4f6; color: #1c1c24; }}
+main {{ max-width: 26rem; margin: 2rem auto; padding: 1.5rem; background: #fff; border-radius: 0.5rem; text-align: center; }}
+h1 {{ font-size: 1.5rem; margin: 0 0 0.25rem; }}
+.fiat {{ margin: 0; color: #5c5c6c; }}
+.qr svg {{ width: 240px; height: 240px; margin: 1rem 0; }}
+code {{ display: block; word-break: break-all; padding: 0.5rem; background: #f4f4f6; border-radius: 0.25rem; }}
+.status {{ margin: 1rem 0; font-weight: bold; }}
+a.button {{ display: inline-block; margin: 0.5rem; padding: 0.5rem 1rem; border-radius: 0.25rem; background: #1c1c24; color: #fff; text-decoration: none; }}
+a.cancel {{ color: #5c5c6c; }}
+</style>
+</head>
+<body>
+<main>
+<h1>{amount}</h1>
+{fiat}
+<div class="qr">{qr_code}</div>
+<p>Send exactly <strong>{amount}</strong> to</p>
+<code>{address}</code>
+<p><a class="button" href="{uri}">Open in wallet</a></p>
+<p class="status">Status: <span id="status">{status}</span></p>
+<p>Expires at {expires_at}</p>
+<p><a class="cancel" href="{cancel_url}">Cancel and return to the shop</a></p>
+</main>
+<script>
+(function () {{
+  var status = document.getElementById("status");
+  function poll() {{
+    fetch("{hash}/status", {{ cache: "no-store" }})
+      .then(function (res) {{ return res.json(); }})
+      .then(function (body) {{
+        var checkout = body.result;
+        if (checkout) {{
+          status.textContent = checkout.invoiceStatus;
+          if (checkout.invoiceStatus === "Funded") {{
+            window.location.replace(checkout.successUrl);
+            return;
+          }}
+          if (checkout.invoiceStatus === "Expired" || checkout.invoiceStatus === "Canceled") {{
+            window.location.replace(checkout.cancelUrl);
+            return;
+          }}
+        }}
+        setTimeout(poll, {poll_ms});
+      }})
+      .catch(function () {{ setTimeout(poll, {poll_ms}); }});
+  }}
+  poll();
+}})();
+</script>
+</body>
+</html>
+"#,
+        amount = html_escape(&amount),
+        fiat = fiat,
+        qr_code = qr_code,
+        address = html_escape(&c.address),
+        uri = html_escape(&c.uri),
+        status = html_escape(&format!("{:?}", c.invoice_status)),
+        expires_at = c.expires_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        cancel_url = html_escape(&c.cancel_url),
+        hash = c.hash,
+        poll_ms = CHECKOUT_POLL_SECS * 1000,
+    )
+}
+
+/// Shown for unknown checkout hashes
+pub fn checkout_not_found_page() -> String {
+    r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Checkout not found</title>
+</head>
+<body>
+<p>This checkout doesn't exist.</p>
+</body>
+</html>
+"#
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use moonramp_core::{chrono, Amount, Hash};
+    use moonramp_entity::invoice;
+    use moonramp_wallet::{Currency, Network, Ticker};
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(
+            html_escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+        assert_eq!(html_escape("bcrt1qhe8apwuv2dsr95"), "bcrt1qhe8apwuv2dsr95");
+    }
+
+    #[test]
+    fn test_checkout_page() {
+        let hash = Hash::from([1u8; 32]);
+        let c = SaleCheckoutResponse {
+            hash: hash.clone(),
+            invoice_hash: hash.clone(),
+            capture_hash: hash.clone(),
+            ticker: Ticker::BTC,
+            currency: Currency::BTC,
+            network: Network::Regtest,
+            invoice_status: invoice::InvoiceStatus::Pending,
+            address: "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80".to_string(),
+            amount: Amount::new(25000000, 8).expect("Invalid amount"),
+            uri: "bitcoin:bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80?amount=0.25000000"
+                .to_string(),
+            contract: None,
+            fiat_amount: Some(Amount::new(5000, 2).expect("Invalid amount")),
+            fiat_currency: Some("USD".to_string()),
+            payment_url: format!("http://127.0.0.1:9371/checkout/{}", hash),
+            success_url: "https://shop/thanks".to_string(),
+            cancel_url: "https://shop/cart?a=1&b=<2>".to_string(),
+            created_at: Utc::now(),
+            expires_at: Utc::now(),
+        };
+
+        let page = checkout_page(&c);
+        assert!(page.contains("<h1>0.25000000 BTC</h1>"));
+        assert!(page.contains("50.00 USD"));
+        assert!(page.contains("<code>bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80</code>"));
+        assert!(page.contains("<div class=\"qr\"><svg"));
+        assert!(page.contains(&format!("fetch(\"{}/status\"", hash)));
+        assert!(page.contains("href=\"https://shop/cart?a=1&amp;b=&lt;2&gt;\""));
+        assert!(!page.contains("<2>"));
+    }
+}
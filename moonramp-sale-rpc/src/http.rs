@@ -16,13 +16,13 @@ use uuid::Uuid;
 
 use moonramp_core::{
     actix_cors, actix_web, actix_web_httpauth, anyhow, sea_orm, serde, serde_json, tokio, uuid,
-    NetworkTunnelSender, Sender, TunnelName,
+    Hash, NetworkTunnelSender, Sender, TunnelName,
 };
 use moonramp_encryption::KeyEncryptionKeyCustodian;
 use moonramp_entity::role;
 use moonramp_http::{api_token, await_response, check_roles, network_tunnel, HttpError};
 
-use crate::params::*;
+use crate::{checkout::*, params::*};
 
 pub struct SaleHttpServer {
     inner: Server,
@@ -74,6 +74,13 @@ impl SaleHttpServer {
                         .service(sale_post)
                         .service(sale_get),
                 )
+                // Public, customers open these from the merchant's redirect
+                .service(
+                    web::scope("/checkout")
+                        .app_data(data.clone())
+                        .service(checkout_status)
+                        .service(checkout_get),
+                )
                 .service(ping)
         })
         .system_exit()
@@ -117,8 +124,16 @@ async fn jsonrpc(
         Some("sale.version") => true,
         Some("sale.invoice") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
         Some("sale.invoiceLookup") => check_roles(&rs, role::Resource::Sale, role::Scope::Read),
+        Some("sale.invoiceCancel") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
         Some("sale.capture") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
+        Some("sale.captureAsync") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
+        Some("sale.checkout") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
         Some("sale.lookup") => check_roles(&rs, role::Resource::Sale, role::Scope::Read),
+        Some("sale.webhookCreate") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
+        Some("sale.webhookList") => check_roles(&rs, role::Resource::Sale, role::Scope::Read),
+        Some("sale.webhookDelete") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
+        Some("sale.webhookDeliveries") => check_roles(&rs, role::Resource::Sale, role::Scope::Read),
+        Some("sale.webhookReplay") => check_roles(&rs, role::Resource::Sale, role::Scope::Write),
         _ => false,
     };
 
@@ -312,6 +327,95 @@ async fn sale_get(
     ))
 }
 
+async fn checkout_lookup(
+    state: &SaleHttpServerData,
+    req: &HttpRequest,
+    checkout_hash: &Hash,
+    path: &str,
+) -> actix_web::Result<serde_json::Value> {
+    let start = Instant::now();
+
+    let id = Uuid::new_v4().to_simple().to_string();
+    let data = json!({
+        "jsonrpc": "2.0",
+        "method": "sale.checkoutLookup",
+        "params": {
+            "request": {
+                "hash": checkout_hash,
+            },
+        },
+        "id": id,
+    });
+
+    let sender = req
+        .peer_addr()
+        .map(|addr| Sender::from(addr))
+        .unwrap_or(Sender::Addr("UNKNOWN_PEER_ADDR".to_string()));
+    let msg = network_tunnel(&id, sender, TunnelName::Sale, data)
+        .map_err(|err| err.downcast().unwrap_or(HttpError::ServerError))?;
+    Ok(await_response(
+        "moonramp_sale::http",
+        state.timeout,
+        start,
+        &state.registry_tx,
+        id,
+        msg,
+        "GET",
+        path,
+    )
+    .await
+    .map_err(|err| err.downcast().unwrap_or(HttpError::ServerError))?)
+}
+
+#[get("/{checkout_hash}")]
+async fn checkout_get(
+    state: web::Data<SaleHttpServerData>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    let not_found = HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body(checkout_not_found_page());
+    let checkout_hash: Hash = match path.into_inner().parse() {
+        Ok(checkout_hash) => checkout_hash,
+        Err(_) => return Ok(not_found),
+    };
+
+    let res = checkout_lookup(
+        &state,
+        &req,
+        &checkout_hash,
+        &format!("/checkout/{}", checkout_hash),
+    )
+    .await?;
+    if res["result"] == serde_json::Value::Null {
+        return Ok(not_found);
+    }
+    let c: SaleCheckoutResponse =
+        serde_json::from_value(res["result"].clone()).map_err(|_| HttpError::ServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(checkout_page(&c)))
+}
+
+#[get("/{checkout_hash}/status")]
+async fn checkout_status(
+    state: web::Data<SaleHttpServerData>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    let checkout_hash: Hash = path.into_inner().parse().map_err(|_| HttpError::NotFound)?;
+    Ok(web::Json(
+        checkout_lookup(
+            &state,
+            &req,
+            &checkout_hash,
+            &format!("/checkout/{}/status", checkout_hash),
+        )
+        .await?,
+    ))
+}
+
 #[get("/ping")]
 async fn ping() -> impl Responder {
     HttpResponse::Ok().body("pong\r\n")
@@ -348,8 +452,8 @@ mod tests {
     use tokio::sync::mpsc;
 
     use moonramp_core::{
-        sha3, Hash, NetworkTunnel, NetworkTunnelChannel, NetworkTunnelReceiver, NodeId, RpcTunnel,
-        TunnelName, TunnelTopic,
+        sha3, Amount, Hash, NetworkTunnel, NetworkTunnelChannel, NetworkTunnelReceiver, NodeId,
+        RpcTunnel, TunnelName, TunnelTopic,
     };
     use moonramp_migration::testing::setup_testdb;
     use moonramp_wallet::Currency;
@@ -597,7 +701,8 @@ mod tests {
                     hash: wallet_hash,
                     uuid: "12345".to_string(),
                     currency: Currency::BTC,
-                    amount: 0.00001000,
+                    amount: Some(Amount::new(1000, 8).expect("Invalid amount")),
+                    fiat: None,
                     expires_in: None,
                     user_data: None,
                     program: None,
@@ -657,7 +762,8 @@ mod tests {
                     hash: wallet_hash,
                     uuid: "12345".to_string(),
                     currency: Currency::BTC,
-                    amount: 0.00001000,
+                    amount: Some(Amount::new(1000, 8).expect("Invalid amount")),
+                    fiat: None,
                     expires_in: None,
                     user_data: None,
                     program: None,
@@ -772,6 +878,103 @@ mod tests {
         );
     }
 
+    #[actix_web::test]
+    async fn test_checkout_status_ok() {
+        let database = Database::connect("sqlite::memory:")
+            .await
+            .expect("Failed to open in-memory sqlite db");
+        let (kek_custodian, _cred, _t) = setup_testdb(&database, "moonramp")
+            .await
+            .expect("Failed to setup testdb");
+
+        let (r_tx, r_rx) = mpsc::channel(1);
+
+        let test_data = web::Data::new(SaleHttpServerData {
+            timeout: Duration::from_millis(5),
+            kek_custodian,
+            database,
+            registry_tx: r_tx,
+        });
+
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/checkout")
+                    .app_data(test_data)
+                    .service(checkout_status)
+                    .service(checkout_get),
+            ),
+        )
+        .await;
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"checkout");
+        let checkout_hash = Hash::try_from(hasher.finalize().to_vec()).unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/checkout/{}/status", checkout_hash))
+            .to_request();
+
+        stub_registry(r_rx).await;
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(
+            resp.into_body().try_into_bytes().ok(),
+            Some(Bytes::from(
+                "{\"id\":\"12345\",\"jsonrpc\":\"2.0\",\"result\":true}"
+            ))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_checkout_get_not_ok() {
+        let database = Database::connect("sqlite::memory:")
+            .await
+            .expect("Failed to open in-memory sqlite db");
+        let (kek_custodian, _cred, _t) = setup_testdb(&database, "moonramp")
+            .await
+            .expect("Failed to setup testdb");
+
+        let (r_tx, _r_rx) = mpsc::channel(1);
+
+        let test_data = web::Data::new(SaleHttpServerData {
+            timeout: Duration::from_millis(5),
+            kek_custodian,
+            database,
+            registry_tx: r_tx,
+        });
+
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/checkout")
+                    .app_data(test_data)
+                    .service(checkout_status)
+                    .service(checkout_get),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/checkout/not-a-hash")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get()
+            .uri("/checkout/not-a-hash/status")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"checkout");
+        let checkout_hash = Hash::try_from(hasher.finalize().to_vec()).unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/checkout/{}", checkout_hash))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::GATEWAY_TIMEOUT);
+    }
+
     #[actix_web::test]
     async fn test_ping_ok() {
         let app = test::init_service(App::new().service(ping)).await;
@@ -1,9 +1,15 @@
+mod checkout;
 mod http;
 mod params;
+mod rate;
 mod rpc;
+mod webhook;
 
+pub use checkout::*;
 pub use http::*;
 pub use params::*;
+pub use rate::*;
 pub use rpc::*;
+pub use webhook::*;
 
 pub use moonramp_sale::*;
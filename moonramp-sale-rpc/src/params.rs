@@ -1,22 +1,34 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use moonramp_core::{chrono, serde, Hash};
-use moonramp_entity::{invoice, sale};
+use moonramp_core::{chrono, serde, Amount, Hash};
+use moonramp_entity::{async_sale, checkout, invoice, sale, webhook, webhook_delivery};
 use moonramp_wallet::{Currency, Network, Ticker};
 
+use crate::webhook::WebhookEventType;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
 pub struct SaleInvoiceRequest {
     pub hash: Hash,
     pub uuid: String,
     pub currency: Currency,
-    pub amount: f64,
+    /// Amount in `currency`, required unless the invoice is priced in `fiat`
+    pub amount: Option<Amount>,
+    pub fiat: Option<FiatAmount>,
     pub expires_in: Option<i64>,
     pub user_data: Option<Vec<u8>>,
     pub program: Option<Hash>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct FiatAmount {
+    pub amount: Amount,
+    /// ISO 4217 code, e.g. `USD`
+    pub currency: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase", untagged)]
 pub enum SaleInvoiceLookupRequest {
@@ -34,8 +46,18 @@ pub struct SaleInvoiceResponse {
     pub invoice_status: invoice::InvoiceStatus,
     pub pubkey: String,
     pub address: String,
-    pub amount: f64,
+    pub amount: Amount,
     pub uri: String,
+    pub contract: Option<String>,
+    pub payment_hash: Option<String>,
+    pub fiat_amount: Option<Amount>,
+    pub fiat_currency: Option<String>,
+    pub rate: Option<Amount>,
+    pub rate_source: Option<String>,
+    pub quoted_at: Option<DateTime<Utc>>,
+    pub late_paid_at: Option<DateTime<Utc>>,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub cancel_reason: Option<String>,
     pub user_data: Option<Vec<u8>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -62,6 +84,16 @@ impl From<invoice::Model> for SaleInvoiceResponse {
             address: model.address,
             amount: model.amount,
             uri: model.uri,
+            contract: model.contract,
+            payment_hash: model.payment_hash,
+            fiat_amount: model.fiat_amount,
+            fiat_currency: model.fiat_currency,
+            rate: model.rate,
+            rate_source: model.rate_source,
+            quoted_at: model.quoted_at,
+            late_paid_at: model.late_paid_at,
+            canceled_at: model.canceled_at,
+            cancel_reason: model.cancel_reason,
             user_data: None,
             created_at: model.created_at,
             updated_at: model.updated_at,
@@ -70,6 +102,13 @@ impl From<invoice::Model> for SaleInvoiceResponse {
     }
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleInvoiceCancelRequest {
+    pub hash: Hash,
+    pub reason: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
 pub struct SaleCaptureRequest {
@@ -85,18 +124,69 @@ pub struct SaleCaptureRequest {
 pub struct SaleCaptureAsyncRequest {
     pub hash: Hash,
     pub uuid: String,
+    /// Hash of the webhook the result is delivered to
     pub webhook_id: String,
     pub confirmations: Option<i64>,
     pub user_data: Option<Vec<u8>>,
     pub program: Option<Hash>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleCaptureAsyncResponse {
+    pub hash: Hash,
+    pub invoice_hash: Hash,
+    /// Unset for the jobs of `sale.checkout` calls without a webhook
+    pub webhook_id: Option<String>,
+    pub async_sale_status: async_sale::AsyncSaleStatus,
+    pub attempts: i64,
+    pub sale_hash: Option<Hash>,
+    pub last_error: Option<String>,
+    /// Only set on delivered events
+    pub sale: Option<SaleResponse>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl SaleCaptureAsyncResponse {
+    pub fn with_sale(mut self, sale: Option<SaleResponse>) -> SaleCaptureAsyncResponse {
+        self.sale = sale;
+        self
+    }
+}
+
+impl From<async_sale::Model> for SaleCaptureAsyncResponse {
+    fn from(model: async_sale::Model) -> SaleCaptureAsyncResponse {
+        SaleCaptureAsyncResponse {
+            hash: model.hash,
+            invoice_hash: model.invoice_hash,
+            webhook_id: model.webhook_id,
+            async_sale_status: model.async_sale_status,
+            attempts: model.attempts,
+            sale_hash: model.sale_hash,
+            last_error: model.last_error,
+            sale: None,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+            delivered_at: model.delivered_at,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
 pub struct SaleCheckoutRequest {
+    /// The wallet to invoice, like `SaleInvoiceRequest::hash`
     pub hash: Hash,
     pub uuid: String,
-    pub webhook_id: String,
+    pub currency: Currency,
+    pub amount: Option<Amount>,
+    pub fiat: Option<FiatAmount>,
+    pub expires_in: Option<i64>,
+    /// Hash of the webhook the capture is delivered to, the redirect alone proves nothing.
+    /// Without one the merchant has to poll `sale.invoiceLookup`
+    pub webhook_id: Option<String>,
     pub confirmations: Option<i64>,
     pub cancel_url: String,
     pub success_url: String,
@@ -104,6 +194,169 @@ pub struct SaleCheckoutRequest {
     pub program: Option<Hash>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleCheckoutLookupRequest {
+    pub hash: Hash,
+}
+
+/// Served to customers by the checkout page, so it leaves out the wallet pubkey and user data
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleCheckoutResponse {
+    pub hash: Hash,
+    pub invoice_hash: Hash,
+    pub capture_hash: Hash,
+    pub ticker: Ticker,
+    pub currency: Currency,
+    pub network: Network,
+    pub invoice_status: invoice::InvoiceStatus,
+    pub address: String,
+    pub amount: Amount,
+    pub uri: String,
+    pub contract: Option<String>,
+    pub fiat_amount: Option<Amount>,
+    pub fiat_currency: Option<String>,
+    pub payment_url: String,
+    pub success_url: String,
+    pub cancel_url: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SaleCheckoutResponse {
+    pub fn with_payment_url(mut self, payment_url: String) -> SaleCheckoutResponse {
+        self.payment_url = payment_url;
+        self
+    }
+}
+
+impl From<(checkout::Model, invoice::Model)> for SaleCheckoutResponse {
+    fn from((c, i): (checkout::Model, invoice::Model)) -> SaleCheckoutResponse {
+        SaleCheckoutResponse {
+            hash: c.hash,
+            invoice_hash: c.invoice_hash,
+            capture_hash: c.async_sale_hash,
+            ticker: i.ticker.into(),
+            currency: i.currency.into(),
+            network: i.network.into(),
+            invoice_status: i.invoice_status,
+            address: i.address,
+            amount: i.amount,
+            uri: i.uri,
+            contract: i.contract,
+            fiat_amount: i.fiat_amount,
+            fiat_currency: i.fiat_currency,
+            payment_url: String::new(),
+            success_url: c.success_url,
+            cancel_url: c.cancel_url,
+            created_at: c.created_at,
+            expires_at: i.expires_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleWebhookCreateRequest {
+    pub url: String,
+    /// Event types to deliver, every event when empty or missing
+    pub event_types: Option<Vec<WebhookEventType>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleWebhookDeleteRequest {
+    pub hash: Hash,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleWebhookResponse {
+    pub hash: Hash,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    /// The signing secret, only returned when the webhook is created
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl SaleWebhookResponse {
+    pub fn with_secret(mut self, secret: Option<String>) -> SaleWebhookResponse {
+        self.secret = secret;
+        self
+    }
+}
+
+impl From<webhook::Model> for SaleWebhookResponse {
+    fn from(model: webhook::Model) -> SaleWebhookResponse {
+        SaleWebhookResponse {
+            hash: model.hash,
+            url: model.url,
+            // Event types are validated before they are stored
+            event_types: model
+                .event_types
+                .split(',')
+                .filter_map(|event_type| event_type.parse().ok())
+                .collect(),
+            secret: None,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+            deleted_at: model.deleted_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase", untagged)]
+pub enum SaleWebhookDeliveriesRequest {
+    WebhookHash { webhook_hash: Hash },
+    EventHash { event_hash: Hash },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleWebhookReplayRequest {
+    /// Any attempt of the delivery to replay
+    pub hash: Hash,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct SaleWebhookDeliveryResponse {
+    pub hash: Hash,
+    pub webhook_hash: Hash,
+    pub event_hash: Hash,
+    pub event_type: String,
+    pub attempt: i64,
+    pub delivery_status: webhook_delivery::DeliveryStatus,
+    pub response_status: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub attempted_at: Option<DateTime<Utc>>,
+}
+
+impl From<webhook_delivery::Model> for SaleWebhookDeliveryResponse {
+    fn from(model: webhook_delivery::Model) -> SaleWebhookDeliveryResponse {
+        SaleWebhookDeliveryResponse {
+            hash: model.hash,
+            webhook_hash: model.webhook_hash,
+            event_hash: model.event_hash,
+            event_type: model.event_type,
+            attempt: model.attempt,
+            delivery_status: model.delivery_status,
+            response_status: model.response_status,
+            error: model.error,
+            created_at: model.created_at,
+            next_attempt_at: model.next_attempt_at,
+            attempted_at: model.attempted_at,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase", untagged)]
 pub enum SaleLookupRequest {
@@ -111,46 +364,6 @@ pub enum SaleLookupRequest {
     InvoiceHash { invoice_hash: Hash },
 }
 
-//#[derive(Clone, Debug, Deserialize, Serialize)]
-//#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
-//pub struct SaleCheckoutResponse {
-//    pub hash: String,
-//    pub wallet_hash: String,
-//    pub invoice_hash: String,
-//    pub ticker: ticker::Ticker,
-//    pub currency: currency::Currency,
-//    pub pubkey: String,
-//    pub address: String,
-//    pub amount: String,
-//    pub payment_url: String,
-//    pub user_data: Option<Vec<u8>>,
-//    pub created_at: DateTime<Utc>,
-//}
-//
-//impl SaleCheckoutResponse {
-//    pub fn with_user_data(mut self, user_data: Option<Vec<u8>>) -> SaleCheckoutResponse {
-//        self.user_data = user_data;
-//        self
-//    }
-//}
-//
-//impl From<sale::Model> for SaleCheckoutResponse {
-//    fn from(model: sale::Model) -> SaleCheckoutResponse {
-//        SaleCheckoutResponse {
-//            hash: model.hash.to_string(),
-//            wallet_hash: model.wallet_hash.to_string(),
-//            invoice_hash: model.invoice_hash.to_string(),
-//            ticker: model.ticker,
-//            currency: model.currency,
-//            pubkey: model.pubkey,
-//            address: model.address,
-//            amount: model.amount,
-//            user_data: None,
-//            created_at: model.created_at,
-//        }
-//    }
-//}
-
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
 pub struct SaleResponse {
@@ -162,7 +375,7 @@ pub struct SaleResponse {
     pub network: Network,
     pub pubkey: String,
     pub address: String,
-    pub amount: f64,
+    pub amount: Amount,
     pub confirmations: i64,
     pub user_data: Option<Vec<u8>>,
     pub created_at: DateTime<Utc>,
@@ -0,0 +1,357 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Instant,
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use moonramp_core::{anyhow, async_trait, chrono, log, serde, serde_json, Amount};
+use moonramp_wallet::Currency;
+
+/// An exchange rate locked into a fiat denominated invoice
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct RateQuote {
+    /// Units of the fiat currency per whole unit of the crypto currency
+    pub rate: Amount,
+    pub source: String,
+    pub quoted_at: DateTime<Utc>,
+}
+
+impl RateQuote {
+    /// `fiat_amount` converted to `currency`, rounded to its smallest unit
+    pub fn crypto_amount(
+        &self,
+        currency: &Currency,
+        fiat_amount: Amount,
+    ) -> anyhow::Result<Amount> {
+        if fiat_amount.is_zero() {
+            return Err(anyhow!("Invalid fiat amount {}", fiat_amount));
+        }
+        if self.rate.is_zero() {
+            return Err(anyhow!("Invalid rate {} from {}", self.rate, self.source));
+        }
+        // fiat / rate in units of 10^-decimals, rounded half up
+        let too_large = || anyhow!("Fiat amount {} is too large", fiat_amount);
+        let numerator = fiat_amount
+            .units()
+            .checked_mul(10u128.pow(self.rate.decimals()))
+            .and_then(|n| n.checked_mul(10u128.pow(currency.decimals())))
+            .ok_or_else(too_large)?;
+        let denominator = self
+            .rate
+            .units()
+            .checked_mul(10u128.pow(fiat_amount.decimals()))
+            .ok_or_else(too_large)?;
+        let units = numerator / denominator
+            + u128::from(numerator % denominator >= denominator - denominator / 2);
+        if units == 0 {
+            return Err(anyhow!(
+                "Fiat amount {} is less than one unit of {:?}",
+                fiat_amount,
+                currency
+            ));
+        }
+        Amount::new(units, currency.decimals())
+    }
+}
+
+/// A source of fiat exchange rates for crypto currencies
+#[async_trait]
+pub trait RateProvider: fmt::Debug + Send + Sync {
+    /// `fiat_currency` is an uppercase ISO 4217 code
+    async fn quote(&self, currency: &Currency, fiat_currency: &str) -> anyhow::Result<RateQuote>;
+}
+
+#[derive(Debug, Clone)]
+pub struct RateProviderConfig {
+    pub provider: Arc<dyn RateProvider>,
+}
+
+impl RateProviderConfig {
+    pub fn new<P: RateProvider + 'static>(provider: P) -> Self {
+        RateProviderConfig {
+            provider: Arc::new(provider),
+        }
+    }
+
+    /// Config for nodes without a rate provider, fiat invoices fail
+    pub fn disabled() -> Self {
+        RateProviderConfig::new(DisabledProvider)
+    }
+}
+
+#[derive(Debug)]
+struct DisabledProvider;
+
+#[async_trait]
+impl RateProvider for DisabledProvider {
+    async fn quote(&self, _: &Currency, _: &str) -> anyhow::Result<RateQuote> {
+        Err(anyhow!("Rate provider not configured"))
+    }
+}
+
+/// Fixed rates, for offline nodes and tests
+#[derive(Debug)]
+pub struct StaticRateProvider {
+    source: String,
+    rates: HashMap<Currency, HashMap<String, Amount>>,
+}
+
+impl StaticRateProvider {
+    pub fn new(source: String) -> Self {
+        StaticRateProvider {
+            source,
+            rates: HashMap::new(),
+        }
+    }
+
+    pub fn with_rate(mut self, currency: Currency, fiat_currency: &str, rate: Amount) -> Self {
+        self.rates
+            .entry(currency)
+            .or_default()
+            .insert(fiat_currency.to_uppercase(), rate);
+        self
+    }
+
+    /// Rates from a JSON object of `{"BTC": {"USD": "20000.00", "EUR": "19500.00"}}`
+    pub fn from_json(source: String, json: &str) -> anyhow::Result<Self> {
+        let rates: HashMap<Currency, HashMap<String, Amount>> = serde_json::from_str(json)?;
+        let mut provider = StaticRateProvider::new(source);
+        for (currency, fiat) in rates {
+            for (fiat_currency, rate) in fiat {
+                provider = provider.with_rate(currency.clone(), &fiat_currency, rate);
+            }
+        }
+        Ok(provider)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path.as_ref())?;
+        StaticRateProvider::from_json(format!("file:{}", path.as_ref().display()), &json)
+    }
+
+    fn rate(&self, currency: &Currency, fiat_currency: &str) -> anyhow::Result<Amount> {
+        self.rates
+            .get(currency)
+            .and_then(|fiat| fiat.get(&fiat_currency.to_uppercase()))
+            .cloned()
+            .ok_or(anyhow!(
+                "No {:?}/{} rate from {}",
+                currency,
+                fiat_currency,
+                self.source
+            ))
+    }
+}
+
+#[async_trait]
+impl RateProvider for StaticRateProvider {
+    async fn quote(&self, currency: &Currency, fiat_currency: &str) -> anyhow::Result<RateQuote> {
+        Ok(RateQuote {
+            rate: self.rate(currency, fiat_currency)?,
+            source: self.source.clone(),
+            quoted_at: Utc::now(),
+        })
+    }
+}
+
+/// Rates from a JSON file an external job keeps current, in the format of
+/// `StaticRateProvider::from_json`. The file is read again once `reload_interval` passed and
+/// quotes are refused once it was last written more than `max_age` ago, so a stopped job
+/// can't lock stale rates into new invoices
+#[derive(Debug)]
+pub struct FileRateProvider {
+    path: PathBuf,
+    reload_interval: std::time::Duration,
+    max_age: Duration,
+    loaded: RwLock<LoadedRates>,
+}
+
+#[derive(Debug)]
+struct LoadedRates {
+    checked_at: Instant,
+    modified: DateTime<Utc>,
+    rates: StaticRateProvider,
+}
+
+impl FileRateProvider {
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        reload_interval: std::time::Duration,
+        max_age: Duration,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let loaded = RwLock::new(Self::load(&path)?);
+        Ok(FileRateProvider {
+            path,
+            reload_interval,
+            max_age,
+            loaded,
+        })
+    }
+
+    fn load(path: &Path) -> anyhow::Result<LoadedRates> {
+        let modified = std::fs::metadata(path)?.modified()?.into();
+        Ok(LoadedRates {
+            checked_at: Instant::now(),
+            modified,
+            rates: StaticRateProvider::from_file(path)?,
+        })
+    }
+
+    /// Rates as of the file's last write, a file that fails to load (e.g. one caught half
+    /// written) keeps the previous rates until they are too old
+    fn quote_loaded(&self, currency: &Currency, fiat_currency: &str) -> anyhow::Result<RateQuote> {
+        let mut loaded = self
+            .loaded
+            .write()
+            .map_err(|_| anyhow!("Rate provider poisoned"))?;
+        if loaded.checked_at.elapsed() >= self.reload_interval {
+            match Self::load(&self.path) {
+                Ok(reloaded) => *loaded = reloaded,
+                Err(err) => {
+                    warn!(
+                        "Failed to reload rates from {}: {}",
+                        self.path.display(),
+                        err
+                    );
+                    loaded.checked_at = Instant::now();
+                }
+            }
+        }
+        let age = Utc::now() - loaded.modified;
+        if age > self.max_age {
+            return Err(anyhow!(
+                "Rates from {} are {}s old",
+                loaded.rates.source,
+                age.num_seconds()
+            ));
+        }
+        Ok(RateQuote {
+            rate: loaded.rates.rate(currency, fiat_currency)?,
+            source: loaded.rates.source.clone(),
+            quoted_at: loaded.modified,
+        })
+    }
+}
+
+#[async_trait]
+impl RateProvider for FileRateProvider {
+    async fn quote(&self, currency: &Currency, fiat_currency: &str) -> anyhow::Result<RateQuote> {
+        self.quote_loaded(currency, fiat_currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moonramp_core::tokio;
+
+    #[tokio::test]
+    async fn test_static_rate_provider() {
+        let provider = StaticRateProvider::from_json(
+            "test".to_string(),
+            r#"{"BTC": {"usd": "20000.00"}, "XMR": {"EUR": 150.0}}"#,
+        )
+        .expect("Invalid rates");
+
+        let quote = provider
+            .quote(&Currency::BTC, "USD")
+            .await
+            .expect("Invalid quote");
+        assert_eq!(quote.rate, "20000".parse().expect("Invalid rate"));
+        assert_eq!(quote.source, "test");
+        assert_eq!(
+            quote
+                .crypto_amount(
+                    &Currency::BTC,
+                    Amount::new(1000, 2).expect("Invalid amount")
+                )
+                .expect("Invalid amount")
+                .to_string(),
+            "0.00050000"
+        );
+        assert!(quote
+            .crypto_amount(&Currency::BTC, Amount::new(0, 2).expect("Invalid amount"))
+            .is_err());
+        assert!(quote
+            .crypto_amount(&Currency::BTC, Amount::new(1, 6).expect("Invalid amount"))
+            .is_err());
+
+        let quote = provider
+            .quote(&Currency::XMR, "eur")
+            .await
+            .expect("Invalid quote");
+        assert_eq!(
+            quote
+                .crypto_amount(&Currency::XMR, Amount::new(100, 0).expect("Invalid amount"))
+                .expect("Invalid amount")
+                .to_string(),
+            "0.666666666667"
+        );
+
+        assert!(provider.quote(&Currency::BTC, "EUR").await.is_err());
+        assert!(RateProviderConfig::disabled()
+            .provider
+            .quote(&Currency::BTC, "USD")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_rate_provider() {
+        let path = std::env::temp_dir().join(format!(
+            "moonramp-rates-{}.json",
+            moonramp_core::uuid::Uuid::new_v4().to_simple()
+        ));
+        std::fs::write(&path, r#"{"BTC": {"USD": "20000.00"}}"#).expect("Failed to write rates");
+
+        let provider = FileRateProvider::new(
+            &path,
+            std::time::Duration::from_secs(0),
+            Duration::seconds(60),
+        )
+        .expect("Invalid rates");
+        let quote = provider
+            .quote(&Currency::BTC, "USD")
+            .await
+            .expect("Invalid quote");
+        assert_eq!(quote.rate, "20000".parse().expect("Invalid rate"));
+        assert_eq!(quote.source, format!("file:{}", path.display()));
+
+        std::fs::write(&path, r#"{"BTC": {"USD": "25000.00"}}"#).expect("Failed to write rates");
+        let quote = provider
+            .quote(&Currency::BTC, "USD")
+            .await
+            .expect("Invalid quote");
+        assert_eq!(quote.rate, "25000".parse().expect("Invalid rate"));
+
+        // A half written file keeps the last rates
+        std::fs::write(&path, r#"{"BTC": {"USD": "#).expect("Failed to write rates");
+        let quote = provider
+            .quote(&Currency::BTC, "USD")
+            .await
+            .expect("Invalid quote");
+        assert_eq!(quote.rate, "25000".parse().expect("Invalid rate"));
+
+        std::fs::write(&path, r#"{"BTC": {"USD": "25000.00"}}"#).expect("Failed to write rates");
+        let stale = FileRateProvider::new(
+            &path,
+            std::time::Duration::from_secs(60),
+            Duration::seconds(-1),
+        )
+        .expect("Invalid rates");
+        assert!(stale.quote(&Currency::BTC, "USD").await.is_err());
+
+        std::fs::remove_file(&path).expect("Failed to remove rates");
+        assert!(provider.quote(&Currency::BTC, "USD").await.is_ok());
+    }
+}
@@ -1,11 +1,16 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use anyhow::anyhow;
 use async_trait::async_trait;
 use chrono::{Duration, Utc};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, RpcModule};
-use log::debug;
-use sea_orm::{entity::*, query::*, DatabaseConnection, DatabaseTransaction};
+use log::{debug, info, warn};
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, ConnectionTrait, DatabaseConnection, DatabaseTransaction,
+};
 use sha3::{Digest, Sha3_256};
 use tokio::{
     sync::{mpsc, RwLock},
@@ -13,19 +18,28 @@ use tokio::{
 };
 
 use moonramp_core::{
-    anyhow, async_trait, chrono, log, sea_orm, serde_json, sha3, tokio, Hash,
+    anyhow, async_trait, bs58, chrono, hyper, log, sea_orm, serde_json, sha3, tokio, Hash,
     NetworkTunnelReceiver, NetworkTunnelSender, NodeId, TunnelName,
 };
 use moonramp_encryption::{
     EncryptionKeyCustodian, KeyCustodian, KeyEncryptionKeyCustodian, MerchantScopedSecret,
 };
-use moonramp_entity::{cipher::Cipher, encryption_key, invoice, program, sale, wallet};
-use moonramp_program::{BitcoinRpcConfig, Runtime};
-use moonramp_rpc::{IntoRpcResult, RpcService};
+use moonramp_entity::{
+    async_sale, checkout, cipher::Cipher, encryption_key, invoice, program, sale, wallet, webhook,
+    webhook_delivery,
+};
+use moonramp_program::{GatewayConfig, LightningRpcConfig, Runtime};
+use moonramp_rpc::{IntoRpcResult, RpcService, RpcServiceState};
 use moonramp_sale::{Invoice, Sale};
-use moonramp_wallet::{Network, Wallet};
-
-use crate::params::*;
+use moonramp_wallet::{Network, Ticker, Wallet};
+
+use crate::{
+    params::*,
+    rate::RateProviderConfig,
+    webhook::{
+        webhook_signature, WebhookConfig, WebhookEvent, WebhookEventType, WEBHOOK_POST_TIMEOUT_SECS,
+    },
+};
 
 #[rpc(server)]
 pub trait SaleRpc {
@@ -46,6 +60,13 @@ pub trait SaleRpc {
         request: SaleInvoiceLookupRequest,
     ) -> RpcResult<Option<SaleInvoiceResponse>>;
 
+    #[method(name = "sale.invoiceCancel")]
+    async fn invoice_cancel(
+        &self,
+        merchant_hash: Hash,
+        request: SaleInvoiceCancelRequest,
+    ) -> RpcResult<SaleInvoiceResponse>;
+
     #[method(name = "sale.capture")]
     async fn capture(
         &self,
@@ -53,19 +74,26 @@ pub trait SaleRpc {
         request: SaleCaptureRequest,
     ) -> RpcResult<SaleResponse>;
 
-    //#[method(name = "sale.captureAsync")]
-    //async fn capture_async(
-    //    &self,
-    //    merchant_hash: String,
-    //    request: SaleCaptureAsyncRequest,
-    //) -> RpcResult<SaleResponse>;
+    #[method(name = "sale.captureAsync")]
+    async fn capture_async(
+        &self,
+        merchant_hash: Hash,
+        request: SaleCaptureAsyncRequest,
+    ) -> RpcResult<SaleCaptureAsyncResponse>;
+
+    #[method(name = "sale.checkout")]
+    async fn checkout(
+        &self,
+        merchant_hash: Hash,
+        request: SaleCheckoutRequest,
+    ) -> RpcResult<SaleCheckoutResponse>;
 
-    //#[method(name = "sale.checkout")]
-    //async fn checkout(
-    //    &self,
-    //    merchant_hash: String,
-    //    request: SaleCheckoutRequest,
-    //) -> RpcResult<SaleCheckoutResponse>;
+    /// Backs the public checkout page, the sale HTTP server never exposes it on `/jsonrpc`
+    #[method(name = "sale.checkoutLookup")]
+    async fn checkout_lookup(
+        &self,
+        request: SaleCheckoutLookupRequest,
+    ) -> RpcResult<Option<SaleCheckoutResponse>>;
 
     #[method(name = "sale.lookup")]
     async fn lookup(
@@ -73,6 +101,37 @@ pub trait SaleRpc {
         merchant_hash: Hash,
         request: SaleLookupRequest,
     ) -> RpcResult<Option<SaleResponse>>;
+
+    #[method(name = "sale.webhookCreate")]
+    async fn webhook_create(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookCreateRequest,
+    ) -> RpcResult<SaleWebhookResponse>;
+
+    #[method(name = "sale.webhookList")]
+    async fn webhook_list(&self, merchant_hash: Hash) -> RpcResult<Vec<SaleWebhookResponse>>;
+
+    #[method(name = "sale.webhookDelete")]
+    async fn webhook_delete(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookDeleteRequest,
+    ) -> RpcResult<SaleWebhookResponse>;
+
+    #[method(name = "sale.webhookDeliveries")]
+    async fn webhook_deliveries(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookDeliveriesRequest,
+    ) -> RpcResult<Vec<SaleWebhookDeliveryResponse>>;
+
+    #[method(name = "sale.webhookReplay")]
+    async fn webhook_replay(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookReplayRequest,
+    ) -> RpcResult<SaleWebhookDeliveryResponse>;
 }
 
 #[derive(Clone)]
@@ -80,7 +139,99 @@ pub struct SaleRpcImpl {
     master_merchant_hash: Arc<Hash>,
     kek_custodian: Arc<KeyEncryptionKeyCustodian>,
     database: DatabaseConnection,
-    bitcoin_gateway_config: BitcoinRpcConfig,
+    gateway_config: GatewayConfig,
+    rates: RateProviderConfig,
+    webhooks: WebhookConfig,
+    /// Public base url of the sale HTTP server, checkout pages are served under it
+    checkout_url: String,
+}
+
+/// Jobs claimed per `run_capture_jobs` call
+const CAPTURE_JOB_BATCH: u64 = 16;
+/// Delay between captures of an unfunded invoice, and the first retry after a failure
+const CAPTURE_JOB_POLL_SECS: i64 = 10;
+const CAPTURE_JOB_MAX_BACKOFF_SECS: i64 = 60 * 60;
+/// Async captures poll the chain briefly instead of holding a request open
+const CAPTURE_JOB_TIMEOUT_MS: u64 = 10000;
+/// Time on top of the program timeout to load the invoice and wallet, store the sale and
+/// queue the job's webhook event
+const CAPTURE_JOB_LEASE_SLACK_SECS: i64 = 110;
+
+/// A claimed job is left alone by other nodes for this long, past the end of its attempt
+fn capture_job_lease() -> Duration {
+    Duration::milliseconds(CAPTURE_JOB_TIMEOUT_MS as i64)
+        + Duration::seconds(CAPTURE_JOB_LEASE_SLACK_SECS)
+}
+
+fn capture_job_backoff(attempts: i64) -> Duration {
+    Duration::seconds(
+        CAPTURE_JOB_POLL_SECS
+            .saturating_mul(1 << attempts.clamp(0, 16))
+            .min(CAPTURE_JOB_MAX_BACKOFF_SECS),
+    )
+}
+
+/// Attempts claimed per `run_webhook_deliveries` call
+const WEBHOOK_DELIVERY_BATCH: u64 = 32;
+/// Time on top of the post timeout to decrypt the event and store the attempt
+const WEBHOOK_DELIVERY_LEASE_SLACK_SECS: i64 = 50;
+/// Attempts before a delivery is left `Failed`, `sale.webhookReplay` starts another round
+const WEBHOOK_MAX_ATTEMPTS: i64 = 10;
+const WEBHOOK_RETRY_SECS: i64 = 30;
+const WEBHOOK_MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+/// Deliveries listed per `sale.webhookDeliveries` call, newest first
+const WEBHOOK_DELIVERIES_LIMIT: u64 = 100;
+
+/// A claimed delivery is left alone by other nodes for this long, past the end of its attempt
+fn webhook_delivery_lease() -> Duration {
+    Duration::seconds(WEBHOOK_POST_TIMEOUT_SECS as i64 + WEBHOOK_DELIVERY_LEASE_SLACK_SECS)
+}
+
+/// Delay after the `attempt`th failed delivery, 30s doubling up to 6h
+fn webhook_backoff(attempt: i64) -> Duration {
+    Duration::seconds(
+        WEBHOOK_RETRY_SECS
+            .saturating_mul(1 << (attempt - 1).clamp(0, 16))
+            .min(WEBHOOK_MAX_BACKOFF_SECS),
+    )
+}
+
+/// A `sale.captureAsync` request, `sale.checkout` queues its jobs without a webhook when
+/// the merchant only polls
+struct CaptureJobRequest {
+    hash: Hash,
+    uuid: String,
+    webhook_id: Option<String>,
+    confirmations: Option<i64>,
+    user_data: Option<Vec<u8>>,
+    program: Option<Hash>,
+}
+
+impl From<SaleCaptureAsyncRequest> for CaptureJobRequest {
+    fn from(request: SaleCaptureAsyncRequest) -> CaptureJobRequest {
+        CaptureJobRequest {
+            hash: request.hash,
+            uuid: request.uuid,
+            webhook_id: Some(request.webhook_id),
+            confirmations: request.confirmations,
+            user_data: request.user_data,
+            program: request.program,
+        }
+    }
+}
+
+/// Checkout pages redirect the customer's browser here, so only absolute web urls are allowed
+fn checkout_redirect_url(url: &str) -> anyhow::Result<()> {
+    let uri: hyper::Uri = url
+        .parse()
+        .map_err(|_| anyhow!("Invalid redirect url {}", url))?;
+    match (uri.scheme_str(), uri.host()) {
+        (Some("http") | Some("https"), Some(_)) => Ok(()),
+        _ => Err(anyhow!(
+            "Redirect url {} must be an absolute http or https url",
+            url
+        )),
+    }
 }
 
 impl SaleRpcImpl {
@@ -179,6 +330,35 @@ impl SaleRpcImpl {
         Ok((w, w_ek_custodian))
     }
 
+    async fn ensure_address_unused(
+        &self,
+        txn: &DatabaseTransaction,
+        wallet_hash: Hash,
+        address: &str,
+    ) -> anyhow::Result<()> {
+        let open = invoice::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(invoice::Column::WalletHash.eq(wallet_hash))
+                    .add(invoice::Column::Address.eq(address))
+                    .add(invoice::Column::InvoiceStatus.is_in([
+                        invoice::InvoiceStatus::Pending,
+                        invoice::InvoiceStatus::Funded,
+                    ])),
+            )
+            .one(txn)
+            .await?;
+        match open {
+            Some(i) => Err(anyhow!(
+                "Program returned address {} which is already used by {:?} invoice {}",
+                address,
+                i.invoice_status,
+                i.hash
+            )),
+            None => Ok(()),
+        }
+    }
+
     async fn load_wallet_with_lock(
         &self,
         txn: &DatabaseTransaction,
@@ -219,204 +399,17 @@ impl SaleRpcImpl {
 
         Ok((w, w_ek_custodian))
     }
-}
-
-#[async_trait]
-impl SaleRpcServer for SaleRpcImpl {
-    fn version(&self) -> RpcResult<String> {
-        Ok(env!("CARGO_PKG_VERSION").to_string())
-    }
-
-    async fn invoice(
-        &self,
-        merchant_hash: Hash,
-        request: SaleInvoiceRequest,
-    ) -> RpcResult<SaleInvoiceResponse> {
-        debug!("sale.invoice {:?}", request);
-        let program_find_start = Instant::now();
-
-        let txn = self.database.begin().await.into_rpc_result()?;
-        let (p, p_ek_custodian) = self
-            .load_program(&txn, merchant_hash.clone(), request.program)
-            .await
-            .into_rpc_result()?;
-
-        debug!(
-            "Program found in {}ms",
-            program_find_start.elapsed().as_millis()
-        );
-
-        let (w, w_ek_custodian) = self
-            .load_wallet_with_lock(&txn, merchant_hash.clone(), request.hash)
-            .await
-            .into_rpc_result()?;
-
-        let program_decrypt_start = Instant::now();
-        let wasm_mod_bytes = p_ek_custodian
-            .decrypt(&p.nonce, &p.blob)
-            .into_rpc_result()?;
-
-        debug!(
-            "Program decrypted in {}ms",
-            program_decrypt_start.elapsed().as_millis()
-        );
-
-        let wallet_bytes = w_ek_custodian
-            .decrypt(&w.nonce, &w.blob)
-            .into_rpc_result()?;
-
-        let live_w: Wallet = serde_json::from_slice(&wallet_bytes).into_rpc_result()?;
-
-        let program_run_start = Instant::now();
-        let i: Invoice = Runtime::exec(
-            &wasm_mod_bytes,
-            moonramp_lunar::EntryData::Invoice {
-                wallet: live_w,
-                currency: request.currency.clone(),
-                amount: request.amount.clone(),
-                user_data: request.user_data,
-            },
-            tokio::time::Duration::from_millis(55000),
-            self.bitcoin_gateway_config.clone(),
-        )
-        .await?
-        .try_into()
-        .into_rpc_result()?;
-
-        debug!(
-            "Program ran in {}ms",
-            program_run_start.elapsed().as_millis()
-        );
-
-        let live_w = i.wallet;
-
-        let (nonce, ciphertext) = w_ek_custodian
-            .encrypt(&serde_json::to_vec(&live_w).into_rpc_result()?)
-            .into_rpc_result()?;
-        let mut w: wallet::ActiveModel = w.into();
-        w.blob = Set(ciphertext);
-        w.nonce = Set(nonce);
-        let w = w.update(&txn).await.into_rpc_result()?;
-        txn.commit().await.into_rpc_result()?;
-
-        let ek = self
-            .kek_custodian
-            .lock(MerchantScopedSecret {
-                merchant_hash: merchant_hash.clone(),
-                secret: self.kek_custodian.gen_secret().into_rpc_result()?,
-            })
-            .into_rpc_result()?
-            .insert(&self.database)
-            .await
-            .into_rpc_result()?;
-
-        let ek_custodian = EncryptionKeyCustodian::new(
-            self.kek_custodian
-                .unlock(ek)
-                .into_rpc_result()?
-                .secret
-                .to_vec(),
-            Cipher::Aes256GcmSiv,
-        )
-        .into_rpc_result()?;
-
-        let (nonce, ciphertext) = ek_custodian
-            .encrypt(&serde_json::to_vec(&i.user_data).into_rpc_result()?)
-            .into_rpc_result()?;
-
-        let mut hasher = Sha3_256::new();
-        hasher.update(request.uuid + &i.address);
-        let hash = Hash::try_from(hasher.finalize().to_vec()).into_rpc_result()?;
-
-        let expires_in = request.expires_in.unwrap_or(15 * 60);
-
-        let invoice_res: SaleInvoiceResponse = invoice::ActiveModel {
-            hash: Set(hash),
-            merchant_hash: Set(merchant_hash),
-            wallet_hash: Set(w.hash),
-            ticker: Set(w.ticker),
-            currency: Set(request.currency.into()),
-            network: Set(w.network),
-            invoice_status: Set(invoice::InvoiceStatus::Pending),
-            pubkey: Set(i.pubkey),
-            address: Set(i.address),
-            amount: Set(request.amount),
-            uri: Set(i.uri),
-            encryption_key_hash: Set(ek_custodian.hash()),
-            cipher: Set(Cipher::Aes256GcmSiv),
-            blob: Set(ciphertext),
-            nonce: Set(nonce),
-            created_at: Set(Utc::now()),
-            updated_at: Set(Utc::now()),
-            expires_at: Set(Utc::now() + Duration::seconds(expires_in)),
-        }
-        .insert(&self.database)
-        .await
-        .into_rpc_result()?
-        .into();
-        Ok(invoice_res.with_user_data(i.user_data))
-    }
-
-    async fn invoice_lookup(
-        &self,
-        merchant_hash: Hash,
-        request: SaleInvoiceLookupRequest,
-    ) -> RpcResult<Option<SaleInvoiceResponse>> {
-        debug!("sale.invoiceLookup {:?}", request);
-        let i = match request {
-            SaleInvoiceLookupRequest::Hash { hash } => invoice::Entity::find()
-                .filter(
-                    Condition::all()
-                        .add(invoice::Column::Hash.eq(hash))
-                        .add(invoice::Column::MerchantHash.eq(merchant_hash)),
-                )
-                .one(&self.database)
-                .await
-                .into_rpc_result()?,
-        };
-
-        match i {
-            Some(i) => {
-                let ek = encryption_key::Entity::find()
-                    .filter(
-                        Condition::all()
-                            .add(encryption_key::Column::Hash.eq(i.encryption_key_hash.clone()))
-                            .add(
-                                encryption_key::Column::KeyEncryptionKeyHash
-                                    .eq(self.kek_custodian.hash()),
-                            ),
-                    )
-                    .one(&self.database)
-                    .await
-                    .into_rpc_result()?
-                    .ok_or(anyhow!("Failed load invoice"))
-                    .into_rpc_result()?;
-
-                let ek_custodian = EncryptionKeyCustodian::new(
-                    self.kek_custodian
-                        .unlock(ek)
-                        .into_rpc_result()?
-                        .secret
-                        .to_vec(),
-                    i.cipher.clone(),
-                )
-                .into_rpc_result()?;
-
-                let blob = ek_custodian.decrypt(&i.nonce, &i.blob).into_rpc_result()?;
-                let user_data: Option<Vec<u8>> = serde_json::from_slice(&blob).into_rpc_result()?;
-                let invoice_res: SaleInvoiceResponse = i.into();
-                Ok(Some(invoice_res.with_user_data(user_data)))
-            }
-            None => Ok(None),
-        }
-    }
 
-    async fn capture(
+    /// Runs the sale program against an invoice and records the sale, funding the invoice
+    /// once the program reports it paid. Unfunded captures are only recorded when
+    /// `record_unfunded`, capture jobs poll under one sale hash until funded
+    async fn capture_sale(
         &self,
         merchant_hash: Hash,
         request: SaleCaptureRequest,
-    ) -> RpcResult<SaleResponse> {
-        debug!("sale.capture {:?}", request);
+        timeout: tokio::time::Duration,
+        record_unfunded: bool,
+    ) -> RpcResult<Option<SaleResponse>> {
         let txn = self.database.begin().await.into_rpc_result()?;
         let i = invoice::Entity::find()
             .filter(
@@ -444,6 +437,7 @@ impl SaleRpcServer for SaleRpcImpl {
                 )
                 .await?
                 .ok_or(anyhow!("Invoice has already been captured but has no sale"))
+                .map(Some)
                 .into_rpc_result();
         }
 
@@ -490,11 +484,15 @@ impl SaleRpcServer for SaleRpcImpl {
                 currency: i.currency.clone().into(),
                 amount: i.amount,
                 address: i.address.clone(),
+                pubkey: i.pubkey.clone(),
+                contract: i.contract.clone(),
+                payment_hash: i.payment_hash.clone(),
+                start_height: i.start_height.map(|height| height as u64),
                 confirmations: confirmations as u64,
                 user_data: request.user_data,
             },
-            tokio::time::Duration::from_millis(55000),
-            self.bitcoin_gateway_config.clone(),
+            timeout,
+            self.gateway_config.clone(),
         )
         .await?
         .try_into()
@@ -506,6 +504,10 @@ impl SaleRpcServer for SaleRpcImpl {
             program_run_start.elapsed().as_millis()
         );
 
+        if !s.funded && !record_unfunded {
+            return Ok(None);
+        }
+
         let ek = self
             .kek_custodian
             .lock(MerchantScopedSecret {
@@ -537,11 +539,28 @@ impl SaleRpcServer for SaleRpcImpl {
         let hash = Hash::try_from(hasher.finalize().to_vec()).into_rpc_result()?;
 
         if s.funded {
-            debug!("Sale {} funded with amount {}", hash, s.amount);
+            // Expired and Canceled are final, a late payment is flagged on the invoice
+            // instead of funding it
+            let closed = matches!(
+                i.invoice_status,
+                invoice::InvoiceStatus::Expired | invoice::InvoiceStatus::Canceled
+            );
+            if closed {
+                warn!(
+                    "Sale {} funded {:?} invoice {} with amount {}",
+                    hash, i.invoice_status, i.hash, s.amount
+                );
+            } else {
+                debug!("Sale {} funded with amount {}", hash, s.amount);
+            }
 
             let mut i: invoice::ActiveModel = i.clone().into();
             i.updated_at = Set(Utc::now());
-            i.invoice_status = Set(invoice::InvoiceStatus::Funded);
+            if closed {
+                i.late_paid_at = Set(Some(Utc::now()));
+            } else {
+                i.invoice_status = Set(invoice::InvoiceStatus::Funded);
+            }
             i.update(&self.database).await.into_rpc_result()?;
         }
 
@@ -567,17 +586,986 @@ impl SaleRpcServer for SaleRpcImpl {
         .await
         .into_rpc_result()?
         .into();
-        Ok(sale_res.with_user_data(s.user_data))
+        Ok(Some(sale_res.with_user_data(s.user_data)))
     }
 
-    async fn lookup(
+    /// Creates an invoice inside `txn`, which holds the wallet's row lock until it commits
+    async fn create_invoice(
         &self,
+        txn: &DatabaseTransaction,
         merchant_hash: Hash,
-        request: SaleLookupRequest,
-    ) -> RpcResult<Option<SaleResponse>> {
-        debug!("sale.lookup {:?}", request);
-        let s = match request {
-            SaleLookupRequest::Hash { hash } => sale::Entity::find()
+        request: SaleInvoiceRequest,
+    ) -> RpcResult<SaleInvoiceResponse> {
+        let (amount, quote) = match (request.amount, &request.fiat) {
+            (Some(amount), None) => (
+                amount
+                    .rescale(request.currency.decimals())
+                    .into_rpc_result()?,
+                None,
+            ),
+            (None, Some(fiat)) => {
+                let quote = self
+                    .rates
+                    .provider
+                    .quote(&request.currency, &fiat.currency.to_uppercase())
+                    .await
+                    .into_rpc_result()?;
+                let amount = quote
+                    .crypto_amount(&request.currency, fiat.amount)
+                    .into_rpc_result()?;
+                debug!(
+                    "Quoted {} {} as {} {:?} at {} from {}",
+                    fiat.amount, fiat.currency, amount, request.currency, quote.rate, quote.source
+                );
+                (amount, Some(quote))
+            }
+            _ => {
+                return Err(anyhow!("Invoice needs either an amount or a fiat amount"))
+                    .into_rpc_result()
+            }
+        };
+
+        let program_find_start = Instant::now();
+
+        let (p, p_ek_custodian) = self
+            .load_program(txn, merchant_hash.clone(), request.program)
+            .await
+            .into_rpc_result()?;
+
+        debug!(
+            "Program found in {}ms",
+            program_find_start.elapsed().as_millis()
+        );
+
+        let (w, w_ek_custodian) = self
+            .load_wallet_with_lock(txn, merchant_hash.clone(), request.hash)
+            .await
+            .into_rpc_result()?;
+        if w.wallet_status != wallet::WalletStatus::Active {
+            return Err(anyhow!(
+                "Wallet is {:?}, new invoices need an Active wallet",
+                w.wallet_status
+            ))
+            .into_rpc_result();
+        }
+
+        let program_decrypt_start = Instant::now();
+        let wasm_mod_bytes = p_ek_custodian
+            .decrypt(&p.nonce, &p.blob)
+            .into_rpc_result()?;
+
+        debug!(
+            "Program decrypted in {}ms",
+            program_decrypt_start.elapsed().as_millis()
+        );
+
+        let wallet_bytes = w_ek_custodian
+            .decrypt(&w.nonce, &w.blob)
+            .into_rpc_result()?;
+
+        let live_w: Wallet = serde_json::from_slice(&wallet_bytes).into_rpc_result()?;
+
+        let contract = if request.currency.is_erc20() {
+            if live_w.ticker() != Ticker::ETH {
+                return Err(anyhow!(
+                    "{:?} requires an ETH wallet not {:?}",
+                    request.currency,
+                    live_w.ticker()
+                ))
+                .into_rpc_result();
+            }
+            Some(
+                request
+                    .currency
+                    .erc20_contract(&live_w.network())
+                    .ok_or(anyhow!(
+                        "{:?} is not available on {:?}",
+                        request.currency,
+                        live_w.network()
+                    ))
+                    .into_rpc_result()?
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+        if request.currency.is_lightning() && live_w.ticker() != Ticker::BTC {
+            return Err(anyhow!(
+                "{:?} requires a BTC wallet not {:?}",
+                request.currency,
+                live_w.ticker()
+            ))
+            .into_rpc_result();
+        }
+
+        let expires_in = request.expires_in.unwrap_or(15 * 60);
+
+        let program_run_start = Instant::now();
+        let i: Invoice = Runtime::exec(
+            &wasm_mod_bytes,
+            moonramp_lunar::EntryData::Invoice {
+                wallet: live_w,
+                currency: request.currency.clone(),
+                amount,
+                contract: contract.clone(),
+                expires_in,
+                user_data: request.user_data,
+            },
+            tokio::time::Duration::from_millis(55000),
+            self.gateway_config.clone(),
+        )
+        .await?
+        .try_into()
+        .into_rpc_result()?;
+
+        debug!(
+            "Program ran in {}ms",
+            program_run_start.elapsed().as_millis()
+        );
+
+        self.ensure_address_unused(txn, w.hash.clone(), &i.address)
+            .await
+            .into_rpc_result()?;
+
+        let live_w = i.wallet;
+
+        let (nonce, ciphertext) = w_ek_custodian
+            .encrypt(&serde_json::to_vec(&live_w).into_rpc_result()?)
+            .into_rpc_result()?;
+        let mut w: wallet::ActiveModel = w.into();
+        w.blob = Set(ciphertext);
+        w.nonce = Set(nonce);
+        let w = w.update(txn).await.into_rpc_result()?;
+
+        let ek = self
+            .kek_custodian
+            .lock(MerchantScopedSecret {
+                merchant_hash: merchant_hash.clone(),
+                secret: self.kek_custodian.gen_secret().into_rpc_result()?,
+            })
+            .into_rpc_result()?
+            .insert(txn)
+            .await
+            .into_rpc_result()?;
+
+        let ek_custodian = EncryptionKeyCustodian::new(
+            self.kek_custodian
+                .unlock(ek)
+                .into_rpc_result()?
+                .secret
+                .to_vec(),
+            Cipher::Aes256GcmSiv,
+        )
+        .into_rpc_result()?;
+
+        let (nonce, ciphertext) = ek_custodian
+            .encrypt(&serde_json::to_vec(&i.user_data).into_rpc_result()?)
+            .into_rpc_result()?;
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(request.uuid + &i.address);
+        let hash = Hash::try_from(hasher.finalize().to_vec()).into_rpc_result()?;
+
+        let invoice_res: SaleInvoiceResponse = invoice::ActiveModel {
+            hash: Set(hash),
+            merchant_hash: Set(merchant_hash),
+            wallet_hash: Set(w.hash),
+            ticker: Set(w.ticker),
+            currency: Set(request.currency.into()),
+            network: Set(w.network),
+            invoice_status: Set(invoice::InvoiceStatus::Pending),
+            pubkey: Set(i.pubkey),
+            address: Set(i.address),
+            amount: Set(amount),
+            uri: Set(i.uri),
+            contract: Set(contract),
+            payment_hash: Set(i.payment_hash),
+            start_height: Set(i.start_height.map(|height| height as i64)),
+            fiat_amount: Set(request.fiat.as_ref().map(|fiat| fiat.amount)),
+            fiat_currency: Set(request.fiat.map(|fiat| fiat.currency.to_uppercase())),
+            rate: Set(quote.as_ref().map(|quote| quote.rate)),
+            rate_source: Set(quote.as_ref().map(|quote| quote.source.clone())),
+            quoted_at: Set(quote.map(|quote| quote.quoted_at)),
+            late_paid_at: Set(None),
+            canceled_at: Set(None),
+            cancel_reason: Set(None),
+            encryption_key_hash: Set(ek_custodian.hash()),
+            cipher: Set(Cipher::Aes256GcmSiv),
+            blob: Set(ciphertext),
+            nonce: Set(nonce),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            expires_at: Set(Utc::now() + Duration::seconds(expires_in)),
+        }
+        .insert(txn)
+        .await
+        .into_rpc_result()?
+        .into();
+        Ok(invoice_res.with_user_data(i.user_data))
+    }
+
+    /// Queues a capture job for `request.hash` inside `txn`, or returns the job an earlier
+    /// request with the same uuid queued
+    async fn queue_capture_job(
+        &self,
+        txn: &DatabaseTransaction,
+        merchant_hash: Hash,
+        request: CaptureJobRequest,
+    ) -> RpcResult<SaleCaptureAsyncResponse> {
+        let i = invoice::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(invoice::Column::Hash.eq(request.hash.clone()))
+                    .add(invoice::Column::MerchantHash.eq(merchant_hash.clone())),
+            )
+            .one(txn)
+            .await
+            .into_rpc_result()?
+            .ok_or(anyhow!("Failed load invoice"))
+            .into_rpc_result()?;
+
+        let mut hasher = Sha3_256::new();
+        hasher.update("sale.captureAsync");
+        hasher.update(&request.uuid);
+        hasher.update(&i.hash);
+        let hash = Hash::try_from(hasher.finalize().to_vec()).into_rpc_result()?;
+
+        // Retrying with the same uuid returns the job instead of queueing another
+        if let Some(job) = async_sale::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(async_sale::Column::Hash.eq(hash.clone()))
+                    .add(async_sale::Column::MerchantHash.eq(merchant_hash.clone())),
+            )
+            .one(txn)
+            .await
+            .into_rpc_result()?
+        {
+            return Ok(job.into());
+        }
+
+        if let Some(webhook_id) = &request.webhook_id {
+            self.find_webhook(txn, &merchant_hash, webhook_id)
+                .await
+                .into_rpc_result()?
+                .ok_or(anyhow!("Webhook {} not found", webhook_id))
+                .into_rpc_result()?;
+        }
+
+        let ek = self
+            .kek_custodian
+            .lock(MerchantScopedSecret {
+                merchant_hash: merchant_hash.clone(),
+                secret: self.kek_custodian.gen_secret().into_rpc_result()?,
+            })
+            .into_rpc_result()?
+            .insert(txn)
+            .await
+            .into_rpc_result()?;
+
+        let ek_custodian = EncryptionKeyCustodian::new(
+            self.kek_custodian
+                .unlock(ek)
+                .into_rpc_result()?
+                .secret
+                .to_vec(),
+            Cipher::Aes256GcmSiv,
+        )
+        .into_rpc_result()?;
+
+        let (nonce, ciphertext) = ek_custodian
+            .encrypt(&serde_json::to_vec(&request.user_data).into_rpc_result()?)
+            .into_rpc_result()?;
+
+        let job_res: SaleCaptureAsyncResponse = async_sale::ActiveModel {
+            hash: Set(hash),
+            merchant_hash: Set(merchant_hash),
+            invoice_hash: Set(i.hash),
+            uuid: Set(request.uuid),
+            webhook_id: Set(request.webhook_id),
+            confirmations: Set(request.confirmations.unwrap_or(0)),
+            program_hash: Set(request.program),
+            async_sale_status: Set(async_sale::AsyncSaleStatus::Pending),
+            sale_hash: Set(None),
+            attempts: Set(0),
+            last_error: Set(None),
+            cipher: Set(Cipher::Aes256GcmSiv),
+            encryption_key_hash: Set(ek_custodian.hash()),
+            blob: Set(ciphertext),
+            nonce: Set(nonce),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            next_attempt_at: Set(Utc::now()),
+            delivered_at: Set(None),
+        }
+        .insert(txn)
+        .await
+        .into_rpc_result()?
+        .into();
+        Ok(job_res)
+    }
+
+    async fn load_encryption_key(
+        &self,
+        hash: Hash,
+        cipher: Cipher,
+    ) -> anyhow::Result<EncryptionKeyCustodian> {
+        let ek = encryption_key::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(encryption_key::Column::Hash.eq(hash))
+                    .add(
+                        encryption_key::Column::KeyEncryptionKeyHash.eq(self.kek_custodian.hash()),
+                    ),
+            )
+            .one(&self.database)
+            .await?
+            .ok_or(anyhow!("Failed load encryption key"))?;
+
+        EncryptionKeyCustodian::new(self.kek_custodian.unlock(ek)?.secret.to_vec(), cipher)
+    }
+
+    async fn job_user_data(&self, job: &async_sale::Model) -> anyhow::Result<Option<Vec<u8>>> {
+        let ek_custodian = self
+            .load_encryption_key(job.encryption_key_hash.clone(), job.cipher.clone())
+            .await?;
+        let blob = ek_custodian.decrypt(&job.nonce, &job.blob)?;
+        Ok(serde_json::from_slice(&blob)?)
+    }
+
+    /// Runs due `sale.captureAsync` jobs, capturing pending ones and delivering finished ones
+    /// to their webhook. Returns the number of jobs run
+    pub async fn run_capture_jobs(&self) -> anyhow::Result<usize> {
+        let jobs = async_sale::Entity::find()
+            .filter(async_sale::Column::NextAttemptAt.lte(Utc::now()))
+            .filter(
+                Condition::any()
+                    .add(
+                        async_sale::Column::AsyncSaleStatus
+                            .eq(async_sale::AsyncSaleStatus::Pending),
+                    )
+                    .add(async_sale::Column::DeliveredAt.is_null()),
+            )
+            .order_by_asc(async_sale::Column::NextAttemptAt)
+            .limit(CAPTURE_JOB_BATCH)
+            .all(&self.database)
+            .await?;
+
+        let mut ran = 0;
+        for job in jobs {
+            // Nodes sharing the database race for jobs, the winner moves the attempt past
+            // the lease. Jobs run one after another, so the lease starts at the claim
+            let claimed = async_sale::Entity::update_many()
+                .col_expr(
+                    async_sale::Column::NextAttemptAt,
+                    Expr::value(Utc::now() + capture_job_lease()),
+                )
+                .filter(async_sale::Column::Hash.eq(job.hash.clone()))
+                .filter(async_sale::Column::NextAttemptAt.eq(job.next_attempt_at))
+                .exec(&self.database)
+                .await?;
+            if claimed.rows_affected != 1 {
+                continue;
+            }
+
+            let hash = job.hash.clone();
+            if let Err(err) = self.run_capture_job(job).await {
+                warn!("Capture job {} failed {}", hash, err);
+            }
+            ran += 1;
+        }
+        Ok(ran)
+    }
+
+    async fn run_capture_job(&self, job: async_sale::Model) -> anyhow::Result<()> {
+        let job = if job.async_sale_status == async_sale::AsyncSaleStatus::Pending {
+            self.capture_job(job).await?
+        } else {
+            job
+        };
+        if job.async_sale_status != async_sale::AsyncSaleStatus::Pending {
+            self.deliver_job(job).await?;
+        }
+        Ok(())
+    }
+
+    async fn capture_job(&self, job: async_sale::Model) -> anyhow::Result<async_sale::Model> {
+        let i = invoice::Entity::find_by_id(job.invoice_hash.clone())
+            .one(&self.database)
+            .await?
+            .ok_or(anyhow!("Failed load invoice"))?;
+
+        let mut next: async_sale::ActiveModel = job.clone().into();
+        next.updated_at = Set(Utc::now());
+        let finished = match i.invoice_status {
+            invoice::InvoiceStatus::Expired => Some((async_sale::AsyncSaleStatus::Expired, None)),
+            invoice::InvoiceStatus::Canceled => Some((async_sale::AsyncSaleStatus::Canceled, None)),
+            _ => {
+                let request = SaleCaptureRequest {
+                    hash: job.invoice_hash.clone(),
+                    uuid: job.uuid.clone(),
+                    confirmations: Some(job.confirmations),
+                    user_data: self.job_user_data(&job).await?,
+                    program: job.program_hash.clone(),
+                };
+                match self
+                    .capture_sale(
+                        job.merchant_hash.clone(),
+                        request,
+                        tokio::time::Duration::from_millis(CAPTURE_JOB_TIMEOUT_MS),
+                        false,
+                    )
+                    .await
+                {
+                    Ok(Some(s)) => Some((async_sale::AsyncSaleStatus::Funded, Some(s.hash))),
+                    Ok(None) => {
+                        next.next_attempt_at =
+                            Set(Utc::now() + Duration::seconds(CAPTURE_JOB_POLL_SECS));
+                        None
+                    }
+                    Err(err) => {
+                        debug!("Capture job {} attempt failed {}", job.hash, err);
+                        next.attempts = Set(job.attempts + 1);
+                        next.last_error = Set(Some(err.to_string()));
+                        next.next_attempt_at = Set(Utc::now() + capture_job_backoff(job.attempts));
+                        None
+                    }
+                }
+            }
+        };
+
+        if let Some((status, sale_hash)) = finished {
+            debug!("Capture job {} finished {:?}", job.hash, status);
+            next.async_sale_status = Set(status);
+            next.sale_hash = Set(sale_hash);
+            next.attempts = Set(0);
+            next.last_error = Set(None);
+            next.next_attempt_at = Set(Utc::now());
+        }
+        Ok(next.update(&self.database).await?)
+    }
+
+    async fn deliver_job(&self, job: async_sale::Model) -> anyhow::Result<()> {
+        let event_type = match job.async_sale_status {
+            async_sale::AsyncSaleStatus::Funded => WebhookEventType::SaleFunded,
+            async_sale::AsyncSaleStatus::Expired => WebhookEventType::SaleExpired,
+            async_sale::AsyncSaleStatus::Canceled => WebhookEventType::SaleCanceled,
+            async_sale::AsyncSaleStatus::Pending => {
+                return Err(anyhow!("Capture job {} is still pending", job.hash))
+            }
+        };
+        let sale = match &job.sale_hash {
+            Some(hash) => {
+                self.lookup(
+                    job.merchant_hash.clone(),
+                    SaleLookupRequest::Hash { hash: hash.clone() },
+                )
+                .await?
+            }
+            None => None,
+        };
+        let job_res: SaleCaptureAsyncResponse = job.clone().into();
+        let event = WebhookEvent {
+            hash: job.hash.clone(),
+            event_type,
+            created_at: Utc::now(),
+            data: serde_json::to_value(job_res.with_sale(sale))?,
+        };
+
+        // The delivery log retries from here on, the job is done once its event is queued
+        let mut next: async_sale::ActiveModel = job.clone().into();
+        next.updated_at = Set(Utc::now());
+        next.delivered_at = Set(Some(Utc::now()));
+        match &job.webhook_id {
+            Some(webhook_id) => {
+                let queued = self
+                    .queue_webhook_event(&job.merchant_hash, webhook_id, &event)
+                    .await?;
+                if queued.is_none() {
+                    debug!(
+                        "Capture job {} {} event not delivered, webhook {} is deleted or unsubscribed",
+                        job.hash,
+                        event.event_type.as_str(),
+                        webhook_id
+                    );
+                }
+            }
+            None => debug!(
+                "Capture job {} {} has no webhook",
+                job.hash,
+                event.event_type.as_str()
+            ),
+        }
+        next.update(&self.database).await?;
+        Ok(())
+    }
+
+    async fn checkout_response(&self, c: checkout::Model) -> anyhow::Result<SaleCheckoutResponse> {
+        let i = invoice::Entity::find_by_id(c.invoice_hash.clone())
+            .one(&self.database)
+            .await?
+            .ok_or(anyhow!("Failed load invoice"))?;
+        let payment_url = format!(
+            "{}/checkout/{}",
+            self.checkout_url.trim_end_matches('/'),
+            c.hash
+        );
+        Ok(SaleCheckoutResponse::from((c, i)).with_payment_url(payment_url))
+    }
+
+    async fn find_webhook<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        merchant_hash: &Hash,
+        webhook_id: &str,
+    ) -> anyhow::Result<Option<webhook::Model>> {
+        let hash: Hash = match webhook_id.parse() {
+            Ok(hash) => hash,
+            Err(_) => return Ok(None),
+        };
+        Ok(webhook::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(webhook::Column::Hash.eq(hash))
+                    .add(webhook::Column::MerchantHash.eq(merchant_hash.clone()))
+                    .add(webhook::Column::DeletedAt.is_null()),
+            )
+            .one(conn)
+            .await?)
+    }
+
+    /// Queues the first delivery attempt of `event` to `webhook_id`. Nothing is queued
+    /// if the webhook is gone or not subscribed to the event type
+    async fn queue_webhook_event(
+        &self,
+        merchant_hash: &Hash,
+        webhook_id: &str,
+        event: &WebhookEvent,
+    ) -> anyhow::Result<Option<webhook_delivery::Model>> {
+        let w = match self
+            .find_webhook(&self.database, merchant_hash, webhook_id)
+            .await?
+        {
+            Some(w) => w,
+            None => return Ok(None),
+        };
+        let subscribed = w.event_types.is_empty()
+            || w.event_types
+                .split(',')
+                .any(|event_type| event_type == event.event_type.as_str());
+        if !subscribed {
+            return Ok(None);
+        }
+
+        let ek_custodian = self
+            .load_encryption_key(w.encryption_key_hash.clone(), w.cipher.clone())
+            .await?;
+        let d = self
+            .insert_webhook_delivery(
+                &w,
+                event.hash.clone(),
+                event.event_type.as_str().to_string(),
+                1,
+                ek_custodian.encrypt(&serde_json::to_vec(event)?)?,
+                Utc::now(),
+            )
+            .await?;
+        Ok(Some(d))
+    }
+
+    async fn insert_webhook_delivery(
+        &self,
+        w: &webhook::Model,
+        event_hash: Hash,
+        event_type: String,
+        attempt: i64,
+        (nonce, ciphertext): (Vec<u8>, Vec<u8>),
+        next_attempt_at: chrono::DateTime<Utc>,
+    ) -> anyhow::Result<webhook_delivery::Model> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(&event_hash);
+        hasher.update(&w.hash);
+        hasher.update(attempt.to_string());
+        let hash = Hash::try_from(hasher.finalize().to_vec())?;
+
+        Ok(webhook_delivery::ActiveModel {
+            hash: Set(hash),
+            merchant_hash: Set(w.merchant_hash.clone()),
+            webhook_hash: Set(w.hash.clone()),
+            event_hash: Set(event_hash),
+            event_type: Set(event_type),
+            attempt: Set(attempt),
+            delivery_status: Set(webhook_delivery::DeliveryStatus::Pending),
+            response_status: Set(None),
+            error: Set(None),
+            cipher: Set(w.cipher.clone()),
+            encryption_key_hash: Set(w.encryption_key_hash.clone()),
+            blob: Set(ciphertext),
+            nonce: Set(nonce),
+            created_at: Set(Utc::now()),
+            next_attempt_at: Set(next_attempt_at),
+            attempted_at: Set(None),
+        }
+        .insert(&self.database)
+        .await?)
+    }
+
+    /// Sends due webhook delivery attempts, queueing a retry for each failure.
+    /// Returns the number of attempts sent
+    pub async fn run_webhook_deliveries(&self) -> anyhow::Result<usize> {
+        let deliveries = webhook_delivery::Entity::find()
+            .filter(
+                webhook_delivery::Column::DeliveryStatus
+                    .eq(webhook_delivery::DeliveryStatus::Pending),
+            )
+            .filter(webhook_delivery::Column::NextAttemptAt.lte(Utc::now()))
+            .order_by_asc(webhook_delivery::Column::NextAttemptAt)
+            .limit(WEBHOOK_DELIVERY_BATCH)
+            .all(&self.database)
+            .await?;
+
+        let mut sent = 0;
+        for d in deliveries {
+            // Like capture jobs, the lease starts at the claim of each delivery
+            let claimed = webhook_delivery::Entity::update_many()
+                .col_expr(
+                    webhook_delivery::Column::NextAttemptAt,
+                    Expr::value(Utc::now() + webhook_delivery_lease()),
+                )
+                .filter(webhook_delivery::Column::Hash.eq(d.hash.clone()))
+                .filter(webhook_delivery::Column::NextAttemptAt.eq(d.next_attempt_at))
+                .exec(&self.database)
+                .await?;
+            if claimed.rows_affected != 1 {
+                continue;
+            }
+
+            let hash = d.hash.clone();
+            if let Err(err) = self.send_webhook_delivery(d).await {
+                warn!("Webhook delivery {} failed {}", hash, err);
+            }
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    async fn send_webhook_delivery(&self, d: webhook_delivery::Model) -> anyhow::Result<()> {
+        let w = webhook::Entity::find_by_id(d.webhook_hash.clone())
+            .one(&self.database)
+            .await?
+            .ok_or(anyhow!("Failed load webhook"))?;
+
+        let (response_status, error) = if w.deleted_at.is_some() {
+            (None, Some("Webhook deleted".to_string()))
+        } else {
+            let ek_custodian = self
+                .load_encryption_key(w.encryption_key_hash.clone(), w.cipher.clone())
+                .await?;
+            let secret = String::from_utf8(ek_custodian.decrypt(&w.nonce, &w.blob)?)?;
+            let body = ek_custodian.decrypt(&d.nonce, &d.blob)?;
+            let signature = webhook_signature(&secret, Utc::now().timestamp(), &body);
+            match self.webhooks.delivery.post(&w.url, &signature, body).await {
+                Ok(status) if (200..300).contains(&status) => (Some(status as i64), None),
+                Ok(status) => (
+                    Some(status as i64),
+                    Some(format!("Webhook returned {}", status)),
+                ),
+                Err(err) => (None, Some(err.to_string())),
+            }
+        };
+
+        let mut next: webhook_delivery::ActiveModel = d.clone().into();
+        next.delivery_status = Set(if error.is_none() {
+            webhook_delivery::DeliveryStatus::Succeeded
+        } else {
+            webhook_delivery::DeliveryStatus::Failed
+        });
+        next.response_status = Set(response_status);
+        next.error = Set(error.clone());
+        next.attempted_at = Set(Some(Utc::now()));
+        next.update(&self.database).await?;
+
+        if let Some(error) = error {
+            debug!(
+                "Webhook delivery {} attempt {} to {} failed {}",
+                d.event_hash, d.attempt, w.url, error
+            );
+            if w.deleted_at.is_none() && d.attempt < WEBHOOK_MAX_ATTEMPTS {
+                self.insert_webhook_delivery(
+                    &w,
+                    d.event_hash,
+                    d.event_type,
+                    d.attempt + 1,
+                    (d.nonce, d.blob),
+                    Utc::now() + webhook_backoff(d.attempt),
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SaleRpcServer for SaleRpcImpl {
+    fn version(&self) -> RpcResult<String> {
+        Ok(env!("CARGO_PKG_VERSION").to_string())
+    }
+
+    async fn invoice(
+        &self,
+        merchant_hash: Hash,
+        request: SaleInvoiceRequest,
+    ) -> RpcResult<SaleInvoiceResponse> {
+        debug!("sale.invoice {:?}", request);
+        let txn = self.database.begin().await.into_rpc_result()?;
+        let invoice_res = self.create_invoice(&txn, merchant_hash, request).await?;
+        txn.commit().await.into_rpc_result()?;
+        Ok(invoice_res)
+    }
+
+    async fn invoice_lookup(
+        &self,
+        merchant_hash: Hash,
+        request: SaleInvoiceLookupRequest,
+    ) -> RpcResult<Option<SaleInvoiceResponse>> {
+        debug!("sale.invoiceLookup {:?}", request);
+        let i = match request {
+            SaleInvoiceLookupRequest::Hash { hash } => invoice::Entity::find()
+                .filter(
+                    Condition::all()
+                        .add(invoice::Column::Hash.eq(hash))
+                        .add(invoice::Column::MerchantHash.eq(merchant_hash)),
+                )
+                .one(&self.database)
+                .await
+                .into_rpc_result()?,
+        };
+
+        match i {
+            Some(i) => {
+                let ek = encryption_key::Entity::find()
+                    .filter(
+                        Condition::all()
+                            .add(encryption_key::Column::Hash.eq(i.encryption_key_hash.clone()))
+                            .add(
+                                encryption_key::Column::KeyEncryptionKeyHash
+                                    .eq(self.kek_custodian.hash()),
+                            ),
+                    )
+                    .one(&self.database)
+                    .await
+                    .into_rpc_result()?
+                    .ok_or(anyhow!("Failed load invoice"))
+                    .into_rpc_result()?;
+
+                let ek_custodian = EncryptionKeyCustodian::new(
+                    self.kek_custodian
+                        .unlock(ek)
+                        .into_rpc_result()?
+                        .secret
+                        .to_vec(),
+                    i.cipher.clone(),
+                )
+                .into_rpc_result()?;
+
+                let blob = ek_custodian.decrypt(&i.nonce, &i.blob).into_rpc_result()?;
+                let user_data: Option<Vec<u8>> = serde_json::from_slice(&blob).into_rpc_result()?;
+                let invoice_res: SaleInvoiceResponse = i.into();
+                Ok(Some(invoice_res.with_user_data(user_data)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn invoice_cancel(
+        &self,
+        merchant_hash: Hash,
+        request: SaleInvoiceCancelRequest,
+    ) -> RpcResult<SaleInvoiceResponse> {
+        debug!("sale.invoiceCancel {:?}", request);
+        let txn = self.database.begin().await.into_rpc_result()?;
+        let i = invoice::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(invoice::Column::Hash.eq(request.hash.clone()))
+                    .add(invoice::Column::MerchantHash.eq(merchant_hash.clone())),
+            )
+            .lock_exclusive()
+            .all(&txn)
+            .await
+            .into_rpc_result()?
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("Failed load invoice"))
+            .into_rpc_result()?;
+
+        match i.invoice_status {
+            // Canceling twice keeps the first reason
+            invoice::InvoiceStatus::Canceled => {}
+            invoice::InvoiceStatus::Pending => {
+                // A BTCLN invoice stays canceled in MoonRamp only if the node stops taking
+                // payments for it, a settled one has been paid and is left to capture
+                if let Some(payment_hash) = &i.payment_hash {
+                    if let Err(err) = self
+                        .gateway_config
+                        .lightning
+                        .backend
+                        .cancel_invoice(payment_hash)
+                        .await
+                    {
+                        txn.rollback().await.into_rpc_result()?;
+                        return Err(anyhow!(
+                            "Failed to cancel Lightning invoice {}: {}",
+                            payment_hash,
+                            err
+                        ))
+                        .into_rpc_result();
+                    }
+                }
+                let mut i: invoice::ActiveModel = i.into();
+                i.invoice_status = Set(invoice::InvoiceStatus::Canceled);
+                i.canceled_at = Set(Some(Utc::now()));
+                i.cancel_reason = Set(Some(request.reason));
+                i.updated_at = Set(Utc::now());
+                i.update(&txn).await.into_rpc_result()?;
+            }
+            status => {
+                txn.rollback().await.into_rpc_result()?;
+                return Err(anyhow!(
+                    "Invoice {} is {:?} and can't be canceled",
+                    request.hash,
+                    status
+                ))
+                .into_rpc_result();
+            }
+        }
+        txn.commit().await.into_rpc_result()?;
+
+        self.invoice_lookup(
+            merchant_hash,
+            SaleInvoiceLookupRequest::Hash { hash: request.hash },
+        )
+        .await?
+        .ok_or(anyhow!("Failed load invoice"))
+        .into_rpc_result()
+    }
+
+    async fn capture(
+        &self,
+        merchant_hash: Hash,
+        request: SaleCaptureRequest,
+    ) -> RpcResult<SaleResponse> {
+        debug!("sale.capture {:?}", request);
+        self.capture_sale(
+            merchant_hash,
+            request,
+            tokio::time::Duration::from_millis(55000),
+            true,
+        )
+        .await?
+        .ok_or(anyhow!("Failed to capture sale"))
+        .into_rpc_result()
+    }
+
+    async fn capture_async(
+        &self,
+        merchant_hash: Hash,
+        request: SaleCaptureAsyncRequest,
+    ) -> RpcResult<SaleCaptureAsyncResponse> {
+        debug!("sale.captureAsync {:?}", request);
+        let txn = self.database.begin().await.into_rpc_result()?;
+        let job_res = self
+            .queue_capture_job(&txn, merchant_hash, request.into())
+            .await?;
+        txn.commit().await.into_rpc_result()?;
+        Ok(job_res)
+    }
+
+    async fn checkout(
+        &self,
+        merchant_hash: Hash,
+        request: SaleCheckoutRequest,
+    ) -> RpcResult<SaleCheckoutResponse> {
+        debug!("sale.checkout {:?}", request);
+        checkout_redirect_url(&request.success_url).into_rpc_result()?;
+        checkout_redirect_url(&request.cancel_url).into_rpc_result()?;
+
+        // A checkout is created whole or not at all, an unknown webhook leaves no invoice behind
+        let txn = self.database.begin().await.into_rpc_result()?;
+        let i = self
+            .create_invoice(
+                &txn,
+                merchant_hash.clone(),
+                SaleInvoiceRequest {
+                    hash: request.hash,
+                    uuid: request.uuid.clone(),
+                    currency: request.currency,
+                    amount: request.amount,
+                    fiat: request.fiat,
+                    expires_in: request.expires_in,
+                    user_data: request.user_data.clone(),
+                    program: request.program.clone(),
+                },
+            )
+            .await?;
+
+        // The page only polls, the capture job funds the invoice and tells the merchant
+        let job = self
+            .queue_capture_job(
+                &txn,
+                merchant_hash.clone(),
+                CaptureJobRequest {
+                    hash: i.hash.clone(),
+                    uuid: request.uuid,
+                    webhook_id: request.webhook_id,
+                    confirmations: request.confirmations,
+                    user_data: request.user_data,
+                    program: request.program,
+                },
+            )
+            .await?;
+
+        let hash = Hash::from(self.kek_custodian.gen_secret().into_rpc_result()?);
+        let c = checkout::ActiveModel {
+            hash: Set(hash),
+            merchant_hash: Set(merchant_hash),
+            invoice_hash: Set(i.hash),
+            async_sale_hash: Set(job.hash),
+            success_url: Set(request.success_url),
+            cancel_url: Set(request.cancel_url),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&txn)
+        .await
+        .into_rpc_result()?;
+        txn.commit().await.into_rpc_result()?;
+        self.checkout_response(c).await.into_rpc_result()
+    }
+
+    async fn checkout_lookup(
+        &self,
+        request: SaleCheckoutLookupRequest,
+    ) -> RpcResult<Option<SaleCheckoutResponse>> {
+        debug!("sale.checkoutLookup {:?}", request);
+        match checkout::Entity::find_by_id(request.hash)
+            .one(&self.database)
+            .await
+            .into_rpc_result()?
+        {
+            Some(c) => Ok(Some(self.checkout_response(c).await.into_rpc_result()?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn lookup(
+        &self,
+        merchant_hash: Hash,
+        request: SaleLookupRequest,
+    ) -> RpcResult<Option<SaleResponse>> {
+        debug!("sale.lookup {:?}", request);
+        let s = match request {
+            SaleLookupRequest::Hash { hash } => sale::Entity::find()
                 .filter(
                     Condition::all()
                         .add(sale::Column::Hash.eq(hash))
@@ -632,12 +1620,267 @@ impl SaleRpcServer for SaleRpcImpl {
             None => Ok(None),
         }
     }
+
+    async fn webhook_create(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookCreateRequest,
+    ) -> RpcResult<SaleWebhookResponse> {
+        debug!("sale.webhookCreate {:?}", request);
+        let uri: hyper::Uri = request
+            .url
+            .parse()
+            .map_err(|_| anyhow!("Invalid webhook url {}", request.url))
+            .into_rpc_result()?;
+        if !matches!(uri.scheme_str(), Some("http") | Some("https")) || uri.host().is_none() {
+            return Err(anyhow!(
+                "Webhook url {} must be an absolute http or https url",
+                request.url
+            ))
+            .into_rpc_result();
+        }
+
+        let mut event_types: Vec<&str> = request
+            .event_types
+            .unwrap_or_default()
+            .iter()
+            .map(|event_type| event_type.as_str())
+            .collect();
+        event_types.sort_unstable();
+        event_types.dedup();
+
+        let secret = format!(
+            "whsec_{}",
+            bs58::encode(self.kek_custodian.gen_secret().into_rpc_result()?).into_string()
+        );
+
+        let ek = self
+            .kek_custodian
+            .lock(MerchantScopedSecret {
+                merchant_hash: merchant_hash.clone(),
+                secret: self.kek_custodian.gen_secret().into_rpc_result()?,
+            })
+            .into_rpc_result()?
+            .insert(&self.database)
+            .await
+            .into_rpc_result()?;
+
+        let ek_custodian = EncryptionKeyCustodian::new(
+            self.kek_custodian
+                .unlock(ek)
+                .into_rpc_result()?
+                .secret
+                .to_vec(),
+            Cipher::Aes256GcmSiv,
+        )
+        .into_rpc_result()?;
+
+        let (nonce, ciphertext) = ek_custodian.encrypt(secret.as_bytes()).into_rpc_result()?;
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(&merchant_hash);
+        hasher.update(&secret);
+        let hash = Hash::try_from(hasher.finalize().to_vec()).into_rpc_result()?;
+
+        let webhook_res: SaleWebhookResponse = webhook::ActiveModel {
+            hash: Set(hash),
+            merchant_hash: Set(merchant_hash),
+            url: Set(request.url),
+            event_types: Set(event_types.join(",")),
+            cipher: Set(Cipher::Aes256GcmSiv),
+            encryption_key_hash: Set(ek_custodian.hash()),
+            blob: Set(ciphertext),
+            nonce: Set(nonce),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            deleted_at: Set(None),
+        }
+        .insert(&self.database)
+        .await
+        .into_rpc_result()?
+        .into();
+        Ok(webhook_res.with_secret(Some(secret)))
+    }
+
+    async fn webhook_list(&self, merchant_hash: Hash) -> RpcResult<Vec<SaleWebhookResponse>> {
+        debug!("sale.webhookList");
+        Ok(webhook::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(webhook::Column::MerchantHash.eq(merchant_hash))
+                    .add(webhook::Column::DeletedAt.is_null()),
+            )
+            .order_by_asc(webhook::Column::CreatedAt)
+            .all(&self.database)
+            .await
+            .into_rpc_result()?
+            .into_iter()
+            .map(SaleWebhookResponse::from)
+            .collect())
+    }
+
+    async fn webhook_delete(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookDeleteRequest,
+    ) -> RpcResult<SaleWebhookResponse> {
+        debug!("sale.webhookDelete {:?}", request);
+        let w = self
+            .find_webhook(&self.database, &merchant_hash, &request.hash.to_string())
+            .await
+            .into_rpc_result()?
+            .ok_or(anyhow!("Webhook {} not found", request.hash))
+            .into_rpc_result()?;
+
+        // Pending attempts fail when they come due, the log is kept
+        let mut w: webhook::ActiveModel = w.into();
+        w.deleted_at = Set(Some(Utc::now()));
+        w.updated_at = Set(Utc::now());
+        Ok(w.update(&self.database).await.into_rpc_result()?.into())
+    }
+
+    async fn webhook_deliveries(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookDeliveriesRequest,
+    ) -> RpcResult<Vec<SaleWebhookDeliveryResponse>> {
+        debug!("sale.webhookDeliveries {:?}", request);
+        let by = match request {
+            SaleWebhookDeliveriesRequest::WebhookHash { webhook_hash } => {
+                webhook_delivery::Column::WebhookHash.eq(webhook_hash)
+            }
+            SaleWebhookDeliveriesRequest::EventHash { event_hash } => {
+                webhook_delivery::Column::EventHash.eq(event_hash)
+            }
+        };
+        Ok(webhook_delivery::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(by)
+                    .add(webhook_delivery::Column::MerchantHash.eq(merchant_hash)),
+            )
+            .order_by_desc(webhook_delivery::Column::CreatedAt)
+            .order_by_desc(webhook_delivery::Column::Attempt)
+            .limit(WEBHOOK_DELIVERIES_LIMIT)
+            .all(&self.database)
+            .await
+            .into_rpc_result()?
+            .into_iter()
+            .map(SaleWebhookDeliveryResponse::from)
+            .collect())
+    }
+
+    async fn webhook_replay(
+        &self,
+        merchant_hash: Hash,
+        request: SaleWebhookReplayRequest,
+    ) -> RpcResult<SaleWebhookDeliveryResponse> {
+        debug!("sale.webhookReplay {:?}", request);
+        let d = webhook_delivery::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(webhook_delivery::Column::Hash.eq(request.hash.clone()))
+                    .add(webhook_delivery::Column::MerchantHash.eq(merchant_hash.clone())),
+            )
+            .one(&self.database)
+            .await
+            .into_rpc_result()?
+            .ok_or(anyhow!("Webhook delivery {} not found", request.hash))
+            .into_rpc_result()?;
+
+        let w = self
+            .find_webhook(&self.database, &merchant_hash, &d.webhook_hash.to_string())
+            .await
+            .into_rpc_result()?
+            .ok_or(anyhow!("Webhook {} not found", d.webhook_hash))
+            .into_rpc_result()?;
+
+        let attempts = webhook_delivery::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(webhook_delivery::Column::WebhookHash.eq(w.hash.clone()))
+                    .add(webhook_delivery::Column::EventHash.eq(d.event_hash.clone())),
+            )
+            .all(&self.database)
+            .await
+            .into_rpc_result()?;
+        if attempts
+            .iter()
+            .any(|a| a.delivery_status == webhook_delivery::DeliveryStatus::Pending)
+        {
+            return Err(anyhow!(
+                "Webhook delivery of event {} already has a pending attempt",
+                d.event_hash
+            ))
+            .into_rpc_result();
+        }
+        let attempt = attempts.iter().map(|a| a.attempt).max().unwrap_or(0) + 1;
+
+        Ok(self
+            .insert_webhook_delivery(
+                &w,
+                d.event_hash,
+                d.event_type,
+                attempt,
+                (d.nonce, d.blob),
+                Utc::now(),
+            )
+            .await
+            .into_rpc_result()?
+            .into())
+    }
+}
+
+/// Moves `Pending` invoices past `expires_at` to `Expired`, releasing their addresses. The
+/// Lightning node cancels the BOLT11 invoices of expired BTCLN invoices
+pub async fn expire_invoices(
+    database: &DatabaseConnection,
+    lightning: &LightningRpcConfig,
+) -> anyhow::Result<u64> {
+    let now = Utc::now();
+    let txn = database.begin().await?;
+    let expired = invoice::Entity::find()
+        .filter(invoice::Column::InvoiceStatus.eq(invoice::InvoiceStatus::Pending))
+        .filter(invoice::Column::ExpiresAt.lt(now))
+        .lock_exclusive()
+        .all(&txn)
+        .await?;
+    if expired.is_empty() {
+        txn.rollback().await?;
+        return Ok(0);
+    }
+    let res = invoice::Entity::update_many()
+        .col_expr(
+            invoice::Column::InvoiceStatus,
+            Expr::value(invoice::InvoiceStatus::Expired),
+        )
+        .col_expr(invoice::Column::UpdatedAt, Expr::value(now))
+        .filter(invoice::Column::Hash.is_in(expired.iter().map(|i| i.hash.clone())))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+
+    // The invoice is already expired here, a payment the node still takes is a late payment
+    // that capture reports
+    for i in expired {
+        if let Some(payment_hash) = i.payment_hash {
+            if let Err(err) = lightning.backend.cancel_invoice(&payment_hash).await {
+                warn!(
+                    "Failed to cancel Lightning invoice {} of invoice {}: {}",
+                    payment_hash, i.hash, err
+                );
+            }
+        }
+    }
+    Ok(res.rows_affected)
 }
 
 pub struct SaleRpcService {
     node_id: NodeId,
     rx: Arc<RwLock<NetworkTunnelReceiver>>,
     rpc: RpcModule<SaleRpcImpl>,
+    sale: SaleRpcImpl,
+    capture_worker: Arc<AtomicBool>,
 }
 
 impl SaleRpcService {
@@ -646,24 +1889,25 @@ impl SaleRpcService {
         master_merchant_hash: Arc<Hash>,
         kek_custodian: Arc<KeyEncryptionKeyCustodian>,
         database: DatabaseConnection,
-        bitcoin_rpc_endpoint: String,
-        bitcoin_rpc_auth: String,
+        gateway_config: GatewayConfig,
+        rates: RateProviderConfig,
+        webhooks: WebhookConfig,
+        checkout_url: String,
         _network: Network,
     ) -> anyhow::Result<(NetworkTunnelSender, Arc<Self>)> {
         let (public_tx, public_network_rx) = mpsc::channel(1024);
 
         // Sale Rpc
-        let bitcoin_gateway_config = BitcoinRpcConfig {
-            endpoint: bitcoin_rpc_endpoint,
-            basic_auth: Some(bitcoin_rpc_auth),
-        };
-        let rpc = SaleRpcImpl {
+        let sale = SaleRpcImpl {
             master_merchant_hash,
             kek_custodian,
             database,
-            bitcoin_gateway_config,
-        }
-        .into_rpc();
+            gateway_config,
+            rates,
+            webhooks,
+            checkout_url,
+        };
+        let rpc = sale.clone().into_rpc();
 
         Ok((
             public_tx,
@@ -671,11 +1915,22 @@ impl SaleRpcService {
                 node_id,
                 rx: Arc::new(RwLock::new(public_network_rx)),
                 rpc,
+                sale,
+                capture_worker: Arc::new(AtomicBool::new(false)),
             }),
         ))
     }
 }
 
+/// Frees the node's capture worker when its batch ends, even if the batch panicked
+struct CaptureWorkerGuard(Arc<AtomicBool>);
+
+impl Drop for CaptureWorkerGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
 #[async_trait]
 impl RpcService<SaleRpcImpl> for SaleRpcService {
     fn log_target(&self) -> String {
@@ -697,6 +1952,39 @@ impl RpcService<SaleRpcImpl> for SaleRpcService {
     fn rpc(&self) -> RpcModule<SaleRpcImpl> {
         self.rpc.clone()
     }
+
+    async fn housekeeping_behavior(
+        &self,
+        state: RpcServiceState<SaleRpcImpl>,
+    ) -> anyhow::Result<()> {
+        state.prune().await;
+        // A failed sweep is retried on the next tick instead of stopping the service
+        match expire_invoices(&self.sale.database, &self.sale.gateway_config.lightning).await {
+            Ok(0) => {}
+            Ok(expired) => info!(target: &self.log_target(), "Expired {} invoices", expired),
+            Err(err) => warn!(target: &self.log_target(), "Failed to expire invoices {}", err),
+        }
+        // Captures and deliveries outlive a tick, at most one batch runs per node
+        if !self.capture_worker.swap(true, Ordering::SeqCst) {
+            let sale = self.sale.clone();
+            let capture_worker = CaptureWorkerGuard(self.capture_worker.clone());
+            let target = self.log_target();
+            tokio::spawn(async move {
+                let _capture_worker = capture_worker;
+                match sale.run_capture_jobs().await {
+                    Ok(0) => {}
+                    Ok(ran) => debug!(target: &target, "Ran {} capture jobs", ran),
+                    Err(err) => warn!(target: &target, "Failed to run capture jobs {}", err),
+                }
+                match sale.run_webhook_deliveries().await {
+                    Ok(0) => {}
+                    Ok(sent) => debug!(target: &target, "Sent {} webhook deliveries", sent),
+                    Err(err) => warn!(target: &target, "Failed to send webhook deliveries {}", err),
+                }
+            });
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -705,13 +1993,32 @@ mod tests {
     use sea_orm::Database;
     use serde_json::json;
 
+    use moonramp_core::Amount;
     use moonramp_migration::testing::setup_testdb;
+    use moonramp_program::{
+        BitcoinCashRpcConfig, BitcoinRpcConfig, EthereumRpcConfig, LightningInvoiceState,
+        LightningRpcConfig, MockLightningBackend, MoneroRpcConfig,
+    };
     use moonramp_wallet::{BitcoinWallet, Currency, Network, Ticker};
 
-    async fn test_rpc(
+    use crate::{
+        rate::{FileRateProvider, StaticRateProvider},
+        webhook::MockWebhookDelivery,
+    };
+
+    async fn test_rpc(
+        create_wallet: bool,
+        create_invoice: bool,
+    ) -> anyhow::Result<(Hash, Option<Hash>, Option<Hash>, RpcModule<SaleRpcImpl>)> {
+        let (merchant_hash, wallet_hash, invoice_hash, rpc) =
+            test_sale_rpc(create_wallet, create_invoice).await?;
+        Ok((merchant_hash, wallet_hash, invoice_hash, rpc.into_rpc()))
+    }
+
+    async fn test_sale_rpc(
         create_wallet: bool,
         create_invoice: bool,
-    ) -> anyhow::Result<(Hash, Option<Hash>, Option<Hash>, RpcModule<SaleRpcImpl>)> {
+    ) -> anyhow::Result<(Hash, Option<Hash>, Option<Hash>, SaleRpcImpl)> {
         let database = Database::connect("sqlite::memory:")
             .await
             .expect("Failed to open in-memory sqlite db");
@@ -787,6 +2094,7 @@ mod tests {
                 ticker: Set(w.ticker().into()),
                 network: Set(w.network().into()),
                 wallet_type: Set(w.wallet_type().into()),
+                wallet_status: Set(wallet::WalletStatus::Active),
                 pubkey: Set(w.pubkey().to_string()),
                 encryption_key_hash: Set(ek_custodian.hash()),
                 cipher: Set(Cipher::Aes256GcmSiv),
@@ -821,8 +2129,19 @@ mod tests {
                 invoice_status: Set(invoice::InvoiceStatus::Pending),
                 pubkey: Set("12345".to_string()),
                 address: Set(address.clone()),
-                amount: Set(0.00001000),
+                amount: Set(Amount::new(1000, 8).expect("Invalid amount")),
                 uri: Set(format!("bitcoin:{}", address)),
+                contract: Set(None),
+                payment_hash: Set(None),
+                start_height: Set(None),
+                fiat_amount: Set(None),
+                fiat_currency: Set(None),
+                rate: Set(None),
+                rate_source: Set(None),
+                quoted_at: Set(None),
+                late_paid_at: Set(None),
+                canceled_at: Set(None),
+                cancel_reason: Set(None),
                 encryption_key_hash: Set(ek_custodian.hash()),
                 cipher: Set(Cipher::Aes256GcmSiv),
                 blob: Set(ciphertext),
@@ -838,17 +2157,34 @@ mod tests {
             None
         };
 
-        let bitcoin_gateway_config = BitcoinRpcConfig {
-            endpoint: "http://localhost:18443".to_string(),
-            basic_auth: None,
+        let gateway_config = GatewayConfig {
+            bitcoin: BitcoinRpcConfig {
+                endpoint: "http://localhost:18443".to_string(),
+                basic_auth: None,
+            },
+            bitcoin_cash: BitcoinCashRpcConfig {
+                endpoint: "http://localhost:28443".to_string(),
+                basic_auth: None,
+            },
+            ethereum: EthereumRpcConfig {
+                endpoint: "http://localhost:8545".to_string(),
+            },
+            lightning: LightningRpcConfig::new(MockLightningBackend::default()),
+            monero: MoneroRpcConfig::new("http://localhost:18082/json_rpc".to_string()),
         };
         let rpc = SaleRpcImpl {
             master_merchant_hash: Arc::new(t.merchant_hash.clone()),
             kek_custodian,
             database,
-            bitcoin_gateway_config,
-        }
-        .into_rpc();
+            gateway_config,
+            rates: RateProviderConfig::new(StaticRateProvider::new("test".to_string()).with_rate(
+                Currency::BTC,
+                "USD",
+                Amount::new(2000000, 2).expect("Invalid amount"),
+            )),
+            webhooks: WebhookConfig::new(MockWebhookDelivery::default()),
+            checkout_url: "http://127.0.0.1:9371".to_string(),
+        };
         Ok((t.merchant_hash, wallet_hash, invoice_hash, rpc))
     }
 
@@ -869,7 +2205,7 @@ mod tests {
                             "hash": wallet_hash.to_string(),
                             "uuid": "12345",
                             "currency": "BTC",
-                            "amount": 0.00001000,
+                            "amount": "0.00001000",
                         },
                     },
                     "id": "12345",
@@ -887,13 +2223,190 @@ mod tests {
             json_rpc["result"]["ticker"],
             serde_json::Value::String("BTC".to_string())
         );
-        assert_eq!(json_rpc["result"]["amount"], 0.00001000,);
+        assert_eq!(json_rpc["result"]["amount"], "0.00001000");
         assert_eq!(
             json_rpc["result"]["address"],
             serde_json::Value::String("test_address".to_string())
         );
     }
 
+    #[tokio::test]
+    async fn test_sale_invoice_fiat_ok() {
+        let (merchant_hash, wallet_hash, _, rpc) = test_rpc(true, false)
+            .await
+            .expect("Failed to create RpcModule<SaleRpcImpl>");
+        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.invoice",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": wallet_hash.to_string(),
+                            "uuid": "12345",
+                            "currency": "BTC",
+                            "fiat": {
+                                "amount": "10.00",
+                                "currency": "usd",
+                            },
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["amount"], "0.00050000");
+        assert_eq!(json_rpc["result"]["fiatAmount"], "10.00");
+        assert_eq!(
+            json_rpc["result"]["fiatCurrency"],
+            serde_json::Value::String("USD".to_string())
+        );
+        assert_eq!(json_rpc["result"]["rate"], "20000.00");
+        assert_eq!(
+            json_rpc["result"]["rateSource"],
+            serde_json::Value::String("test".to_string())
+        );
+        assert_ne!(json_rpc["result"]["quotedAt"], serde_json::Value::Null);
+
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.invoice",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": wallet_hash.to_string(),
+                            "uuid": "12346",
+                            "currency": "BTC",
+                            "fiat": {
+                                "amount": "10.00",
+                                "currency": "JPY",
+                            },
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_sale_invoice_rate_locked() {
+        let (merchant_hash, wallet_hash, _, mut sale_rpc) = test_sale_rpc(true, false)
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let rate_path = std::env::temp_dir().join(format!(
+            "moonramp-rates-{}.json",
+            moonramp_core::uuid::Uuid::new_v4().to_simple()
+        ));
+        std::fs::write(&rate_path, r#"{"BTC": {"USD": "20000.00"}}"#)
+            .expect("Failed to write rates");
+        sale_rpc.rates = RateProviderConfig::new(
+            FileRateProvider::new(
+                &rate_path,
+                std::time::Duration::from_secs(0),
+                Duration::seconds(60),
+            )
+            .expect("Invalid rates"),
+        );
+        let rates = sale_rpc.rates.clone();
+        let rpc = sale_rpc.into_rpc();
+
+        let (resp, _) = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.invoice",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": wallet_hash.to_string(),
+                            "uuid": "12345",
+                            "currency": "BTC",
+                            "fiat": {
+                                "amount": "10.00",
+                                "currency": "USD",
+                            },
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["amount"], "0.00050000");
+        let invoice_hash: Hash = serde_json::from_value(json_rpc["result"]["hash"].clone())
+            .expect("Invalid invoice hash");
+
+        // The rate moves after the invoice was created
+        std::fs::write(&rate_path, r#"{"BTC": {"USD": "25000.00"}}"#)
+            .expect("Failed to write rates");
+        let quote = rates
+            .provider
+            .quote(&Currency::BTC, "USD")
+            .await
+            .expect("Invalid quote");
+        assert_eq!(
+            quote
+                .crypto_amount(
+                    &Currency::BTC,
+                    Amount::new(1000, 2).expect("Invalid amount")
+                )
+                .expect("Invalid amount")
+                .to_string(),
+            "0.00040000"
+        );
+
+        let (resp, _) = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.capture",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": invoice_hash.to_string(),
+                            "uuid": "12345",
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["amount"], "0.00050000");
+
+        let json_rpc = invoice_status(&rpc, &merchant_hash, &invoice_hash).await;
+        assert_eq!(json_rpc["result"]["amount"], "0.00050000");
+        assert_eq!(json_rpc["result"]["rate"], "20000.00");
+
+        std::fs::remove_file(&rate_path).expect("Failed to remove rates");
+    }
+
     #[tokio::test]
     async fn test_sale_invoice_not_ok() {
         let (merchant_hash, _, _, rpc) = test_rpc(false, false)
@@ -926,23 +2439,263 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_sale_invoice_lookup_ok() {
-        let (merchant_hash, wallet_hash, _, rpc) = test_rpc(true, false)
+    async fn test_sale_invoice_address_used() {
+        let (merchant_hash, wallet_hash, invoice_hash, sale_rpc) = test_sale_rpc(true, true)
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let invoice_hash = invoice_hash.expect("Invalid invoice hash");
+        let database = sale_rpc.database.clone();
+        let rpc = sale_rpc.into_rpc();
+
+        // The test program always hands out `test_address`, the address of the pending invoice
+        let request = serde_json::to_string(&json!({
+            "jsonrpc": "2.0",
+            "method": "sale.invoice",
+            "params": {
+                "merchant_hash": merchant_hash,
+                "request": {
+                    "hash": wallet_hash.to_string(),
+                    "uuid": "12345",
+                    "currency": "BTC",
+                    "amount": "0.00001000",
+                },
+            },
+            "id": "12345",
+        }))
+        .expect("Invalid request");
+        let (resp, _) = rpc
+            .raw_json_request(&request)
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_eq!(
+            json_rpc["error"]["message"],
+            json!(format!(
+                "Program returned address test_address which is already used by Pending invoice {}",
+                invoice_hash
+            ))
+        );
+        assert_eq!(
+            invoice::Entity::find()
+                .all(&database)
+                .await
+                .expect("Failed to find invoices")
+                .len(),
+            1
+        );
+
+        let mut i: invoice::ActiveModel = invoice::Entity::find_by_id(invoice_hash)
+            .one(&database)
+            .await
+            .expect("Failed to find invoice")
+            .expect("Invalid invoice")
+            .into();
+        i.invoice_status = Set(invoice::InvoiceStatus::Expired);
+        i.update(&database).await.expect("Failed to update invoice");
+
+        let (resp, _) = rpc
+            .raw_json_request(&request)
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["address"], json!("test_address"));
+    }
+
+    #[tokio::test]
+    async fn test_sale_invoice_lookup_ok() {
+        let (merchant_hash, wallet_hash, _, rpc) = test_rpc(true, false)
+            .await
+            .expect("Failed to create RpcModule<SaleRpcImpl>");
+        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.invoice",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": wallet_hash.to_string(),
+                            "uuid": "12345",
+                            "currency": "BTC",
+                            "amount": "0.00001000",
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.invoiceLookup",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": json_rpc["result"]["hash"],
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_ne!(json_rpc["result"]["hash"], serde_json::Value::Null);
+        assert_eq!(
+            json_rpc["result"]["ticker"],
+            serde_json::Value::String("BTC".to_string())
+        );
+        assert_eq!(json_rpc["result"]["amount"], "0.00001000");
+        assert_eq!(
+            json_rpc["result"]["address"],
+            serde_json::Value::String("test_address".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sale_invoice_lookup_not_ok() {
+        let (merchant_hash, _, _, rpc) = test_rpc(false, false)
+            .await
+            .expect("Failed to create RpcModule<SaleRpcImpl>");
+
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.invoiceLookup",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": merchant_hash,
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+    }
+
+    async fn invoice_cancel(
+        rpc: &RpcModule<SaleRpcImpl>,
+        merchant_hash: &Hash,
+        invoice_hash: &Hash,
+        reason: &str,
+    ) -> serde_json::Value {
+        let (resp, _) = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "sale.invoiceCancel",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": invoice_hash.to_string(),
+                            "reason": reason,
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await
+            .expect("Invalid response");
+        serde_json::from_str(&resp).expect("Invalid json response")
+    }
+
+    #[tokio::test]
+    async fn test_sale_invoice_cancel_ok() {
+        let (merchant_hash, _, invoice_hash, sale_rpc) = test_sale_rpc(true, true)
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let invoice_hash = invoice_hash.expect("Invalid invoice hash");
+        let database = sale_rpc.database.clone();
+        let lightning = sale_rpc.gateway_config.lightning.clone();
+        let rpc = sale_rpc.into_rpc();
+
+        // Canceling a BTCLN invoice cancels it on the Lightning node too
+        let ln_invoice = lightning
+            .backend
+            .create_invoice(1_000_000, "test".to_string(), 10)
+            .await
+            .expect("Failed to create Lightning invoice");
+        let mut i: invoice::ActiveModel = invoice::Entity::find_by_id(invoice_hash.clone())
+            .one(&database)
+            .await
+            .expect("Failed to find invoice")
+            .expect("Invalid invoice")
+            .into();
+        i.payment_hash = Set(Some(ln_invoice.payment_hash.clone()));
+        i.update(&database).await.expect("Failed to update invoice");
+
+        let json_rpc = invoice_cancel(&rpc, &merchant_hash, &invoice_hash, "abandoned").await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Canceled");
+        assert_eq!(json_rpc["result"]["cancelReason"], "abandoned");
+        let canceled_at = json_rpc["result"]["canceledAt"].clone();
+        assert_ne!(canceled_at, serde_json::Value::Null);
+        assert_eq!(
+            lightning
+                .backend
+                .lookup_invoice(&ln_invoice.payment_hash)
+                .await
+                .expect("Failed to lookup Lightning invoice")
+                .state,
+            LightningInvoiceState::Canceled
+        );
+
+        let json_rpc = invoice_cancel(&rpc, &merchant_hash, &invoice_hash, "again").await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Canceled");
+        assert_eq!(json_rpc["result"]["cancelReason"], "abandoned");
+        assert_eq!(json_rpc["result"]["canceledAt"], canceled_at);
+    }
+
+    #[tokio::test]
+    async fn test_sale_invoice_cancel_not_ok() {
+        let (merchant_hash, _, invoice_hash, rpc) = test_rpc(true, true)
             .await
             .expect("Failed to create RpcModule<SaleRpcImpl>");
-        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let invoice_hash = invoice_hash.expect("Invalid invoice hash");
+
+        let json_rpc = invoice_cancel(&rpc, &merchant_hash, &merchant_hash, "abandoned").await;
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+
         let result = rpc
             .raw_json_request(
                 &serde_json::to_string(&json!({
                     "jsonrpc": "2.0",
-                    "method": "sale.invoice",
+                    "method": "sale.capture",
                     "params": {
                         "merchant_hash": merchant_hash,
                         "request": {
-                            "hash": wallet_hash.to_string(),
+                            "hash": invoice_hash.to_string(),
                             "uuid": "12345",
-                            "currency": "BTC",
-                            "amount": 0.00001000,
                         },
                     },
                     "id": "12345",
@@ -951,20 +2704,33 @@ mod tests {
             )
             .await;
         assert!(result.is_ok());
-        let (resp, _) = result.expect("Invalid response");
-        let json_rpc: serde_json::Value =
-            serde_json::from_str(&resp).expect("Invalid json response");
-        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+
+        let json_rpc = invoice_cancel(&rpc, &merchant_hash, &invoice_hash, "abandoned").await;
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+        let json_rpc = invoice_status(&rpc, &merchant_hash, &invoice_hash).await;
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Funded");
+        assert_eq!(json_rpc["result"]["cancelReason"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_sale_capture_ok() {
+        let (merchant_hash, _, invoice_hash, rpc) = test_rpc(true, true)
+            .await
+            .expect("Failed to create RpcModule<SaleRpcImpl>");
+        let invoice_hash = invoice_hash.expect("Invalid wallet hash");
 
         let result = rpc
             .raw_json_request(
                 &serde_json::to_string(&json!({
                     "jsonrpc": "2.0",
-                    "method": "sale.invoiceLookup",
+                    "method": "sale.capture",
                     "params": {
                         "merchant_hash": merchant_hash,
                         "request": {
-                            "hash": json_rpc["result"]["hash"],
+                            "hash": invoice_hash.to_string(),
+                            "uuid": "12345",
+                            "amount": "0.00001000",
                         },
                     },
                     "id": "12345",
@@ -982,28 +2748,23 @@ mod tests {
             json_rpc["result"]["ticker"],
             serde_json::Value::String("BTC".to_string())
         );
-        assert_eq!(json_rpc["result"]["amount"], 0.00001000,);
+        assert_eq!(json_rpc["result"]["amount"], "0.00001000");
         assert_eq!(
             json_rpc["result"]["address"],
             serde_json::Value::String("test_address".to_string())
         );
-    }
-
-    #[tokio::test]
-    async fn test_sale_invoice_lookup_not_ok() {
-        let (merchant_hash, _, _, rpc) = test_rpc(false, false)
-            .await
-            .expect("Failed to create RpcModule<SaleRpcImpl>");
 
         let result = rpc
             .raw_json_request(
                 &serde_json::to_string(&json!({
                     "jsonrpc": "2.0",
-                    "method": "sale.invoiceLookup",
+                    "method": "sale.capture",
                     "params": {
                         "merchant_hash": merchant_hash,
                         "request": {
-                            "hash": merchant_hash,
+                            "hash": invoice_hash.to_string(),
+                            "uuid": "12345",
+                            "amount": "0.00001000",
                         },
                     },
                     "id": "12345",
@@ -1016,50 +2777,94 @@ mod tests {
         let json_rpc: serde_json::Value =
             serde_json::from_str(&resp).expect("Invalid json response");
         assert_eq!(json_rpc["error"], serde_json::Value::Null);
-        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_ne!(json_rpc["result"]["hash"], serde_json::Value::Null);
     }
 
-    #[tokio::test]
-    async fn test_sale_capture_ok() {
-        let (merchant_hash, _, invoice_hash, rpc) = test_rpc(true, true)
-            .await
-            .expect("Failed to create RpcModule<SaleRpcImpl>");
-        let invoice_hash = invoice_hash.expect("Invalid wallet hash");
-
-        let result = rpc
+    async fn invoice_status(
+        rpc: &RpcModule<SaleRpcImpl>,
+        merchant_hash: &Hash,
+        invoice_hash: &Hash,
+    ) -> serde_json::Value {
+        let (resp, _) = rpc
             .raw_json_request(
                 &serde_json::to_string(&json!({
                     "jsonrpc": "2.0",
-                    "method": "sale.capture",
+                    "method": "sale.invoiceLookup",
                     "params": {
                         "merchant_hash": merchant_hash,
                         "request": {
                             "hash": invoice_hash.to_string(),
-                            "uuid": "12345",
-                            "amount": 0.00001000,
                         },
                     },
                     "id": "12345",
                 }))
                 .expect("Invalid request"),
             )
-            .await;
-        assert!(result.is_ok());
-        let (resp, _) = result.expect("Invalid response");
-        let json_rpc: serde_json::Value =
-            serde_json::from_str(&resp).expect("Invalid json response");
-        assert_eq!(json_rpc["error"], serde_json::Value::Null);
-        assert_ne!(json_rpc["result"]["hash"], serde_json::Value::Null);
+            .await
+            .expect("Invalid response");
+        serde_json::from_str(&resp).expect("Invalid json response")
+    }
+
+    #[tokio::test]
+    async fn test_expire_invoices() {
+        let (merchant_hash, _, invoice_hash, sale_rpc) = test_sale_rpc(true, true)
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let invoice_hash = invoice_hash.expect("Invalid invoice hash");
+        let database = sale_rpc.database.clone();
+        let lightning = sale_rpc.gateway_config.lightning.clone();
+        let rpc = sale_rpc.into_rpc();
+
         assert_eq!(
-            json_rpc["result"]["ticker"],
-            serde_json::Value::String("BTC".to_string())
+            expire_invoices(&database, &lightning)
+                .await
+                .expect("Failed to expire invoices"),
+            0
         );
-        assert_eq!(json_rpc["result"]["amount"], 0.00001000,);
+
+        let mut i: invoice::ActiveModel = invoice::Entity::find_by_id(invoice_hash.clone())
+            .one(&database)
+            .await
+            .expect("Failed to find invoice")
+            .expect("Invalid invoice")
+            .into();
+        // Expiring a BTCLN invoice cancels it on the Lightning node
+        let ln_invoice = lightning
+            .backend
+            .create_invoice(1_000_000, "test".to_string(), 10)
+            .await
+            .expect("Failed to create Lightning invoice");
+        i.payment_hash = Set(Some(ln_invoice.payment_hash.clone()));
+        i.expires_at = Set(Utc::now() - Duration::seconds(1));
+        i.update(&database).await.expect("Failed to update invoice");
+
         assert_eq!(
-            json_rpc["result"]["address"],
-            serde_json::Value::String("test_address".to_string())
+            expire_invoices(&database, &lightning)
+                .await
+                .expect("Failed to expire invoices"),
+            1
+        );
+        assert_eq!(
+            lightning
+                .backend
+                .lookup_invoice(&ln_invoice.payment_hash)
+                .await
+                .expect("Failed to lookup Lightning invoice")
+                .state,
+            LightningInvoiceState::Canceled
+        );
+        assert_eq!(
+            expire_invoices(&database, &lightning)
+                .await
+                .expect("Failed to expire invoices"),
+            0
         );
 
+        let json_rpc = invoice_status(&rpc, &merchant_hash, &invoice_hash).await;
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Expired");
+        assert_eq!(json_rpc["result"]["latePaidAt"], serde_json::Value::Null);
+
+        // The test program always reports the invoice funded
         let result = rpc
             .raw_json_request(
                 &serde_json::to_string(&json!({
@@ -1070,7 +2875,6 @@ mod tests {
                         "request": {
                             "hash": invoice_hash.to_string(),
                             "uuid": "12345",
-                            "amount": 0.00001000,
                         },
                     },
                     "id": "12345",
@@ -1083,7 +2887,11 @@ mod tests {
         let json_rpc: serde_json::Value =
             serde_json::from_str(&resp).expect("Invalid json response");
         assert_eq!(json_rpc["error"], serde_json::Value::Null);
-        assert_ne!(json_rpc["result"]["hash"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["amount"], "0.00001000");
+
+        let json_rpc = invoice_status(&rpc, &merchant_hash, &invoice_hash).await;
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Expired");
+        assert_ne!(json_rpc["result"]["latePaidAt"], serde_json::Value::Null);
     }
 
     #[tokio::test]
@@ -1117,6 +2925,551 @@ mod tests {
         );
     }
 
+    async fn sale_request(
+        rpc: &RpcModule<SaleRpcImpl>,
+        method: &str,
+        params: serde_json::Value,
+    ) -> serde_json::Value {
+        let (resp, _) = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": method,
+                    "params": params,
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await
+            .expect("Invalid response");
+        serde_json::from_str(&resp).expect("Invalid json response")
+    }
+
+    async fn capture_async(
+        rpc: &RpcModule<SaleRpcImpl>,
+        merchant_hash: &Hash,
+        invoice_hash: &Hash,
+        webhook_id: &serde_json::Value,
+    ) -> serde_json::Value {
+        sale_request(
+            rpc,
+            "sale.captureAsync",
+            json!({
+                "merchant_hash": merchant_hash,
+                "request": {
+                    "hash": invoice_hash.to_string(),
+                    "uuid": "12345",
+                    "webhookId": webhook_id,
+                },
+            }),
+        )
+        .await
+    }
+
+    async fn webhook_create(
+        rpc: &RpcModule<SaleRpcImpl>,
+        merchant_hash: &Hash,
+        url: &str,
+        event_types: serde_json::Value,
+    ) -> serde_json::Value {
+        sale_request(
+            rpc,
+            "sale.webhookCreate",
+            json!({
+                "merchant_hash": merchant_hash,
+                "request": {
+                    "url": url,
+                    "eventTypes": event_types,
+                },
+            }),
+        )
+        .await
+    }
+
+    async fn webhook_deliveries(
+        rpc: &RpcModule<SaleRpcImpl>,
+        merchant_hash: &Hash,
+        webhook_hash: &serde_json::Value,
+    ) -> serde_json::Value {
+        sale_request(
+            rpc,
+            "sale.webhookDeliveries",
+            json!({
+                "merchant_hash": merchant_hash,
+                "request": {
+                    "webhook_hash": webhook_hash,
+                },
+            }),
+        )
+        .await
+    }
+
+    async fn test_sale_rpc_with_webhooks(
+    ) -> anyhow::Result<(Hash, Hash, MockWebhookDelivery, SaleRpcImpl)> {
+        let (merchant_hash, _, invoice_hash, sale_rpc) = test_sale_rpc(true, true).await?;
+        let webhooks = MockWebhookDelivery::default();
+        let sale_rpc = SaleRpcImpl {
+            webhooks: WebhookConfig::new(webhooks.clone()),
+            ..sale_rpc
+        };
+        Ok((
+            merchant_hash,
+            invoice_hash.expect("Invalid invoice hash"),
+            webhooks,
+            sale_rpc,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_sale_capture_async_ok() {
+        let (merchant_hash, invoice_hash, webhooks, sale_rpc) = test_sale_rpc_with_webhooks()
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let rpc = sale_rpc.clone().into_rpc();
+
+        let json_rpc = webhook_create(&rpc, &merchant_hash, "http://shop/hooks", json!([])).await;
+        let webhook_hash = json_rpc["result"]["hash"].clone();
+
+        let json_rpc = capture_async(&rpc, &merchant_hash, &invoice_hash, &webhook_hash).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["asyncSaleStatus"], "Pending");
+        assert_eq!(json_rpc["result"]["saleHash"], serde_json::Value::Null);
+        let hash = json_rpc["result"]["hash"].clone();
+
+        let json_rpc = capture_async(&rpc, &merchant_hash, &invoice_hash, &webhook_hash).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["hash"], hash);
+
+        assert_eq!(
+            sale_rpc
+                .run_capture_jobs()
+                .await
+                .expect("Failed to run capture jobs"),
+            1
+        );
+        assert_eq!(
+            sale_rpc
+                .run_webhook_deliveries()
+                .await
+                .expect("Failed to run webhook deliveries"),
+            1
+        );
+        let requests = webhooks.requests().expect("Invalid requests");
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, "http://shop/hooks");
+        let event = requests[0].event().expect("Invalid event");
+        assert_eq!(event.event_type, WebhookEventType::SaleFunded);
+        assert_eq!(json!(event.hash), hash);
+        assert_eq!(event.data["asyncSaleStatus"], "Funded");
+        assert_eq!(event.data["sale"]["amount"], "0.00001000");
+
+        let json_rpc = invoice_status(&rpc, &merchant_hash, &invoice_hash).await;
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Funded");
+
+        let json_rpc = capture_async(&rpc, &merchant_hash, &invoice_hash, &webhook_hash).await;
+        assert_eq!(json_rpc["result"]["asyncSaleStatus"], "Funded");
+        assert_eq!(json_rpc["result"]["saleHash"], event.data["sale"]["hash"]);
+        assert_ne!(json_rpc["result"]["deliveredAt"], serde_json::Value::Null);
+        assert_eq!(
+            sale_rpc
+                .run_capture_jobs()
+                .await
+                .expect("Failed to run capture jobs"),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sale_capture_async_canceled() {
+        let (merchant_hash, invoice_hash, webhooks, sale_rpc) = test_sale_rpc_with_webhooks()
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let rpc = sale_rpc.clone().into_rpc();
+
+        let json_rpc = capture_async(&rpc, &merchant_hash, &invoice_hash, &json!("orders")).await;
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+
+        let json_rpc = webhook_create(
+            &rpc,
+            &merchant_hash,
+            "http://shop/hooks",
+            json!(["sale.funded"]),
+        )
+        .await;
+        let webhook_hash = json_rpc["result"]["hash"].clone();
+
+        let json_rpc = capture_async(&rpc, &merchant_hash, &merchant_hash, &webhook_hash).await;
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+
+        let json_rpc = capture_async(&rpc, &merchant_hash, &invoice_hash, &webhook_hash).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        invoice_cancel(&rpc, &merchant_hash, &invoice_hash, "abandoned").await;
+
+        // The webhook only subscribed to sale.funded, the job finishes without a delivery
+        assert_eq!(
+            sale_rpc
+                .run_capture_jobs()
+                .await
+                .expect("Failed to run capture jobs"),
+            1
+        );
+        let json_rpc = capture_async(&rpc, &merchant_hash, &invoice_hash, &webhook_hash).await;
+        assert_eq!(json_rpc["result"]["asyncSaleStatus"], "Canceled");
+        assert_eq!(json_rpc["result"]["saleHash"], serde_json::Value::Null);
+        assert_ne!(json_rpc["result"]["deliveredAt"], serde_json::Value::Null);
+        assert_eq!(
+            sale_rpc
+                .run_webhook_deliveries()
+                .await
+                .expect("Failed to run webhook deliveries"),
+            0
+        );
+        assert!(webhooks.requests().expect("Invalid requests").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sale_webhook_ok() {
+        let (merchant_hash, _, _, sale_rpc) = test_sale_rpc_with_webhooks()
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let rpc = sale_rpc.into_rpc();
+
+        let json_rpc = webhook_create(
+            &rpc,
+            &merchant_hash,
+            "http://shop/hooks",
+            json!(["sale.funded", "sale.canceled", "sale.funded"]),
+        )
+        .await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(
+            json_rpc["result"]["eventTypes"],
+            json!(["sale.canceled", "sale.funded"])
+        );
+        assert!(json_rpc["result"]["secret"]
+            .as_str()
+            .expect("Invalid secret")
+            .starts_with("whsec_"));
+        let webhook_hash = json_rpc["result"]["hash"].clone();
+
+        let list = json!({ "merchant_hash": merchant_hash });
+        let json_rpc = sale_request(&rpc, "sale.webhookList", list.clone()).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"][0]["hash"], webhook_hash);
+        assert_eq!(json_rpc["result"][0]["secret"], serde_json::Value::Null);
+
+        let delete = json!({
+            "merchant_hash": merchant_hash,
+            "request": { "hash": webhook_hash },
+        });
+        let json_rpc = sale_request(&rpc, "sale.webhookDelete", delete.clone()).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_ne!(json_rpc["result"]["deletedAt"], serde_json::Value::Null);
+
+        let json_rpc = sale_request(&rpc, "sale.webhookList", list.clone()).await;
+        assert_eq!(json_rpc["result"], json!([]));
+        let json_rpc = sale_request(&rpc, "sale.webhookDelete", delete).await;
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+
+        let json_rpc = webhook_create(&rpc, &merchant_hash, "https://shop/hooks", json!([])).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["url"], "https://shop/hooks");
+        let json_rpc = sale_request(&rpc, "sale.webhookList", list).await;
+        assert_eq!(json_rpc["result"][0]["url"], "https://shop/hooks");
+    }
+
+    #[tokio::test]
+    async fn test_sale_webhook_not_ok() {
+        let (merchant_hash, _, _, sale_rpc) = test_sale_rpc_with_webhooks()
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let rpc = sale_rpc.into_rpc();
+
+        for url in ["ftp://shop/hooks", "/hooks", "not a url"] {
+            let json_rpc = webhook_create(&rpc, &merchant_hash, url, json!([])).await;
+            assert_eq!(json_rpc["result"], serde_json::Value::Null);
+            assert_ne!(json_rpc["error"], serde_json::Value::Null);
+        }
+        let json_rpc = webhook_create(
+            &rpc,
+            &merchant_hash,
+            "http://shop/hooks",
+            json!(["sale.refunded"]),
+        )
+        .await;
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_sale_webhook_deliveries() {
+        let (merchant_hash, invoice_hash, webhooks, sale_rpc) = test_sale_rpc_with_webhooks()
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let rpc = sale_rpc.clone().into_rpc();
+
+        let json_rpc = webhook_create(&rpc, &merchant_hash, "http://shop/hooks", json!([])).await;
+        let webhook_hash = json_rpc["result"]["hash"].clone();
+        let secret = json_rpc["result"]["secret"]
+            .as_str()
+            .expect("Invalid secret")
+            .to_string();
+
+        capture_async(&rpc, &merchant_hash, &invoice_hash, &webhook_hash).await;
+        sale_rpc
+            .run_capture_jobs()
+            .await
+            .expect("Failed to run capture jobs");
+        sale_rpc
+            .run_webhook_deliveries()
+            .await
+            .expect("Failed to run webhook deliveries");
+
+        let requests = webhooks.requests().expect("Invalid requests");
+        let timestamp: i64 = requests[0]
+            .signature
+            .trim_start_matches("t=")
+            .split(',')
+            .next()
+            .and_then(|t| t.parse().ok())
+            .expect("Invalid signature");
+        assert_eq!(
+            requests[0].signature,
+            webhook_signature(&secret, timestamp, &requests[0].body)
+        );
+
+        let json_rpc = webhook_deliveries(&rpc, &merchant_hash, &webhook_hash).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"][0]["deliveryStatus"], "Succeeded");
+        assert_eq!(json_rpc["result"][0]["responseStatus"], 200);
+        assert_eq!(json_rpc["result"][0]["attempt"], 1);
+        let delivery_hash = json_rpc["result"][0]["hash"].clone();
+
+        // A replay is a new attempt of the same event, failures queue a retry
+        webhooks.set_status(500).expect("Invalid status");
+        let replay = json!({
+            "merchant_hash": merchant_hash,
+            "request": { "hash": delivery_hash },
+        });
+        let json_rpc = sale_request(&rpc, "sale.webhookReplay", replay.clone()).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["deliveryStatus"], "Pending");
+        assert_eq!(json_rpc["result"]["attempt"], 2);
+        let json_rpc = sale_request(&rpc, "sale.webhookReplay", replay).await;
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+
+        assert_eq!(
+            sale_rpc
+                .run_webhook_deliveries()
+                .await
+                .expect("Failed to run webhook deliveries"),
+            1
+        );
+        let requests = webhooks.requests().expect("Invalid requests");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].body, requests[1].body);
+
+        let json_rpc = webhook_deliveries(&rpc, &merchant_hash, &webhook_hash).await;
+        let deliveries = json_rpc["result"].as_array().expect("Invalid deliveries");
+        assert_eq!(deliveries.len(), 3);
+        let attempt = |n: i64| {
+            deliveries
+                .iter()
+                .find(|d| d["attempt"] == n)
+                .expect("Invalid attempt")
+        };
+        assert_eq!(attempt(2)["deliveryStatus"], "Failed");
+        assert_eq!(attempt(2)["responseStatus"], 500);
+        assert_ne!(attempt(2)["error"], serde_json::Value::Null);
+        assert_eq!(attempt(3)["deliveryStatus"], "Pending");
+        assert_eq!(
+            sale_rpc
+                .run_webhook_deliveries()
+                .await
+                .expect("Failed to run webhook deliveries"),
+            0
+        );
+    }
+
+    async fn checkout(
+        rpc: &RpcModule<SaleRpcImpl>,
+        merchant_hash: &Hash,
+        wallet_hash: &Hash,
+        webhook_id: &serde_json::Value,
+        success_url: &str,
+    ) -> serde_json::Value {
+        sale_request(
+            rpc,
+            "sale.checkout",
+            json!({
+                "merchant_hash": merchant_hash,
+                "request": {
+                    "hash": wallet_hash.to_string(),
+                    "uuid": "12345",
+                    "currency": "BTC",
+                    "amount": "0.00001000",
+                    "webhookId": webhook_id,
+                    "successUrl": success_url,
+                    "cancelUrl": "https://shop/cart",
+                },
+            }),
+        )
+        .await
+    }
+
+    async fn checkout_lookup(
+        rpc: &RpcModule<SaleRpcImpl>,
+        hash: &serde_json::Value,
+    ) -> serde_json::Value {
+        sale_request(
+            rpc,
+            "sale.checkoutLookup",
+            json!({
+                "request": {
+                    "hash": hash,
+                },
+            }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_sale_checkout_ok() {
+        let (merchant_hash, wallet_hash, _, sale_rpc) = test_sale_rpc(true, false)
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let rpc = sale_rpc.clone().into_rpc();
+
+        let json_rpc = webhook_create(&rpc, &merchant_hash, "http://shop/hooks", json!([])).await;
+        let webhook_hash = json_rpc["result"]["hash"].clone();
+
+        let json_rpc = checkout(
+            &rpc,
+            &merchant_hash,
+            &wallet_hash,
+            &webhook_hash,
+            "https://shop/thanks",
+        )
+        .await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        let hash = json_rpc["result"]["hash"].clone();
+        assert_eq!(
+            json_rpc["result"]["paymentUrl"],
+            format!(
+                "http://127.0.0.1:9371/checkout/{}",
+                hash.as_str().expect("Invalid checkout hash")
+            )
+        );
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Pending");
+        assert_eq!(json_rpc["result"]["address"], "test_address");
+        assert_eq!(json_rpc["result"]["amount"], "0.00001000");
+        assert_eq!(json_rpc["result"]["successUrl"], "https://shop/thanks");
+        assert_eq!(json_rpc["result"]["cancelUrl"], "https://shop/cart");
+        assert_eq!(json_rpc["result"]["pubkey"], serde_json::Value::Null);
+        let invoice_hash = json_rpc["result"]["invoiceHash"].clone();
+
+        let json_rpc = checkout_lookup(&rpc, &hash).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["invoiceHash"], invoice_hash);
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Pending");
+
+        assert_eq!(
+            sale_rpc
+                .run_capture_jobs()
+                .await
+                .expect("Failed to run capture jobs"),
+            1
+        );
+        let json_rpc = checkout_lookup(&rpc, &hash).await;
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Funded");
+    }
+
+    #[tokio::test]
+    async fn test_sale_checkout_without_webhook() {
+        let (merchant_hash, wallet_hash, _, sale_rpc) = test_sale_rpc(true, false)
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let rpc = sale_rpc.clone().into_rpc();
+
+        let json_rpc = checkout(
+            &rpc,
+            &merchant_hash,
+            &wallet_hash,
+            &serde_json::Value::Null,
+            "https://shop/thanks",
+        )
+        .await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        let hash = json_rpc["result"]["hash"].clone();
+
+        assert_eq!(
+            sale_rpc
+                .run_capture_jobs()
+                .await
+                .expect("Failed to run capture jobs"),
+            1
+        );
+        let json_rpc = checkout_lookup(&rpc, &hash).await;
+        assert_eq!(json_rpc["result"]["invoiceStatus"], "Funded");
+
+        // Finished without a webhook to deliver to, the job is done
+        assert_eq!(
+            sale_rpc
+                .run_capture_jobs()
+                .await
+                .expect("Failed to run capture jobs"),
+            0
+        );
+        assert!(webhook_delivery::Entity::find()
+            .all(&sale_rpc.database)
+            .await
+            .expect("Failed to load webhook deliveries")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sale_checkout_not_ok() {
+        let (merchant_hash, wallet_hash, _, sale_rpc) = test_sale_rpc(true, false)
+            .await
+            .expect("Failed to create SaleRpcImpl");
+        let wallet_hash = wallet_hash.expect("Invalid wallet hash");
+        let database = sale_rpc.database.clone();
+        let rpc = sale_rpc.into_rpc();
+
+        let json_rpc = checkout(
+            &rpc,
+            &merchant_hash,
+            &wallet_hash,
+            &json!("orders"),
+            "https://shop/thanks",
+        )
+        .await;
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_ne!(json_rpc["error"], serde_json::Value::Null);
+        // The invoice created before the webhook was found missing is rolled back
+        assert!(invoice::Entity::find()
+            .all(&database)
+            .await
+            .expect("Failed to load invoices")
+            .is_empty());
+
+        let json_rpc = webhook_create(&rpc, &merchant_hash, "http://shop/hooks", json!([])).await;
+        let webhook_hash = json_rpc["result"]["hash"].clone();
+        for url in ["javascript:alert(1)", "/thanks", "not a url"] {
+            let json_rpc = checkout(&rpc, &merchant_hash, &wallet_hash, &webhook_hash, url).await;
+            assert_eq!(json_rpc["result"], serde_json::Value::Null);
+            assert_ne!(json_rpc["error"], serde_json::Value::Null);
+        }
+
+        let json_rpc = checkout_lookup(&rpc, &json!(wallet_hash)).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+    }
+
     #[tokio::test]
     async fn test_sale_lookup_ok() {
         let (merchant_hash, _, invoice_hash, rpc) = test_rpc(true, true)
@@ -1134,7 +3487,7 @@ mod tests {
                         "request": {
                             "hash": invoice_hash.to_string(),
                             "uuid": "12345",
-                            "amount": 0.00001000,
+                            "amount": "0.00001000",
                         },
                     },
                     "id": "12345",
@@ -1174,7 +3527,7 @@ mod tests {
             json_rpc["result"]["ticker"],
             serde_json::Value::String("BTC".to_string())
         );
-        assert_eq!(json_rpc["result"]["amount"], 0.00001000,);
+        assert_eq!(json_rpc["result"]["amount"], "0.00001000");
         assert_eq!(
             json_rpc["result"]["address"],
             serde_json::Value::String("test_address".to_string())
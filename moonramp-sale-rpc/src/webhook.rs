@@ -0,0 +1,376 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bitcoin::hashes::{
+    hmac::{Hmac, HmacEngine},
+    sha256, Hash as _, HashEngine,
+};
+use chrono::{DateTime, Utc};
+use hyper::{
+    client::{
+        connect::{Connected, Connection},
+        HttpConnector,
+    },
+    http::header::CONTENT_TYPE,
+    service::Service,
+    Body, Client, Method, Request, Uri,
+};
+use log::trace;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+use tokio_rustls::{client::TlsStream, rustls::ClientConfig, webpki::DNSNameRef, TlsConnector};
+
+use moonramp_core::{
+    anyhow, async_trait, bitcoin, chrono, hyper, log, serde, serde_json, tokio, tokio_rustls,
+    webpki_roots, Hash,
+};
+
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "MoonRamp-Signature";
+/// A webhook endpoint has this long to connect and answer with a status
+pub const WEBHOOK_POST_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum WebhookEventType {
+    #[serde(rename = "sale.funded")]
+    SaleFunded,
+    #[serde(rename = "sale.expired")]
+    SaleExpired,
+    #[serde(rename = "sale.canceled")]
+    SaleCanceled,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::SaleFunded => "sale.funded",
+            WebhookEventType::SaleExpired => "sale.expired",
+            WebhookEventType::SaleCanceled => "sale.canceled",
+        }
+    }
+}
+
+impl FromStr for WebhookEventType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "sale.funded" => Ok(WebhookEventType::SaleFunded),
+            "sale.expired" => Ok(WebhookEventType::SaleExpired),
+            "sale.canceled" => Ok(WebhookEventType::SaleCanceled),
+            _ => Err(anyhow!("Unknown webhook event type {}", s)),
+        }
+    }
+}
+
+/// The body POSTed to a webhook endpoint
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WebhookEvent {
+    /// Stable across retries and replays, receivers should use it to drop duplicates
+    pub hash: Hash,
+    pub event_type: WebhookEventType,
+    pub created_at: DateTime<Utc>,
+    pub data: serde_json::Value,
+}
+
+/// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`, sent in the
+/// `MoonRamp-Signature` header
+pub fn webhook_signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(format!("{}.", timestamp).as_bytes());
+    engine.input(body);
+    format!(
+        "t={},v1={:x}",
+        timestamp,
+        Hmac::<sha256::Hash>::from_engine(engine)
+    )
+}
+
+/// Sends signed event bodies to webhook endpoints
+#[async_trait]
+pub trait WebhookDelivery: fmt::Debug + Send + Sync {
+    /// POSTs `body` to `url` and returns the response status
+    async fn post(&self, url: &str, signature: &str, body: Vec<u8>) -> anyhow::Result<u16>;
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub delivery: Arc<dyn WebhookDelivery>,
+}
+
+impl WebhookConfig {
+    pub fn new<D: WebhookDelivery + 'static>(delivery: D) -> Self {
+        WebhookConfig {
+            delivery: Arc::new(delivery),
+        }
+    }
+}
+
+/// Connects `http` urls over plain TCP and `https` urls over rustls, trusting the webpki roots
+#[derive(Clone)]
+pub struct HttpsConnector {
+    http: HttpConnector,
+    tls: TlsConnector,
+}
+
+impl HttpsConnector {
+    pub fn new() -> Self {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        let mut config = ClientConfig::new();
+        config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        HttpsConnector {
+            http,
+            tls: TlsConnector::from(Arc::new(config)),
+        }
+    }
+}
+
+impl Default for HttpsConnector {
+    fn default() -> Self {
+        HttpsConnector::new()
+    }
+}
+
+impl fmt::Debug for HttpsConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpsConnector").finish()
+    }
+}
+
+impl Service<Uri> for HttpsConnector {
+    type Response = HttpsStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<HttpsStream>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.http.poll_ready(cx).map_err(io::Error::other)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let https = uri.scheme_str() == Some("https");
+        let host = uri.host().unwrap_or_default().to_string();
+        let connecting = self.http.call(uri);
+        let tls = self.tls.clone();
+        Box::pin(async move {
+            let tcp = connecting.await.map_err(io::Error::other)?;
+            if !https {
+                return Ok(HttpsStream::Http(tcp));
+            }
+            let domain = DNSNameRef::try_from_ascii_str(&host).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid TLS host {}", host),
+                )
+            })?;
+            Ok(HttpsStream::Https(Box::new(
+                tls.connect(domain, tcp).await?,
+            )))
+        })
+    }
+}
+
+pub enum HttpsStream {
+    Http(TcpStream),
+    Https(Box<TlsStream<TcpStream>>),
+}
+
+impl Connection for HttpsStream {
+    fn connected(&self) -> Connected {
+        match self {
+            HttpsStream::Http(s) => s.connected(),
+            HttpsStream::Https(s) => s.get_ref().0.connected(),
+        }
+    }
+}
+
+impl AsyncRead for HttpsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            HttpsStream::Http(s) => Pin::new(s).poll_read(cx, buf),
+            HttpsStream::Https(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for HttpsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            HttpsStream::Http(s) => Pin::new(s).poll_write(cx, buf),
+            HttpsStream::Https(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            HttpsStream::Http(s) => Pin::new(s).poll_flush(cx),
+            HttpsStream::Https(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            HttpsStream::Http(s) => Pin::new(s).poll_shutdown(cx),
+            HttpsStream::Https(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
+/// POSTs over `http` or `https`, giving up after `WEBHOOK_POST_TIMEOUT_SECS`
+#[derive(Debug)]
+pub struct HttpWebhookDelivery {
+    client: Client<HttpsConnector>,
+}
+
+impl Default for HttpWebhookDelivery {
+    fn default() -> Self {
+        HttpWebhookDelivery {
+            client: Client::builder().build(HttpsConnector::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl WebhookDelivery for HttpWebhookDelivery {
+    async fn post(&self, url: &str, signature: &str, body: Vec<u8>) -> anyhow::Result<u16> {
+        trace!("WEBHOOK {}", url);
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_SIGNATURE_HEADER, signature)
+            .body(Body::from(body))?;
+        let res = tokio::time::timeout(
+            tokio::time::Duration::from_secs(WEBHOOK_POST_TIMEOUT_SECS),
+            self.client.request(req),
+        )
+        .await
+        .map_err(|_| anyhow!("Webhook timed out after {}s", WEBHOOK_POST_TIMEOUT_SECS))??;
+        Ok(res.status().as_u16())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockWebhookRequest {
+    pub url: String,
+    pub signature: String,
+    pub body: Vec<u8>,
+}
+
+impl MockWebhookRequest {
+    pub fn event(&self) -> anyhow::Result<WebhookEvent> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// Records requests in memory for tests and local development, answering with `status`
+#[derive(Debug, Clone)]
+pub struct MockWebhookDelivery {
+    requests: Arc<Mutex<Vec<MockWebhookRequest>>>,
+    status: Arc<Mutex<u16>>,
+}
+
+impl Default for MockWebhookDelivery {
+    fn default() -> Self {
+        MockWebhookDelivery {
+            requests: Arc::new(Mutex::new(vec![])),
+            status: Arc::new(Mutex::new(200)),
+        }
+    }
+}
+
+impl MockWebhookDelivery {
+    pub fn requests(&self) -> anyhow::Result<Vec<MockWebhookRequest>> {
+        Ok(self
+            .requests
+            .lock()
+            .map_err(|_| anyhow!("Mock webhook delivery poisoned"))?
+            .clone())
+    }
+
+    pub fn set_status(&self, status: u16) -> anyhow::Result<()> {
+        *self
+            .status
+            .lock()
+            .map_err(|_| anyhow!("Mock webhook delivery poisoned"))? = status;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl WebhookDelivery for MockWebhookDelivery {
+    async fn post(&self, url: &str, signature: &str, body: Vec<u8>) -> anyhow::Result<u16> {
+        self.requests
+            .lock()
+            .map_err(|_| anyhow!("Mock webhook delivery poisoned"))?
+            .push(MockWebhookRequest {
+                url: url.to_string(),
+                signature: signature.to_string(),
+                body,
+            });
+        Ok(*self
+            .status
+            .lock()
+            .map_err(|_| anyhow!("Mock webhook delivery poisoned"))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_signature() {
+        // Receivers verify with any HMAC-SHA256, e.g. Python's
+        // hmac.new(b"Jefe", b"1672531200." + body, sha256)
+        assert_eq!(
+            webhook_signature("Jefe", 1672531200, br#"{"hash":1}"#),
+            "t=1672531200,v1=9e4cbf5e34c81e03bb2d394573b0146528537696934cdcaa57be63b64dfa1112"
+        );
+    }
+
+    #[test]
+    fn test_webhook_event_type() {
+        for event_type in [
+            WebhookEventType::SaleFunded,
+            WebhookEventType::SaleExpired,
+            WebhookEventType::SaleCanceled,
+        ] {
+            assert_eq!(
+                serde_json::to_value(&event_type).expect("Invalid event type"),
+                event_type.as_str()
+            );
+            assert_eq!(
+                event_type
+                    .as_str()
+                    .parse::<WebhookEventType>()
+                    .expect("Invalid event type"),
+                event_type
+            );
+        }
+        assert!("sale.refunded".parse::<WebhookEventType>().is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 
-use moonramp_core::{anyhow, serde};
+use moonramp_core::{anyhow, serde, Amount};
 #[cfg(feature = "entity")]
 use moonramp_entity::invoice;
 use moonramp_wallet::Wallet;
@@ -46,6 +46,8 @@ pub struct Invoice {
     pub pubkey: String,
     pub address: String,
     pub uri: String,
+    pub payment_hash: Option<String>,
+    pub start_height: Option<u64>,
     pub user_data: Option<Vec<u8>>,
 }
 
@@ -58,12 +60,16 @@ impl TryFrom<moonramp_lunar::ExitData> for Invoice {
                 pubkey,
                 address,
                 uri,
+                payment_hash,
+                start_height,
                 user_data,
             } => Ok(Invoice {
                 wallet,
                 pubkey,
                 address,
                 uri,
+                payment_hash,
+                start_height,
                 user_data,
             }),
             _ => Err(anyhow!("ExitData is not sale")),
@@ -75,7 +81,7 @@ impl TryFrom<moonramp_lunar::ExitData> for Invoice {
 #[serde(crate = "moonramp_core::serde")]
 pub struct Sale {
     pub funded: bool,
-    pub amount: f64,
+    pub amount: Amount,
     pub user_data: Option<Vec<u8>>,
 }
 
@@ -109,8 +115,8 @@ mod tests {
             BitcoinWallet::new_cold(
                 Ticker::BTC,
                 Network::Testnet,
-                "p2pkh_12345".to_string(),
-                BitcoinColdWalletType::XPubkey,
+                "tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp".to_string(),
+                BitcoinColdWalletType::P2WPKH,
             )
             .expect("Invalid Wallet"),
         );
@@ -119,6 +125,8 @@ mod tests {
             pubkey: "xpub12345".to_string(),
             address: "12345".to_string(),
             uri: "bitcoin:12345;version=1.0?amount=0.01".to_string(),
+            payment_hash: None,
+            start_height: Some(16_000_000),
             user_data: None,
         };
         assert_eq!(
@@ -128,14 +136,16 @@ mod tests {
                     BitcoinWallet::new_cold(
                         Ticker::BTC,
                         Network::Testnet,
-                        "p2pkh_12345".to_string(),
-                        BitcoinColdWalletType::XPubkey
+                        "tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp".to_string(),
+                        BitcoinColdWalletType::P2WPKH
                     )
                     .expect("Invalid Wallet"),
                 ),
                 pubkey: "xpub12345".to_string(),
                 address: "12345".to_string(),
                 uri: "bitcoin:12345;version=1.0?amount=0.01".to_string(),
+                payment_hash: None,
+                start_height: Some(16_000_000),
                 user_data: None,
             })
         );
@@ -145,14 +155,14 @@ mod tests {
     fn test_sale_try_into() {
         let exit_data = moonramp_lunar::ExitData::Sale {
             funded: true,
-            amount: 0.00001000,
+            amount: Amount::new(1000, 8).expect("Invalid amount"),
             user_data: None,
         };
         assert_eq!(
             exit_data.try_into().ok(),
             Some(Sale {
                 funded: true,
-                amount: 0.00001000,
+                amount: Amount::new(1000, 8).expect("Invalid amount"),
                 user_data: None,
             })
         );
@@ -4,6 +4,7 @@ use actix_web::{
     dev::Server, get, guard, post, web, App, HttpRequest, HttpResponse, HttpServer, Responder,
 };
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use log::warn;
 use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -11,7 +12,7 @@ use tokio::time::{Duration, Instant};
 use uuid::Uuid;
 
 use moonramp_core::{
-    actix_web, actix_web_httpauth, anyhow, sea_orm, serde, serde_json, tokio, uuid,
+    actix_web, actix_web_httpauth, anyhow, log, sea_orm, serde, serde_json, tokio, uuid,
     NetworkTunnelSender, Sender, TunnelName,
 };
 use moonramp_encryption::KeyEncryptionKeyCustodian;
@@ -104,34 +105,77 @@ async fn jsonrpc(
         Some("wallet.version") => true,
         Some("wallet.create") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
         Some("wallet.lookup") => check_roles(&rs, role::Resource::Wallet, role::Scope::Read),
+        Some("wallet.script") => check_roles(&rs, role::Resource::Wallet, role::Scope::Read),
+        Some("wallet.balance") => check_roles(&rs, role::Resource::Wallet, role::Scope::Read),
+        Some("wallet.utxos") => check_roles(&rs, role::Resource::Wallet, role::Scope::Read),
+        Some("wallet.sweep") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
+        Some("wallet.createPsbt") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
+        Some("wallet.finalizePsbt") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
+        Some("wallet.update") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
+        Some("wallet.delete") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
+        Some("wallet.rotateKey") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
+        Some("wallet.rescan") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
+        Some("wallet.rescanStatus") => check_roles(&rs, role::Resource::Wallet, role::Scope::Read),
+        Some("wallet.export") => check_roles(&rs, role::Resource::WalletSecret, role::Scope::Write),
+        Some("wallet.import") => check_roles(&rs, role::Resource::Wallet, role::Scope::Write),
         _ => false,
     };
 
-    if !allowed {
-        return Err(HttpError::Unauthorized)?;
-    }
-
     let sender = req
         .peer_addr()
         .map(|addr| Sender::from(addr))
         .unwrap_or(Sender::Addr("UNKNOWN_PEER_ADDR".to_string()));
     let id = Uuid::new_v4().to_simple().to_string();
+    let export_hash = match data["method"].as_str() {
+        Some("wallet.export") => Some(data["params"]["request"]["hash"].to_string()),
+        _ => None,
+    };
+    if let Some(wallet_hash) = &export_hash {
+        // Every export attempt is recorded, tokens without the role included, the secret
+        // itself never is
+        warn!(
+            target: "moonramp_wallet_rpc::audit",
+            "{} wallet.export wallet={} merchant={} token={} peer={:?} allowed={}",
+            id,
+            wallet_hash,
+            t.merchant_hash,
+            t.hash,
+            sender,
+            allowed,
+        );
+    }
+
+    if !allowed {
+        return Err(HttpError::Unauthorized)?;
+    }
+
     let msg = network_tunnel(&id, sender, TunnelName::Wallet, data)
         .map_err(|err| err.downcast().unwrap_or(HttpError::ServerError))?;
-    Ok(web::Json(
-        await_response(
-            "moonramp_wallet_rpc::http",
-            state.timeout,
-            start,
-            &state.registry_tx,
+    let res = await_response(
+        "moonramp_wallet_rpc::http",
+        state.timeout,
+        start,
+        &state.registry_tx,
+        id.clone(),
+        msg,
+        "POST",
+        "/jsonrpc",
+    )
+    .await;
+    if let Some(wallet_hash) = export_hash {
+        warn!(
+            target: "moonramp_wallet_rpc::audit",
+            "{} wallet.export wallet={} exported={}",
             id,
-            msg,
-            "POST",
-            "/jsonrpc",
-        )
-        .await
-        .map_err(|err| err.downcast().unwrap_or(HttpError::ServerError))?,
-    ))
+            wallet_hash,
+            res.as_ref()
+                .map(|res| res["result"] != serde_json::Value::Null)
+                .unwrap_or(false),
+        );
+    }
+    Ok(web::Json(res.map_err(|err| {
+        err.downcast().unwrap_or(HttpError::ServerError)
+    })?))
 }
 
 #[get("/version")]
@@ -331,7 +375,7 @@ mod tests {
         NetworkTunnel, NetworkTunnelChannel, NetworkTunnelReceiver, NodeId, RpcTunnel, TunnelName,
         TunnelTopic,
     };
-    use moonramp_migration::testing::setup_testdb;
+    use moonramp_migration::testing::{add_role, setup_testdb};
 
     async fn stub_registry(mut r_rx: NetworkTunnelReceiver) {
         tokio::spawn(async move {
@@ -484,6 +528,80 @@ mod tests {
         );
     }
 
+    #[actix_web::test]
+    async fn test_jsonrpc_export_ok() {
+        let database = Database::connect("sqlite::memory:")
+            .await
+            .expect("Failed to open in-memory sqlite db");
+        let (kek_custodian, cred, t) = setup_testdb(&database, "moonramp")
+            .await
+            .expect("Failed to setup testdb");
+
+        let (r_tx, r_rx) = mpsc::channel(1);
+
+        let test_data = web::Data::new(WalletHttpServerData {
+            timeout: Duration::from_millis(5),
+            kek_custodian,
+            database: database.clone(),
+            registry_tx: r_tx,
+        });
+
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/jsonrpc")
+                    .app_data(test_data)
+                    .guard(guard::Header("content-type", "application/json"))
+                    .service(jsonrpc),
+            ),
+        )
+        .await;
+
+        let export_req = || {
+            test::TestRequest::post()
+                .uri("/jsonrpc")
+                .insert_header((
+                    AUTHORIZATION,
+                    format!("Bearer {}", cred.to_bearer().unwrap()),
+                ))
+                .set_json(json!({
+                    "jsonrpc": "2.0",
+                    "method": "wallet.export",
+                    "params": {
+                        "request": {
+                            "hash": "2kg8XtHv1t5e5soBBFTGehn32sUwuqDmzyJjBtCg5K6q",
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .to_request()
+        };
+
+        // Wallet Write is not enough to read secrets
+        let resp = test::call_service(&app, export_req()).await;
+        assert_eq!(resp.status(), http::StatusCode::UNAUTHORIZED);
+
+        add_role(
+            &database,
+            t.merchant_hash,
+            t.hash,
+            role::Resource::WalletSecret,
+            role::Scope::Write,
+        )
+        .await
+        .expect("Failed to add role");
+
+        stub_registry(r_rx).await;
+
+        let resp = test::call_service(&app, export_req()).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(
+            resp.into_body().try_into_bytes().ok(),
+            Some(Bytes::from(
+                "{\"id\":\"12345\",\"jsonrpc\":\"2.0\",\"result\":true}"
+            ))
+        );
+    }
+
     #[actix_web::test]
     async fn test_version_ok() {
         let database = Database::connect("sqlite::memory:")
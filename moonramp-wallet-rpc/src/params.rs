@@ -1,31 +1,58 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use moonramp_core::{chrono, serde, Hash};
 use moonramp_entity::wallet;
 
-use crate::{BitcoinColdWalletType, MoneroColdWalletType, Network, Ticker, WalletType};
+use crate::{
+    BitcoinColdWalletType, BitcoinDerivation, EthereumColdWalletType, MoneroColdWalletType,
+    Network, Ticker, WalletSecret, WalletType,
+};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
 pub enum WalletCreateRequest {
     BtcHot,
     #[serde(rename_all = "camelCase")]
+    BtcHotAccount {
+        derivation: BitcoinDerivation,
+        account: u32,
+    },
+    #[serde(rename_all = "camelCase")]
     BtcCold {
         pubkey: String,
         cold_type: BitcoinColdWalletType,
     },
+    #[serde(rename_all = "camelCase")]
+    BtcMultisig {
+        threshold: usize,
+        pubkeys: Vec<String>,
+    },
     BchHot,
     #[serde(rename_all = "camelCase")]
+    BchHotAccount {
+        derivation: BitcoinDerivation,
+        account: u32,
+    },
+    #[serde(rename_all = "camelCase")]
     BchCold {
         pubkey: String,
         cold_type: BitcoinColdWalletType,
     },
-    //EthereumHot(ticker::Ticker),
-    //EthereumCold {
-    //    ticker: ticker::Ticker,
-    //    pubkey: String,
-    //},
+    EthHot,
+    #[serde(rename_all = "camelCase")]
+    EthCold {
+        pubkey: String,
+        cold_type: EthereumColdWalletType,
+    },
+    EtcHot,
+    #[serde(rename_all = "camelCase")]
+    EtcCold {
+        pubkey: String,
+        cold_type: EthereumColdWalletType,
+    },
     XmrHot,
     #[serde(rename_all = "camelCase")]
     XmrCold {
@@ -34,6 +61,84 @@ pub enum WalletCreateRequest {
     },
 }
 
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub enum WalletImportRequest {
+    /// BIP39 mnemonic, `derivation` defaults to BIP84 like `btcHot`
+    #[serde(rename_all = "camelCase")]
+    BtcHot {
+        mnemonic: String,
+        #[serde(default)]
+        passphrase: String,
+        derivation: Option<BitcoinDerivation>,
+        #[serde(default)]
+        account: u32,
+    },
+    /// BIP39 mnemonic, `derivation` defaults to BIP44 like `bchHot`
+    #[serde(rename_all = "camelCase")]
+    BchHot {
+        mnemonic: String,
+        #[serde(default)]
+        passphrase: String,
+        derivation: Option<BitcoinDerivation>,
+        #[serde(default)]
+        account: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    EthHot {
+        mnemonic: String,
+        #[serde(default)]
+        passphrase: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    EtcHot {
+        mnemonic: String,
+        #[serde(default)]
+        passphrase: String,
+    },
+    /// 25 word Electrum style seed
+    #[serde(rename_all = "camelCase")]
+    XmrHot { mnemonic: String },
+}
+
+impl fmt::Debug for WalletImportRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WalletImportRequest::BtcHot {
+                derivation,
+                account,
+                ..
+            } => write!(f, "BtcHot({:?}, {})", derivation, account),
+            WalletImportRequest::BchHot {
+                derivation,
+                account,
+                ..
+            } => write!(f, "BchHot({:?}, {})", derivation, account),
+            WalletImportRequest::EthHot { .. } => write!(f, "EthHot"),
+            WalletImportRequest::EtcHot { .. } => write!(f, "EtcHot"),
+            WalletImportRequest::XmrHot { .. } => write!(f, "XmrHot"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletExportRequest {
+    pub hash: Hash,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletExportResponse {
+    pub hash: Hash,
+    pub ticker: Ticker,
+    pub network: Network,
+    pub secret: WalletSecret,
+    /// BTC and BCH derivation scheme and account the secret must be imported with
+    pub derivation: Option<BitcoinDerivation>,
+    pub account: Option<u32>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase", untagged)]
 pub enum WalletLookupRequest {
@@ -41,6 +146,215 @@ pub enum WalletLookupRequest {
     Pubkey { pubkey: String },
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletScriptRequest {
+    pub hash: Hash,
+    pub index: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletScriptResponse {
+    pub hash: Hash,
+    pub index: u64,
+    pub address: String,
+    pub descriptor: String,
+    pub redeem_script: Option<String>,
+    pub witness_script: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub enum SweepDestination {
+    Address(String),
+    Wallet(Hash),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletSweepRequest {
+    pub hash: Hash,
+    pub destination: SweepDestination,
+    /// sat/vB
+    pub fee_rate: u64,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletSweepResponse {
+    pub hash: Hash,
+    pub address: String,
+    pub inputs: usize,
+    /// sats
+    pub amount: u64,
+    /// sats
+    pub fee: u64,
+    pub txid: Option<String>,
+    pub psbt: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletCreatePsbtRequest {
+    pub hash: Hash,
+    pub destination: SweepDestination,
+    /// sat/vB
+    pub fee_rate: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletFinalizePsbtRequest {
+    pub hash: Hash,
+    /// base64
+    pub psbt: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletFinalizePsbtResponse {
+    pub hash: Hash,
+    pub txid: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletBalanceRequest {
+    pub hash: Hash,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletAddressBalance {
+    pub index: u64,
+    pub address: String,
+    /// sats
+    pub confirmed: u64,
+    /// sats
+    pub unconfirmed: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletBalanceResponse {
+    pub hash: Hash,
+    pub ticker: Ticker,
+    /// sats
+    pub confirmed: u64,
+    /// sats
+    pub unconfirmed: u64,
+    /// Addresses holding funds
+    pub addresses: Vec<WalletAddressBalance>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletUtxosRequest {
+    pub hash: Hash,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletUtxo {
+    pub txid: String,
+    pub vout: u32,
+    pub index: u64,
+    pub address: String,
+    /// sats
+    pub amount: u64,
+    /// `None` while in the mempool
+    pub height: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletUtxosResponse {
+    pub hash: Hash,
+    pub ticker: Ticker,
+    pub utxos: Vec<WalletUtxo>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletUpdateRequest {
+    pub hash: Hash,
+    pub wallet_status: wallet::WalletStatus,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletDeleteRequest {
+    pub hash: Hash,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletRotateKeyRequest {
+    pub hash: Hash,
+}
+
+/// BIP44 gap limit, how many unused addresses in a row end a rescan
+pub const DEFAULT_GAP_LIMIT: u64 = 20;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletRescanRequest {
+    pub hash: Hash,
+    pub gap_limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletRescanAddress {
+    pub index: u64,
+    pub address: String,
+    /// sats the address ever received, spent or not
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletRescanResponse {
+    pub hash: Hash,
+    pub ticker: Ticker,
+    pub gap_limit: u64,
+    pub previous_index: u64,
+    pub index: u64,
+    pub highest_used: Option<u64>,
+    /// Used addresses no invoice of the wallet was issued for
+    pub unmatched: Vec<WalletRescanAddress>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletRescanStatusRequest {
+    pub hash: Hash,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub enum WalletRescanStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+/// Background rescan of a wallet, `wallet.rescanStatus` polls it until it is no longer running
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub struct WalletRescanJobResponse {
+    pub hash: Hash,
+    pub status: WalletRescanStatus,
+    pub started_at: DateTime<Utc>,
+    /// Set once the rescan is `Done`
+    pub result: Option<WalletRescanResponse>,
+    /// Set once the rescan `Failed`
+    pub error: Option<String>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
 pub struct WalletResponse {
@@ -48,6 +362,7 @@ pub struct WalletResponse {
     pub ticker: Ticker,
     pub network: Network,
     pub wallet_type: WalletType,
+    pub wallet_status: wallet::WalletStatus,
     pub pubkey: String,
     pub created_at: DateTime<Utc>,
 }
@@ -59,6 +374,7 @@ impl From<wallet::Model> for WalletResponse {
             ticker: model.ticker.into(),
             network: model.network.into(),
             wallet_type: model.wallet_type.into(),
+            wallet_status: model.wallet_status,
             pubkey: model.pubkey,
             created_at: model.created_at,
         }
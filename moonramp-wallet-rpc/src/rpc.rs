@@ -1,169 +1,1420 @@
-use std::sync::Arc;
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap, HashSet},
+    ops::Range,
+    str::FromStr,
+    sync::Arc,
+};
 
+use anyhow::anyhow;
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, RpcModule};
 use log::debug;
-use sea_orm::{entity::*, query::*, DatabaseConnection};
+use sea_orm::{entity::*, query::*, ConnectionTrait, DatabaseConnection};
 use sha3::{Digest, Sha3_256};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, Mutex, RwLock};
 
 use moonramp_core::{
-    anyhow, async_trait, chrono, log, sea_orm, serde_json, sha3, tokio, Hash,
+    anyhow, async_trait,
+    bitcoin::{self, hashes::hex::FromHex},
+    bitcoincore_rpc_json, chrono, log, sea_orm, serde_json, sha3, tokio, Hash,
     NetworkTunnelReceiver, NetworkTunnelSender, NodeId, TunnelName,
 };
 use moonramp_encryption::{
     EncryptionKeyCustodian, KeyCustodian, KeyEncryptionKeyCustodian, MerchantScopedSecret,
 };
-use moonramp_entity::{cipher::Cipher, wallet};
+use moonramp_entity::{cipher::Cipher, currency, encryption_key, invoice, wallet};
+use moonramp_gateway::bitcoin::{
+    get_raw_transaction, get_tx_outs, import_descriptor, imported_range_end,
+    list_received_by_address, list_unspent, load_watch_only_wallet, scan_tx_out,
+    send_raw_transaction, BitcoinRpcConfig,
+};
 use moonramp_rpc::{IntoRpcResult, RpcService};
 
-use crate::{params::*, BitcoinWallet, MoneroWallet, Network, Ticker, Wallet};
+use crate::{
+    descriptor_checksum, finalize_psbt, params::*, psbt_from_base64, psbt_to_base64,
+    verify_psbt_prevouts, BitcoinUtxo, BitcoinWallet, EthereumWallet, MoneroWallet, Network,
+    Ticker, Wallet,
+};
+
+#[rpc(server)]
+pub trait WalletRpc {
+    #[method(name = "wallet.version")]
+    fn version(&self) -> RpcResult<String>;
+
+    #[method(name = "wallet.create")]
+    async fn create(
+        &self,
+        merchant_hash: Hash,
+        request: WalletCreateRequest,
+    ) -> RpcResult<WalletResponse>;
+
+    #[method(name = "wallet.lookup")]
+    async fn lookup(
+        &self,
+        merchant_hash: Hash,
+        request: WalletLookupRequest,
+    ) -> RpcResult<Option<WalletResponse>>;
+
+    #[method(name = "wallet.script")]
+    async fn script(
+        &self,
+        merchant_hash: Hash,
+        request: WalletScriptRequest,
+    ) -> RpcResult<WalletScriptResponse>;
+
+    #[method(name = "wallet.sweep")]
+    async fn sweep(
+        &self,
+        merchant_hash: Hash,
+        request: WalletSweepRequest,
+    ) -> RpcResult<WalletSweepResponse>;
+
+    #[method(name = "wallet.createPsbt")]
+    async fn create_psbt(
+        &self,
+        merchant_hash: Hash,
+        request: WalletCreatePsbtRequest,
+    ) -> RpcResult<WalletSweepResponse>;
+
+    #[method(name = "wallet.finalizePsbt")]
+    async fn finalize_psbt(
+        &self,
+        merchant_hash: Hash,
+        request: WalletFinalizePsbtRequest,
+    ) -> RpcResult<WalletFinalizePsbtResponse>;
+
+    #[method(name = "wallet.balance")]
+    async fn balance(
+        &self,
+        merchant_hash: Hash,
+        request: WalletBalanceRequest,
+    ) -> RpcResult<WalletBalanceResponse>;
+
+    #[method(name = "wallet.utxos")]
+    async fn utxos(
+        &self,
+        merchant_hash: Hash,
+        request: WalletUtxosRequest,
+    ) -> RpcResult<WalletUtxosResponse>;
+
+    #[method(name = "wallet.update")]
+    async fn update(
+        &self,
+        merchant_hash: Hash,
+        request: WalletUpdateRequest,
+    ) -> RpcResult<WalletResponse>;
+
+    #[method(name = "wallet.delete")]
+    async fn delete(
+        &self,
+        merchant_hash: Hash,
+        request: WalletDeleteRequest,
+    ) -> RpcResult<WalletResponse>;
+
+    #[method(name = "wallet.rotateKey")]
+    async fn rotate_key(
+        &self,
+        merchant_hash: Hash,
+        request: WalletRotateKeyRequest,
+    ) -> RpcResult<WalletResponse>;
+
+    #[method(name = "wallet.rescan")]
+    async fn rescan(
+        &self,
+        merchant_hash: Hash,
+        request: WalletRescanRequest,
+    ) -> RpcResult<WalletRescanJobResponse>;
+
+    #[method(name = "wallet.rescanStatus")]
+    async fn rescan_status(
+        &self,
+        merchant_hash: Hash,
+        request: WalletRescanStatusRequest,
+    ) -> RpcResult<WalletRescanJobResponse>;
+
+    #[method(name = "wallet.export")]
+    async fn export(
+        &self,
+        merchant_hash: Hash,
+        request: WalletExportRequest,
+    ) -> RpcResult<WalletExportResponse>;
+
+    #[method(name = "wallet.import")]
+    async fn import(
+        &self,
+        merchant_hash: Hash,
+        request: WalletImportRequest,
+    ) -> RpcResult<WalletResponse>;
+}
+
+#[derive(Clone)]
+pub struct WalletRpcImpl {
+    kek_custodian: Arc<KeyEncryptionKeyCustodian>,
+    database: DatabaseConnection,
+    bitcoin: BitcoinRpcConfig,
+    network: Network,
+    /// Rescans this node ran, by merchant and wallet. A rescan outlives the request starting it
+    rescans: Arc<Mutex<HashMap<(Hash, Hash), WalletRescanJobResponse>>>,
+}
+
+impl WalletRpcImpl {
+    async fn load_wallet(&self, merchant_hash: Hash, hash: Hash) -> anyhow::Result<Wallet> {
+        let (_, _, w) = self
+            .load_wallet_model(&self.database, merchant_hash, hash, false)
+            .await?;
+        Ok(w)
+    }
+
+    async fn load_wallet_model<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        merchant_hash: Hash,
+        hash: Hash,
+        lock: bool,
+    ) -> anyhow::Result<(wallet::Model, EncryptionKeyCustodian, Wallet)> {
+        let mut query = wallet::Entity::find().filter(
+            Condition::all()
+                .add(wallet::Column::Hash.eq(hash))
+                .add(wallet::Column::MerchantHash.eq(merchant_hash)),
+        );
+        if lock {
+            query = query.lock_exclusive();
+        }
+        let w = query
+            .one(conn)
+            .await?
+            .ok_or(anyhow!("Failed to find wallet"))?;
+
+        let w_ek = encryption_key::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(encryption_key::Column::Hash.eq(w.encryption_key_hash.clone()))
+                    .add(
+                        encryption_key::Column::KeyEncryptionKeyHash.eq(self.kek_custodian.hash()),
+                    ),
+            )
+            .one(conn)
+            .await?
+            .ok_or(anyhow!("Failed load wallet"))?;
+
+        let w_ek_custodian = EncryptionKeyCustodian::new(
+            self.kek_custodian.unlock(w_ek)?.secret.to_vec(),
+            w.cipher.clone(),
+        )?;
+        let wallet_bytes = w_ek_custodian.decrypt(&w.nonce, &w.blob)?;
+        let live_w = serde_json::from_slice(&wallet_bytes)?;
+        Ok((w, w_ek_custodian, live_w))
+    }
+
+    /// New merchant scoped encryption key, locked by the current key encryption key
+    async fn new_encryption_key<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        merchant_hash: Hash,
+    ) -> anyhow::Result<EncryptionKeyCustodian> {
+        let ek = self
+            .kek_custodian
+            .lock(MerchantScopedSecret {
+                merchant_hash,
+                secret: self.kek_custodian.gen_secret()?,
+            })?
+            .insert(conn)
+            .await?;
+
+        EncryptionKeyCustodian::new(
+            self.kek_custodian.unlock(ek)?.secret.to_vec(),
+            Cipher::Aes256GcmSiv,
+        )
+    }
+
+    /// Encrypts `w` under a new merchant scoped encryption key and stores it
+    async fn insert_wallet(&self, merchant_hash: Hash, w: Wallet) -> anyhow::Result<wallet::Model> {
+        let ek_custodian = self
+            .new_encryption_key(&self.database, merchant_hash.clone())
+            .await?;
+
+        let (nonce, ciphertext) = ek_custodian.encrypt(&serde_json::to_vec(&w)?)?;
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(w.pubkey().as_bytes());
+        let hash = Hash::try_from(hasher.finalize().to_vec())?;
+
+        Ok(wallet::ActiveModel {
+            hash: Set(hash),
+            merchant_hash: Set(merchant_hash),
+            ticker: Set(w.ticker().into()),
+            network: Set(w.network().into()),
+            wallet_type: Set(w.wallet_type().into()),
+            wallet_status: Set(wallet::WalletStatus::Active),
+            pubkey: Set(w.pubkey().to_string()),
+            encryption_key_hash: Set(ek_custodian.hash()),
+            cipher: Set(Cipher::Aes256GcmSiv),
+            blob: Set(ciphertext),
+            nonce: Set(nonce),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&self.database)
+        .await?)
+    }
+
+    async fn save_wallet<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        w: wallet::Model,
+        w_ek_custodian: EncryptionKeyCustodian,
+        live_w: &Wallet,
+    ) -> anyhow::Result<()> {
+        let (nonce, ciphertext) = w_ek_custodian.encrypt(&serde_json::to_vec(live_w)?)?;
+        let mut w: wallet::ActiveModel = w.into();
+        w.blob = Set(ciphertext);
+        w.nonce = Set(nonce);
+        w.update(conn).await?;
+        Ok(())
+    }
+
+    /// Address funds are sent to, with the destination wallet advanced past it when it is
+    /// one of ours
+    async fn resolve_destination<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        merchant_hash: Hash,
+        source: &Hash,
+        destination: SweepDestination,
+    ) -> anyhow::Result<(
+        String,
+        Option<(wallet::Model, EncryptionKeyCustodian, Wallet)>,
+    )> {
+        match destination {
+            SweepDestination::Address(address) => Ok((address, None)),
+            SweepDestination::Wallet(hash) if hash == *source => {
+                Err(anyhow!("Cannot sweep a wallet into itself"))
+            }
+            SweepDestination::Wallet(hash) => {
+                let (dest_w, dest_ek_custodian, mut live_dest_w) = self
+                    .load_wallet_model(conn, merchant_hash, hash, true)
+                    .await?;
+                if dest_w.wallet_status != wallet::WalletStatus::Active {
+                    return Err(anyhow!("Destination wallet is {:?}", dest_w.wallet_status));
+                }
+                let address = match &mut live_dest_w {
+                    Wallet::Bitcoin(dest) if dest.ticker() == Ticker::BTC => {
+                        dest.next_invoice_addr()?.1
+                    }
+                    _ => return Err(anyhow!("Destination must be a BTC wallet")),
+                };
+                Ok((address, Some((dest_w, dest_ek_custodian, live_dest_w))))
+            }
+        }
+    }
+
+    /// Confirmed outputs paying any receive address the wallet has handed out
+    async fn bitcoin_utxos(&self, w: &BitcoinWallet) -> anyhow::Result<Vec<BitcoinUtxo>> {
+        if w.index() == 0 {
+            return Err(anyhow!("Wallet has no receive addresses"));
+        }
+        let scripts = w.script_pubkeys()?;
+        Ok(self
+            .scan_bitcoin_utxos(w, &scripts, 0..w.index())
+            .await?
+            .into_iter()
+            .map(|(utxo, _)| utxo)
+            .collect())
+    }
+
+    /// Outputs paying the wallet with the height they confirmed at, `None` for those still in
+    /// the mempool. Outputs the mempool already spends are left out
+    async fn bitcoin_outputs(
+        &self,
+        hash: &Hash,
+        w: &BitcoinWallet,
+    ) -> anyhow::Result<Vec<(BitcoinUtxo, Option<u64>)>> {
+        if w.index() == 0 {
+            return Ok(vec![]);
+        }
+        let scripts = w.script_pubkeys()?;
+        let confirmed = self.scan_bitcoin_utxos(w, &scripts, 0..w.index()).await?;
+        let outpoints: Vec<(String, u32)> = confirmed
+            .iter()
+            .map(|(utxo, _)| (utxo.outpoint.txid.to_string(), utxo.outpoint.vout))
+            .collect();
+        let unspent = get_tx_outs(&self.bitcoin, &outpoints).await?;
+        let mut outputs: Vec<(BitcoinUtxo, Option<u64>)> = confirmed
+            .into_iter()
+            .zip(unspent)
+            .filter(|(_, txout)| txout.is_some())
+            .map(|((utxo, height), _)| (utxo, Some(height)))
+            .collect();
+
+        for utxo in self.unconfirmed_outputs(hash, w).await? {
+            if let Some(index) = scripts.get(&utxo.txout.script_pubkey) {
+                outputs.push((
+                    BitcoinUtxo {
+                        index: *index,
+                        ..utxo
+                    },
+                    None,
+                ));
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Unspent mempool outputs paying the wallet, as seen by the node's watch-only wallet
+    /// following it. Indexes are left for the caller to fill in
+    async fn unconfirmed_outputs(
+        &self,
+        hash: &Hash,
+        w: &BitcoinWallet,
+    ) -> anyhow::Result<Vec<BitcoinUtxo>> {
+        let watch_wallet = self.watch_wallet(hash, w).await?;
+        Ok(list_unspent(&self.bitcoin, &watch_wallet, 0)
+            .await?
+            .into_iter()
+            .filter(|entry| entry.confirmations == 0)
+            .map(|entry| BitcoinUtxo {
+                outpoint: bitcoin::OutPoint::new(entry.txid, entry.vout),
+                txout: bitcoin::TxOut {
+                    value: entry.amount.as_sat(),
+                    script_pubkey: entry.script_pub_key,
+                },
+                index: 0,
+            })
+            .collect())
+    }
+
+    /// Name of the node's watch-only wallet following the receive addresses of wallet `hash`,
+    /// its descriptor is imported `WATCH_LOOKAHEAD` addresses past the next index and imported
+    /// again once half of them are handed out
+    async fn watch_wallet(&self, hash: &Hash, w: &BitcoinWallet) -> anyhow::Result<String> {
+        let name = watch_wallet_name(hash);
+        load_watch_only_wallet(&self.bitcoin, &name).await?;
+        let imported = imported_range_end(&self.bitcoin, &name).await?;
+        if imported.map_or(true, |end| end < w.index() + WATCH_LOOKAHEAD / 2) {
+            let descriptor = w.receive_descriptor()?;
+            let descriptor = match descriptor.contains('#') {
+                true => descriptor,
+                false => format!("{}#{}", descriptor, descriptor_checksum(&descriptor)?),
+            };
+            // Confirmed funds are found with scantxoutset, the wallet only needs to follow
+            // the mempool from now on
+            import_descriptor(
+                &self.bitcoin,
+                &name,
+                &descriptor,
+                w.index() + WATCH_LOOKAHEAD,
+                Utc::now().timestamp(),
+            )
+            .await?;
+        }
+        Ok(name)
+    }
+
+    /// Name of the node's watch-only wallet holding the transaction history of the receive
+    /// addresses of wallet `hash` up to at least `end`. No address was handed out before the
+    /// wallet was `created_at`, its descriptor is imported from then on
+    async fn history_wallet(
+        &self,
+        hash: &Hash,
+        w: &BitcoinWallet,
+        created_at: DateTime<Utc>,
+        end: u64,
+    ) -> anyhow::Result<(String, u64)> {
+        let name = history_wallet_name(hash);
+        load_watch_only_wallet(&self.bitcoin, &name).await?;
+        match imported_range_end(&self.bitcoin, &name).await? {
+            Some(imported) if imported >= end => Ok((name, imported)),
+            _ => {
+                let descriptor = w.receive_descriptor()?;
+                let descriptor = match descriptor.contains('#') {
+                    true => descriptor,
+                    false => format!("{}#{}", descriptor, descriptor_checksum(&descriptor)?),
+                };
+                let imported = end + WATCH_LOOKAHEAD;
+                import_descriptor(
+                    &self.bitcoin,
+                    &name,
+                    &descriptor,
+                    imported,
+                    created_at.timestamp(),
+                )
+                .await?;
+                Ok((name, imported))
+            }
+        }
+    }
+
+    /// Unspent outputs of the receive addresses in `indexes`, `scripts` must cover them
+    async fn scan_bitcoin_utxos(
+        &self,
+        w: &BitcoinWallet,
+        scripts: &HashMap<bitcoin::Script, u64>,
+        indexes: Range<u64>,
+    ) -> anyhow::Result<Vec<(BitcoinUtxo, u64)>> {
+        let res = scan_tx_out(
+            &self.bitcoin,
+            &[bitcoincore_rpc_json::ScanTxOutRequest::Extended {
+                desc: w.receive_descriptor()?,
+                range: (indexes.start, indexes.end - 1),
+            }],
+        )
+        .await?;
+        res.unspents
+            .into_iter()
+            .map(|utxo| {
+                let index = *scripts
+                    .get(&utxo.script_pub_key)
+                    .ok_or(anyhow!("Unknown output script {:x}", utxo.script_pub_key))?;
+                let height = utxo.height;
+                Ok((
+                    BitcoinUtxo {
+                        outpoint: bitcoin::OutPoint::new(utxo.txid, utxo.vout),
+                        txout: bitcoin::TxOut {
+                            value: utxo.amount.as_sat(),
+                            script_pubkey: utxo.script_pub_key,
+                        },
+                        index,
+                    },
+                    height,
+                ))
+            })
+            .collect()
+    }
+
+    /// Confirmed outputs a sweep may spend. Outputs a mempool transaction already spends and
+    /// those paying the address of an open invoice are left alone
+    async fn sweepable_utxos(
+        &self,
+        wallet_hash: &Hash,
+        w: &BitcoinWallet,
+    ) -> anyhow::Result<Vec<BitcoinUtxo>> {
+        let utxos = self.bitcoin_utxos(w).await?;
+        let outpoints: Vec<(String, u32)> = utxos
+            .iter()
+            .map(|utxo| (utxo.outpoint.txid.to_string(), utxo.outpoint.vout))
+            .collect();
+        let unspent: Vec<bool> = get_tx_outs(&self.bitcoin, &outpoints)
+            .await?
+            .iter()
+            .map(Option::is_some)
+            .collect();
+        let reserved = self.open_invoice_scripts(wallet_hash).await?;
+        Ok(sweepable(utxos, &unspent, &reserved))
+    }
+
+    /// scriptPubKeys of the addresses the wallet's pending on-chain invoices are still waiting
+    /// on, Lightning invoices hold a BOLT11 payment request instead of an address
+    async fn open_invoice_scripts(
+        &self,
+        wallet_hash: &Hash,
+    ) -> anyhow::Result<HashSet<bitcoin::Script>> {
+        invoice::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(invoice::Column::WalletHash.eq(wallet_hash.clone()))
+                    .add(invoice::Column::InvoiceStatus.eq(invoice::InvoiceStatus::Pending))
+                    .add(invoice::Column::PaymentHash.is_null())
+                    .add(invoice::Column::Currency.ne(currency::Currency::BTCLN)),
+            )
+            .all(&self.database)
+            .await?
+            .into_iter()
+            .map(|i| Ok(bitcoin::Address::from_str(&i.address)?.script_pubkey()))
+            .collect()
+    }
+
+    /// Unspent outputs `tx` spends, an error if any of them is missing or already spent
+    async fn bitcoin_prevouts(
+        &self,
+        tx: &bitcoin::Transaction,
+    ) -> anyhow::Result<Vec<bitcoin::TxOut>> {
+        let outpoints: Vec<(String, u32)> = tx
+            .input
+            .iter()
+            .map(|txin| {
+                (
+                    txin.previous_output.txid.to_string(),
+                    txin.previous_output.vout,
+                )
+            })
+            .collect();
+        get_tx_outs(&self.bitcoin, &outpoints)
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(i, txout)| {
+                let txout =
+                    txout.ok_or_else(|| anyhow!("Input {} spends a missing or spent output", i))?;
+                Ok(bitcoin::TxOut {
+                    value: txout.value.as_sat(),
+                    script_pubkey: txout.script_pub_key.script()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Hardware signers want the full previous transactions of non taproot inputs and legacy
+    /// inputs cannot be signed without them
+    async fn add_previous_transactions(
+        &self,
+        psbt: &mut bitcoin::psbt::PartiallySignedTransaction,
+        utxos: &[BitcoinUtxo],
+    ) -> anyhow::Result<()> {
+        for (input, utxo) in psbt.inputs.iter_mut().zip(utxos) {
+            if utxo.txout.script_pubkey.is_v1_p2tr() {
+                continue;
+            }
+            let prev_tx = get_raw_transaction(&self.bitcoin, &utxo.outpoint.txid.to_string())
+                .await
+                .and_then(|tx_hex| {
+                    let tx: bitcoin::Transaction =
+                        bitcoin::consensus::encode::deserialize(&Vec::from_hex(&tx_hex)?)?;
+                    if tx.txid() != utxo.outpoint.txid {
+                        return Err(anyhow!("Node returned the wrong transaction"));
+                    }
+                    Ok(tx)
+                });
+            match prev_tx {
+                Ok(prev_tx) => input.non_witness_utxo = Some(prev_tx),
+                Err(err) if input.witness_utxo.is_none() => return Err(err),
+                Err(err) => debug!("{} previous transaction not found: {}", utxo.outpoint, err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks the receive addresses of `w` against their history and moves its index past the
+    /// highest used one
+    async fn rescan_wallet(
+        &self,
+        merchant_hash: Hash,
+        hash: Hash,
+        created_at: DateTime<Utc>,
+        w: BitcoinWallet,
+        gap_limit: u64,
+    ) -> anyhow::Result<WalletRescanResponse> {
+        // Extend the scanned range until `gap_limit` addresses in a row past the highest used
+        // one never received anything, spent or not
+        let mut end = gap_limit;
+        let used = loop {
+            let (history_wallet, imported) =
+                self.history_wallet(&hash, &w, created_at, end).await?;
+            let scripts = w.script_pubkeys_in(0..imported + 1)?;
+            let received = list_received_by_address(&self.bitcoin, &history_wallet).await?;
+            let used = received_by_index(received, &scripts);
+            let next_end = match used.keys().next_back() {
+                Some(highest) => (highest + 1 + gap_limit).max(end),
+                None => end,
+            };
+            if next_end <= imported {
+                break used;
+            }
+            end = next_end;
+        };
+        let highest_used = used.keys().next_back().cloned();
+
+        let invoiced: HashSet<String> = invoice::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(invoice::Column::WalletHash.eq(hash.clone()))
+                    .add(invoice::Column::MerchantHash.eq(merchant_hash.clone())),
+            )
+            .all(&self.database)
+            .await?
+            .into_iter()
+            .map(|i| i.address)
+            .collect();
+        let unmatched = used
+            .into_iter()
+            .map(|(index, amount)| {
+                Ok(WalletRescanAddress {
+                    index,
+                    address: w.addr_at(index)?,
+                    amount,
+                })
+            })
+            .collect::<anyhow::Result<Vec<WalletRescanAddress>>>()?
+            .into_iter()
+            .filter(|used| !invoiced.contains(&used.address))
+            .collect();
+
+        // Invoices may have been issued while scanning, only ever move the index forward
+        let txn = self.database.begin().await?;
+        let (w_model, w_ek_custodian, mut live_w) = self
+            .load_wallet_model(&txn, merchant_hash, hash.clone(), true)
+            .await?;
+        let (previous_index, index) = match &mut live_w {
+            Wallet::Bitcoin(live_w) => {
+                let previous_index = live_w.index();
+                if let Some(highest_used) = highest_used {
+                    live_w.advance_index(highest_used + 1);
+                }
+                (previous_index, live_w.index())
+            }
+            w => return Err(anyhow!("Ticker {:?} not supported", w.ticker())),
+        };
+        if index != previous_index {
+            self.save_wallet(&txn, w_model, w_ek_custodian, &live_w)
+                .await?;
+        }
+        txn.commit().await?;
+
+        Ok(WalletRescanResponse {
+            hash,
+            ticker: w.ticker(),
+            gap_limit,
+            previous_index,
+            index,
+            highest_used,
+            unmatched,
+        })
+    }
+
+    async fn load_btc_wallet(
+        &self,
+        merchant_hash: Hash,
+        hash: Hash,
+    ) -> anyhow::Result<BitcoinWallet> {
+        match self.load_wallet(merchant_hash, hash).await? {
+            Wallet::Bitcoin(w) if w.ticker() == Ticker::BTC => Ok(w),
+            w => Err(anyhow!("Ticker {:?} not supported", w.ticker())),
+        }
+    }
+}
+
+/// `utxos` that `unspent` marks as unspent and that don't pay one of the `reserved` scripts
+fn sweepable(
+    utxos: Vec<BitcoinUtxo>,
+    unspent: &[bool],
+    reserved: &HashSet<bitcoin::Script>,
+) -> Vec<BitcoinUtxo> {
+    utxos
+        .into_iter()
+        .zip(unspent)
+        .filter(|(utxo, unspent)| **unspent && !reserved.contains(&utxo.txout.script_pubkey))
+        .map(|(utxo, _)| utxo)
+        .collect()
+}
+
+/// Node watch-only wallet of a MoonRamp wallet
+fn watch_wallet_name(hash: &Hash) -> String {
+    format!("moonramp-{}", hash)
+}
+
+/// Node watch-only wallet with the history of a MoonRamp wallet since it was created, rescans
+/// read it
+fn history_wallet_name(hash: &Hash) -> String {
+    format!("moonramp-{}-history", hash)
+}
+
+/// Sats each receive address in `scripts` ever received according to the node wallet's
+/// `received` history, whether or not the funds were spent since
+fn received_by_index(
+    received: Vec<bitcoincore_rpc_json::ListReceivedByAddressResult>,
+    scripts: &HashMap<bitcoin::Script, u64>,
+) -> BTreeMap<u64, u64> {
+    let mut used = BTreeMap::new();
+    for entry in received {
+        if let Some(index) = scripts.get(&entry.address.script_pubkey()) {
+            *used.entry(*index).or_insert(0) += entry.amount.as_sat();
+        }
+    }
+    used
+}
+
+/// Receive addresses past the next index the node's watch-only wallets follow
+const WATCH_LOOKAHEAD: u64 = 1000;
+
+/// Largest gap limit a rescan accepts
+const MAX_GAP_LIMIT: u64 = 1000;
+
+#[async_trait]
+impl WalletRpcServer for WalletRpcImpl {
+    fn version(&self) -> RpcResult<String> {
+        Ok(env!("CARGO_PKG_VERSION").to_string())
+    }
+
+    async fn create(
+        &self,
+        merchant_hash: Hash,
+        request: WalletCreateRequest,
+    ) -> RpcResult<WalletResponse> {
+        debug!("wallet.create {:?}", request);
+
+        let network = |ticker: &Ticker| self.network.for_ticker(ticker);
+        let w = match request {
+            WalletCreateRequest::BtcHot => Wallet::Bitcoin(
+                BitcoinWallet::new_hot(Ticker::BTC, network(&Ticker::BTC)).into_rpc_result()?,
+            ),
+            WalletCreateRequest::BtcHotAccount {
+                derivation,
+                account,
+            } => Wallet::Bitcoin(
+                BitcoinWallet::new_hot_with_derivation(
+                    Ticker::BTC,
+                    network(&Ticker::BTC),
+                    derivation,
+                    account,
+                )
+                .into_rpc_result()?,
+            ),
+            WalletCreateRequest::BtcCold { pubkey, cold_type } => Wallet::Bitcoin(
+                BitcoinWallet::new_cold(Ticker::BTC, network(&Ticker::BTC), pubkey, cold_type)
+                    .into_rpc_result()?,
+            ),
+            WalletCreateRequest::BtcMultisig { threshold, pubkeys } => Wallet::Bitcoin(
+                BitcoinWallet::new_multisig(Ticker::BTC, network(&Ticker::BTC), threshold, pubkeys)
+                    .into_rpc_result()?,
+            ),
+            WalletCreateRequest::BchHot => Wallet::Bitcoin(
+                BitcoinWallet::new_hot(Ticker::BCH, network(&Ticker::BCH)).into_rpc_result()?,
+            ),
+            WalletCreateRequest::BchHotAccount {
+                derivation,
+                account,
+            } => Wallet::Bitcoin(
+                BitcoinWallet::new_hot_with_derivation(
+                    Ticker::BCH,
+                    network(&Ticker::BCH),
+                    derivation,
+                    account,
+                )
+                .into_rpc_result()?,
+            ),
+            WalletCreateRequest::BchCold { pubkey, cold_type } => Wallet::Bitcoin(
+                BitcoinWallet::new_cold(Ticker::BCH, network(&Ticker::BCH), pubkey, cold_type)
+                    .into_rpc_result()?,
+            ),
+            WalletCreateRequest::EthHot => Wallet::Ethereum(
+                EthereumWallet::new_hot(Ticker::ETH, network(&Ticker::ETH)).into_rpc_result()?,
+            ),
+            WalletCreateRequest::EthCold { pubkey, cold_type } => Wallet::Ethereum(
+                EthereumWallet::new_cold(Ticker::ETH, network(&Ticker::ETH), pubkey, cold_type)
+                    .into_rpc_result()?,
+            ),
+            WalletCreateRequest::EtcHot => Wallet::Ethereum(
+                EthereumWallet::new_hot(Ticker::ETC, network(&Ticker::ETC)).into_rpc_result()?,
+            ),
+            WalletCreateRequest::EtcCold { pubkey, cold_type } => Wallet::Ethereum(
+                EthereumWallet::new_cold(Ticker::ETC, network(&Ticker::ETC), pubkey, cold_type)
+                    .into_rpc_result()?,
+            ),
+            WalletCreateRequest::XmrHot => {
+                Wallet::Monero(MoneroWallet::new_hot(network(&Ticker::XMR)).into_rpc_result()?)
+            }
+            WalletCreateRequest::XmrCold {
+                view_key,
+                cold_type,
+            } => todo!(),
+        };
+
+        Ok(self
+            .insert_wallet(merchant_hash, w)
+            .await
+            .into_rpc_result()?
+            .into())
+    }
+
+    async fn lookup(
+        &self,
+        merchant_hash: Hash,
+        request: WalletLookupRequest,
+    ) -> RpcResult<Option<WalletResponse>> {
+        debug!("wallet.lookup {:?}", request);
+
+        // TODO balance lookup
+
+        Ok(match request {
+            WalletLookupRequest::Hash { hash } => wallet::Entity::find()
+                .filter(
+                    Condition::all()
+                        .add(wallet::Column::Hash.eq(hash))
+                        .add(wallet::Column::MerchantHash.eq(merchant_hash.clone())),
+                )
+                .one(&self.database)
+                .await
+                .into_rpc_result()?
+                .map(|w| w.into()),
+            WalletLookupRequest::Pubkey { pubkey } => wallet::Entity::find()
+                .filter(
+                    Condition::all()
+                        .add(wallet::Column::Pubkey.eq(pubkey))
+                        .add(wallet::Column::MerchantHash.eq(merchant_hash.clone())),
+                )
+                .one(&self.database)
+                .await
+                .into_rpc_result()?
+                .map(|w| w.into()),
+        })
+    }
+
+    async fn script(
+        &self,
+        merchant_hash: Hash,
+        request: WalletScriptRequest,
+    ) -> RpcResult<WalletScriptResponse> {
+        debug!("wallet.script {:?}", request);
+
+        let descriptor = match self
+            .load_wallet(merchant_hash, request.hash.clone())
+            .await
+            .into_rpc_result()?
+        {
+            Wallet::Bitcoin(w) => w.output_descriptor().into_rpc_result()?,
+            _ => None,
+        }
+        .ok_or(anyhow!("Wallet has no output descriptor"))
+        .into_rpc_result()?;
+
+        Ok(WalletScriptResponse {
+            hash: request.hash,
+            index: request.index,
+            address: descriptor
+                .address(request.index, &self.network)
+                .into_rpc_result()?,
+            descriptor: descriptor.at_index(request.index).into_rpc_result()?,
+            redeem_script: descriptor
+                .redeem_script(request.index)
+                .into_rpc_result()?
+                .map(|script| format!("{:x}", script)),
+            witness_script: descriptor
+                .witness_script(request.index)
+                .into_rpc_result()?
+                .map(|script| format!("{:x}", script)),
+        })
+    }
+
+    async fn sweep(
+        &self,
+        merchant_hash: Hash,
+        request: WalletSweepRequest,
+    ) -> RpcResult<WalletSweepResponse> {
+        debug!("wallet.sweep {:?}", request);
+
+        let w = match self
+            .load_wallet(merchant_hash.clone(), request.hash.clone())
+            .await
+            .into_rpc_result()?
+        {
+            Wallet::Bitcoin(w @ BitcoinWallet::Hot(Ticker::BTC, _, _)) => w,
+            _ => {
+                return Err(anyhow!("Only BTC hot wallets can be swept")).into_rpc_result();
+            }
+        };
+
+        let utxos = self
+            .sweepable_utxos(&request.hash, &w)
+            .await
+            .into_rpc_result()?;
+
+        let txn = self.database.begin().await.into_rpc_result()?;
+        let (address, destination) = self
+            .resolve_destination(&txn, merchant_hash, &request.hash, request.destination)
+            .await
+            .into_rpc_result()?;
+
+        let mut psbt = w
+            .create_sweep_psbt(&utxos, &address, request.fee_rate)
+            .into_rpc_result()?;
+        let total: u64 = utxos.iter().map(|utxo| utxo.txout.value).sum();
+        let amount = psbt.unsigned_tx.output[0].value;
+        let mut res = WalletSweepResponse {
+            hash: request.hash,
+            address,
+            inputs: utxos.len(),
+            amount,
+            fee: total - amount,
+            txid: None,
+            psbt: None,
+        };
+
+        if request.dry_run {
+            txn.rollback().await.into_rpc_result()?;
+            self.add_previous_transactions(&mut psbt, &utxos)
+                .await
+                .into_rpc_result()?;
+            res.psbt = Some(psbt_to_base64(&psbt));
+            return Ok(res);
+        }
+
+        w.sign_psbt(&mut psbt).into_rpc_result()?;
+        let tx = finalize_psbt(psbt).into_rpc_result()?;
+        res.txid = Some(
+            send_raw_transaction(
+                &self.bitcoin,
+                &bitcoin::consensus::encode::serialize_hex(&tx),
+            )
+            .await
+            .into_rpc_result()?,
+        );
+
+        // Only hand out the destination address once the sweep is broadcast
+        if let Some((dest_w, dest_ek_custodian, live_dest_w)) = destination {
+            self.save_wallet(&txn, dest_w, dest_ek_custodian, &live_dest_w)
+                .await
+                .into_rpc_result()?;
+        }
+        txn.commit().await.into_rpc_result()?;
+
+        Ok(res)
+    }
+
+    async fn create_psbt(
+        &self,
+        merchant_hash: Hash,
+        request: WalletCreatePsbtRequest,
+    ) -> RpcResult<WalletSweepResponse> {
+        debug!("wallet.createPsbt {:?}", request);
+
+        let w = self
+            .load_btc_wallet(merchant_hash.clone(), request.hash.clone())
+            .await
+            .into_rpc_result()?;
+
+        let utxos = self
+            .sweepable_utxos(&request.hash, &w)
+            .await
+            .into_rpc_result()?;
+
+        let txn = self.database.begin().await.into_rpc_result()?;
+        let (address, destination) = self
+            .resolve_destination(&txn, merchant_hash, &request.hash, request.destination)
+            .await
+            .into_rpc_result()?;
+
+        let mut psbt = w
+            .create_sweep_psbt(&utxos, &address, request.fee_rate)
+            .into_rpc_result()?;
+        self.add_previous_transactions(&mut psbt, &utxos)
+            .await
+            .into_rpc_result()?;
+
+        if let Some((dest_w, dest_ek_custodian, live_dest_w)) = destination {
+            self.save_wallet(&txn, dest_w, dest_ek_custodian, &live_dest_w)
+                .await
+                .into_rpc_result()?;
+        }
+        txn.commit().await.into_rpc_result()?;
+
+        let total: u64 = utxos.iter().map(|utxo| utxo.txout.value).sum();
+        let amount = psbt.unsigned_tx.output[0].value;
+        Ok(WalletSweepResponse {
+            hash: request.hash,
+            address,
+            inputs: utxos.len(),
+            amount,
+            fee: total - amount,
+            txid: None,
+            psbt: Some(psbt_to_base64(&psbt)),
+        })
+    }
+
+    async fn finalize_psbt(
+        &self,
+        merchant_hash: Hash,
+        request: WalletFinalizePsbtRequest,
+    ) -> RpcResult<WalletFinalizePsbtResponse> {
+        debug!("wallet.finalizePsbt {:?}", request.hash);
+
+        let w = self
+            .load_btc_wallet(merchant_hash, request.hash.clone())
+            .await
+            .into_rpc_result()?;
+
+        let psbt = psbt_from_base64(&request.psbt).into_rpc_result()?;
+
+        // Only broadcast spends of this wallet's receive addresses
+        let scripts = w.script_pubkeys().into_rpc_result()?;
+        for (i, (input, txin)) in psbt.inputs.iter().zip(&psbt.unsigned_tx.input).enumerate() {
+            let script_pubkey = match (&input.witness_utxo, &input.non_witness_utxo) {
+                (Some(prevout), _) => Some(&prevout.script_pubkey),
+                (None, Some(prev_tx)) => prev_tx
+                    .output
+                    .get(txin.previous_output.vout as usize)
+                    .map(|prevout| &prevout.script_pubkey),
+                (None, None) => None,
+            };
+            if !script_pubkey.is_some_and(|script| scripts.contains_key(script)) {
+                return Err(anyhow!("Input {} does not spend from this wallet", i))
+                    .into_rpc_result();
+            }
+        }
+        // The PSBT comes back from outside, what it claims the inputs spend must be on chain
+        let prevouts = self
+            .bitcoin_prevouts(&psbt.unsigned_tx)
+            .await
+            .into_rpc_result()?;
+        verify_psbt_prevouts(&psbt, &prevouts).into_rpc_result()?;
+
+        let tx = finalize_psbt(psbt).into_rpc_result()?;
+        let txid = send_raw_transaction(
+            &self.bitcoin,
+            &bitcoin::consensus::encode::serialize_hex(&tx),
+        )
+        .await
+        .into_rpc_result()?;
+
+        Ok(WalletFinalizePsbtResponse {
+            hash: request.hash,
+            txid,
+        })
+    }
+
+    async fn balance(
+        &self,
+        merchant_hash: Hash,
+        request: WalletBalanceRequest,
+    ) -> RpcResult<WalletBalanceResponse> {
+        debug!("wallet.balance {:?}", request);
+
+        let w = self
+            .load_btc_wallet(merchant_hash, request.hash.clone())
+            .await
+            .into_rpc_result()?;
+
+        let mut addresses: BTreeMap<u64, WalletAddressBalance> = BTreeMap::new();
+        for (utxo, height) in self
+            .bitcoin_outputs(&request.hash, &w)
+            .await
+            .into_rpc_result()?
+        {
+            let balance = match addresses.entry(utxo.index) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(WalletAddressBalance {
+                    index: utxo.index,
+                    address: w.addr_at(utxo.index).into_rpc_result()?,
+                    confirmed: 0,
+                    unconfirmed: 0,
+                }),
+            };
+            match height {
+                Some(_) => balance.confirmed += utxo.txout.value,
+                None => balance.unconfirmed += utxo.txout.value,
+            }
+        }
+
+        Ok(WalletBalanceResponse {
+            hash: request.hash,
+            ticker: w.ticker(),
+            confirmed: addresses.values().map(|balance| balance.confirmed).sum(),
+            unconfirmed: addresses.values().map(|balance| balance.unconfirmed).sum(),
+            addresses: addresses.into_values().collect(),
+        })
+    }
+
+    async fn utxos(
+        &self,
+        merchant_hash: Hash,
+        request: WalletUtxosRequest,
+    ) -> RpcResult<WalletUtxosResponse> {
+        debug!("wallet.utxos {:?}", request);
+
+        let w = self
+            .load_btc_wallet(merchant_hash, request.hash.clone())
+            .await
+            .into_rpc_result()?;
 
-#[rpc(server)]
-pub trait WalletRpc {
-    #[method(name = "wallet.version")]
-    fn version(&self) -> RpcResult<String>;
+        let utxos = self
+            .bitcoin_outputs(&request.hash, &w)
+            .await
+            .into_rpc_result()?
+            .into_iter()
+            .map(|(utxo, height)| {
+                Ok(WalletUtxo {
+                    txid: utxo.outpoint.txid.to_string(),
+                    vout: utxo.outpoint.vout,
+                    index: utxo.index,
+                    address: w.addr_at(utxo.index)?,
+                    amount: utxo.txout.value,
+                    height,
+                })
+            })
+            .collect::<anyhow::Result<Vec<WalletUtxo>>>()
+            .into_rpc_result()?;
 
-    #[method(name = "wallet.create")]
-    async fn create(
+        Ok(WalletUtxosResponse {
+            hash: request.hash,
+            ticker: w.ticker(),
+            utxos,
+        })
+    }
+
+    async fn update(
         &self,
         merchant_hash: Hash,
-        request: WalletCreateRequest,
-    ) -> RpcResult<WalletResponse>;
+        request: WalletUpdateRequest,
+    ) -> RpcResult<WalletResponse> {
+        debug!("wallet.update {:?}", request);
 
-    #[method(name = "wallet.lookup")]
-    async fn lookup(
+        let w = wallet::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(wallet::Column::Hash.eq(request.hash))
+                    .add(wallet::Column::MerchantHash.eq(merchant_hash)),
+            )
+            .one(&self.database)
+            .await
+            .into_rpc_result()?
+            .ok_or(anyhow!("Failed to find wallet"))
+            .into_rpc_result()?;
+        if w.wallet_status == request.wallet_status {
+            return Ok(w.into());
+        }
+
+        let mut w: wallet::ActiveModel = w.into();
+        w.wallet_status = Set(request.wallet_status);
+        Ok(w.update(&self.database).await.into_rpc_result()?.into())
+    }
+
+    async fn delete(
         &self,
         merchant_hash: Hash,
-        request: WalletLookupRequest,
-    ) -> RpcResult<Option<WalletResponse>>;
-}
-
-#[derive(Clone)]
-pub struct WalletRpcImpl {
-    kek_custodian: Arc<KeyEncryptionKeyCustodian>,
-    database: DatabaseConnection,
-    network: Network,
-}
+        request: WalletDeleteRequest,
+    ) -> RpcResult<WalletResponse> {
+        debug!("wallet.delete {:?}", request);
 
-#[async_trait]
-impl WalletRpcServer for WalletRpcImpl {
-    fn version(&self) -> RpcResult<String> {
-        Ok(env!("CARGO_PKG_VERSION").to_string())
+        self.update(
+            merchant_hash,
+            WalletUpdateRequest {
+                hash: request.hash,
+                wallet_status: wallet::WalletStatus::Archived,
+            },
+        )
+        .await
     }
 
-    async fn create(
+    async fn rotate_key(
         &self,
         merchant_hash: Hash,
-        request: WalletCreateRequest,
+        request: WalletRotateKeyRequest,
     ) -> RpcResult<WalletResponse> {
-        debug!("wallet.create {:?}", request);
+        debug!("wallet.rotateKey {:?}", request);
 
-        let ek = self
-            .kek_custodian
-            .lock(MerchantScopedSecret {
-                merchant_hash: merchant_hash.clone(),
-                secret: self.kek_custodian.gen_secret().into_rpc_result()?,
-            })
-            .into_rpc_result()?
-            .insert(&self.database)
+        let txn = self.database.begin().await.into_rpc_result()?;
+        let (w, _, live_w) = self
+            .load_wallet_model(&txn, merchant_hash.clone(), request.hash, true)
             .await
             .into_rpc_result()?;
 
-        let network = self.network.clone();
-        let w = match request {
-            WalletCreateRequest::BtcHot => {
-                Wallet::Bitcoin(BitcoinWallet::new_hot(Ticker::BTC, network).into_rpc_result()?)
-            }
-            WalletCreateRequest::BtcCold { pubkey, cold_type } => Wallet::Bitcoin(
-                BitcoinWallet::new_cold(Ticker::BTC, network, pubkey, cold_type)
-                    .into_rpc_result()?,
-            ),
-            WalletCreateRequest::BchHot => {
-                Wallet::Bitcoin(BitcoinWallet::new_hot(Ticker::BCH, network).into_rpc_result()?)
-            }
-            WalletCreateRequest::BchCold { pubkey, cold_type } => Wallet::Bitcoin(
-                BitcoinWallet::new_cold(Ticker::BCH, network, pubkey, cold_type)
-                    .into_rpc_result()?,
-            ),
-            WalletCreateRequest::XmrHot => {
-                Wallet::Monero(MoneroWallet::new_hot(network).into_rpc_result()?)
+        let ek_custodian = self
+            .new_encryption_key(&txn, merchant_hash)
+            .await
+            .into_rpc_result()?;
+        let (nonce, ciphertext) = ek_custodian
+            .encrypt(&serde_json::to_vec(&live_w).into_rpc_result()?)
+            .into_rpc_result()?;
+
+        let mut w: wallet::ActiveModel = w.into();
+        w.encryption_key_hash = Set(ek_custodian.hash());
+        w.cipher = Set(Cipher::Aes256GcmSiv);
+        w.blob = Set(ciphertext);
+        w.nonce = Set(nonce);
+        let w = w.update(&txn).await.into_rpc_result()?;
+        txn.commit().await.into_rpc_result()?;
+        Ok(w.into())
+    }
+
+    async fn rescan(
+        &self,
+        merchant_hash: Hash,
+        request: WalletRescanRequest,
+    ) -> RpcResult<WalletRescanJobResponse> {
+        debug!("wallet.rescan {:?}", request);
+
+        let gap_limit = request.gap_limit.unwrap_or(DEFAULT_GAP_LIMIT);
+        if !(1..=MAX_GAP_LIMIT).contains(&gap_limit) {
+            return Err(anyhow!("Gap limit must be between 1 and {}", MAX_GAP_LIMIT))
+                .into_rpc_result();
+        }
+
+        let (w_model, _, w) = self
+            .load_wallet_model(
+                &self.database,
+                merchant_hash.clone(),
+                request.hash.clone(),
+                false,
+            )
+            .await
+            .into_rpc_result()?;
+        let w = match w {
+            Wallet::Bitcoin(w) if w.ticker() == Ticker::BTC => w,
+            w => {
+                return Err(anyhow!("Ticker {:?} not supported", w.ticker())).into_rpc_result();
             }
-            WalletCreateRequest::XmrCold {
-                view_key,
-                cold_type,
-            } => todo!(),
         };
 
-        let ek_custodian = EncryptionKeyCustodian::new(
-            self.kek_custodian
-                .unlock(ek)
-                .into_rpc_result()?
-                .secret
-                .to_vec(),
-            Cipher::Aes256GcmSiv,
-        )
-        .into_rpc_result()?;
+        let key = (merchant_hash.clone(), request.hash.clone());
+        let mut rescans = self.rescans.lock().await;
+        if let Some(job) = rescans.get(&key) {
+            if job.status == WalletRescanStatus::Running {
+                return Ok(job.clone());
+            }
+        }
+        let job = WalletRescanJobResponse {
+            hash: request.hash.clone(),
+            status: WalletRescanStatus::Running,
+            started_at: Utc::now(),
+            result: None,
+            error: None,
+        };
+        rescans.insert(key.clone(), job.clone());
+        drop(rescans);
 
-        let (nonce, ciphertext) = ek_custodian
-            .encrypt(&serde_json::to_vec(&w).into_rpc_result()?)
-            .into_rpc_result()?;
+        // Importing the history makes the node scan the chain, far longer than a request lasts
+        let rpc = self.clone();
+        tokio::spawn(async move {
+            let res = rpc
+                .rescan_wallet(
+                    merchant_hash,
+                    request.hash,
+                    w_model.created_at,
+                    w,
+                    gap_limit,
+                )
+                .await;
+            if let Some(job) = rpc.rescans.lock().await.get_mut(&key) {
+                match res {
+                    Ok(res) => {
+                        job.status = WalletRescanStatus::Done;
+                        job.result = Some(res);
+                    }
+                    Err(err) => {
+                        debug!("wallet.rescan {} failed {}", job.hash, err);
+                        job.status = WalletRescanStatus::Failed;
+                        job.error = Some(err.to_string());
+                    }
+                }
+            }
+        });
+        Ok(job)
+    }
 
-        let mut hasher = Sha3_256::new();
-        hasher.update(w.pubkey().as_bytes());
-        let hash = Hash::try_from(hasher.finalize().to_vec()).into_rpc_result()?;
+    async fn rescan_status(
+        &self,
+        merchant_hash: Hash,
+        request: WalletRescanStatusRequest,
+    ) -> RpcResult<WalletRescanJobResponse> {
+        debug!("wallet.rescanStatus {:?}", request);
 
-        Ok(wallet::ActiveModel {
-            hash: Set(hash),
-            merchant_hash: Set(merchant_hash),
-            ticker: Set(w.ticker().into()),
-            network: Set(w.network().into()),
-            wallet_type: Set(w.wallet_type().into()),
-            pubkey: Set(w.pubkey().to_string()),
-            encryption_key_hash: Set(ek_custodian.hash()),
-            cipher: Set(Cipher::Aes256GcmSiv),
-            blob: Set(ciphertext),
-            nonce: Set(nonce),
-            created_at: Set(Utc::now()),
-        }
-        .insert(&self.database)
-        .await
-        .into_rpc_result()?
-        .into())
+        self.rescans
+            .lock()
+            .await
+            .get(&(merchant_hash, request.hash.clone()))
+            .cloned()
+            .ok_or(anyhow!("No rescan of wallet {} on this node", request.hash))
+            .into_rpc_result()
     }
 
-    async fn lookup(
+    async fn export(
         &self,
         merchant_hash: Hash,
-        request: WalletLookupRequest,
-    ) -> RpcResult<Option<WalletResponse>> {
-        debug!("wallet.lookup {:?}", request);
+        request: WalletExportRequest,
+    ) -> RpcResult<WalletExportResponse> {
+        debug!("wallet.export {:?}", request);
 
-        // TODO balance lookup
+        let w = self
+            .load_wallet(merchant_hash, request.hash.clone())
+            .await
+            .into_rpc_result()?;
+        let (derivation, account) = match &w {
+            Wallet::Bitcoin(w) => (w.derivation(), w.account()),
+            _ => (None, None),
+        };
 
-        Ok(match request {
-            WalletLookupRequest::Hash { hash } => wallet::Entity::find()
-                .filter(
-                    Condition::all()
-                        .add(wallet::Column::Hash.eq(hash))
-                        .add(wallet::Column::MerchantHash.eq(merchant_hash.clone())),
+        Ok(WalletExportResponse {
+            hash: request.hash,
+            ticker: w.ticker(),
+            network: w.network(),
+            secret: w.secret().into_rpc_result()?,
+            derivation,
+            account,
+        })
+    }
+
+    async fn import(
+        &self,
+        merchant_hash: Hash,
+        request: WalletImportRequest,
+    ) -> RpcResult<WalletResponse> {
+        debug!("wallet.import {:?}", request);
+
+        let network = |ticker: &Ticker| self.network.for_ticker(ticker);
+        let w = match request {
+            WalletImportRequest::BtcHot {
+                mnemonic,
+                passphrase,
+                derivation,
+                account,
+            } => Wallet::Bitcoin(
+                BitcoinWallet::import_hot(
+                    Ticker::BTC,
+                    network(&Ticker::BTC),
+                    &mnemonic,
+                    passphrase,
+                    derivation,
+                    account,
                 )
-                .one(&self.database)
-                .await
-                .into_rpc_result()?
-                .map(|w| w.into()),
-            WalletLookupRequest::Pubkey { pubkey } => wallet::Entity::find()
-                .filter(
-                    Condition::all()
-                        .add(wallet::Column::Pubkey.eq(pubkey))
-                        .add(wallet::Column::MerchantHash.eq(merchant_hash.clone())),
+                .into_rpc_result()?,
+            ),
+            WalletImportRequest::BchHot {
+                mnemonic,
+                passphrase,
+                derivation,
+                account,
+            } => Wallet::Bitcoin(
+                BitcoinWallet::import_hot(
+                    Ticker::BCH,
+                    network(&Ticker::BCH),
+                    &mnemonic,
+                    passphrase,
+                    derivation,
+                    account,
                 )
-                .one(&self.database)
-                .await
-                .into_rpc_result()?
-                .map(|w| w.into()),
-        })
+                .into_rpc_result()?,
+            ),
+            WalletImportRequest::EthHot {
+                mnemonic,
+                passphrase,
+            } => Wallet::Ethereum(
+                EthereumWallet::import_hot(
+                    Ticker::ETH,
+                    network(&Ticker::ETH),
+                    &mnemonic,
+                    passphrase,
+                )
+                .into_rpc_result()?,
+            ),
+            WalletImportRequest::EtcHot {
+                mnemonic,
+                passphrase,
+            } => Wallet::Ethereum(
+                EthereumWallet::import_hot(
+                    Ticker::ETC,
+                    network(&Ticker::ETC),
+                    &mnemonic,
+                    passphrase,
+                )
+                .into_rpc_result()?,
+            ),
+            WalletImportRequest::XmrHot { mnemonic } => Wallet::Monero(
+                MoneroWallet::import_hot(network(&Ticker::XMR), &mnemonic).into_rpc_result()?,
+            ),
+        };
+
+        let existing = wallet::Entity::find()
+            .filter(wallet::Column::Pubkey.eq(w.pubkey()))
+            .one(&self.database)
+            .await
+            .into_rpc_result()?;
+        if existing.is_some() {
+            return Err(anyhow!("Wallet already exists")).into_rpc_result();
+        }
+
+        Ok(self
+            .insert_wallet(merchant_hash, w)
+            .await
+            .into_rpc_result()?
+            .into())
     }
 }
 
@@ -178,8 +1429,8 @@ impl WalletRpcService {
         node_id: NodeId,
         kek_custodian: Arc<KeyEncryptionKeyCustodian>,
         database: DatabaseConnection,
-        _bitcoin_rpc_endpoint: String,
-        _bitcoin_rpc_auth: String,
+        bitcoin_rpc_endpoint: String,
+        bitcoin_rpc_auth: String,
         network: Network,
     ) -> anyhow::Result<(NetworkTunnelSender, Arc<Self>)> {
         let (public_tx, public_network_rx) = mpsc::channel(1024);
@@ -188,7 +1439,12 @@ impl WalletRpcService {
         let rpc = WalletRpcImpl {
             kek_custodian,
             database,
+            bitcoin: BitcoinRpcConfig {
+                endpoint: bitcoin_rpc_endpoint,
+                basic_auth: Some(bitcoin_rpc_auth),
+            },
             network,
+            rescans: Arc::new(Mutex::new(HashMap::new())),
         }
         .into_rpc();
 
@@ -229,12 +1485,14 @@ impl RpcService<WalletRpcImpl> for WalletRpcService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
+
     use sea_orm::Database;
     use serde_json::json;
 
     use moonramp_migration::testing::setup_testdb;
 
-    async fn test_rpc() -> anyhow::Result<(Hash, RpcModule<WalletRpcImpl>)> {
+    async fn test_rpc_impl() -> anyhow::Result<(Hash, WalletRpcImpl)> {
         let database = Database::connect("sqlite::memory:")
             .await
             .expect("Failed to open in-memory sqlite db");
@@ -245,14 +1503,105 @@ mod tests {
         let rpc = WalletRpcImpl {
             kek_custodian,
             database,
+            bitcoin: BitcoinRpcConfig {
+                endpoint: "http://127.0.0.1:18443".to_string(),
+                basic_auth: None,
+            },
             network: Network::Regtest,
-        }
-        .into_rpc();
+            rescans: Arc::new(Mutex::new(HashMap::new())),
+        };
         Ok((t.merchant_hash, rpc))
     }
 
+    async fn test_rpc() -> anyhow::Result<(Hash, RpcModule<WalletRpcImpl>)> {
+        let (merchant_hash, rpc) = test_rpc_impl().await?;
+        Ok((merchant_hash, rpc.into_rpc()))
+    }
+
+    #[tokio::test]
+    async fn test_wallet_create_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "wallet.create",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": "btcHot",
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_ne!(json_rpc["result"]["hash"], serde_json::Value::Null);
+        assert_ne!(json_rpc["result"]["pubkey"], serde_json::Value::Null);
+        assert_eq!(
+            json_rpc["result"]["ticker"],
+            serde_json::Value::String("BTC".to_string())
+        );
+        assert_eq!(
+            json_rpc["result"]["walletType"],
+            serde_json::Value::String("Hot".to_string())
+        );
+
+        let w =
+            BitcoinWallet::new_hot(Ticker::BTC, Network::Testnet).expect("Invalid BitcoinWallet");
+        let mut hasher = Sha3_256::new();
+        hasher.update(w.pubkey().as_bytes());
+        let wallet_hash = Hash::try_from(hasher.finalize().to_vec()).expect("Invalid Hash");
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "wallet.create",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "btcCold": {
+                                "pubkey": w.addr().expect("Invalid BitcoinWallet").0.to_string(),
+                                "coldType": "XPUBKEY",
+                            },
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(
+            json_rpc["result"]["hash"],
+            serde_json::Value::String(wallet_hash.to_string())
+        );
+        assert_eq!(
+            json_rpc["result"]["pubkey"],
+            serde_json::Value::String(w.pubkey())
+        );
+        assert_eq!(
+            json_rpc["result"]["ticker"],
+            serde_json::Value::String("BTC".to_string())
+        );
+        assert_eq!(
+            json_rpc["result"]["walletType"],
+            serde_json::Value::String("Cold".to_string())
+        );
+    }
+
     #[tokio::test]
-    async fn test_wallet_create_ok() {
+    async fn test_wallet_create_eth_ok() {
         let (merchant_hash, rpc) = test_rpc()
             .await
             .expect("Failed to create RpcModule<WalletRpcImpl>");
@@ -263,7 +1612,7 @@ mod tests {
                     "method": "wallet.create",
                     "params": {
                         "merchant_hash": merchant_hash,
-                        "request": "btcHot",
+                        "request": "ethHot",
                     },
                     "id": "12345",
                 }))
@@ -275,11 +1624,9 @@ mod tests {
         let json_rpc: serde_json::Value =
             serde_json::from_str(&resp).expect("Invalid json response");
         assert_eq!(json_rpc["error"], serde_json::Value::Null);
-        assert_ne!(json_rpc["result"]["hash"], serde_json::Value::Null);
-        assert_ne!(json_rpc["result"]["pubkey"], serde_json::Value::Null);
         assert_eq!(
             json_rpc["result"]["ticker"],
-            serde_json::Value::String("BTC".to_string())
+            serde_json::Value::String("ETH".to_string())
         );
         assert_eq!(
             json_rpc["result"]["walletType"],
@@ -287,7 +1634,7 @@ mod tests {
         );
 
         let w =
-            BitcoinWallet::new_hot(Ticker::BTC, Network::Testnet).expect("Invalid BitcoinWallet");
+            EthereumWallet::new_hot(Ticker::ETC, Network::Testnet).expect("Invalid EthereumWallet");
         let mut hasher = Sha3_256::new();
         hasher.update(w.pubkey().as_bytes());
         let wallet_hash = Hash::try_from(hasher.finalize().to_vec()).expect("Invalid Hash");
@@ -299,8 +1646,8 @@ mod tests {
                     "params": {
                         "merchant_hash": merchant_hash,
                         "request": {
-                            "btcCold": {
-                                "pubkey": w.addr().expect("Invalid BitcoinWallet").0.to_string(),
+                            "etcCold": {
+                                "pubkey": w.addr().expect("Invalid EthereumWallet").0.to_string(),
                                 "coldType": "XPUBKEY",
                             },
                         },
@@ -319,13 +1666,9 @@ mod tests {
             json_rpc["result"]["hash"],
             serde_json::Value::String(wallet_hash.to_string())
         );
-        assert_eq!(
-            json_rpc["result"]["pubkey"],
-            serde_json::Value::String(w.pubkey())
-        );
         assert_eq!(
             json_rpc["result"]["ticker"],
-            serde_json::Value::String("BTC".to_string())
+            serde_json::Value::String("ETC".to_string())
         );
         assert_eq!(
             json_rpc["result"]["walletType"],
@@ -360,8 +1703,494 @@ mod tests {
         assert_eq!(json_rpc["result"], serde_json::Value::Null);
         assert_eq!(
             json_rpc["error"],
-            json!({"code": -32602, "message": "unknown variant `Invalid`, expected one of `btcHot`, `btcCold`, `bchHot`, `bchCold`, `xmrHot`, `xmrCold` at line 1 column 83"})
+            json!({"code": -32602, "message": "unknown variant `Invalid`, expected one of `btcHot`, `btcHotAccount`, `btcCold`, `btcMultisig`, `bchHot`, `bchHotAccount`, `bchCold`, `ethHot`, `ethCold`, `etcHot`, `etcCold`, `xmrHot`, `xmrCold` at line 1 column 83"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wallet_multisig_script_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+        let pubkeys = (0..3)
+            .map(|_| {
+                BitcoinWallet::new_hot(Ticker::BTC, Network::Regtest)
+                    .expect("Invalid BitcoinWallet")
+                    .addr()
+                    .expect("Invalid BitcoinWallet")
+                    .0
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "wallet.create",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "btcMultisig": {
+                                "threshold": 2,
+                                "pubkeys": pubkeys,
+                            },
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert!(json_rpc["result"]["pubkey"]
+            .as_str()
+            .expect("Invalid pubkey")
+            .starts_with("wsh(sortedmulti(2,"));
+
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "wallet.script",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": json_rpc["result"]["hash"],
+                            "index": 7,
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        assert!(result.is_ok());
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["index"], json!(7));
+        assert!(json_rpc["result"]["address"]
+            .as_str()
+            .expect("Invalid address")
+            .starts_with("bcrt1q"));
+        assert!(json_rpc["result"]["descriptor"]
+            .as_str()
+            .expect("Invalid descriptor")
+            .contains("/0/7,"));
+        assert_eq!(json_rpc["result"]["redeemScript"], serde_json::Value::Null);
+        assert!(json_rpc["result"]["witnessScript"]
+            .as_str()
+            .expect("Invalid witnessScript")
+            .ends_with("53ae"));
+    }
+
+    #[tokio::test]
+    async fn test_wallet_sweep_not_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+
+        let mut hashes = vec![];
+        for request in [json!("btcHot"), json!("ethHot")] {
+            let result = rpc
+                .raw_json_request(
+                    &serde_json::to_string(&json!({
+                        "jsonrpc": "2.0",
+                        "method": "wallet.create",
+                        "params": {
+                            "merchant_hash": merchant_hash,
+                            "request": request,
+                        },
+                        "id": "12345",
+                    }))
+                    .expect("Invalid request"),
+                )
+                .await;
+            let (resp, _) = result.expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            hashes.push(json_rpc["result"]["hash"].clone());
+        }
+
+        for (hash, message) in [
+            (&hashes[0], "Wallet has no receive addresses"),
+            (&hashes[1], "Only BTC hot wallets can be swept"),
+        ] {
+            let result = rpc
+                .raw_json_request(
+                    &serde_json::to_string(&json!({
+                        "jsonrpc": "2.0",
+                        "method": "wallet.sweep",
+                        "params": {
+                            "merchant_hash": merchant_hash,
+                            "request": {
+                                "hash": hash,
+                                "destination": {
+                                    "address": "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80",
+                                },
+                                "feeRate": 2,
+                                "dryRun": true,
+                            },
+                        },
+                        "id": "12345",
+                    }))
+                    .expect("Invalid request"),
+                )
+                .await;
+            assert!(result.is_ok());
+            let (resp, _) = result.expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            assert_eq!(json_rpc["result"], serde_json::Value::Null);
+            assert!(json_rpc["error"]["message"]
+                .as_str()
+                .expect("Invalid error")
+                .contains(message));
+        }
+    }
+
+    #[test]
+    fn test_sweepable_utxos() {
+        let w = BitcoinWallet::new_hot(Ticker::BTC, Network::Regtest).expect("Invalid wallet");
+        let utxos: Vec<BitcoinUtxo> = (0..4)
+            .map(|index| BitcoinUtxo {
+                outpoint: bitcoin::OutPoint::new(
+                    bitcoin::Txid::from_hex(
+                        "9f1c3b6f7b0c1e5d2a8e4f6b3c7d9e0a1b2c3d4e5f60718293a4b5c6d7e8f901",
+                    )
+                    .expect("Invalid txid"),
+                    index as u32,
+                ),
+                txout: bitcoin::TxOut {
+                    value: 10_000,
+                    script_pubkey: w.script_pubkey_at(index).expect("Invalid script"),
+                },
+                index,
+            })
+            .collect();
+
+        let reserved = HashSet::from([utxos[2].txout.script_pubkey.clone()]);
+        let swept = sweepable(utxos.clone(), &[true, false, true, true], &reserved);
+        assert_eq!(swept, vec![utxos[0].clone(), utxos[3].clone()]);
+
+        assert_eq!(sweepable(utxos.clone(), &[true; 4], &HashSet::new()), utxos);
+        assert!(sweepable(utxos, &[false; 4], &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_received_by_index() {
+        let w = BitcoinWallet::new_hot(Ticker::BTC, Network::Regtest).expect("Invalid wallet");
+        let scripts = w.script_pubkeys_in(0..30).expect("Invalid scripts");
+        // Index 25 was paid and its funds were swept since, it has no UTXO left but its
+        // history still marks it used
+        let received: Vec<bitcoincore_rpc_json::ListReceivedByAddressResult> =
+            serde_json::from_value(json!([
+                {
+                    "involvesWatchonly": true,
+                    "address": w.addr_at(0).expect("Invalid address"),
+                    "amount": 0.0005,
+                    "confirmations": 12,
+                    "label": "",
+                    "txids": ["9f1c3b6f7b0c1e5d2a8e4f6b3c7d9e0a1b2c3d4e5f60718293a4b5c6d7e8f901"],
+                },
+                {
+                    "involvesWatchonly": true,
+                    "address": w.addr_at(25).expect("Invalid address"),
+                    "amount": 0.001,
+                    "confirmations": 3,
+                    "label": "",
+                    "txids": ["0a1b2c3d4e5f60718293a4b5c6d7e8f9019f1c3b6f7b0c1e5d2a8e4f6b3c7d9e"],
+                },
+                {
+                    "involvesWatchonly": true,
+                    "address": w.addr_at(40).expect("Invalid address"),
+                    "amount": 0.002,
+                    "confirmations": 0,
+                    "label": "",
+                    "txids": ["1b2c3d4e5f60718293a4b5c6d7e8f9019f1c3b6f7b0c1e5d2a8e4f6b3c7d9e0a"],
+                },
+            ]))
+            .expect("Invalid listreceivedbyaddress result");
+        assert_eq!(
+            received_by_index(received, &scripts),
+            BTreeMap::from([(0, 50_000), (25, 100_000)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_invoice_scripts() {
+        let (merchant_hash, rpc) = test_rpc_impl()
+            .await
+            .expect("Failed to create WalletRpcImpl");
+        let ek_custodian = rpc
+            .new_encryption_key(&rpc.database, merchant_hash.clone())
+            .await
+            .expect("Failed to create encryption key");
+        let w = BitcoinWallet::new_hot(Ticker::BTC, Network::Regtest).expect("Invalid wallet");
+        let wallet_hash = Hash::from([7u8; 32]);
+
+        for (index, invoice_status, currency) in [
+            (0, invoice::InvoiceStatus::Pending, crate::Currency::BTC),
+            (1, invoice::InvoiceStatus::Funded, crate::Currency::BTC),
+            (2, invoice::InvoiceStatus::Expired, crate::Currency::BTC),
+            (3, invoice::InvoiceStatus::Pending, crate::Currency::BTC),
+            (4, invoice::InvoiceStatus::Pending, crate::Currency::BTCLN),
+        ] {
+            // Lightning invoices store the BOLT11 payment request as their address
+            let (address, payment_hash) = match currency {
+                crate::Currency::BTCLN => (
+                    "lnbcrt10u1p3xnhl2pp5jptserfk3zk4qy42tlucycrfwxhydvlemu9pqr93tuzlv9cc7g3s"
+                        .to_string(),
+                    Some("ab".repeat(32)),
+                ),
+                _ => (w.addr_at(index).expect("Invalid address"), None),
+            };
+            let (nonce, ciphertext) = ek_custodian
+                .encrypt(&serde_json::to_vec(&Vec::<u8>::new()).expect("Invalid user data"))
+                .expect("Failed to encrypt");
+            invoice::ActiveModel {
+                hash: Set(Hash::from([index as u8; 32])),
+                merchant_hash: Set(merchant_hash.clone()),
+                wallet_hash: Set(wallet_hash.clone()),
+                ticker: Set(Ticker::BTC.into()),
+                currency: Set(currency.into()),
+                network: Set(Network::Regtest.into()),
+                invoice_status: Set(invoice_status),
+                pubkey: Set(w.pubkey().to_string()),
+                address: Set(address.clone()),
+                amount: Set(moonramp_core::Amount::new(1000, 8).expect("Invalid amount")),
+                uri: Set(format!("bitcoin:{}", address)),
+                contract: Set(None),
+                payment_hash: Set(payment_hash),
+                start_height: Set(None),
+                fiat_amount: Set(None),
+                fiat_currency: Set(None),
+                rate: Set(None),
+                rate_source: Set(None),
+                quoted_at: Set(None),
+                late_paid_at: Set(None),
+                canceled_at: Set(None),
+                cancel_reason: Set(None),
+                encryption_key_hash: Set(ek_custodian.hash()),
+                cipher: Set(Cipher::Aes256GcmSiv),
+                blob: Set(ciphertext),
+                nonce: Set(nonce),
+                created_at: Set(Utc::now()),
+                updated_at: Set(Utc::now()),
+                expires_at: Set(Utc::now()),
+            }
+            .insert(&rpc.database)
+            .await
+            .expect("Failed to insert invoice");
+        }
+
+        // A pending Lightning invoice does not keep the wallet from being swept
+        let scripts = rpc
+            .open_invoice_scripts(&wallet_hash)
+            .await
+            .expect("Failed to load invoice scripts");
+        assert_eq!(
+            scripts,
+            HashSet::from([
+                w.script_pubkey_at(0).expect("Invalid script"),
+                w.script_pubkey_at(3).expect("Invalid script"),
+            ])
         );
+        assert!(rpc
+            .open_invoice_scripts(&Hash::from([8u8; 32]))
+            .await
+            .expect("Failed to load invoice scripts")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_psbt_not_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "wallet.create",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": "btcHot",
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        let hash = json_rpc["result"]["hash"].clone();
+
+        // A PSBT spending some other wallet
+        let mut other =
+            BitcoinWallet::new_hot(Ticker::BTC, Network::Regtest).expect("Invalid BitcoinWallet");
+        let (_, addr) = other.next_addr().expect("Invalid Addr");
+        let utxo = BitcoinUtxo {
+            outpoint: bitcoin::OutPoint::default(),
+            txout: bitcoin::TxOut {
+                value: 100_000,
+                script_pubkey: bitcoin::Address::from_str(&addr)
+                    .expect("Invalid Addr")
+                    .script_pubkey(),
+            },
+            index: 0,
+        };
+        let psbt = other
+            .create_sweep_psbt(&[utxo], "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80", 2)
+            .expect("Invalid Psbt");
+
+        for (method, request, message) in [
+            (
+                "wallet.createPsbt",
+                json!({
+                    "hash": hash,
+                    "destination": {
+                        "address": "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80",
+                    },
+                    "feeRate": 2,
+                }),
+                "Wallet has no receive addresses",
+            ),
+            (
+                "wallet.finalizePsbt",
+                json!({
+                    "hash": hash,
+                    "psbt": psbt_to_base64(&psbt),
+                }),
+                "Input 0 does not spend from this wallet",
+            ),
+            (
+                "wallet.finalizePsbt",
+                json!({
+                    "hash": hash,
+                    "psbt": "cHNidP8=",
+                }),
+                "",
+            ),
+        ] {
+            let result = rpc
+                .raw_json_request(
+                    &serde_json::to_string(&json!({
+                        "jsonrpc": "2.0",
+                        "method": method,
+                        "params": {
+                            "merchant_hash": merchant_hash,
+                            "request": request,
+                        },
+                        "id": "12345",
+                    }))
+                    .expect("Invalid request"),
+                )
+                .await;
+            assert!(result.is_ok());
+            let (resp, _) = result.expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            assert_eq!(json_rpc["result"], serde_json::Value::Null);
+            assert!(json_rpc["error"]["message"]
+                .as_str()
+                .expect("Invalid error")
+                .contains(message));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wallet_balance_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+
+        let mut hashes = vec![];
+        for request in [json!("btcHot"), json!("ethHot")] {
+            let result = rpc
+                .raw_json_request(
+                    &serde_json::to_string(&json!({
+                        "jsonrpc": "2.0",
+                        "method": "wallet.create",
+                        "params": {
+                            "merchant_hash": merchant_hash,
+                            "request": request,
+                        },
+                        "id": "12345",
+                    }))
+                    .expect("Invalid request"),
+                )
+                .await;
+            let (resp, _) = result.expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            hashes.push(json_rpc["result"]["hash"].clone());
+        }
+
+        // No receive address handed out yet, nothing to scan
+        for method in ["wallet.balance", "wallet.utxos"] {
+            let result = rpc
+                .raw_json_request(
+                    &serde_json::to_string(&json!({
+                        "jsonrpc": "2.0",
+                        "method": method,
+                        "params": {
+                            "merchant_hash": merchant_hash,
+                            "request": {
+                                "hash": hashes[0],
+                            },
+                        },
+                        "id": "12345",
+                    }))
+                    .expect("Invalid request"),
+                )
+                .await;
+            assert!(result.is_ok());
+            let (resp, _) = result.expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            assert_eq!(json_rpc["error"], serde_json::Value::Null);
+            assert_eq!(json_rpc["result"]["ticker"], json!("BTC"));
+            if method == "wallet.balance" {
+                assert_eq!(json_rpc["result"]["confirmed"], json!(0));
+                assert_eq!(json_rpc["result"]["unconfirmed"], json!(0));
+                assert_eq!(json_rpc["result"]["addresses"], json!([]));
+            } else {
+                assert_eq!(json_rpc["result"]["utxos"], json!([]));
+            }
+        }
+
+        let result = rpc
+            .raw_json_request(
+                &serde_json::to_string(&json!({
+                    "jsonrpc": "2.0",
+                    "method": "wallet.balance",
+                    "params": {
+                        "merchant_hash": merchant_hash,
+                        "request": {
+                            "hash": hashes[1],
+                        },
+                    },
+                    "id": "12345",
+                }))
+                .expect("Invalid request"),
+            )
+            .await;
+        let (resp, _) = result.expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert!(json_rpc["error"]["message"]
+            .as_str()
+            .expect("Invalid error")
+            .contains("Ticker ETH not supported"));
     }
 
     #[tokio::test]
@@ -451,4 +2280,259 @@ mod tests {
         assert_eq!(json_rpc["error"], serde_json::Value::Null);
         assert_eq!(json_rpc["result"], serde_json::Value::Null);
     }
+
+    #[tokio::test]
+    async fn test_wallet_lifecycle_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+        let request = |method: &str, request: serde_json::Value| {
+            serde_json::to_string(&json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": {
+                    "merchant_hash": merchant_hash,
+                    "request": request,
+                },
+                "id": "12345",
+            }))
+            .expect("Invalid request")
+        };
+        let rpc = &rpc;
+        let call = |req: String| async move {
+            let (resp, _) = rpc.raw_json_request(&req).await.expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            json_rpc
+        };
+
+        let json_rpc = call(request("wallet.create", json!("btcHot"))).await;
+        assert_eq!(json_rpc["result"]["walletStatus"], json!("Active"));
+        let hash = json_rpc["result"]["hash"].clone();
+
+        let json_rpc = call(request(
+            "wallet.update",
+            json!({ "hash": hash, "walletStatus": "ReceiveDisabled" }),
+        ))
+        .await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["walletStatus"], json!("ReceiveDisabled"));
+
+        let json_rpc = call(request("wallet.delete", json!({ "hash": hash }))).await;
+        assert_eq!(json_rpc["result"]["walletStatus"], json!("Archived"));
+
+        // Archived wallets are still found
+        let json_rpc = call(request("wallet.lookup", json!({ "hash": hash }))).await;
+        assert_eq!(json_rpc["result"]["walletStatus"], json!("Archived"));
+
+        let json_rpc = call(request(
+            "wallet.update",
+            json!({ "hash": hash, "walletStatus": "Active" }),
+        ))
+        .await;
+        assert_eq!(json_rpc["result"]["walletStatus"], json!("Active"));
+
+        let exported = call(request("wallet.export", json!({ "hash": hash }))).await;
+        let json_rpc = call(request("wallet.rotateKey", json!({ "hash": hash }))).await;
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["hash"], hash);
+        let rotated = call(request("wallet.export", json!({ "hash": hash }))).await;
+        assert_eq!(rotated["error"], serde_json::Value::Null);
+        assert_eq!(rotated["result"], exported["result"]);
+
+        let json_rpc = call(request(
+            "wallet.update",
+            json!({ "hash": "2kg8XtHv1t5e5soBBFTGehn32sUwuqDmzyJjBtCg5K6q", "walletStatus": "Active" }),
+        ))
+        .await;
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_eq!(json_rpc["error"]["message"], json!("Failed to find wallet"));
+    }
+
+    #[tokio::test]
+    async fn test_wallet_rescan_not_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+        let request = |method: &str, request: serde_json::Value| {
+            serde_json::to_string(&json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": {
+                    "merchant_hash": merchant_hash,
+                    "request": request,
+                },
+                "id": "12345",
+            }))
+            .expect("Invalid request")
+        };
+
+        let mut hashes = vec![];
+        for w in [json!("btcHot"), json!("ethHot")] {
+            let (resp, _) = rpc
+                .raw_json_request(&request("wallet.create", w))
+                .await
+                .expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            hashes.push(json_rpc["result"]["hash"].clone());
+        }
+
+        for gap_limit in [0, 1001] {
+            let (resp, _) = rpc
+                .raw_json_request(&request(
+                    "wallet.rescan",
+                    json!({ "hash": hashes[0], "gapLimit": gap_limit }),
+                ))
+                .await
+                .expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            assert_eq!(json_rpc["result"], serde_json::Value::Null);
+            assert_eq!(
+                json_rpc["error"]["message"],
+                json!("Gap limit must be between 1 and 1000")
+            );
+        }
+
+        let (resp, _) = rpc
+            .raw_json_request(&request("wallet.rescan", json!({ "hash": hashes[1] })))
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_eq!(
+            json_rpc["error"]["message"],
+            json!("Ticker ETH not supported")
+        );
+
+        let (resp, _) = rpc
+            .raw_json_request(&request(
+                "wallet.rescanStatus",
+                json!({ "hash": hashes[0] }),
+            ))
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert!(json_rpc["error"]["message"]
+            .as_str()
+            .expect("Invalid error")
+            .starts_with("No rescan of wallet"));
+
+        // The rescan runs in the background, no node listens so it fails once polled
+        let (resp, _) = rpc
+            .raw_json_request(&request("wallet.rescan", json!({ "hash": hashes[0] })))
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["result"]["hash"], hashes[0]);
+        assert_eq!(json_rpc["result"]["status"], json!("running"));
+        let mut status = json_rpc["result"].clone();
+        for _ in 0..50 {
+            if status["status"] != json!("running") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            let (resp, _) = rpc
+                .raw_json_request(&request(
+                    "wallet.rescanStatus",
+                    json!({ "hash": hashes[0] }),
+                ))
+                .await
+                .expect("Invalid response");
+            let json_rpc: serde_json::Value =
+                serde_json::from_str(&resp).expect("Invalid json response");
+            status = json_rpc["result"].clone();
+        }
+        assert_eq!(status["status"], json!("failed"));
+        assert_eq!(status["result"], serde_json::Value::Null);
+        assert!(status["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_export_import_ok() {
+        let (merchant_hash, rpc) = test_rpc()
+            .await
+            .expect("Failed to create RpcModule<WalletRpcImpl>");
+        let request = |method: &str, request: serde_json::Value| {
+            serde_json::to_string(&json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": {
+                    "merchant_hash": merchant_hash,
+                    "request": request,
+                },
+                "id": "12345",
+            }))
+            .expect("Invalid request")
+        };
+
+        let (resp, _) = rpc
+            .raw_json_request(&request("wallet.create", json!("btcHot")))
+            .await
+            .expect("Invalid response");
+        let created: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        let hash = created["result"]["hash"].clone();
+
+        let (resp, _) = rpc
+            .raw_json_request(&request("wallet.export", json!({ "hash": hash })))
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["derivation"], json!("BIP84"));
+        assert_eq!(json_rpc["result"]["account"], json!(0));
+        let secret = json_rpc["result"]["secret"]["bip39"].clone();
+        assert_eq!(
+            secret["mnemonic"]
+                .as_str()
+                .expect("Invalid mnemonic")
+                .split(' ')
+                .count(),
+            24
+        );
+
+        let (resp, _) = rpc
+            .raw_json_request(&request("wallet.import", json!({ "btcHot": secret })))
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["result"], serde_json::Value::Null);
+        assert_eq!(json_rpc["error"]["message"], json!("Wallet already exists"));
+
+        let mnemonic = "emit upcoming igloo orbit suture addicted boss pavements nouns oxygen sulking fleet cousin colony maximum awkward gigantic limits wildly violin ankle vinegar glide hiker maximum";
+        let (resp, _) = rpc
+            .raw_json_request(&request(
+                "wallet.import",
+                json!({ "xmrHot": { "mnemonic": mnemonic } }),
+            ))
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(json_rpc["error"], serde_json::Value::Null);
+        assert_eq!(json_rpc["result"]["ticker"], json!("XMR"));
+
+        let (resp, _) = rpc
+            .raw_json_request(&request(
+                "wallet.export",
+                json!({ "hash": json_rpc["result"]["hash"] }),
+            ))
+            .await
+            .expect("Invalid response");
+        let json_rpc: serde_json::Value =
+            serde_json::from_str(&resp).expect("Invalid json response");
+        assert_eq!(
+            json_rpc["result"]["secret"],
+            json!({ "moneroSeed": { "mnemonic": mnemonic } })
+        );
+        assert_eq!(json_rpc["result"]["derivation"], serde_json::Value::Null);
+    }
 }
@@ -0,0 +1,914 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+use bip39::Mnemonic;
+use bitcoin::{
+    blockdata::script::{Builder, Instruction},
+    consensus::encode,
+    psbt::{self, PartiallySignedTransaction},
+    secp256k1::{self, Message, Secp256k1},
+    util::{
+        bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey, KeySource},
+        schnorr::TapTweak,
+        sighash::{Prevouts, SighashCache},
+    },
+    Address, EcdsaSig, EcdsaSighashType, KeyPair, OutPoint, PublicKey, SchnorrSig,
+    SchnorrSighashType, Script, Transaction, TxIn, TxOut, Witness, XOnlyPublicKey,
+};
+
+use moonramp_core::{anyhow, base64, bip39, bitcoin};
+
+use crate::{
+    parse_key_origin, BitcoinAddressType, BitcoinColdWallet, BitcoinDerivation, BitcoinHotWallet,
+    BitcoinWallet, Network, OutputDescriptor, Ticker,
+};
+
+/// Outputs below this many satoshis are not relayed
+pub const BITCOIN_DUST_LIMIT: u64 = 546;
+
+/// Sweeps paying more than this many sat/vB are rejected as a mistyped fee rate
+pub const BITCOIN_MAX_FEE_RATE: u64 = 10_000;
+
+/// Opts inputs into replace-by-fee so a stuck sweep can be bumped
+const RBF_SEQUENCE: u32 = 0xffff_fffd;
+
+/// Confirmed output paying receive address `index` of a wallet
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BitcoinUtxo {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    pub index: u64,
+}
+
+pub fn psbt_to_base64(psbt: &PartiallySignedTransaction) -> String {
+    base64::encode(encode::serialize(psbt))
+}
+
+pub fn psbt_from_base64(psbt: &str) -> anyhow::Result<PartiallySignedTransaction> {
+    Ok(encode::deserialize(&base64::decode(psbt.trim())?)?)
+}
+
+impl BitcoinAddressType {
+    /// Virtual size of a signed input spending this output type, rounded up
+    fn input_vsize(&self) -> anyhow::Result<u64> {
+        match self {
+            BitcoinAddressType::P2PKH => Ok(148),
+            BitcoinAddressType::P2SHWPKH => Ok(91),
+            BitcoinAddressType::P2WPKH => Ok(68),
+            BitcoinAddressType::P2TR => Ok(58),
+            address_type => Err(anyhow!("Spending {:?} outputs not supported", address_type)),
+        }
+    }
+}
+
+/// Master fingerprint and account path of an imported xpub, a key without an origin is only
+/// its own origin when it is a master key
+fn xpub_origin(xpub: &ExtendedPubKey, origin: &Option<String>) -> anyhow::Result<KeySource> {
+    match origin {
+        Some(origin) => parse_key_origin(origin),
+        None if xpub.depth == 0 => Ok((xpub.fingerprint(), DerivationPath::master())),
+        None => Err(anyhow!(
+            "PSBTs need the xpub's [fingerprint/path] key origin, import it with one"
+        )),
+    }
+}
+
+impl BitcoinHotWallet {
+    fn master_key(&self, network: &Network) -> anyhow::Result<ExtendedPrivKey> {
+        let mnemonic = Mnemonic::from_entropy(&self.mnemonic)?;
+        let seed = mnemonic.to_seed(self.password.clone());
+        Ok(ExtendedPrivKey::new_master(network.clone().into(), &seed)?)
+    }
+}
+
+impl BitcoinWallet {
+    /// Ranged descriptor of the receive addresses, scanned over `0..index` to find the
+    /// wallet's outputs
+    pub fn receive_descriptor(&self) -> anyhow::Result<String> {
+        let (xpub, derivation) = match self {
+            BitcoinWallet::Hot(_, _, w) => {
+                (ExtendedPubKey::decode(&w.xpub)?.to_string(), &w.derivation)
+            }
+            BitcoinWallet::Cold(
+                _,
+                _,
+                BitcoinColdWallet::XPubkey {
+                    xpub,
+                    derivation,
+                    origin,
+                    ..
+                },
+            ) => (
+                format!("{}{}", origin.as_deref().unwrap_or_default(), xpub),
+                derivation,
+            ),
+            BitcoinWallet::Cold(_, _, BitcoinColdWallet::Descriptor { descriptor, .. }) => {
+                return Ok(descriptor.clone())
+            }
+        };
+        let key = match derivation {
+            BitcoinDerivation::Legacy => format!("{}/1/0/*", xpub),
+            _ => format!("{}/0/*", xpub),
+        };
+        match self.address_type() {
+            BitcoinAddressType::P2PKH => Ok(format!("pkh({})", key)),
+            BitcoinAddressType::P2SHWPKH => Ok(format!("sh(wpkh({}))", key)),
+            BitcoinAddressType::P2WPKH => Ok(format!("wpkh({})", key)),
+            BitcoinAddressType::P2TR => Ok(format!("tr({})", key)),
+            address_type => Err(anyhow!("{:?} requires a descriptor wallet", address_type)),
+        }
+    }
+
+    /// scriptPubKey of receive address `index`
+    pub fn script_pubkey_at(&self, index: u64) -> anyhow::Result<Script> {
+        if self.ticker() != Ticker::BTC {
+            return Err(anyhow!("Ticker {:?} not supported", self.ticker()));
+        }
+        Ok(Address::from_str(&self.addr_at(index)?)?.script_pubkey())
+    }
+
+    /// scriptPubKeys of every receive address handed out so far mapped to their index
+    pub fn script_pubkeys(&self) -> anyhow::Result<HashMap<Script, u64>> {
+        self.script_pubkeys_in(0..self.index())
+    }
+
+    /// scriptPubKeys of the receive addresses in `indexes` mapped to their index
+    pub fn script_pubkeys_in(&self, indexes: Range<u64>) -> anyhow::Result<HashMap<Script, u64>> {
+        indexes
+            .map(|index| Ok((self.script_pubkey_at(index)?, index)))
+            .collect()
+    }
+
+    /// Keys of receive `index` with their origins, the master fingerprint and full path for
+    /// hot wallets and descriptor keys with an origin, the xpub fingerprint and relative path
+    /// for other cold keys
+    fn input_keys(&self, index: u64) -> anyhow::Result<Vec<(secp256k1::PublicKey, KeySource)>> {
+        match self {
+            BitcoinWallet::Hot(ticker, network, w) => {
+                let secp = Secp256k1::new();
+                let master = w.master_key(network)?;
+                let path = w
+                    .derivation
+                    .account_path(ticker, network, w.account)?
+                    .unwrap_or_else(DerivationPath::master)
+                    .extend(w.derivation.receive_path(index)?);
+                Ok(vec![(
+                    self.xpub_at(index)?.public_key,
+                    (master.fingerprint(&secp), path),
+                )])
+            }
+            BitcoinWallet::Cold(
+                _,
+                _,
+                BitcoinColdWallet::XPubkey {
+                    xpub,
+                    derivation,
+                    origin,
+                    ..
+                },
+            ) => {
+                let (fingerprint, path) = xpub_origin(&ExtendedPubKey::from_str(xpub)?, origin)?;
+                Ok(vec![(
+                    self.xpub_at(index)?.public_key,
+                    (fingerprint, path.extend(derivation.receive_path(index)?)),
+                )])
+            }
+            BitcoinWallet::Cold(_, network, BitcoinColdWallet::Descriptor { descriptor, .. }) => {
+                OutputDescriptor::parse(descriptor, network)?
+                    .keys()
+                    .iter()
+                    .map(|key| Ok((key.derive(index)?.public_key, key.key_source(index)?)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Account level xpubs of the wallet with their origins, the PSBT global xpubs
+    fn account_xpubs(&self) -> anyhow::Result<Vec<(ExtendedPubKey, KeySource)>> {
+        match self {
+            BitcoinWallet::Hot(ticker, network, w) => {
+                let secp = Secp256k1::new();
+                let master = w.master_key(network)?;
+                let path = w
+                    .derivation
+                    .account_path(ticker, network, w.account)?
+                    .unwrap_or_else(DerivationPath::master);
+                Ok(vec![(
+                    ExtendedPubKey::decode(&w.xpub)?,
+                    (master.fingerprint(&secp), path),
+                )])
+            }
+            BitcoinWallet::Cold(_, _, BitcoinColdWallet::XPubkey { xpub, origin, .. }) => {
+                let xpub = ExtendedPubKey::from_str(xpub)?;
+                Ok(vec![(xpub, xpub_origin(&xpub, origin)?)])
+            }
+            BitcoinWallet::Cold(_, network, BitcoinColdWallet::Descriptor { descriptor, .. }) => {
+                OutputDescriptor::parse(descriptor, network)?
+                    .keys()
+                    .iter()
+                    .map(|key| Ok((*key.xpub(), key.origin()?)))
+                    .collect()
+            }
+        }
+    }
+
+    fn redeem_script_at(&self, index: u64) -> anyhow::Result<Option<Script>> {
+        match (self.output_descriptor()?, self.address_type()) {
+            (Some(descriptor), _) => descriptor.redeem_script(index),
+            (None, BitcoinAddressType::P2SHWPKH) => {
+                let wpubkey_hash = PublicKey::new(self.xpub_at(index)?.public_key)
+                    .wpubkey_hash()
+                    .ok_or_else(|| anyhow!("Uncompressed key"))?;
+                Ok(Some(Script::new_v0_p2wpkh(&wpubkey_hash)))
+            }
+            (None, _) => Ok(None),
+        }
+    }
+
+    fn witness_script_at(&self, index: u64) -> anyhow::Result<Option<Script>> {
+        match self.output_descriptor()? {
+            Some(descriptor) => descriptor.witness_script(index),
+            None => Ok(None),
+        }
+    }
+
+    /// Virtual size of a signed input, rounded up
+    fn input_vsize(&self) -> anyhow::Result<u64> {
+        let (threshold, keys) = match self.output_descriptor()? {
+            Some(
+                OutputDescriptor::Sh(multi)
+                | OutputDescriptor::Wsh(multi)
+                | OutputDescriptor::ShWsh(multi),
+            ) => (multi.threshold as u64, multi.keys.len() as u64),
+            _ => return self.address_type().input_vsize(),
+        };
+        // OP_k <33 byte pubkeys> OP_n OP_CHECKMULTISIG
+        let script_len = 3 + 34 * keys;
+        // Stack of a dummy element, 73 byte signatures and the script
+        let stack_len = 1 + 73 * threshold + 3 + script_len;
+        match self.address_type() {
+            BitcoinAddressType::P2SH => Ok(41 + stack_len),
+            BitcoinAddressType::P2WSH => Ok(41 + (1 + stack_len).div_ceil(4)),
+            _ => Ok(76 + (1 + stack_len).div_ceil(4)),
+        }
+    }
+
+    /// Unsigned transaction spending every `utxo` to `destination` less a `fee_rate` sat/vB
+    /// fee, inputs carry the key origins `sign_psbt` signs with
+    pub fn create_sweep_psbt(
+        &self,
+        utxos: &[BitcoinUtxo],
+        destination: &str,
+        fee_rate: u64,
+    ) -> anyhow::Result<PartiallySignedTransaction> {
+        if self.ticker() != Ticker::BTC {
+            return Err(anyhow!("Ticker {:?} not supported", self.ticker()));
+        }
+        if utxos.is_empty() {
+            return Err(anyhow!("No confirmed outputs to sweep"));
+        }
+        let destination = Address::from_str(destination)?;
+        if !destination.is_valid_for_network(self.network().into()) {
+            return Err(anyhow!(
+                "Address {} is not valid for {:?}",
+                destination,
+                self.network()
+            ));
+        }
+        if fee_rate > BITCOIN_MAX_FEE_RATE {
+            return Err(anyhow!(
+                "Fee rate {} sat/vB is above {} sat/vB",
+                fee_rate,
+                BITCOIN_MAX_FEE_RATE
+            ));
+        }
+        let script_pubkey = destination.script_pubkey();
+
+        let input_vsize = self.input_vsize()?;
+        let vsize = 11 + input_vsize * utxos.len() as u64 + 9 + script_pubkey.len() as u64;
+        let fee = vsize
+            .checked_mul(fee_rate)
+            .ok_or_else(|| anyhow!("Fee of {} vB at {} sat/vB overflows", vsize, fee_rate))?;
+        let total = utxos
+            .iter()
+            .try_fold(0u64, |total, utxo| total.checked_add(utxo.txout.value))
+            .ok_or_else(|| anyhow!("Swept outputs overflow"))?;
+        let amount = total
+            .checked_sub(fee)
+            .filter(|amount| *amount >= BITCOIN_DUST_LIMIT)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} sats is too little to sweep with a {} sats fee",
+                    total,
+                    fee
+                )
+            })?;
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: utxos
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.outpoint,
+                    script_sig: Script::new(),
+                    sequence: RBF_SEQUENCE,
+                    witness: Witness::default(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: amount,
+                script_pubkey,
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx)?;
+        psbt.xpub.extend(self.account_xpubs()?);
+        for (input, utxo) in psbt.inputs.iter_mut().zip(utxos) {
+            self.fill_psbt_input(input, utxo)?;
+        }
+        Ok(psbt)
+    }
+
+    fn fill_psbt_input(&self, input: &mut psbt::Input, utxo: &BitcoinUtxo) -> anyhow::Result<()> {
+        if utxo.txout.script_pubkey != self.script_pubkey_at(utxo.index)? {
+            return Err(anyhow!(
+                "{} does not pay receive address {}",
+                utxo.outpoint,
+                utxo.index
+            ));
+        }
+        let keys = self.input_keys(utxo.index)?;
+        match self.address_type() {
+            // Legacy inputs sign over the previous output script, which is known from the keys
+            BitcoinAddressType::P2PKH | BitcoinAddressType::P2SH => {}
+            _ => input.witness_utxo = Some(utxo.txout.clone()),
+        }
+        input.redeem_script = self.redeem_script_at(utxo.index)?;
+        input.witness_script = self.witness_script_at(utxo.index)?;
+        match self.address_type() {
+            BitcoinAddressType::P2TR => {
+                let (pubkey, key_source) = keys
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing taproot key"))?;
+                let internal_key = XOnlyPublicKey::from(pubkey);
+                input.tap_internal_key = Some(internal_key);
+                input
+                    .tap_key_origins
+                    .insert(internal_key, (vec![], key_source));
+            }
+            _ => input.bip32_derivation.extend(keys),
+        }
+        Ok(())
+    }
+
+    /// Signs every input whose key origin matches this hot wallet's master key
+    pub fn sign_psbt(&self, psbt: &mut PartiallySignedTransaction) -> anyhow::Result<()> {
+        let (network, w) = match self {
+            BitcoinWallet::Hot(Ticker::BTC, network, w) => (network, w),
+            BitcoinWallet::Hot(ticker, _, _) => {
+                return Err(anyhow!("Ticker {:?} not supported", ticker))
+            }
+            BitcoinWallet::Cold(_, _, _) => {
+                return Err(anyhow!("Cold wallets have no private keys"))
+            }
+        };
+        let secp = Secp256k1::new();
+        let master = w.master_key(network)?;
+        let fingerprint = master.fingerprint(&secp);
+
+        let tx = psbt.unsigned_tx.clone();
+        let prevouts = psbt
+            .inputs
+            .iter()
+            .map(|input| input.witness_utxo.clone())
+            .collect::<Option<Vec<TxOut>>>();
+        let mut cache = SighashCache::new(&tx);
+
+        for (i, input) in psbt.inputs.iter_mut().enumerate() {
+            for (pubkey, (key_fingerprint, path)) in input.bip32_derivation.clone() {
+                if key_fingerprint != fingerprint {
+                    continue;
+                }
+                let secret_key = master.derive_priv(&secp, &path)?.private_key;
+                if secp256k1::PublicKey::from_secret_key(&secp, &secret_key) != pubkey {
+                    return Err(anyhow!("Input {} key does not match {}", i, path));
+                }
+                let pubkey = PublicKey::new(pubkey);
+                let script_code = match (&input.witness_script, &input.redeem_script) {
+                    (Some(witness_script), _) => witness_script.clone(),
+                    (None, Some(redeem_script)) if !redeem_script.is_v0_p2wpkh() => {
+                        redeem_script.clone()
+                    }
+                    _ => Script::new_p2pkh(&pubkey.pubkey_hash()),
+                };
+                let sighash = match &input.witness_utxo {
+                    Some(prevout) => cache.segwit_signature_hash(
+                        i,
+                        &script_code,
+                        prevout.value,
+                        EcdsaSighashType::All,
+                    )?,
+                    None => cache.legacy_signature_hash(
+                        i,
+                        &script_code,
+                        EcdsaSighashType::All.to_u32(),
+                    )?,
+                };
+                let sig = secp.sign_ecdsa(&Message::from_slice(&sighash[..])?, &secret_key);
+                input
+                    .partial_sigs
+                    .insert(pubkey, EcdsaSig::sighash_all(sig));
+            }
+
+            let internal_key = match input.tap_internal_key {
+                Some(internal_key) => internal_key,
+                None => continue,
+            };
+            if let Some((_, (key_fingerprint, path))) = input.tap_key_origins.get(&internal_key) {
+                if *key_fingerprint != fingerprint {
+                    continue;
+                }
+                let prevouts = prevouts
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Taproot inputs need every witness_utxo"))?;
+                let sighash = cache.taproot_key_spend_signature_hash(
+                    i,
+                    &Prevouts::All(prevouts),
+                    SchnorrSighashType::Default,
+                )?;
+                let keypair =
+                    KeyPair::from_secret_key(&secp, master.derive_priv(&secp, path)?.private_key)
+                        .tap_tweak(&secp, input.tap_merkle_root)
+                        .into_inner();
+                let sig =
+                    secp.sign_schnorr_no_aux_rand(&Message::from_slice(&sighash[..])?, &keypair);
+                input.tap_key_sig = Some(SchnorrSig {
+                    sig,
+                    hash_ty: SchnorrSighashType::Default,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Signatures of a `multi` script in key order, `None` below the threshold
+fn multisig_sigs(
+    script: &Script,
+    partial_sigs: &BTreeMap<PublicKey, EcdsaSig>,
+) -> anyhow::Result<Option<Vec<Vec<u8>>>> {
+    let mut instructions = script.instructions();
+    let threshold = match instructions.next() {
+        Some(Ok(Instruction::Op(op))) if (0x51..=0x60).contains(&op.into_u8()) => {
+            op.into_u8() as usize - 0x50
+        }
+        _ => return Err(anyhow!("Not a multisig script {:x}", script)),
+    };
+    let mut sigs = vec![];
+    for instruction in instructions {
+        if let Ok(Instruction::PushBytes(bytes)) = instruction {
+            if let Some(sig) = partial_sigs.get(&PublicKey::from_slice(bytes)?) {
+                if sigs.len() < threshold {
+                    sigs.push(sig.to_vec());
+                }
+            }
+        }
+    }
+    Ok(if sigs.len() == threshold {
+        Some(sigs)
+    } else {
+        None
+    })
+}
+
+/// Builds the final scriptSig and witness of every signed input and extracts the transaction,
+/// inputs a signer already finalized are kept as is
+/// Checks the previous outputs a PSBT claims for its inputs against `prevouts`, what the node
+/// knows them to be. The PSBT comes back from outside and its claims can't be trusted
+pub fn verify_psbt_prevouts(
+    psbt: &PartiallySignedTransaction,
+    prevouts: &[TxOut],
+) -> anyhow::Result<()> {
+    if prevouts.len() != psbt.unsigned_tx.input.len() {
+        return Err(anyhow!(
+            "{} previous outputs for {} inputs",
+            prevouts.len(),
+            psbt.unsigned_tx.input.len()
+        ));
+    }
+    for (i, ((input, txin), prevout)) in psbt
+        .inputs
+        .iter()
+        .zip(&psbt.unsigned_tx.input)
+        .zip(prevouts)
+        .enumerate()
+    {
+        if input
+            .witness_utxo
+            .as_ref()
+            .is_some_and(|witness_utxo| witness_utxo != prevout)
+        {
+            return Err(anyhow!("Input {} witness UTXO does not match the chain", i));
+        }
+        if let Some(prev_tx) = &input.non_witness_utxo {
+            if prev_tx.txid() != txin.previous_output.txid
+                || prev_tx.output.get(txin.previous_output.vout as usize) != Some(prevout)
+            {
+                return Err(anyhow!(
+                    "Input {} previous transaction does not match the chain",
+                    i
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn finalize_psbt(mut psbt: PartiallySignedTransaction) -> anyhow::Result<Transaction> {
+    let tx = psbt.unsigned_tx.clone();
+    for (i, (input, txin)) in psbt.inputs.iter_mut().zip(&tx.input).enumerate() {
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            continue;
+        }
+        let prevout = match (&input.witness_utxo, &input.non_witness_utxo) {
+            (Some(prevout), _) => prevout.clone(),
+            (None, Some(prev_tx)) => prev_tx
+                .output
+                .get(txin.previous_output.vout as usize)
+                .cloned()
+                .ok_or_else(|| anyhow!("Input {} previous output not found", i))?,
+            // Our own legacy inputs only carry the key, which implies a P2PKH output
+            (None, None) => match (input.partial_sigs.keys().next(), &input.redeem_script) {
+                (Some(pubkey), None) if input.partial_sigs.len() == 1 => TxOut {
+                    value: 0,
+                    script_pubkey: Script::new_p2pkh(&pubkey.pubkey_hash()),
+                },
+                _ => return Err(anyhow!("Input {} has no previous output", i)),
+            },
+        };
+        let single_sig = || match input.partial_sigs.iter().next() {
+            Some((pubkey, sig)) => Ok(vec![sig.to_vec(), pubkey.to_bytes()]),
+            None => Err(anyhow!("Input {} is not signed", i)),
+        };
+        let multi_sig = |script: &Script| match multisig_sigs(script, &input.partial_sigs)? {
+            Some(sigs) => Ok(std::iter::once(vec![])
+                .chain(sigs)
+                .chain(std::iter::once(script.to_bytes()))
+                .collect::<Vec<Vec<u8>>>()),
+            None => Err(anyhow!("Input {} is missing signatures", i)),
+        };
+        let script_pubkey = &prevout.script_pubkey;
+        let redeem_script = input.redeem_script.clone();
+        let (script_sig, witness) = if script_pubkey.is_v1_p2tr() {
+            let sig = input
+                .tap_key_sig
+                .ok_or_else(|| anyhow!("Input {} is not signed", i))?;
+            (None, Some(vec![sig.to_vec()]))
+        } else if script_pubkey.is_p2pkh() {
+            let stack = single_sig()?;
+            let script_sig = Builder::new()
+                .push_slice(&stack[0])
+                .push_slice(&stack[1])
+                .into_script();
+            (Some(script_sig), None)
+        } else {
+            let witness_program = match &redeem_script {
+                Some(redeem_script) if script_pubkey.is_p2sh() => redeem_script,
+                _ => script_pubkey,
+            };
+            let witness = if witness_program.is_v0_p2wpkh() {
+                single_sig()?
+            } else if witness_program.is_v0_p2wsh() {
+                let witness_script = input
+                    .witness_script
+                    .clone()
+                    .ok_or_else(|| anyhow!("Input {} has no witness script", i))?;
+                multi_sig(&witness_script)?
+            } else if let (true, Some(redeem_script)) = (script_pubkey.is_p2sh(), &redeem_script) {
+                // Bare multisig behind P2SH, the whole stack goes in the scriptSig
+                let script_sig = multi_sig(redeem_script)?
+                    .iter()
+                    .fold(Builder::new(), |b, item| b.push_slice(item))
+                    .into_script();
+                input.final_script_sig = Some(script_sig);
+                clear_psbt_input(input);
+                continue;
+            } else {
+                return Err(anyhow!("Input {} has an unsupported script", i));
+            };
+            let script_sig = match &redeem_script {
+                Some(redeem_script) if script_pubkey.is_p2sh() => Some(
+                    Builder::new()
+                        .push_slice(redeem_script.as_bytes())
+                        .into_script(),
+                ),
+                _ => None,
+            };
+            (script_sig, Some(witness))
+        };
+        input.final_script_sig = script_sig;
+        input.final_script_witness = witness.map(Witness::from_vec);
+        clear_psbt_input(input);
+    }
+    Ok(psbt.extract_tx())
+}
+
+fn clear_psbt_input(input: &mut psbt::Input) {
+    input.partial_sigs.clear();
+    input.bip32_derivation.clear();
+    input.redeem_script = None;
+    input.witness_script = None;
+    input.tap_key_sig = None;
+    input.tap_key_origins.clear();
+    input.tap_internal_key = None;
+}
+
+#[test]
+fn test_sweep_psbt() {
+    for derivation in [
+        BitcoinDerivation::Bip44,
+        BitcoinDerivation::Bip49,
+        BitcoinDerivation::Bip84,
+        BitcoinDerivation::Bip86,
+    ] {
+        let mut w = BitcoinWallet::new_hot_with_derivation(
+            Ticker::BTC,
+            Network::Regtest,
+            derivation.clone(),
+            0,
+        )
+        .expect("Invalid BitcoinWallet");
+        let utxos = (0..3)
+            .map(|index| {
+                let (_, addr) = w.next_addr().expect("Invalid Addr");
+                BitcoinUtxo {
+                    outpoint: OutPoint::new(bitcoin::Txid::default(), index as u32),
+                    txout: TxOut {
+                        value: 50_000,
+                        script_pubkey: Address::from_str(&addr)
+                            .expect("Invalid Addr")
+                            .script_pubkey(),
+                    },
+                    index,
+                }
+            })
+            .collect::<Vec<BitcoinUtxo>>();
+        let destination = "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80";
+
+        let mut psbt = w
+            .create_sweep_psbt(&utxos, destination, 2)
+            .expect("Invalid Psbt");
+        assert_eq!(
+            psbt_from_base64(&psbt_to_base64(&psbt)).expect("Invalid Psbt"),
+            psbt
+        );
+        let fee = 150_000 - psbt.unsigned_tx.output[0].value;
+        assert!(fee > 0 && fee < 1_000);
+
+        w.sign_psbt(&mut psbt).expect("Failed to sign");
+        let tx = finalize_psbt(psbt).expect("Failed to finalize");
+        assert_eq!(tx.input.len(), 3);
+        for input in &tx.input {
+            match derivation {
+                BitcoinDerivation::Bip44 => {
+                    assert!(!input.script_sig.is_empty() && input.witness.is_empty())
+                }
+                BitcoinDerivation::Bip86 => assert_eq!(input.witness.len(), 1),
+                _ => assert_eq!(input.witness.len(), 2),
+            }
+        }
+        // Estimates never underpay
+        assert!(fee >= tx.vsize() as u64 * 2);
+
+        assert!(w.create_sweep_psbt(&utxos, destination, 1_000).is_err());
+        assert!(w
+            .create_sweep_psbt(&utxos, destination, BITCOIN_MAX_FEE_RATE + 1)
+            .is_err());
+        assert!(w.create_sweep_psbt(&utxos, destination, u64::MAX).is_err());
+        assert!(w
+            .create_sweep_psbt(&utxos, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu", 2)
+            .is_err());
+        let mut wrong_index = utxos.clone();
+        wrong_index[0].index = 1;
+        assert!(w.create_sweep_psbt(&wrong_index, destination, 2).is_err());
+    }
+}
+
+#[test]
+fn test_multisig_psbt() {
+    let cosigners = (0..3)
+        .map(|_| {
+            BitcoinWallet::new_hot_with_derivation(
+                Ticker::BTC,
+                Network::Regtest,
+                BitcoinDerivation::Bip84,
+                0,
+            )
+            .expect("Invalid BitcoinWallet")
+        })
+        .collect::<Vec<BitcoinWallet>>();
+    let keys = cosigners
+        .iter()
+        .map(|w| {
+            let (xpub, (fingerprint, path)) = w.account_xpubs().expect("Invalid Xpub")[0].clone();
+            format!(
+                "[{}{}]{}/0/*",
+                fingerprint,
+                path.to_string().trim_start_matches('m'),
+                xpub
+            )
+        })
+        .collect::<Vec<String>>();
+    let mut w = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Regtest,
+        format!("wsh(sortedmulti(2,{}))", keys.join(",")),
+        crate::BitcoinColdWalletType::Descriptor,
+    )
+    .expect("Invalid BitcoinWallet");
+    let utxos = (0..2)
+        .map(|index| {
+            let (_, addr) = w.next_invoice_addr().expect("Invalid Addr");
+            BitcoinUtxo {
+                outpoint: OutPoint::new(bitcoin::Txid::default(), index as u32),
+                txout: TxOut {
+                    value: 100_000,
+                    script_pubkey: Address::from_str(&addr)
+                        .expect("Invalid Addr")
+                        .script_pubkey(),
+                },
+                index,
+            }
+        })
+        .collect::<Vec<BitcoinUtxo>>();
+
+    let mut psbt = w
+        .create_sweep_psbt(&utxos, "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80", 3)
+        .expect("Invalid Psbt");
+    assert_eq!(psbt.xpub.len(), 3);
+    for input in &psbt.inputs {
+        assert_eq!(input.bip32_derivation.len(), 3);
+        assert!(input.witness_script.is_some());
+    }
+    assert!(w.sign_psbt(&mut psbt).is_err());
+
+    cosigners[0].sign_psbt(&mut psbt).expect("Failed to sign");
+    assert!(finalize_psbt(psbt.clone()).is_err());
+    cosigners[2].sign_psbt(&mut psbt).expect("Failed to sign");
+    let tx = finalize_psbt(psbt).expect("Failed to finalize");
+    for input in &tx.input {
+        assert!(input.script_sig.is_empty());
+        assert_eq!(input.witness.len(), 4);
+    }
+    let fee = 200_000 - tx.output[0].value;
+    assert!(fee >= tx.vsize() as u64 * 3 && fee < tx.vsize() as u64 * 4);
+}
+
+#[test]
+fn test_xpub_psbt() {
+    let signer = BitcoinWallet::new_hot_with_derivation(
+        Ticker::BTC,
+        Network::Regtest,
+        BitcoinDerivation::Bip84,
+        0,
+    )
+    .expect("Invalid BitcoinWallet");
+    let (xpub, (fingerprint, path)) = signer.account_xpubs().expect("Invalid Xpub")[0].clone();
+    let origin = format!(
+        "[{}{}]",
+        fingerprint,
+        path.to_string().trim_start_matches('m')
+    );
+
+    let mut w = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Regtest,
+        format!("{}{}", origin, xpub),
+        crate::BitcoinColdWalletType::P2WPKH,
+    )
+    .expect("Invalid BitcoinWallet");
+    let utxos = (0..2)
+        .map(|index| {
+            let (_, addr) = w.next_invoice_addr().expect("Invalid Addr");
+            BitcoinUtxo {
+                outpoint: OutPoint::new(bitcoin::Txid::default(), index as u32),
+                txout: TxOut {
+                    value: 100_000,
+                    script_pubkey: Address::from_str(&addr)
+                        .expect("Invalid Addr")
+                        .script_pubkey(),
+                },
+                index,
+            }
+        })
+        .collect::<Vec<BitcoinUtxo>>();
+    let destination = "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80";
+
+    let mut psbt = w
+        .create_sweep_psbt(&utxos, destination, 2)
+        .expect("Invalid Psbt");
+    // Origins lead back to the signer's master key, not the account xpub
+    assert_eq!(psbt.xpub.get(&xpub), Some(&(fingerprint, path.clone())));
+    for (input, utxo) in psbt.inputs.iter().zip(&utxos) {
+        let (_, key_source) = input
+            .bip32_derivation
+            .iter()
+            .next()
+            .expect("Missing key origin");
+        assert_eq!(
+            key_source,
+            &(
+                fingerprint,
+                path.extend(
+                    DerivationPath::from_str(&format!("m/0/{}", utxo.index)).expect("Invalid path")
+                )
+            )
+        );
+    }
+    signer.sign_psbt(&mut psbt).expect("Failed to sign");
+    let tx = finalize_psbt(psbt).expect("Failed to finalize");
+    for input in &tx.input {
+        assert_eq!(input.witness.len(), 2);
+    }
+
+    // An account xpub without its origin can't say where its keys come from
+    let bare = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Regtest,
+        xpub.to_string(),
+        crate::BitcoinColdWalletType::P2WPKH,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert!(bare.create_sweep_psbt(&utxos, destination, 2).is_err());
+
+    // The origin path must lead to the xpub's depth
+    assert!(BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Regtest,
+        format!("[{}]{}", fingerprint, xpub),
+        crate::BitcoinColdWalletType::P2WPKH,
+    )
+    .is_err());
+    assert!(BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Regtest,
+        format!("[{}/84']{}", fingerprint, xpub),
+        crate::BitcoinColdWalletType::P2WPKH,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_verify_psbt_prevouts() {
+    let mut w = BitcoinWallet::new_hot_with_derivation(
+        Ticker::BTC,
+        Network::Regtest,
+        BitcoinDerivation::Bip84,
+        0,
+    )
+    .expect("Invalid BitcoinWallet");
+    let (_, addr) = w.next_addr().expect("Invalid Addr");
+    let prevout = TxOut {
+        value: 100_000,
+        script_pubkey: Address::from_str(&addr)
+            .expect("Invalid Addr")
+            .script_pubkey(),
+    };
+    let prev_tx = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![],
+        output: vec![prevout.clone()],
+    };
+    let utxos = vec![BitcoinUtxo {
+        outpoint: OutPoint::new(prev_tx.txid(), 0),
+        txout: prevout.clone(),
+        index: 0,
+    }];
+    let mut psbt = w
+        .create_sweep_psbt(&utxos, "bcrt1qhe8apwuv2dsr95yknu826qad3cdxvs323ygc80", 2)
+        .expect("Invalid Psbt");
+    psbt.inputs[0].non_witness_utxo = Some(prev_tx.clone());
+    verify_psbt_prevouts(&psbt, &[prevout.clone()]).expect("Invalid prevouts");
+    assert!(verify_psbt_prevouts(&psbt, &[]).is_err());
+
+    // A PSBT claiming more funds than the chain has
+    let mut inflated = prevout.clone();
+    inflated.value = 200_000;
+    assert!(verify_psbt_prevouts(&psbt, &[inflated.clone()]).is_err());
+    let mut forged = psbt.clone();
+    forged.inputs[0].witness_utxo = Some(inflated);
+    assert!(verify_psbt_prevouts(&forged, &[prevout.clone()]).is_err());
+
+    // A previous transaction that isn't the one the input spends
+    let mut other_tx = prev_tx;
+    other_tx.lock_time = 1;
+    let mut forged = psbt;
+    forged.inputs[0].non_witness_utxo = Some(other_tx);
+    assert!(verify_psbt_prevouts(&forged, &[prevout]).is_err());
+}
@@ -3,9 +3,10 @@ use std::{fmt, str::FromStr};
 use anyhow::anyhow;
 use bip39::Mnemonic;
 use bitcoin::{
-    secp256k1::Secp256k1,
+    secp256k1::{Secp256k1, XOnlyPublicKey},
     util::{
         address::Address,
+        base58,
         bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey},
     },
 };
@@ -14,7 +15,11 @@ use serde::{Deserialize, Serialize};
 
 use moonramp_core::{anyhow, bip39, bitcoin, bs58, rand, serde};
 
-use crate::{BitcoinColdWalletType, Network, Ticker, WalletType};
+use crate::{
+    cashaddr_encode, parse_key_origin, split_key_origin, strip_descriptor_checksum,
+    BitcoinColdWalletType, BitcoinDerivation, Network, OutputDescriptor, Ticker, WalletSecret,
+    WalletType, CASHADDR_P2PKH,
+};
 
 #[derive(Eq, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
@@ -23,6 +28,10 @@ pub struct BitcoinHotWallet {
     pub password: String,
     pub xpub: Vec<u8>,
     pub index: u64,
+    #[serde(default)]
+    pub derivation: BitcoinDerivation,
+    #[serde(default)]
+    pub account: u32,
 }
 
 impl fmt::Debug for BitcoinHotWallet {
@@ -47,7 +56,17 @@ impl fmt::Display for BitcoinHotWallet {
 #[derive(Eq, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum BitcoinColdWallet {
-    XPubkey { xpub: String, index: u64 },
+    XPubkey {
+        xpub: String,
+        index: u64,
+        #[serde(default)]
+        derivation: BitcoinDerivation,
+        /// `[fingerprint/path]` of the account key, what signers match their master key against
+        #[serde(default)]
+        origin: Option<String>,
+    },
+    /// Ranged output descriptor stored with its `#checksum`
+    Descriptor { descriptor: String, index: u64 },
 }
 
 impl fmt::Debug for BitcoinColdWallet {
@@ -61,6 +80,7 @@ impl fmt::Debug for BitcoinColdWallet {
                 ),
                 Err(err) => write!(f, "Invalid BitcoinHotWallet({})", err),
             },
+            BitcoinColdWallet::Descriptor { descriptor, .. } => write!(f, "{}", descriptor),
         }
     }
 }
@@ -91,8 +111,142 @@ impl fmt::Display for BitcoinWallet {
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BitcoinAddressType {
+    P2PKH,
+    P2SHWPKH,
+    P2WPKH,
+    P2TR,
+    P2SH,
+    P2WSH,
+    P2SHWSH,
+}
+
+impl BitcoinDerivation {
+    pub fn purpose(&self) -> Option<u32> {
+        match self {
+            BitcoinDerivation::Legacy => None,
+            BitcoinDerivation::Bip44 => Some(44),
+            BitcoinDerivation::Bip49 => Some(49),
+            BitcoinDerivation::Bip84 => Some(84),
+            BitcoinDerivation::Bip86 => Some(86),
+        }
+    }
+
+    pub fn address_type(&self, ticker: &Ticker) -> BitcoinAddressType {
+        match (self, ticker) {
+            (BitcoinDerivation::Legacy, Ticker::BTC) => BitcoinAddressType::P2WPKH,
+            (BitcoinDerivation::Legacy, _) | (BitcoinDerivation::Bip44, _) => {
+                BitcoinAddressType::P2PKH
+            }
+            (BitcoinDerivation::Bip49, _) => BitcoinAddressType::P2SHWPKH,
+            (BitcoinDerivation::Bip84, _) => BitcoinAddressType::P2WPKH,
+            (BitcoinDerivation::Bip86, _) => BitcoinAddressType::P2TR,
+        }
+    }
+
+    /// Hardened account path `m/purpose'/coin_type'/account'`, `None` for `Legacy`
+    pub fn account_path(
+        &self,
+        ticker: &Ticker,
+        network: &Network,
+        account: u32,
+    ) -> anyhow::Result<Option<DerivationPath>> {
+        match self.purpose() {
+            Some(purpose) => Ok(Some(DerivationPath::from_str(&format!(
+                "m/{}'/{}'/{}'",
+                purpose,
+                coin_type(ticker, network)?,
+                account
+            ))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Receive chain path relative to the stored xpub
+    pub(crate) fn receive_path(&self, index: u64) -> anyhow::Result<DerivationPath> {
+        let path = match self {
+            BitcoinDerivation::Legacy => format!("m/1/0/{}", index),
+            _ => format!("m/0/{}", index),
+        };
+        Ok(DerivationPath::from_str(&path)?)
+    }
+}
+
+/// SLIP-44 coin type
+fn coin_type(ticker: &Ticker, network: &Network) -> anyhow::Result<u32> {
+    match (ticker, network) {
+        (Ticker::BTC | Ticker::BCH, Network::Testnet | Network::Regtest) => Ok(1),
+        (Ticker::BTC, Network::Signet) => Ok(1),
+        (Ticker::BTC, Network::Mainnet) => Ok(0),
+        (Ticker::BCH, Network::Mainnet) => Ok(145),
+        _ => Err(anyhow!(
+            "Ticker {:?} not supported on {:?}",
+            ticker,
+            network
+        )),
+    }
+}
+
+fn check_derivation(ticker: &Ticker, derivation: &BitcoinDerivation) -> anyhow::Result<()> {
+    match (ticker, derivation.address_type(ticker)) {
+        (Ticker::BTC, _) | (Ticker::BCH, BitcoinAddressType::P2PKH) => Ok(()),
+        (Ticker::BCH, _) => Err(anyhow!("{:?} not supported for BCH", derivation)),
+        _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
+    }
+}
+
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const TPUB_VERSION: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+
+/// Reads a SLIP-132 extended public key (xpub/ypub/zpub and their testnet forms)
+/// into a standard `ExtendedPubKey` and the derivation implied by its prefix
+pub fn parse_slip132(
+    pubkey: &str,
+    network: &Network,
+) -> anyhow::Result<(ExtendedPubKey, BitcoinDerivation)> {
+    let mut data = base58::from_check(pubkey)?;
+    if data.len() != 78 {
+        return Err(anyhow!("Invalid extended public key length {}", data.len()));
+    }
+    let (mainnet, derivation) = match data[..4] {
+        [0x04, 0x88, 0xb2, 0x1e] => (true, BitcoinDerivation::Legacy),
+        [0x04, 0x9d, 0x7c, 0xb2] => (true, BitcoinDerivation::Bip49),
+        [0x04, 0xb2, 0x47, 0x46] => (true, BitcoinDerivation::Bip84),
+        [0x04, 0x35, 0x87, 0xcf] => (false, BitcoinDerivation::Legacy),
+        [0x04, 0x4a, 0x52, 0x62] => (false, BitcoinDerivation::Bip49),
+        [0x04, 0x5f, 0x1c, 0xf6] => (false, BitcoinDerivation::Bip84),
+        _ => return Err(anyhow!("Unknown extended public key version")),
+    };
+    if mainnet != (*network == Network::Mainnet) {
+        return Err(anyhow!(
+            "Extended public key is not valid for {:?}",
+            network
+        ));
+    }
+    data[..4].copy_from_slice(if mainnet {
+        &XPUB_VERSION
+    } else {
+        &TPUB_VERSION
+    });
+    Ok((ExtendedPubKey::decode(&data)?, derivation))
+}
+
 impl BitcoinWallet {
     pub fn new_hot(ticker: Ticker, network: Network) -> anyhow::Result<BitcoinWallet> {
+        let derivation = match ticker {
+            Ticker::BTC => BitcoinDerivation::Bip84,
+            _ => BitcoinDerivation::Bip44,
+        };
+        BitcoinWallet::new_hot_with_derivation(ticker, network, derivation, 0)
+    }
+
+    pub fn new_hot_with_derivation(
+        ticker: Ticker,
+        network: Network,
+        derivation: BitcoinDerivation,
+        account: u32,
+    ) -> anyhow::Result<BitcoinWallet> {
         let password: String = thread_rng()
             .sample_iter(&Alphanumeric)
             .take(32)
@@ -103,18 +257,56 @@ impl BitcoinWallet {
         thread_rng().fill(&mut entropy);
 
         let mnemonic = Mnemonic::from_entropy(&entropy)?;
+        BitcoinWallet::from_mnemonic(ticker, network, mnemonic, password, derivation, account)
+    }
+
+    /// Restores a hot wallet from its BIP39 mnemonic words and passphrase
+    pub fn import_hot(
+        ticker: Ticker,
+        network: Network,
+        mnemonic: &str,
+        password: String,
+        derivation: Option<BitcoinDerivation>,
+        account: u32,
+    ) -> anyhow::Result<BitcoinWallet> {
+        let derivation = derivation.unwrap_or(match ticker {
+            Ticker::BTC => BitcoinDerivation::Bip84,
+            _ => BitcoinDerivation::Bip44,
+        });
+        let mnemonic = Mnemonic::parse(mnemonic.to_lowercase())?;
+        BitcoinWallet::from_mnemonic(ticker, network, mnemonic, password, derivation, account)
+    }
+
+    fn from_mnemonic(
+        ticker: Ticker,
+        network: Network,
+        mnemonic: Mnemonic,
+        password: String,
+        derivation: BitcoinDerivation,
+        account: u32,
+    ) -> anyhow::Result<BitcoinWallet> {
+        check_derivation(&ticker, &derivation)?;
+
         let seed = mnemonic.to_seed(password.clone());
         let key = ExtendedPrivKey::new_master(network.clone().into(), &seed)?;
 
         let secp = Secp256k1::new();
+        let xpub = match derivation.account_path(&ticker, &network, account)? {
+            Some(account_path) => {
+                ExtendedPubKey::from_priv(&secp, &key.derive_priv(&secp, &account_path)?)
+            }
+            None => ExtendedPubKey::from_priv(&secp, &key),
+        };
         Ok(BitcoinWallet::Hot(
             ticker,
             network,
             BitcoinHotWallet {
                 mnemonic: mnemonic.to_entropy(),
                 password,
-                xpub: ExtendedPubKey::from_priv(&secp, &key).encode().to_vec(),
+                xpub: xpub.encode().to_vec(),
                 index: 0,
+                derivation,
+                account,
             },
         ))
     }
@@ -125,25 +317,104 @@ impl BitcoinWallet {
         pubkey: String,
         cold_type: BitcoinColdWalletType,
     ) -> anyhow::Result<BitcoinWallet> {
-        match (ticker, cold_type) {
-            (Ticker::BTC, BitcoinColdWalletType::XPubkey) => Ok(BitcoinWallet::Cold(
-                Ticker::BTC,
-                network,
-                BitcoinColdWallet::XPubkey {
-                    xpub: pubkey,
-                    index: 0,
-                },
-            )),
-            (Ticker::BCH, BitcoinColdWalletType::XPubkey) => Ok(BitcoinWallet::Cold(
-                Ticker::BCH,
+        if let BitcoinColdWalletType::Descriptor = cold_type {
+            if ticker != Ticker::BTC {
+                return Err(anyhow!("Descriptor wallets not supported for {:?}", ticker));
+            }
+            let descriptor = OutputDescriptor::parse(&pubkey, &network)?;
+            return Ok(BitcoinWallet::Cold(
+                ticker,
                 network,
-                BitcoinColdWallet::XPubkey {
-                    xpub: pubkey,
+                BitcoinColdWallet::Descriptor {
+                    descriptor: descriptor.to_string_with_checksum()?,
                     index: 0,
                 },
-            )),
-            _ => Err(anyhow!("Ticker not supported")),
+            ));
         }
+
+        let (origin, pubkey) = split_key_origin(pubkey.trim())?;
+        let (xpub, prefix_derivation) = parse_slip132(pubkey, &network)?;
+        if let Some(origin) = &origin {
+            let (_, path) = parse_key_origin(origin)?;
+            if path.len() != xpub.depth as usize {
+                return Err(anyhow!(
+                    "Key origin {} does not match an extended public key of depth {}",
+                    origin,
+                    xpub.depth
+                ));
+            }
+        }
+        let derivation = match (cold_type, prefix_derivation) {
+            // A plain xpub/tpub doesn't say which script it was exported for, BCH only has one
+            (BitcoinColdWalletType::XPubkey, BitcoinDerivation::Legacy) => match ticker {
+                Ticker::BCH => BitcoinDerivation::Bip44,
+                _ => {
+                    return Err(anyhow!(
+                        "An xpub has no script type, import it as P2PKH, P2SHWPKH, P2WPKH or P2TR"
+                    ))
+                }
+            },
+            (BitcoinColdWalletType::XPubkey, derivation) => derivation,
+            (BitcoinColdWalletType::P2PKH, BitcoinDerivation::Legacy) => BitcoinDerivation::Bip44,
+            (
+                BitcoinColdWalletType::P2SHWPKH,
+                BitcoinDerivation::Legacy | BitcoinDerivation::Bip49,
+            ) => BitcoinDerivation::Bip49,
+            (
+                BitcoinColdWalletType::P2WPKH,
+                BitcoinDerivation::Legacy | BitcoinDerivation::Bip84,
+            ) => BitcoinDerivation::Bip84,
+            (BitcoinColdWalletType::P2TR, BitcoinDerivation::Legacy) => BitcoinDerivation::Bip86,
+            (cold_type, derivation) => {
+                return Err(anyhow!(
+                    "{:?} does not match a {:?} extended public key",
+                    cold_type,
+                    derivation
+                ))
+            }
+        };
+        check_derivation(&ticker, &derivation)?;
+        Ok(BitcoinWallet::Cold(
+            ticker,
+            network,
+            BitcoinColdWallet::XPubkey {
+                xpub: xpub.to_string(),
+                index: 0,
+                derivation,
+                origin,
+            },
+        ))
+    }
+
+    /// M-of-N P2WSH `sortedmulti` wallet from the cosigners' account level extended keys,
+    /// stored as its output descriptor. Keys may carry their `[fingerprint/path]` origin, which
+    /// is kept so hardware signers can find their key in PSBTs
+    pub fn new_multisig(
+        ticker: Ticker,
+        network: Network,
+        threshold: usize,
+        pubkeys: Vec<String>,
+    ) -> anyhow::Result<BitcoinWallet> {
+        let keys = pubkeys
+            .iter()
+            .map(|pubkey| {
+                let (origin, pubkey) = split_key_origin(pubkey.trim())?;
+                Ok(format!(
+                    "{}{}/0/*",
+                    origin.unwrap_or_default(),
+                    parse_slip132(pubkey, &network)?.0
+                ))
+            })
+            .collect::<anyhow::Result<Vec<String>>>()?;
+        if keys.len() < 2 {
+            return Err(anyhow!("Multisig wallets need at least 2 keys"));
+        }
+        BitcoinWallet::new_cold(
+            ticker,
+            network,
+            format!("wsh(sortedmulti({},{}))", threshold, keys.join(",")),
+            BitcoinColdWalletType::Descriptor,
+        )
     }
 
     pub fn pubkey(&self) -> String {
@@ -157,8 +428,8 @@ impl BitcoinWallet {
                     Ok(xpub) => xpub.to_pub().to_string(),
                     Err(_) => "Invalid BitcoinHotWallet".to_string(),
                 },
-            }
-            .to_string(),
+                BitcoinColdWallet::Descriptor { descriptor, .. } => descriptor.clone(),
+            },
         }
     }
 
@@ -184,134 +455,277 @@ impl BitcoinWallet {
             BitcoinWallet::Cold(_, _, _) => WalletType::Cold,
         }
     }
-}
 
-impl BitcoinWallet {
-    pub fn next_xpub(&mut self) -> anyhow::Result<ExtendedPubKey> {
+    /// Key derivation scheme, `None` for descriptor wallets
+    pub fn derivation(&self) -> Option<BitcoinDerivation> {
         match self {
-            BitcoinWallet::Hot(ticker, _, w) => match ticker {
-                Ticker::BTC | Ticker::BCH => {
-                    let xpub = ExtendedPubKey::decode(&w.xpub)?;
-                    let derivation_path = format!("m/1/0/{}", w.index);
-                    let secp = Secp256k1::new();
-                    let chxpub =
-                        xpub.derive_pub(&secp, &DerivationPath::from_str(&derivation_path)?)?;
-                    w.index += 1;
-                    Ok(chxpub)
-                }
-                _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
-            },
-            BitcoinWallet::Cold(ticker, _, w) => match ticker {
-                Ticker::BTC | Ticker::BCH => {
-                    let BitcoinColdWallet::XPubkey { xpub, index } = w;
-                    let xpub = ExtendedPubKey::from_str(xpub)?;
-                    let derivation_path = format!("m/1/0/{}", index);
-                    let secp = Secp256k1::new();
-                    let chxpub =
-                        xpub.derive_pub(&secp, &DerivationPath::from_str(&derivation_path)?)?;
-                    *index += 1;
-                    Ok(chxpub)
-                }
-                _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
-            },
+            BitcoinWallet::Hot(_, _, w) => Some(w.derivation.clone()),
+            BitcoinWallet::Cold(_, _, BitcoinColdWallet::XPubkey { derivation, .. }) => {
+                Some(derivation.clone())
+            }
+            BitcoinWallet::Cold(_, _, BitcoinColdWallet::Descriptor { .. }) => None,
         }
     }
 
-    pub fn next_p2pkh_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+    /// BIP32 account of a hot wallet's derivation
+    pub fn account(&self) -> Option<u32> {
         match self {
-            BitcoinWallet::Hot(ticker, network, _) => match ticker {
-                Ticker::BTC | Ticker::BCH => {
-                    let network = network.into();
-                    let next_xpub = self.next_xpub()?;
-                    Ok((
-                        next_xpub,
-                        Address::p2pkh(&next_xpub.to_pub(), network).to_string(),
-                    ))
-                }
-                _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
-            },
-            BitcoinWallet::Cold(ticker, network, _) => match ticker {
-                Ticker::BTC | Ticker::BCH => {
-                    let network = network.into();
-                    let next_xpub = self.next_xpub()?;
-                    Ok((
-                        next_xpub,
-                        Address::p2pkh(&next_xpub.to_pub(), network).to_string(),
-                    ))
-                }
-                _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
-            },
+            BitcoinWallet::Hot(_, _, w) => Some(w.account),
+            BitcoinWallet::Cold(_, _, _) => None,
         }
     }
 
-    pub fn next_p2wpkh_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+    /// BIP39 mnemonic and passphrase of a hot wallet
+    pub fn secret(&self) -> anyhow::Result<WalletSecret> {
         match self {
-            BitcoinWallet::Hot(ticker, network, _) => match ticker {
-                Ticker::BTC => {
-                    let network = network.into();
-                    let next_xpub = self.next_xpub()?;
-                    Ok((
-                        next_xpub,
-                        Address::p2wpkh(&next_xpub.to_pub(), network)?.to_string(),
-                    ))
-                }
-                _ => Err(anyhow!("p2wpkh not supported for BCH")),
-            },
-            BitcoinWallet::Cold(ticker, network, _) => match ticker {
-                Ticker::BTC => {
-                    let network = network.into();
-                    let next_xpub = self.next_xpub()?;
-                    Ok((
-                        next_xpub,
-                        Address::p2wpkh(&next_xpub.to_pub(), network)?.to_string(),
-                    ))
-                }
-                _ => Err(anyhow!("p2wpkh not supported for BCH")),
-            },
+            BitcoinWallet::Hot(_, _, w) => Ok(WalletSecret::Bip39 {
+                mnemonic: Mnemonic::from_entropy(&w.mnemonic)?.to_string(),
+                passphrase: w.password.clone(),
+            }),
+            BitcoinWallet::Cold(_, _, _) => Err(anyhow!("Cold wallets have no secret")),
         }
     }
 
-    pub fn next_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+    /// Parsed output descriptor of a descriptor wallet
+    pub fn output_descriptor(&self) -> anyhow::Result<Option<OutputDescriptor>> {
         match self {
-            BitcoinWallet::Hot(ticker, _, _) => match ticker {
-                Ticker::BTC => self.next_p2wpkh_addr(),
-                Ticker::BCH => self.next_p2pkh_addr(),
-                _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
-            },
-            BitcoinWallet::Cold(ticker, _, _) => match ticker {
-                Ticker::BTC => self.next_p2wpkh_addr(),
-                Ticker::BCH => self.next_p2pkh_addr(),
-                _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
+            BitcoinWallet::Cold(_, network, BitcoinColdWallet::Descriptor { descriptor, .. }) => {
+                Ok(Some(OutputDescriptor::parse(descriptor, network)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn address_type(&self) -> BitcoinAddressType {
+        match self.output_descriptor() {
+            Ok(Some(descriptor)) => match descriptor {
+                OutputDescriptor::Pkh(_) => BitcoinAddressType::P2PKH,
+                OutputDescriptor::ShWpkh(_) => BitcoinAddressType::P2SHWPKH,
+                OutputDescriptor::Wpkh(_) => BitcoinAddressType::P2WPKH,
+                OutputDescriptor::Tr(_) => BitcoinAddressType::P2TR,
+                OutputDescriptor::Sh(_) => BitcoinAddressType::P2SH,
+                OutputDescriptor::Wsh(_) => BitcoinAddressType::P2WSH,
+                OutputDescriptor::ShWsh(_) => BitcoinAddressType::P2SHWSH,
             },
+            _ => self
+                .derivation()
+                .unwrap_or_default()
+                .address_type(&self.ticker()),
         }
     }
+}
 
-    pub fn addr(&self) -> anyhow::Result<(ExtendedPubKey, String)> {
+fn p2pkh_cashaddr(xpub: &ExtendedPubKey, network: &Network) -> anyhow::Result<String> {
+    let pubkey_hash = xpub.to_pub().pubkey_hash();
+    cashaddr_encode(network, CASHADDR_P2PKH, &pubkey_hash[..])
+}
+
+impl BitcoinWallet {
+    /// Receive index the next address is derived at
+    pub fn index(&self) -> u64 {
         match self {
-            BitcoinWallet::Hot(ticker, network, w) => {
-                match (ticker, ExtendedPubKey::decode(&w.xpub)) {
-                    (Ticker::BTC, Ok(xpub)) => Ok((
-                        xpub,
-                        Address::p2wpkh(&xpub.to_pub(), network.into())?.to_string(),
-                    )),
-                    (Ticker::BCH, Ok(xpub)) => Ok((
-                        xpub,
-                        Address::p2pkh(&xpub.to_pub(), network.into()).to_string(),
-                    )),
-                    _ => Err(anyhow!("Invalid BitcoinHotWallet")),
-                }
+            BitcoinWallet::Hot(_, _, w) => w.index,
+            BitcoinWallet::Cold(_, _, BitcoinColdWallet::XPubkey { index, .. })
+            | BitcoinWallet::Cold(_, _, BitcoinColdWallet::Descriptor { index, .. }) => *index,
+        }
+    }
+
+    /// Moves the next receive index forward to `index`, never back. Returns whether it moved
+    pub fn advance_index(&mut self, index: u64) -> bool {
+        let current = self.index_mut();
+        if index > *current {
+            *current = index;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn index_mut(&mut self) -> &mut u64 {
+        match self {
+            BitcoinWallet::Hot(_, _, w) => &mut w.index,
+            BitcoinWallet::Cold(_, _, BitcoinColdWallet::XPubkey { index, .. })
+            | BitcoinWallet::Cold(_, _, BitcoinColdWallet::Descriptor { index, .. }) => index,
+        }
+    }
+
+    /// Receive key at `index`, for descriptor wallets the first key of the descriptor
+    pub fn xpub_at(&self, index: u64) -> anyhow::Result<ExtendedPubKey> {
+        let secp = Secp256k1::new();
+        match self {
+            BitcoinWallet::Hot(Ticker::BTC | Ticker::BCH, _, w) => {
+                let xpub = ExtendedPubKey::decode(&w.xpub)?;
+                Ok(xpub.derive_pub(&secp, &w.derivation.receive_path(index)?)?)
             }
-            BitcoinWallet::Cold(_, network, w) => match w {
-                BitcoinColdWallet::XPubkey { xpub, .. } => match ExtendedPubKey::from_str(xpub) {
-                    Ok(xpub) => Ok((
-                        xpub,
-                        Address::p2pkh(&xpub.to_pub(), network.into()).to_string(),
-                    )),
-                    Err(_) => Err(anyhow!("Invalid BitcoinColdWallet")),
+            BitcoinWallet::Cold(
+                Ticker::BTC | Ticker::BCH,
+                _,
+                BitcoinColdWallet::XPubkey {
+                    xpub, derivation, ..
                 },
-            },
+            ) => {
+                let xpub = ExtendedPubKey::from_str(xpub)?;
+                Ok(xpub.derive_pub(&secp, &derivation.receive_path(index)?)?)
+            }
+            // Multisig addresses need every key of the descriptor
+            BitcoinWallet::Cold(
+                Ticker::BTC,
+                network,
+                BitcoinColdWallet::Descriptor { descriptor, .. },
+            ) => OutputDescriptor::parse(descriptor, network)?.keys()[0].derive(index),
+            w => Err(anyhow!("Ticker {:?} not supported", w.ticker())),
+        }
+    }
+
+    /// Receive address at `index`
+    pub fn addr_at(&self, index: u64) -> anyhow::Result<String> {
+        match self.output_descriptor()? {
+            Some(descriptor) => descriptor.address(index, &self.network()),
+            None => self.encode_addr(&self.xpub_at(index)?, &self.address_type()),
         }
     }
+
+    pub fn next_xpub(&mut self) -> anyhow::Result<ExtendedPubKey> {
+        let chxpub = self.xpub_at(self.index())?;
+        *self.index_mut() += 1;
+        Ok(chxpub)
+    }
+
+    fn encode_addr(
+        &self,
+        xpub: &ExtendedPubKey,
+        address_type: &BitcoinAddressType,
+    ) -> anyhow::Result<String> {
+        let network = self.network();
+        match (self.ticker(), address_type) {
+            (Ticker::BCH, BitcoinAddressType::P2PKH) => p2pkh_cashaddr(xpub, &network),
+            (Ticker::BTC, BitcoinAddressType::P2PKH) => {
+                Ok(Address::p2pkh(&xpub.to_pub(), network.into()).to_string())
+            }
+            (Ticker::BTC, BitcoinAddressType::P2SHWPKH) => {
+                Ok(Address::p2shwpkh(&xpub.to_pub(), network.into())?.to_string())
+            }
+            (Ticker::BTC, BitcoinAddressType::P2WPKH) => {
+                Ok(Address::p2wpkh(&xpub.to_pub(), network.into())?.to_string())
+            }
+            (Ticker::BTC, BitcoinAddressType::P2TR) => {
+                let secp = Secp256k1::verification_only();
+                let internal_key = XOnlyPublicKey::from(xpub.public_key);
+                Ok(Address::p2tr(&secp, internal_key, None, network.into()).to_string())
+            }
+            (ticker, address_type) => {
+                Err(anyhow!("{:?} not supported for {:?}", address_type, ticker))
+            }
+        }
+    }
+
+    fn next_typed_addr(
+        &mut self,
+        address_type: BitcoinAddressType,
+    ) -> anyhow::Result<(ExtendedPubKey, String)> {
+        if self.output_descriptor()?.is_some() {
+            if address_type != self.address_type() {
+                return Err(anyhow!(
+                    "{:?} not supported by the wallet descriptor",
+                    address_type
+                ));
+            }
+            let addr = self.addr_at(self.index())?;
+            return Ok((self.next_xpub()?, addr));
+        }
+        match (self.ticker(), &address_type) {
+            (Ticker::BTC, _) | (Ticker::BCH, BitcoinAddressType::P2PKH) => {
+                let next_xpub = self.next_xpub()?;
+                Ok((next_xpub, self.encode_addr(&next_xpub, &address_type)?))
+            }
+            (ticker, address_type) => {
+                Err(anyhow!("{:?} not supported for {:?}", address_type, ticker))
+            }
+        }
+    }
+
+    pub fn next_p2pkh_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        self.next_typed_addr(BitcoinAddressType::P2PKH)
+    }
+
+    pub fn next_p2shwpkh_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        self.next_typed_addr(BitcoinAddressType::P2SHWPKH)
+    }
+
+    pub fn next_p2wpkh_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        self.next_typed_addr(BitcoinAddressType::P2WPKH)
+    }
+
+    /// Key path only taproot output, the internal key is tweaked with no script tree. Only
+    /// BIP86 (and `tr` descriptor) wallets have signers that look for taproot outputs
+    pub fn next_p2tr_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        if self.address_type() != BitcoinAddressType::P2TR {
+            return Err(anyhow!(
+                "P2TR addresses need a BIP86 wallet not {:?}",
+                self.address_type()
+            ));
+        }
+        self.next_typed_addr(BitcoinAddressType::P2TR)
+    }
+
+    pub fn next_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        let address_type = self.address_type();
+        self.next_typed_addr(address_type)
+    }
+
+    /// Next address with the pubkey an invoice records for it, the derived xpub or, for
+    /// descriptor wallets, the descriptor of that single output
+    pub fn next_invoice_addr(&mut self) -> anyhow::Result<(String, String)> {
+        match self.output_descriptor()? {
+            Some(descriptor) => {
+                let output_descriptor = descriptor.at_index(self.index())?;
+                let (_, addr) = self.next_addr()?;
+                Ok((output_descriptor, addr))
+            }
+            None => {
+                let (xpub, addr) = self.next_addr()?;
+                Ok((xpub.to_string(), addr))
+            }
+        }
+    }
+
+    /// Output descriptor for an address derived by this wallet, `xpub` is the invoice pubkey
+    /// returned by `next_invoice_addr`
+    pub fn descriptor(&self, xpub: &str) -> anyhow::Result<String> {
+        if self.output_descriptor()?.is_some() {
+            // Invoices of descriptor wallets already record the exact descriptor
+            strip_descriptor_checksum(xpub)?;
+            return Ok(xpub.to_string());
+        }
+        let xpub = ExtendedPubKey::from_str(xpub)?;
+        let pubkey = xpub.to_pub();
+        match self.address_type() {
+            BitcoinAddressType::P2PKH => Ok(format!("pkh({})", pubkey)),
+            BitcoinAddressType::P2SHWPKH => Ok(format!("sh(wpkh({}))", pubkey)),
+            BitcoinAddressType::P2WPKH => Ok(format!("wpkh({})", pubkey)),
+            BitcoinAddressType::P2TR => {
+                Ok(format!("tr({})", XOnlyPublicKey::from(xpub.public_key)))
+            }
+            address_type => Err(anyhow!("{:?} requires a descriptor wallet", address_type)),
+        }
+    }
+
+    pub fn addr(&self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        let xpub = match self {
+            BitcoinWallet::Hot(_, _, w) => {
+                ExtendedPubKey::decode(&w.xpub).map_err(|_| anyhow!("Invalid BitcoinHotWallet"))?
+            }
+            BitcoinWallet::Cold(_, _, BitcoinColdWallet::XPubkey { xpub, .. }) => {
+                ExtendedPubKey::from_str(xpub).map_err(|_| anyhow!("Invalid BitcoinColdWallet"))?
+            }
+            BitcoinWallet::Cold(_, network, BitcoinColdWallet::Descriptor { descriptor, .. }) => {
+                let descriptor = OutputDescriptor::parse(descriptor, network)?;
+                let xpub = descriptor.keys()[0].derive(0)?;
+                return Ok((xpub, descriptor.address(0, network)?));
+            }
+        };
+        Ok((xpub, self.encode_addr(&xpub, &self.address_type())?))
+    }
 }
 
 #[test]
@@ -328,6 +742,8 @@ fn test_hot_wallet() {
         password,
         xpub: ExtendedPubKey::from_priv(&secp, &key).encode().to_vec(),
         index: 0,
+        derivation: BitcoinDerivation::Legacy,
+        account: 0,
     };
     assert_eq!(
         w.to_string(),
@@ -348,4 +764,444 @@ fn test_hot_wallet() {
         mainnet_w.next_addr().expect("Invalid Addr").1,
         "bc1qufnwcpajzuzg0qp0lhj5uawdmxpqlw0ersa68p"
     );
+
+    assert!(mainnet_w.advance_index(5));
+    assert!(!mainnet_w.advance_index(3));
+    assert_eq!(mainnet_w.index(), 5);
+}
+
+#[test]
+fn test_bch_cashaddr() {
+    use crate::to_cashaddr;
+
+    let password = "moonramp".to_string();
+    let entropy = [7u8; 32];
+    let mnemonic = Mnemonic::from_entropy(&entropy).expect("Invalid Mnemonic");
+    let seed = mnemonic.to_seed(password.clone());
+    let key = ExtendedPrivKey::new_master(Network::Mainnet.into(), &seed)
+        .expect("Invalid ExtendedPrivKey");
+    let secp = Secp256k1::new();
+    let xpub = ExtendedPubKey::from_priv(&secp, &key);
+    let w = BitcoinHotWallet {
+        mnemonic: mnemonic.to_entropy(),
+        password,
+        xpub: xpub.encode().to_vec(),
+        index: 0,
+        derivation: BitcoinDerivation::Legacy,
+        account: 0,
+    };
+
+    let mut mainnet_w = BitcoinWallet::Hot(Ticker::BCH, Network::Mainnet, w);
+    let (next_xpub, addr) = mainnet_w.next_addr().expect("Invalid Addr");
+    assert!(addr.starts_with("bitcoincash:q"));
+    assert_eq!(
+        to_cashaddr(
+            &Network::Mainnet,
+            &Address::p2pkh(&next_xpub.to_pub(), Network::Mainnet.into()).to_string()
+        )
+        .expect("Invalid Addr"),
+        addr
+    );
+
+    let mut cold_w = BitcoinWallet::new_cold(
+        Ticker::BCH,
+        Network::Mainnet,
+        xpub.to_string(),
+        BitcoinColdWalletType::XPubkey,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(cold_w.derivation(), Some(BitcoinDerivation::Bip44));
+    let receive_xpub = xpub
+        .derive_pub(
+            &secp,
+            &DerivationPath::from_str("m/0/0").expect("Invalid DerivationPath"),
+        )
+        .expect("Invalid ExtendedPubKey");
+    assert_eq!(
+        cold_w.next_addr().expect("Invalid Addr").1,
+        p2pkh_cashaddr(&receive_xpub, &Network::Mainnet).expect("Invalid Addr")
+    );
+}
+
+#[test]
+fn test_derivation_schemes() {
+    // BIP44/84/86 test vectors for "abandon abandon ... about" with no passphrase
+    let mut bip44_w = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj".to_string(),
+        BitcoinColdWalletType::P2PKH,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(bip44_w.derivation(), Some(BitcoinDerivation::Bip44));
+    assert_eq!(
+        bip44_w.next_addr().expect("Invalid Addr").1,
+        "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"
+    );
+    assert_eq!(
+        bip44_w.next_addr().expect("Invalid Addr").1,
+        "1Ak8PffB2meyfYnbXZR9EGfLfFZVpzJvQP"
+    );
+
+    let mut bip84_w = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs".to_string(),
+        BitcoinColdWalletType::XPubkey,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(bip84_w.derivation(), Some(BitcoinDerivation::Bip84));
+    assert_eq!(
+        bip84_w.next_addr().expect("Invalid Addr").1,
+        "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    );
+
+    let (bip86_xpub, _) = parse_slip132(
+        "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ",
+        &Network::Mainnet,
+    )
+    .expect("Invalid xpub");
+    let mut bip86_w = BitcoinWallet::Hot(
+        Ticker::BTC,
+        Network::Mainnet,
+        BitcoinHotWallet {
+            mnemonic: vec![0u8; 16],
+            password: "".to_string(),
+            xpub: bip86_xpub.encode().to_vec(),
+            index: 0,
+            derivation: BitcoinDerivation::Bip86,
+            account: 0,
+        },
+    );
+    let (next_xpub, addr) = bip86_w.next_p2tr_addr().expect("Invalid Addr");
+    assert_eq!(
+        addr,
+        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+    );
+    assert_eq!(
+        bip86_w
+            .descriptor(&next_xpub.to_string())
+            .expect("Invalid Descriptor"),
+        "tr(cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115)"
+    );
+
+    let mut p2tr_cold_w = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        bip86_xpub.to_string(),
+        BitcoinColdWalletType::P2TR,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(p2tr_cold_w.derivation(), Some(BitcoinDerivation::Bip86));
+    assert_eq!(p2tr_cold_w.next_addr().expect("Invalid Addr").1, addr);
+    let bip44_index = bip44_w.index();
+    assert!(bip44_w.next_p2tr_addr().is_err());
+    assert!(bip84_w.next_p2tr_addr().is_err());
+    assert_eq!(bip44_w.index(), bip44_index);
+
+    assert!(BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs".to_string(),
+        BitcoinColdWalletType::P2PKH,
+    )
+    .is_err());
+    assert!(BitcoinWallet::new_cold(
+        Ticker::BCH,
+        Network::Mainnet,
+        "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs".to_string(),
+        BitcoinColdWalletType::XPubkey,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_plain_xpub_cold_wallet() {
+    // BIP84 account xpub of "abandon abandon ... about" without its zpub prefix
+    let (xpub, _) = parse_slip132(
+        "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs",
+        &Network::Mainnet,
+    )
+    .expect("Invalid zpub");
+    assert!(xpub.to_string().starts_with("xpub"));
+
+    assert!(BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        xpub.to_string(),
+        BitcoinColdWalletType::XPubkey,
+    )
+    .is_err());
+
+    let mut w = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        xpub.to_string(),
+        BitcoinColdWalletType::P2WPKH,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(w.derivation(), Some(BitcoinDerivation::Bip84));
+    // Standard `m/0/i` receive branch, the same addresses as the zpub
+    assert_eq!(
+        w.next_addr().expect("Invalid Addr").1,
+        "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    );
+}
+
+#[test]
+fn test_descriptor_wallet() {
+    let descriptor = "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)";
+    let mut w = BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        descriptor.to_string(),
+        BitcoinColdWalletType::Descriptor,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(w.derivation(), None);
+    assert_eq!(w.address_type(), BitcoinAddressType::P2WPKH);
+    assert!(w.pubkey().starts_with(&format!("{}#", descriptor)));
+
+    let (pubkey, addr) = w.next_invoice_addr().expect("Invalid Addr");
+    assert_eq!(addr, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    assert!(pubkey.starts_with(&descriptor.replace("/0/*)", "/0/0)#")));
+    assert_eq!(w.descriptor(&pubkey).expect("Invalid Descriptor"), pubkey);
+    assert!(w.next_p2pkh_addr().is_err());
+    assert_ne!(w.next_addr().expect("Invalid Addr").1, addr);
+
+    assert!(BitcoinWallet::new_cold(
+        Ticker::BCH,
+        Network::Mainnet,
+        descriptor.to_string(),
+        BitcoinColdWalletType::Descriptor,
+    )
+    .is_err());
+    assert!(BitcoinWallet::new_cold(
+        Ticker::BTC,
+        Network::Mainnet,
+        format!("{}#00000000", descriptor),
+        BitcoinColdWalletType::Descriptor,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_multisig_wallet() {
+    let pubkeys = vec![
+        "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V".to_string(),
+        "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj".to_string(),
+        "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ".to_string(),
+    ];
+    let mut w = BitcoinWallet::new_multisig(Ticker::BTC, Network::Mainnet, 2, pubkeys.clone())
+        .expect("Invalid BitcoinWallet");
+    assert_eq!(w.address_type(), BitcoinAddressType::P2WSH);
+
+    let mut reordered = pubkeys.clone();
+    reordered.reverse();
+    let reordered_w = BitcoinWallet::new_multisig(Ticker::BTC, Network::Mainnet, 2, reordered)
+        .expect("Invalid BitcoinWallet");
+
+    let descriptor = w
+        .output_descriptor()
+        .expect("Invalid Descriptor")
+        .expect("Expected a descriptor");
+    let reordered_descriptor = reordered_w
+        .output_descriptor()
+        .expect("Invalid Descriptor")
+        .expect("Expected a descriptor");
+    for index in 0..2 {
+        let (_, addr) = w.next_invoice_addr().expect("Invalid Addr");
+        assert!(addr.starts_with("bc1q"));
+        assert_eq!(
+            reordered_descriptor
+                .address(index, &Network::Mainnet)
+                .expect("Invalid Addr"),
+            addr
+        );
+
+        let witness_script = descriptor
+            .witness_script(index)
+            .expect("Invalid Script")
+            .expect("Expected a witness script");
+        assert_eq!(
+            Address::p2wsh(&witness_script, Network::Mainnet.into()).to_string(),
+            addr
+        );
+        assert!(witness_script.as_bytes().starts_with(&[0x52]));
+        assert!(witness_script.as_bytes().ends_with(&[0x53, 0xae]));
+        assert_eq!(
+            descriptor.redeem_script(index).expect("Invalid Script"),
+            None
+        );
+    }
+
+    // Key origins survive the import so signers can match their fingerprint
+    let with_origins = vec![
+        format!("[73c5da0a/48'/0'/0'/2']{}", pubkeys[0]),
+        format!("[deadbeef/48h/0h/0h/2h]{}", pubkeys[1]),
+        pubkeys[2].clone(),
+    ];
+    let origin_w = BitcoinWallet::new_multisig(Ticker::BTC, Network::Mainnet, 2, with_origins)
+        .expect("Invalid BitcoinWallet");
+    let origin_descriptor = origin_w
+        .output_descriptor()
+        .expect("Invalid Descriptor")
+        .expect("Expected a descriptor");
+    assert!(origin_w
+        .pubkey()
+        .contains(&format!("[73c5da0a/48'/0'/0'/2']{}/0/*", pubkeys[0])));
+    let (fingerprint, path) = origin_descriptor.keys()[1]
+        .key_source(7)
+        .expect("Invalid KeySource");
+    assert_eq!(fingerprint.to_string(), "deadbeef");
+    assert_eq!(path.to_string(), "m/48'/0'/0'/2'/0/7");
+    assert_eq!(
+        origin_descriptor
+            .address(0, &Network::Mainnet)
+            .expect("Invalid Addr"),
+        descriptor
+            .address(0, &Network::Mainnet)
+            .expect("Invalid Addr")
+    );
+    assert!(BitcoinWallet::new_multisig(
+        Ticker::BTC,
+        Network::Mainnet,
+        2,
+        vec![format!("[73c5da0a/48'{}", pubkeys[0]), pubkeys[1].clone()],
+    )
+    .is_err());
+
+    assert!(
+        BitcoinWallet::new_multisig(Ticker::BTC, Network::Mainnet, 4, pubkeys.clone()).is_err()
+    );
+    assert!(
+        BitcoinWallet::new_multisig(Ticker::BTC, Network::Mainnet, 1, pubkeys[..1].to_vec())
+            .is_err()
+    );
+    assert!(BitcoinWallet::new_multisig(Ticker::BCH, Network::Mainnet, 2, pubkeys).is_err());
+}
+
+#[test]
+fn test_hot_wallet_account() {
+    let w = BitcoinWallet::new_hot_with_derivation(
+        Ticker::BTC,
+        Network::Mainnet,
+        BitcoinDerivation::Bip49,
+        3,
+    )
+    .expect("Invalid BitcoinWallet");
+    let (account_xpub, addr) = w.addr().expect("Invalid Addr");
+    assert!(addr.starts_with('3'));
+
+    if let BitcoinWallet::Hot(_, _, hot) = &w {
+        let mnemonic = Mnemonic::from_entropy(&hot.mnemonic).expect("Invalid Mnemonic");
+        let seed = mnemonic.to_seed(hot.password.clone());
+        let key = ExtendedPrivKey::new_master(Network::Mainnet.into(), &seed)
+            .expect("Invalid ExtendedPrivKey");
+        let secp = Secp256k1::new();
+        let path = DerivationPath::from_str("m/49'/0'/3'").expect("Invalid DerivationPath");
+        let expected = ExtendedPubKey::from_priv(
+            &secp,
+            &key.derive_priv(&secp, &path)
+                .expect("Invalid ExtendedPrivKey"),
+        );
+        assert_eq!(account_xpub, expected);
+    } else {
+        panic!("Expected BitcoinWallet::Hot");
+    }
+
+    assert!(BitcoinWallet::new_hot_with_derivation(
+        Ticker::BCH,
+        Network::Mainnet,
+        BitcoinDerivation::Bip84,
+        0
+    )
+    .is_err());
+}
+
+#[test]
+fn test_hot_wallet_import() {
+    let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let w = BitcoinWallet::import_hot(
+        Ticker::BTC,
+        Network::Mainnet,
+        mnemonic,
+        "".to_string(),
+        None,
+        0,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(w.derivation(), Some(BitcoinDerivation::Bip84));
+    assert_eq!(
+        w.addr_at(0).expect("Invalid Addr"),
+        "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    );
+    assert_eq!(
+        w.secret().expect("Invalid WalletSecret"),
+        WalletSecret::Bip39 {
+            mnemonic: mnemonic.to_string(),
+            passphrase: "".to_string(),
+        }
+    );
+
+    let w = BitcoinWallet::new_hot(Ticker::BCH, Network::Mainnet).expect("Invalid BitcoinWallet");
+    let (mnemonic, passphrase) = match w.secret().expect("Invalid WalletSecret") {
+        WalletSecret::Bip39 {
+            mnemonic,
+            passphrase,
+        } => (mnemonic, passphrase),
+        secret => panic!("Expected WalletSecret::Bip39 found {:?}", secret),
+    };
+    assert_eq!(mnemonic.split(' ').count(), 24);
+    let restored = BitcoinWallet::import_hot(
+        Ticker::BCH,
+        Network::Mainnet,
+        &mnemonic.to_uppercase(),
+        passphrase,
+        w.derivation(),
+        0,
+    )
+    .expect("Invalid BitcoinWallet");
+    assert_eq!(restored, w);
+
+    assert!(BitcoinWallet::import_hot(
+        Ticker::BTC,
+        Network::Mainnet,
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+        "".to_string(),
+        None,
+        0,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_signet_hot_wallet() {
+    let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let signet_w = BitcoinWallet::import_hot(
+        Ticker::BTC,
+        Network::Signet,
+        mnemonic,
+        "".to_string(),
+        None,
+        0,
+    )
+    .expect("Invalid BitcoinWallet");
+    let testnet_w = BitcoinWallet::import_hot(
+        Ticker::BTC,
+        Network::Testnet,
+        mnemonic,
+        "".to_string(),
+        None,
+        0,
+    )
+    .expect("Invalid BitcoinWallet");
+    let addr = signet_w.addr_at(0).expect("Invalid Addr");
+    assert!(addr.starts_with("tb1q"));
+    assert_eq!(addr, testnet_w.addr_at(0).expect("Invalid Addr"));
+
+    assert_eq!(Network::Stagenet.for_ticker(&Ticker::BTC), Network::Signet);
+    assert_eq!(Network::Signet.for_ticker(&Ticker::XMR), Network::Stagenet);
+    assert_eq!(Network::Signet.for_ticker(&Ticker::BCH), Network::Testnet);
+    assert!(BitcoinWallet::new_hot(Ticker::BCH, Network::Signet).is_err());
 }
@@ -0,0 +1,209 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bitcoin::util::address::{Address, Payload};
+
+use moonramp_core::{anyhow, bitcoin};
+
+use crate::Network;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// CashAddr version byte for a 160 bit P2PKH hash
+pub const CASHADDR_P2PKH: u8 = 0x00;
+/// CashAddr version byte for a 160 bit P2SH hash
+pub const CASHADDR_P2SH: u8 = 0x08;
+
+pub fn cashaddr_prefix(network: &Network) -> &'static str {
+    match network {
+        Network::Mainnet => "bitcoincash",
+        // BCH has no signet, its test networks all share `bchtest`
+        Network::Testnet | Network::Signet | Network::Stagenet => "bchtest",
+        Network::Regtest => "bchreg",
+    }
+}
+
+fn polymod(values: &[u8]) -> u64 {
+    let mut c = 1u64;
+    for v in values {
+        let c0 = (c >> 35) as u8;
+        c = ((c & 0x07_ffff_ffff) << 5) ^ (*v as u64);
+        if c0 & 0x01 != 0 {
+            c ^= 0x98_f2bc_8e61;
+        }
+        if c0 & 0x02 != 0 {
+            c ^= 0x79_b76d_99e2;
+        }
+        if c0 & 0x04 != 0 {
+            c ^= 0xf3_3e5f_b3c4;
+        }
+        if c0 & 0x08 != 0 {
+            c ^= 0xae_2eab_e2a8;
+        }
+        if c0 & 0x10 != 0 {
+            c ^= 0x1e_4f43_e470;
+        }
+    }
+    c ^ 1
+}
+
+fn expand_prefix(prefix: &str) -> Vec<u8> {
+    prefix
+        .bytes()
+        .map(|b| b & 0x1f)
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> anyhow::Result<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1u32 << to) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for v in data {
+        acc = (acc << from) | *v as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return Err(anyhow!("Invalid CashAddr padding"));
+    }
+    Ok(out)
+}
+
+/// Encodes a 160 bit hash as a CashAddr with its network prefix
+pub fn cashaddr_encode(network: &Network, version: u8, hash: &[u8]) -> anyhow::Result<String> {
+    if hash.len() != 20 {
+        return Err(anyhow!("CashAddr hash must be 20 bytes not {}", hash.len()));
+    }
+    let prefix = cashaddr_prefix(network);
+    let mut payload = vec![version];
+    payload.extend_from_slice(hash);
+    let data = convert_bits(&payload, 8, 5, true)?;
+
+    let mut checksum_input = expand_prefix(prefix);
+    checksum_input.extend_from_slice(&data);
+    checksum_input.extend_from_slice(&[0u8; 8]);
+    let checksum = polymod(&checksum_input);
+
+    let mut addr = format!("{}:", prefix);
+    for d in data {
+        addr.push(CHARSET[d as usize] as char);
+    }
+    for i in (0..8).rev() {
+        addr.push(CHARSET[((checksum >> (5 * i)) & 0x1f) as usize] as char);
+    }
+    Ok(addr)
+}
+
+/// Decodes a CashAddr, with or without its prefix, into its version byte and hash
+pub fn cashaddr_decode(network: &Network, addr: &str) -> anyhow::Result<(u8, Vec<u8>)> {
+    if addr.chars().any(|c| c.is_ascii_lowercase()) && addr.chars().any(|c| c.is_ascii_uppercase())
+    {
+        return Err(anyhow!("CashAddr must not be mixed case"));
+    }
+    let addr = addr.to_lowercase();
+    let prefix = cashaddr_prefix(network);
+    let payload = match addr.split_once(':') {
+        Some((p, payload)) if p == prefix => payload,
+        Some((p, _)) => {
+            return Err(anyhow!(
+                "CashAddr prefix {} is not valid for {:?}",
+                p,
+                network
+            ))
+        }
+        None => addr.as_str(),
+    };
+
+    let data = payload
+        .bytes()
+        .map(|b| {
+            CHARSET
+                .iter()
+                .position(|c| *c == b)
+                .map(|d| d as u8)
+                .ok_or_else(|| anyhow!("Invalid CashAddr character {}", b as char))
+        })
+        .collect::<anyhow::Result<Vec<u8>>>()?;
+    if data.len() < 8 {
+        return Err(anyhow!("CashAddr too short"));
+    }
+
+    let mut checksum_input = expand_prefix(prefix);
+    checksum_input.extend_from_slice(&data);
+    if polymod(&checksum_input) != 0 {
+        return Err(anyhow!("Invalid CashAddr checksum"));
+    }
+
+    let payload = convert_bits(&data[..data.len() - 8], 5, 8, false)?;
+    match payload.split_first() {
+        Some((version @ (&CASHADDR_P2PKH | &CASHADDR_P2SH), hash)) if hash.len() == 20 => {
+            Ok((*version, hash.to_vec()))
+        }
+        _ => Err(anyhow!("Unsupported CashAddr payload")),
+    }
+}
+
+/// Accepts a CashAddr or legacy base58 address and returns the prefixed CashAddr
+pub fn to_cashaddr(network: &Network, addr: &str) -> anyhow::Result<String> {
+    if let Ok((version, hash)) = cashaddr_decode(network, addr) {
+        return cashaddr_encode(network, version, &hash);
+    }
+    let legacy = Address::from_str(addr).map_err(|_| anyhow!("Invalid BCH address {}", addr))?;
+    let legacy_network: bitcoin::Network = network.clone().into();
+    let network_matches = match legacy_network {
+        bitcoin::Network::Bitcoin => legacy.network == bitcoin::Network::Bitcoin,
+        _ => legacy.network != bitcoin::Network::Bitcoin,
+    };
+    if !network_matches {
+        return Err(anyhow!("Address {} is not valid for {:?}", addr, network));
+    }
+    match legacy.payload {
+        Payload::PubkeyHash(hash) => cashaddr_encode(network, CASHADDR_P2PKH, &hash[..]),
+        Payload::ScriptHash(hash) => cashaddr_encode(network, CASHADDR_P2SH, &hash[..]),
+        _ => Err(anyhow!("Address {} has no CashAddr form", addr)),
+    }
+}
+
+#[test]
+fn test_cashaddr() {
+    let p2pkh = "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a";
+    let p2sh = "bitcoincash:ppm2qsznhks23z7629mms6s4cwef74vcwvn0h829pq";
+
+    assert_eq!(
+        to_cashaddr(&Network::Mainnet, "1BpEi6DfDAUFd7GtittLSdBeYJvcoaVggu").expect("Invalid Addr"),
+        p2pkh
+    );
+    assert_eq!(
+        to_cashaddr(&Network::Mainnet, "3CWFddi6m4ndiGyKqzYvsFYagqDLPVMTzC").expect("Invalid Addr"),
+        p2sh
+    );
+    assert_eq!(
+        to_cashaddr(&Network::Mainnet, &p2pkh.to_uppercase()).expect("Invalid Addr"),
+        p2pkh
+    );
+    assert_eq!(
+        to_cashaddr(
+            &Network::Mainnet,
+            "qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a"
+        )
+        .expect("Invalid Addr"),
+        p2pkh
+    );
+
+    assert!(cashaddr_decode(
+        &Network::Mainnet,
+        "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6b"
+    )
+    .is_err());
+    assert!(cashaddr_decode(&Network::Testnet, p2pkh).is_err());
+    assert!(to_cashaddr(&Network::Testnet, "1BpEi6DfDAUFd7GtittLSdBeYJvcoaVggu").is_err());
+}
@@ -0,0 +1,592 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use bitcoin::{
+    blockdata::{opcodes, script::Builder},
+    hashes::hex::FromHex,
+    secp256k1::{Secp256k1, XOnlyPublicKey},
+    util::{
+        address::Address,
+        bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint, KeySource},
+    },
+    PublicKey, Script,
+};
+
+use moonramp_core::{anyhow, bitcoin};
+
+use crate::Network;
+
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x07_ffff_ffff) << 5) ^ val;
+    if c0 & 0x01 != 0 {
+        c ^= 0xf5_dee5_1989;
+    }
+    if c0 & 0x02 != 0 {
+        c ^= 0xa9_fdca_3312;
+    }
+    if c0 & 0x04 != 0 {
+        c ^= 0x1b_ab10_e32d;
+    }
+    if c0 & 0x08 != 0 {
+        c ^= 0x37_06b1_677a;
+    }
+    if c0 & 0x10 != 0 {
+        c ^= 0x64_4d62_6ffd;
+    }
+    c
+}
+
+/// BIP380 checksum of a descriptor without its `#checksum` suffix
+pub fn descriptor_checksum(desc: &str) -> anyhow::Result<String> {
+    let mut c = 1u64;
+    let mut cls = 0u64;
+    let mut cls_count = 0;
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| anyhow!("Invalid descriptor character {}", ch))?
+            as u64;
+        c = polymod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        cls_count += 1;
+        if cls_count == 3 {
+            c = polymod(c, cls);
+            cls = 0;
+            cls_count = 0;
+        }
+    }
+    if cls_count > 0 {
+        c = polymod(c, cls);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+/// Splits off and verifies the `#checksum` suffix of a descriptor
+pub fn strip_descriptor_checksum(desc: &str) -> anyhow::Result<&str> {
+    match desc.split_once('#') {
+        Some((body, checksum)) if descriptor_checksum(body)? == checksum => Ok(body),
+        Some((_, checksum)) => Err(anyhow!("Invalid descriptor checksum {}", checksum)),
+        None => Err(anyhow!("Descriptor {} has no checksum", desc)),
+    }
+}
+
+/// Splits the optional `[fingerprint/path]` origin off the front of a key
+pub fn split_key_origin(s: &str) -> anyhow::Result<(Option<String>, &str)> {
+    match s.strip_prefix('[') {
+        Some(rest) => {
+            let (origin, key) = rest
+                .split_once(']')
+                .ok_or_else(|| anyhow!("Unterminated key origin in {}", s))?;
+            let origin = format!("[{}]", origin);
+            parse_key_origin(&origin)?;
+            Ok((Some(origin), key))
+        }
+        None => Ok((None, s)),
+    }
+}
+
+/// Master fingerprint and path of a `[fingerprint/path]` key origin
+pub fn parse_key_origin(origin: &str) -> anyhow::Result<KeySource> {
+    let inner = origin.trim_start_matches('[').trim_end_matches(']');
+    let mut steps = inner.split('/');
+    let fingerprint = steps.next().unwrap_or_default();
+    if fingerprint.len() != 8 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid key origin fingerprint {}", fingerprint));
+    }
+    let path = steps
+        .map(|step| {
+            ChildNumber::from_str(&step.replace('h', "'"))
+                .map_err(|_| anyhow!("Invalid key origin path {}", inner))
+        })
+        .collect::<anyhow::Result<Vec<ChildNumber>>>()?;
+    Ok((
+        Fingerprint::from_hex(fingerprint)?,
+        DerivationPath::from(path),
+    ))
+}
+
+/// An extended key expression ending in a `/*` receive range, `[fingerprint/path]xpub/0/*`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DescriptorKey {
+    origin: Option<String>,
+    xpub: ExtendedPubKey,
+    path: Vec<ChildNumber>,
+}
+
+impl DescriptorKey {
+    fn parse(s: &str, network: &Network) -> anyhow::Result<DescriptorKey> {
+        let (origin, key) = split_key_origin(s)?;
+
+        let mut steps = key.split('/');
+        let xpub = ExtendedPubKey::from_str(steps.next().unwrap_or_default())
+            .map_err(|_| anyhow!("Descriptor keys must be xpub or tpub, found {}", key))?;
+        if (xpub.network == bitcoin::Network::Bitcoin) != (*network == Network::Mainnet) {
+            return Err(anyhow!("Descriptor key is not valid for {:?}", network));
+        }
+        let steps: Vec<&str> = steps.collect();
+        match steps.split_last() {
+            Some((&"*", path)) => {
+                let path = path
+                    .iter()
+                    .map(|step| match ChildNumber::from_str(step) {
+                        Ok(child) if child.is_normal() => Ok(child),
+                        _ => Err(anyhow!("Invalid unhardened derivation step {}", step)),
+                    })
+                    .collect::<anyhow::Result<Vec<ChildNumber>>>()?;
+                Ok(DescriptorKey { origin, xpub, path })
+            }
+            _ => Err(anyhow!(
+                "Descriptor key {} must end in an unhardened /*",
+                key
+            )),
+        }
+    }
+
+    /// Extended key at `index` of the receive range
+    pub fn derive(&self, index: u64) -> anyhow::Result<ExtendedPubKey> {
+        let index = u32::try_from(index).map_err(|_| anyhow!("Index {} out of range", index))?;
+        let mut path = self.path.clone();
+        path.push(ChildNumber::from_normal_idx(index)?);
+        let secp = Secp256k1::verification_only();
+        Ok(self.xpub.derive_pub(&secp, &DerivationPath::from(path))?)
+    }
+
+    pub fn xpub(&self) -> &ExtendedPubKey {
+        &self.xpub
+    }
+
+    /// Master fingerprint and path of the xpub, the xpub itself when the key has no origin
+    pub fn origin(&self) -> anyhow::Result<KeySource> {
+        match &self.origin {
+            Some(origin) => parse_key_origin(origin),
+            None => Ok((self.xpub.fingerprint(), DerivationPath::master())),
+        }
+    }
+
+    /// Key origin of the key at `index`, what signers derive it from
+    pub fn key_source(&self, index: u64) -> anyhow::Result<KeySource> {
+        let index = u32::try_from(index).map_err(|_| anyhow!("Index {} out of range", index))?;
+        let (fingerprint, path) = self.origin()?;
+        let path = path
+            .extend(&self.path)
+            .child(ChildNumber::from_normal_idx(index)?);
+        Ok((fingerprint, path))
+    }
+
+    fn fmt_with_tail(&self, f: &mut fmt::Formatter, tail: &str) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{}", origin)?;
+        }
+        write!(f, "{}", self.xpub)?;
+        for step in &self.path {
+            write!(f, "/{}", step)?;
+        }
+        write!(f, "/{}", tail)
+    }
+}
+
+impl fmt::Display for DescriptorKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_tail(f, "*")
+    }
+}
+
+/// Keys of a bare P2SH `multi`, 16 compressed keys overflow the 520 byte redeem script limit
+const MAX_P2SH_MULTISIG_KEYS: usize = 15;
+/// Keys of a segwit `multi`, `OP_16` is the largest key count the script builder pushes
+const MAX_MULTISIG_KEYS: usize = 16;
+
+/// `multi(k,...)` or `sortedmulti(k,...)`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DescriptorMulti {
+    pub threshold: usize,
+    pub keys: Vec<DescriptorKey>,
+    pub sorted: bool,
+}
+
+impl DescriptorMulti {
+    fn parse(s: &str, network: &Network, max_keys: usize) -> anyhow::Result<DescriptorMulti> {
+        let (args, sorted) = match (unwrap_fn(s, "multi"), unwrap_fn(s, "sortedmulti")) {
+            (Some(args), _) => (args, false),
+            (_, Some(args)) => (args, true),
+            _ => return Err(anyhow!("Expected multi or sortedmulti, found {}", s)),
+        };
+        let mut args = args.split(',');
+        let threshold = args
+            .next()
+            .and_then(|k| usize::from_str(k).ok())
+            .ok_or_else(|| anyhow!("Invalid multisig threshold in {}", s))?;
+        let keys = args
+            .map(|key| DescriptorKey::parse(key, network))
+            .collect::<anyhow::Result<Vec<DescriptorKey>>>()?;
+        if keys.len() > max_keys || threshold == 0 || threshold > keys.len() {
+            return Err(anyhow!("Invalid {} of {} multisig", threshold, keys.len()));
+        }
+        Ok(DescriptorMulti {
+            threshold,
+            keys,
+            sorted,
+        })
+    }
+
+    /// `OP_k <pubkeys> OP_n OP_CHECKMULTISIG` at `index`, keys sorted by BIP67 for `sortedmulti`
+    pub fn script(&self, index: u64) -> anyhow::Result<Script> {
+        let mut pubkeys = self
+            .keys
+            .iter()
+            .map(|key| Ok(key.derive(index)?.to_pub()))
+            .collect::<anyhow::Result<Vec<PublicKey>>>()?;
+        if self.sorted {
+            pubkeys.sort_by_key(|pubkey| pubkey.to_bytes());
+        }
+        let builder = pubkeys.iter().fold(
+            Builder::new().push_int(self.threshold as i64),
+            |b, pubkey| b.push_key(pubkey),
+        );
+        Ok(builder
+            .push_int(pubkeys.len() as i64)
+            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+            .into_script())
+    }
+
+    fn fmt_with_tail(&self, f: &mut fmt::Formatter, tail: &str) -> fmt::Result {
+        let name = if self.sorted { "sortedmulti" } else { "multi" };
+        write!(f, "{}({}", name, self.threshold)?;
+        for key in &self.keys {
+            write!(f, ",")?;
+            key.fmt_with_tail(f, tail)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Ranged output descriptor a cold wallet can receive on
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OutputDescriptor {
+    Pkh(DescriptorKey),
+    ShWpkh(DescriptorKey),
+    Wpkh(DescriptorKey),
+    Tr(DescriptorKey),
+    Sh(DescriptorMulti),
+    Wsh(DescriptorMulti),
+    ShWsh(DescriptorMulti),
+}
+
+fn unwrap_fn<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    s.strip_prefix(name)?.strip_prefix('(')?.strip_suffix(')')
+}
+
+impl OutputDescriptor {
+    /// Parses a descriptor with an optional `#checksum`, which is verified when present
+    pub fn parse(desc: &str, network: &Network) -> anyhow::Result<OutputDescriptor> {
+        let desc = desc.trim();
+        let body = if desc.contains('#') {
+            strip_descriptor_checksum(desc)?
+        } else {
+            desc
+        };
+        if let Some(inner) = unwrap_fn(body, "sh") {
+            if let Some(key) = unwrap_fn(inner, "wpkh") {
+                return Ok(OutputDescriptor::ShWpkh(DescriptorKey::parse(
+                    key, network,
+                )?));
+            }
+            if let Some(multi) = unwrap_fn(inner, "wsh") {
+                return Ok(OutputDescriptor::ShWsh(DescriptorMulti::parse(
+                    multi,
+                    network,
+                    MAX_MULTISIG_KEYS,
+                )?));
+            }
+            return Ok(OutputDescriptor::Sh(DescriptorMulti::parse(
+                inner,
+                network,
+                MAX_P2SH_MULTISIG_KEYS,
+            )?));
+        }
+        if let Some(multi) = unwrap_fn(body, "wsh") {
+            return Ok(OutputDescriptor::Wsh(DescriptorMulti::parse(
+                multi,
+                network,
+                MAX_MULTISIG_KEYS,
+            )?));
+        }
+        if let Some(key) = unwrap_fn(body, "pkh") {
+            return Ok(OutputDescriptor::Pkh(DescriptorKey::parse(key, network)?));
+        }
+        if let Some(key) = unwrap_fn(body, "wpkh") {
+            return Ok(OutputDescriptor::Wpkh(DescriptorKey::parse(key, network)?));
+        }
+        if let Some(key) = unwrap_fn(body, "tr") {
+            return Ok(OutputDescriptor::Tr(DescriptorKey::parse(key, network)?));
+        }
+        Err(anyhow!("Unsupported descriptor {}", body))
+    }
+
+    /// Ranged descriptor with its checksum
+    pub fn to_string_with_checksum(&self) -> anyhow::Result<String> {
+        let desc = self.to_string();
+        Ok(format!("{}#{}", desc, descriptor_checksum(&desc)?))
+    }
+
+    /// Descriptor for the single output at `index` with its checksum
+    pub fn at_index(&self, index: u64) -> anyhow::Result<String> {
+        let desc = DescriptorAt(self, index.to_string()).to_string();
+        Ok(format!("{}#{}", desc, descriptor_checksum(&desc)?))
+    }
+
+    pub fn keys(&self) -> &[DescriptorKey] {
+        match self {
+            OutputDescriptor::Pkh(key)
+            | OutputDescriptor::ShWpkh(key)
+            | OutputDescriptor::Wpkh(key)
+            | OutputDescriptor::Tr(key) => std::slice::from_ref(key),
+            OutputDescriptor::Sh(multi)
+            | OutputDescriptor::Wsh(multi)
+            | OutputDescriptor::ShWsh(multi) => &multi.keys,
+        }
+    }
+
+    /// P2SH redeem script at `index` for `sh(...)` descriptors
+    pub fn redeem_script(&self, index: u64) -> anyhow::Result<Option<Script>> {
+        match self {
+            OutputDescriptor::ShWpkh(key) => {
+                let pubkey_hash = key
+                    .derive(index)?
+                    .to_pub()
+                    .wpubkey_hash()
+                    .ok_or_else(|| anyhow!("Uncompressed key in sh(wpkh)"))?;
+                Ok(Some(Script::new_v0_p2wpkh(&pubkey_hash)))
+            }
+            OutputDescriptor::Sh(multi) => Ok(Some(multi.script(index)?)),
+            OutputDescriptor::ShWsh(multi) => Ok(Some(Script::new_v0_p2wsh(
+                &multi.script(index)?.wscript_hash(),
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// P2WSH witness script at `index` for `wsh(...)` and `sh(wsh(...))` descriptors
+    pub fn witness_script(&self, index: u64) -> anyhow::Result<Option<Script>> {
+        match self {
+            OutputDescriptor::Wsh(multi) | OutputDescriptor::ShWsh(multi) => {
+                Ok(Some(multi.script(index)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn address(&self, index: u64, network: &Network) -> anyhow::Result<String> {
+        let network: bitcoin::Network = network.clone().into();
+        let addr = match self {
+            OutputDescriptor::Pkh(key) => Address::p2pkh(&key.derive(index)?.to_pub(), network),
+            OutputDescriptor::ShWpkh(key) => {
+                Address::p2shwpkh(&key.derive(index)?.to_pub(), network)?
+            }
+            OutputDescriptor::Wpkh(key) => Address::p2wpkh(&key.derive(index)?.to_pub(), network)?,
+            OutputDescriptor::Tr(key) => {
+                let secp = Secp256k1::verification_only();
+                let internal_key = XOnlyPublicKey::from(key.derive(index)?.public_key);
+                Address::p2tr(&secp, internal_key, None, network)
+            }
+            OutputDescriptor::Sh(multi) => Address::p2sh(&multi.script(index)?, network)?,
+            OutputDescriptor::Wsh(multi) => Address::p2wsh(&multi.script(index)?, network),
+            OutputDescriptor::ShWsh(multi) => Address::p2shwsh(&multi.script(index)?, network),
+        };
+        Ok(addr.to_string())
+    }
+
+    fn fmt_with_tail(&self, f: &mut fmt::Formatter, tail: &str) -> fmt::Result {
+        match self {
+            OutputDescriptor::Pkh(key) => {
+                write!(f, "pkh(")?;
+                key.fmt_with_tail(f, tail)?;
+                write!(f, ")")
+            }
+            OutputDescriptor::ShWpkh(key) => {
+                write!(f, "sh(wpkh(")?;
+                key.fmt_with_tail(f, tail)?;
+                write!(f, "))")
+            }
+            OutputDescriptor::Wpkh(key) => {
+                write!(f, "wpkh(")?;
+                key.fmt_with_tail(f, tail)?;
+                write!(f, ")")
+            }
+            OutputDescriptor::Tr(key) => {
+                write!(f, "tr(")?;
+                key.fmt_with_tail(f, tail)?;
+                write!(f, ")")
+            }
+            OutputDescriptor::Sh(multi) => {
+                write!(f, "sh(")?;
+                multi.fmt_with_tail(f, tail)?;
+                write!(f, ")")
+            }
+            OutputDescriptor::Wsh(multi) => {
+                write!(f, "wsh(")?;
+                multi.fmt_with_tail(f, tail)?;
+                write!(f, ")")
+            }
+            OutputDescriptor::ShWsh(multi) => {
+                write!(f, "sh(wsh(")?;
+                multi.fmt_with_tail(f, tail)?;
+                write!(f, "))")
+            }
+        }
+    }
+}
+
+impl fmt::Display for OutputDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_tail(f, "*")
+    }
+}
+
+struct DescriptorAt<'a>(&'a OutputDescriptor, String);
+
+impl fmt::Display for DescriptorAt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_with_tail(f, &self.1)
+    }
+}
+
+#[test]
+fn test_descriptor_checksum() {
+    assert_eq!(
+        descriptor_checksum("raw(deadbeef)").expect("Invalid Checksum"),
+        "89f8spxm"
+    );
+    assert!(strip_descriptor_checksum("raw(deadbeef)#89f8spxm").is_ok());
+    assert!(strip_descriptor_checksum("raw(deadbeef)#89f8spxn").is_err());
+    assert!(strip_descriptor_checksum("raw(deadbeef)").is_err());
+}
+
+#[test]
+fn test_descriptor() {
+    // BIP84 account key for "abandon abandon ... about" with no passphrase
+    let body = "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)";
+    let desc = OutputDescriptor::parse(body, &Network::Mainnet).expect("Invalid Descriptor");
+    assert_eq!(desc.to_string(), body);
+    assert_eq!(
+        desc.address(0, &Network::Mainnet).expect("Invalid Addr"),
+        "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    );
+
+    let with_checksum = desc.to_string_with_checksum().expect("Invalid Checksum");
+    assert_eq!(
+        OutputDescriptor::parse(&with_checksum, &Network::Mainnet).expect("Invalid Descriptor"),
+        desc
+    );
+    let at_index = desc.at_index(0).expect("Invalid Descriptor");
+    assert!(at_index.starts_with(&body.replace("/*)", "/0)#")));
+    assert!(strip_descriptor_checksum(&at_index).is_ok());
+    let (fingerprint, path) = desc.keys()[0].key_source(5).expect("Invalid KeySource");
+    assert_eq!(fingerprint.to_string(), "73c5da0a");
+    assert_eq!(path.to_string(), "m/84'/0'/0'/0/5");
+
+    assert!(OutputDescriptor::parse(body, &Network::Testnet).is_err());
+    assert!(OutputDescriptor::parse(&body.replace("/0/*", "/0"), &Network::Mainnet).is_err());
+    assert!(OutputDescriptor::parse(&body.replace("/0/*", "/0'/*"), &Network::Mainnet).is_err());
+
+    let xpub_a = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+    let xpub_b = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+    let sorted_ab = OutputDescriptor::parse(
+        &format!("wsh(sortedmulti(1,{}/0/*,{}/0/*))", xpub_a, xpub_b),
+        &Network::Mainnet,
+    )
+    .expect("Invalid Descriptor");
+    let sorted_ba = OutputDescriptor::parse(
+        &format!("wsh(sortedmulti(1,{}/0/*,{}/0/*))", xpub_b, xpub_a),
+        &Network::Mainnet,
+    )
+    .expect("Invalid Descriptor");
+    let addr = sorted_ab
+        .address(3, &Network::Mainnet)
+        .expect("Invalid Addr");
+    assert!(addr.starts_with("bc1q"));
+    assert_eq!(
+        sorted_ba
+            .address(3, &Network::Mainnet)
+            .expect("Invalid Addr"),
+        addr
+    );
+    assert_eq!(
+        sorted_ab.witness_script(3).expect("Invalid Script"),
+        sorted_ba.witness_script(3).expect("Invalid Script")
+    );
+    assert!(OutputDescriptor::parse(
+        &format!("sh(wsh(sortedmulti(3,{}/0/*,{}/0/*)))", xpub_a, xpub_b),
+        &Network::Mainnet,
+    )
+    .is_err());
+    assert!(OutputDescriptor::parse(
+        &format!("sh(wsh(sortedmulti(2,{}/0/*,{}/0/*)))", xpub_a, xpub_b),
+        &Network::Mainnet,
+    )
+    .expect("Invalid Descriptor")
+    .address(0, &Network::Mainnet)
+    .expect("Invalid Addr")
+    .starts_with('3'));
+}
+
+#[test]
+fn test_multisig_key_limits() {
+    let xpub = ExtendedPubKey::from_str("xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V")
+        .expect("Invalid xpub");
+    let secp = Secp256k1::verification_only();
+    let keys = |n: u32| {
+        (0..n)
+            .map(|i| {
+                let child = xpub
+                    .ckd_pub(
+                        &secp,
+                        ChildNumber::from_normal_idx(i).expect("Invalid index"),
+                    )
+                    .expect("Invalid xpub");
+                format!("{}/0/*", child)
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+
+    let sh_15 = OutputDescriptor::parse(&format!("sh(multi(1,{}))", keys(15)), &Network::Mainnet)
+        .expect("Invalid Descriptor");
+    assert!(
+        sh_15
+            .redeem_script(0)
+            .expect("Invalid Script")
+            .expect("No Script")
+            .len()
+            <= 520
+    );
+    assert!(sh_15
+        .address(0, &Network::Mainnet)
+        .expect("Invalid Addr")
+        .starts_with('3'));
+    assert!(
+        OutputDescriptor::parse(&format!("sh(multi(1,{}))", keys(16)), &Network::Mainnet).is_err()
+    );
+    assert!(
+        OutputDescriptor::parse(&format!("wsh(multi(1,{}))", keys(16)), &Network::Mainnet).is_ok()
+    );
+    assert!(OutputDescriptor::parse(
+        &format!("sh(wsh(sortedmulti(1,{})))", keys(16)),
+        &Network::Mainnet
+    )
+    .is_ok());
+    assert!(
+        OutputDescriptor::parse(&format!("wsh(multi(1,{}))", keys(17)), &Network::Mainnet).is_err()
+    );
+}
@@ -0,0 +1,394 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use bip39::Mnemonic;
+use bitcoin::{
+    secp256k1::Secp256k1,
+    util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey},
+};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use moonramp_core::{anyhow, bip39, bitcoin, bs58, rand, serde, sha3};
+
+use crate::{EthereumColdWalletType, Network, Ticker, WalletSecret, WalletType};
+
+#[derive(Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub struct EthereumHotWallet {
+    pub mnemonic: Vec<u8>,
+    pub password: String,
+    pub xpub: Vec<u8>,
+    pub index: u64,
+}
+
+impl fmt::Debug for EthereumHotWallet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match ExtendedPubKey::decode(&self.xpub) {
+            Ok(xpub) => write!(
+                f,
+                "{}",
+                bs58::encode(xpub.to_pub().to_bytes()).into_string()
+            ),
+            Err(err) => write!(f, "Invalid EthereumHotWallet({})", err),
+        }
+    }
+}
+
+impl fmt::Display for EthereumHotWallet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum EthereumColdWallet {
+    XPubkey { xpub: String, index: u64 },
+}
+
+impl fmt::Debug for EthereumColdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EthereumColdWallet::XPubkey { xpub, .. } => match ExtendedPubKey::from_str(xpub) {
+                Ok(xpub) => write!(
+                    f,
+                    "{}",
+                    bs58::encode(xpub.to_pub().to_bytes()).into_string()
+                ),
+                Err(err) => write!(f, "Invalid EthereumColdWallet({})", err),
+            },
+        }
+    }
+}
+
+impl fmt::Display for EthereumColdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum EthereumWallet {
+    Hot(Ticker, Network, EthereumHotWallet),
+    Cold(Ticker, Network, EthereumColdWallet),
+}
+
+impl fmt::Display for EthereumWallet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EthereumWallet::Hot(_, _, w) => {
+                write!(f, "{}", w)
+            }
+            EthereumWallet::Cold(_, _, w) => {
+                write!(f, "{}", w)
+            }
+        }
+    }
+}
+
+/// BIP44 coin type registered in SLIP-0044
+fn coin_type(ticker: &Ticker) -> anyhow::Result<u32> {
+    match ticker {
+        Ticker::ETH => Ok(60),
+        Ticker::ETC => Ok(61),
+        _ => Err(anyhow!("Ticker {:?} not supported", ticker)),
+    }
+}
+
+/// Hex encodes the last 20 bytes of the keccak256 hash of the uncompressed public key with an
+/// EIP-55 mixed-case checksum
+pub fn eip55_address(xpub: &ExtendedPubKey) -> String {
+    let pubkey = xpub.public_key.serialize_uncompressed();
+    let hash = Keccak256::digest(&pubkey[1..]);
+    eip55_checksum(&hex_encode(&hash[12..]))
+}
+
+/// Applies the EIP-55 mixed-case checksum to a hex encoded address with or without a 0x prefix
+pub fn eip55_checksum(address: &str) -> String {
+    let address = address.trim_start_matches("0x").to_lowercase();
+    let hash = hex_encode(&Keccak256::digest(address.as_bytes()));
+    let checksummed: String = address
+        .chars()
+        .zip(hash.chars())
+        .map(|(c, h)| match h.to_digit(16) {
+            Some(n) if n >= 8 => c.to_ascii_uppercase(),
+            _ => c,
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+/// Scales a decimal amount to integer base units (wei for ETH/ETC)
+/// EIP-681 native value transfer request for `value` wei
+pub fn eip681_uri(address: &str, value: u128) -> String {
+    format!("ethereum:{}?value={}", address, value)
+}
+
+/// EIP-681 ERC-20 `transfer(address,uint256)` request for `value` base units of the token
+pub fn eip681_erc20_transfer_uri(contract: &str, address: &str, value: u128) -> String {
+    format!(
+        "ethereum:{}/transfer?address={}&uint256={}",
+        contract, address, value
+    )
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl EthereumWallet {
+    pub fn new_hot(ticker: Ticker, network: Network) -> anyhow::Result<EthereumWallet> {
+        coin_type(&ticker)?;
+
+        let password: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+
+        let mut entropy = [0u8; 32];
+        thread_rng().fill(&mut entropy);
+
+        let mnemonic = Mnemonic::from_entropy(&entropy)?;
+        EthereumWallet::from_mnemonic(ticker, network, mnemonic, password)
+    }
+
+    /// Restores a hot wallet from its BIP39 mnemonic words and passphrase
+    pub fn import_hot(
+        ticker: Ticker,
+        network: Network,
+        mnemonic: &str,
+        password: String,
+    ) -> anyhow::Result<EthereumWallet> {
+        let mnemonic = Mnemonic::parse(mnemonic.to_lowercase())?;
+        EthereumWallet::from_mnemonic(ticker, network, mnemonic, password)
+    }
+
+    fn from_mnemonic(
+        ticker: Ticker,
+        network: Network,
+        mnemonic: Mnemonic,
+        password: String,
+    ) -> anyhow::Result<EthereumWallet> {
+        let coin_type = coin_type(&ticker)?;
+        let seed = mnemonic.to_seed(password.clone());
+        let key = ExtendedPrivKey::new_master(network.clone().into(), &seed)?;
+
+        let secp = Secp256k1::new();
+        let account_path = format!("m/44'/{}'/0'", coin_type);
+        let account_key = key.derive_priv(&secp, &DerivationPath::from_str(&account_path)?)?;
+        Ok(EthereumWallet::Hot(
+            ticker,
+            network,
+            EthereumHotWallet {
+                mnemonic: mnemonic.to_entropy(),
+                password,
+                xpub: ExtendedPubKey::from_priv(&secp, &account_key)
+                    .encode()
+                    .to_vec(),
+                index: 0,
+            },
+        ))
+    }
+
+    pub fn new_cold(
+        ticker: Ticker,
+        network: Network,
+        pubkey: String,
+        cold_type: EthereumColdWalletType,
+    ) -> anyhow::Result<EthereumWallet> {
+        coin_type(&ticker)?;
+        match cold_type {
+            EthereumColdWalletType::XPubkey => {
+                ExtendedPubKey::from_str(&pubkey)?;
+                Ok(EthereumWallet::Cold(
+                    ticker,
+                    network,
+                    EthereumColdWallet::XPubkey {
+                        xpub: pubkey,
+                        index: 0,
+                    },
+                ))
+            }
+        }
+    }
+
+    pub fn pubkey(&self) -> String {
+        match self {
+            EthereumWallet::Hot(_, _, w) => match ExtendedPubKey::decode(&w.xpub) {
+                Ok(xpub) => xpub.to_pub().to_string(),
+                Err(_) => "Invalid EthereumHotWallet".to_string(),
+            },
+            EthereumWallet::Cold(_, _, w) => match w {
+                EthereumColdWallet::XPubkey { xpub, .. } => match ExtendedPubKey::from_str(xpub) {
+                    Ok(xpub) => xpub.to_pub().to_string(),
+                    Err(_) => "Invalid EthereumColdWallet".to_string(),
+                },
+            },
+        }
+    }
+
+    pub fn ticker(&self) -> Ticker {
+        match self {
+            EthereumWallet::Hot(ticker, _, _) => ticker,
+            EthereumWallet::Cold(ticker, _, _) => ticker,
+        }
+        .clone()
+    }
+
+    pub fn network(&self) -> Network {
+        match self {
+            EthereumWallet::Hot(_, network, _) => network,
+            EthereumWallet::Cold(_, network, _) => network,
+        }
+        .clone()
+    }
+
+    pub fn wallet_type(&self) -> WalletType {
+        match self {
+            EthereumWallet::Hot(_, _, _) => WalletType::Hot,
+            EthereumWallet::Cold(_, _, _) => WalletType::Cold,
+        }
+    }
+
+    /// BIP39 mnemonic and passphrase of a hot wallet
+    pub fn secret(&self) -> anyhow::Result<WalletSecret> {
+        match self {
+            EthereumWallet::Hot(_, _, w) => Ok(WalletSecret::Bip39 {
+                mnemonic: Mnemonic::from_entropy(&w.mnemonic)?.to_string(),
+                passphrase: w.password.clone(),
+            }),
+            EthereumWallet::Cold(_, _, _) => Err(anyhow!("Cold wallets have no secret")),
+        }
+    }
+}
+
+impl EthereumWallet {
+    /// Derives the next external chain key `0/{index}` from the account level xpub
+    /// (m/44'/coin_type'/0')
+    pub fn next_xpub(&mut self) -> anyhow::Result<ExtendedPubKey> {
+        let (xpub, index) = match self {
+            EthereumWallet::Hot(_, _, w) => (ExtendedPubKey::decode(&w.xpub)?, &mut w.index),
+            EthereumWallet::Cold(_, _, EthereumColdWallet::XPubkey { xpub, index }) => {
+                (ExtendedPubKey::from_str(xpub)?, index)
+            }
+        };
+        let derivation_path = format!("m/0/{}", index);
+        let secp = Secp256k1::new();
+        let chxpub = xpub.derive_pub(&secp, &DerivationPath::from_str(&derivation_path)?)?;
+        *index += 1;
+        Ok(chxpub)
+    }
+
+    pub fn next_addr(&mut self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        let next_xpub = self.next_xpub()?;
+        Ok((next_xpub, eip55_address(&next_xpub)))
+    }
+
+    pub fn addr(&self) -> anyhow::Result<(ExtendedPubKey, String)> {
+        let xpub = match self {
+            EthereumWallet::Hot(_, _, w) => {
+                ExtendedPubKey::decode(&w.xpub).map_err(|_| anyhow!("Invalid EthereumHotWallet"))?
+            }
+            EthereumWallet::Cold(_, _, EthereumColdWallet::XPubkey { xpub, .. }) => {
+                ExtendedPubKey::from_str(xpub).map_err(|_| anyhow!("Invalid EthereumColdWallet"))?
+            }
+        };
+        Ok((xpub, eip55_address(&xpub)))
+    }
+}
+
+#[test]
+fn test_eip55_checksum() {
+    for addr in [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ] {
+        assert_eq!(eip55_checksum(&addr.to_lowercase()), addr);
+    }
+}
+
+#[test]
+fn test_eip681_uri() {
+    use crate::Currency;
+
+    let address = "0x9858EfFD232B4033E47d90003D41EC34EcaEda94";
+    let value = Currency::ETH
+        .base_units(&"1.5".parse().expect("Invalid Amount"))
+        .expect("Invalid base units");
+    assert_eq!(
+        eip681_uri(address, value),
+        "ethereum:0x9858EfFD232B4033E47d90003D41EC34EcaEda94?value=1500000000000000000"
+    );
+    let value = Currency::USDC
+        .base_units(&"12.34".parse().expect("Invalid Amount"))
+        .expect("Invalid base units");
+    assert_eq!(
+        eip681_erc20_transfer_uri("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", address, value),
+        "ethereum:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48/transfer?address=0x9858EfFD232B4033E47d90003D41EC34EcaEda94&uint256=12340000"
+    );
+    assert_eq!(
+        Currency::USDC
+            .amount(12340000)
+            .expect("Invalid amount")
+            .to_string(),
+        "12.340000"
+    );
+    assert!(Currency::USDC
+        .base_units(&"12.3456789".parse().expect("Invalid Amount"))
+        .is_err());
+}
+
+#[test]
+fn test_hot_wallet() {
+    let password = "".to_string();
+    let entropy = [0u8; 16];
+    let mnemonic = Mnemonic::from_entropy(&entropy).expect("Invalid Mnemonic");
+    let seed = mnemonic.to_seed(password.clone());
+    let key = ExtendedPrivKey::new_master(Network::Mainnet.into(), &seed)
+        .expect("Invalid ExtendedPrivKey");
+    let secp = Secp256k1::new();
+    let account_key = key
+        .derive_priv(
+            &secp,
+            &DerivationPath::from_str("m/44'/60'/0'").expect("Invalid DerivationPath"),
+        )
+        .expect("Invalid ExtendedPrivKey");
+    let w = EthereumHotWallet {
+        mnemonic: mnemonic.to_entropy(),
+        password,
+        xpub: ExtendedPubKey::from_priv(&secp, &account_key)
+            .encode()
+            .to_vec(),
+        index: 0,
+    };
+
+    let mut mainnet_w = EthereumWallet::Hot(Ticker::ETH, Network::Mainnet, w);
+    assert_eq!(
+        mainnet_w.next_addr().expect("Invalid Addr").1,
+        "0x9858EfFD232B4033E47d90003D41EC34EcaEda94"
+    );
+    assert_eq!(
+        mainnet_w.next_addr().expect("Invalid Addr").1,
+        "0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9C0"
+    );
+
+    let account_xpub = mainnet_w.addr().expect("Invalid Addr").0.to_string();
+    let mut cold_w = EthereumWallet::new_cold(
+        Ticker::ETH,
+        Network::Mainnet,
+        account_xpub,
+        EthereumColdWalletType::XPubkey,
+    )
+    .expect("Invalid EthereumWallet");
+    assert_eq!(cold_w.pubkey(), mainnet_w.pubkey());
+    assert_eq!(
+        cold_w.next_addr().expect("Invalid Addr").1,
+        "0x9858EfFD232B4033E47d90003D41EC34EcaEda94"
+    );
+}
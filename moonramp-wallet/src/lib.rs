@@ -4,15 +4,35 @@ use serde::{Deserialize, Serialize};
 use moonramp_core::bitcoin;
 #[cfg(feature = "monero")]
 use moonramp_core::monero;
-use moonramp_core::{anyhow, serde};
+use moonramp_core::{anyhow, serde, Amount};
 #[cfg(feature = "entity")]
 use moonramp_entity::{currency, network, ticker, wallet};
 
+#[cfg(feature = "bitcoin")]
+mod bitcoin_psbt;
+#[cfg(feature = "bitcoin")]
+pub use bitcoin_psbt::*;
+
 #[cfg(feature = "bitcoin")]
 mod bitcoin_wallet;
 #[cfg(feature = "bitcoin")]
 pub use bitcoin_wallet::*;
 
+#[cfg(feature = "bitcoin")]
+mod cashaddr;
+#[cfg(feature = "bitcoin")]
+pub use cashaddr::*;
+
+#[cfg(feature = "bitcoin")]
+mod descriptor;
+#[cfg(feature = "bitcoin")]
+pub use descriptor::*;
+
+#[cfg(feature = "ethereum")]
+mod ethereum_wallet;
+#[cfg(feature = "ethereum")]
+pub use ethereum_wallet::*;
+
 #[cfg(feature = "monero")]
 mod monero_wallet;
 #[cfg(feature = "monero")]
@@ -67,11 +87,13 @@ impl From<ticker::Ticker> for Ticker {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum Currency {
     BCH,
     BTC,
+    /// BTC paid over the Lightning Network
+    BTCLN,
     ETC,
     ETH,
     USDC,
@@ -86,6 +108,7 @@ impl From<Currency> for currency::Currency {
         match c {
             Currency::BCH => currency::Currency::BCH,
             Currency::BTC => currency::Currency::BTC,
+            Currency::BTCLN => currency::Currency::BTCLN,
             Currency::ETC => currency::Currency::ETC,
             Currency::ETH => currency::Currency::ETH,
             Currency::USDC => currency::Currency::USDC,
@@ -102,6 +125,7 @@ impl From<&Currency> for currency::Currency {
         match c {
             Currency::BCH => currency::Currency::BCH,
             Currency::BTC => currency::Currency::BTC,
+            Currency::BTCLN => currency::Currency::BTCLN,
             Currency::ETC => currency::Currency::ETC,
             Currency::ETH => currency::Currency::ETH,
             Currency::USDC => currency::Currency::USDC,
@@ -118,6 +142,7 @@ impl From<currency::Currency> for Currency {
         match c {
             currency::Currency::BCH => Currency::BCH,
             currency::Currency::BTC => Currency::BTC,
+            currency::Currency::BTCLN => Currency::BTCLN,
             currency::Currency::ETC => Currency::ETC,
             currency::Currency::ETH => Currency::ETH,
             currency::Currency::USDC => Currency::USDC,
@@ -128,20 +153,102 @@ impl From<currency::Currency> for Currency {
     }
 }
 
+impl Currency {
+    /// ERC-20 token contract address, `Testnet` is Ethereum Sepolia. Sepolia USDC is Circle's
+    /// own deployment, Sepolia USDT is a community test token. USDP has no Sepolia deployment
+    /// and regtest chains have no canonical contracts
+    pub fn erc20_contract(&self, network: &Network) -> Option<&'static str> {
+        match (self, network) {
+            (Currency::USDC, Network::Mainnet) => {
+                Some("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")
+            }
+            (Currency::USDP, Network::Mainnet) => {
+                Some("0x8E870D67F660D95d5be530380D0eC0bd388289E1")
+            }
+            (Currency::USDT, Network::Mainnet) => {
+                Some("0xdAC17F958D2ee523a2206206994597C13D831ec7")
+            }
+            (Currency::USDC, Network::Testnet) => {
+                Some("0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238")
+            }
+            // Tether has no Sepolia deployment, this is a community faucet token
+            (Currency::USDT, Network::Testnet) => {
+                Some("0xaA8E23Fb1079EA71e0a56F48a2aA51851D8433D0")
+            }
+            _ => None,
+        }
+    }
+
+    /// Decimal places of the currency's smallest unit
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Currency::BCH | Currency::BTC => 8,
+            // Lightning amounts are in millisatoshis
+            Currency::BTCLN => 11,
+            Currency::ETC | Currency::ETH => 18,
+            Currency::USDC | Currency::USDT => 6,
+            Currency::USDP => 18,
+            Currency::XMR => 12,
+        }
+    }
+
+    /// An exact amount of `units` of the currency's smallest unit
+    pub fn amount(&self, units: u128) -> anyhow::Result<Amount> {
+        Amount::new(units, self.decimals())
+    }
+
+    /// `amount` in the currency's smallest unit, fails if it has more decimals than the currency
+    pub fn base_units(&self, amount: &Amount) -> anyhow::Result<u128> {
+        amount.to_base_units(self.decimals())
+    }
+
+    pub fn erc20_decimals(&self) -> Option<u32> {
+        self.is_erc20().then(|| self.decimals())
+    }
+
+    pub fn is_erc20(&self) -> bool {
+        matches!(self, Currency::USDC | Currency::USDP | Currency::USDT)
+    }
+
+    pub fn is_lightning(&self) -> bool {
+        *self == Currency::BTCLN
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum Network {
     Mainnet,
     Testnet,
+    /// Bitcoin signet, pairs with Monero `Stagenet`
+    Signet,
+    /// Monero stagenet, pairs with Bitcoin `Signet`
+    Stagenet,
     Regtest,
 }
 
+impl Network {
+    /// The network a `ticker` wallet uses on a node running `self`, staging
+    /// networks map to the chain's own staging network (Signet <-> Stagenet,
+    /// BCH falls back to its testnet)
+    pub fn for_ticker(&self, ticker: &Ticker) -> Network {
+        match (ticker, self) {
+            (Ticker::XMR, Network::Signet) => Network::Stagenet,
+            (Ticker::BTC, Network::Stagenet) => Network::Signet,
+            (Ticker::BCH, Network::Signet | Network::Stagenet) => Network::Testnet,
+            (_, network) => network.clone(),
+        }
+    }
+}
+
 #[cfg(feature = "entity")]
 impl From<Network> for network::Network {
     fn from(n: Network) -> network::Network {
         match n {
             Network::Mainnet => network::Network::Mainnet,
             Network::Testnet => network::Network::Testnet,
+            Network::Signet => network::Network::Signet,
+            Network::Stagenet => network::Network::Stagenet,
             Network::Regtest => network::Network::Regtest,
         }
     }
@@ -153,6 +260,8 @@ impl From<&Network> for network::Network {
         match n {
             Network::Mainnet => network::Network::Mainnet,
             Network::Testnet => network::Network::Testnet,
+            Network::Signet => network::Network::Signet,
+            Network::Stagenet => network::Network::Stagenet,
             Network::Regtest => network::Network::Regtest,
         }
     }
@@ -164,6 +273,8 @@ impl From<network::Network> for Network {
         match n {
             network::Network::Mainnet => Network::Mainnet,
             network::Network::Testnet => Network::Testnet,
+            network::Network::Signet => Network::Signet,
+            network::Network::Stagenet => Network::Stagenet,
             network::Network::Regtest => Network::Regtest,
         }
     }
@@ -175,6 +286,8 @@ impl From<Network> for bitcoin::Network {
         match n {
             Network::Mainnet => bitcoin::Network::Bitcoin,
             Network::Testnet => bitcoin::Network::Testnet,
+            Network::Signet => bitcoin::Network::Signet,
+            Network::Stagenet => bitcoin::Network::Signet,
             Network::Regtest => bitcoin::Network::Regtest,
         }
     }
@@ -186,6 +299,8 @@ impl From<&Network> for bitcoin::Network {
         match n {
             Network::Mainnet => bitcoin::Network::Bitcoin,
             Network::Testnet => bitcoin::Network::Testnet,
+            Network::Signet => bitcoin::Network::Signet,
+            Network::Stagenet => bitcoin::Network::Signet,
             Network::Regtest => bitcoin::Network::Regtest,
         }
     }
@@ -197,6 +312,8 @@ impl From<&mut Network> for bitcoin::Network {
         match n {
             Network::Mainnet => bitcoin::Network::Bitcoin,
             Network::Testnet => bitcoin::Network::Testnet,
+            Network::Signet => bitcoin::Network::Signet,
+            Network::Stagenet => bitcoin::Network::Signet,
             Network::Regtest => bitcoin::Network::Regtest,
         }
     }
@@ -207,7 +324,9 @@ impl From<Network> for monero::Network {
     fn from(n: Network) -> monero::Network {
         match n {
             Network::Mainnet => monero::Network::Mainnet,
-            Network::Testnet => monero::Network::Stagenet,
+            Network::Testnet => monero::Network::Testnet,
+            Network::Signet => monero::Network::Stagenet,
+            Network::Stagenet => monero::Network::Stagenet,
             Network::Regtest => monero::Network::Testnet,
         }
     }
@@ -218,7 +337,9 @@ impl From<&Network> for monero::Network {
     fn from(n: &Network) -> monero::Network {
         match n {
             Network::Mainnet => monero::Network::Mainnet,
-            Network::Testnet => monero::Network::Stagenet,
+            Network::Testnet => monero::Network::Testnet,
+            Network::Signet => monero::Network::Stagenet,
+            Network::Stagenet => monero::Network::Stagenet,
             Network::Regtest => monero::Network::Testnet,
         }
     }
@@ -229,7 +350,9 @@ impl From<&mut Network> for monero::Network {
     fn from(n: &mut Network) -> monero::Network {
         match n {
             Network::Mainnet => monero::Network::Mainnet,
-            Network::Testnet => monero::Network::Stagenet,
+            Network::Testnet => monero::Network::Testnet,
+            Network::Signet => monero::Network::Stagenet,
+            Network::Stagenet => monero::Network::Stagenet,
             Network::Regtest => monero::Network::Testnet,
         }
     }
@@ -277,9 +400,31 @@ impl From<wallet::WalletType> for WalletType {
 #[serde(crate = "moonramp_core::serde", rename_all = "UPPERCASE")]
 pub enum BitcoinColdWalletType {
     XPubkey,
-    //P2PKH,
-    //P2SHWPKH,
-    //P2WPKH,
+    P2PKH,
+    P2SHWPKH,
+    P2WPKH,
+    P2TR,
+    Descriptor,
+}
+
+#[cfg(feature = "bitcoin")]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "UPPERCASE")]
+pub enum BitcoinDerivation {
+    /// `m/1/0/{index}` from the master key, used by wallets created before BIP44 support
+    #[default]
+    Legacy,
+    Bip44,
+    Bip49,
+    Bip84,
+    Bip86,
+}
+
+#[cfg(feature = "ethereum")]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "UPPERCASE")]
+pub enum EthereumColdWalletType {
+    XPubkey,
 }
 
 #[cfg(feature = "monero")]
@@ -291,11 +436,26 @@ pub enum MoneroColdWalletType {
              //P2WPKH,
 }
 
+/// Secret a hot wallet can be restored from
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde", rename_all = "camelCase")]
+pub enum WalletSecret {
+    /// BIP39 mnemonic words and passphrase of BTC, BCH, ETH and ETC wallets
+    Bip39 {
+        mnemonic: String,
+        passphrase: String,
+    },
+    /// 25 word Electrum style seed of XMR wallets
+    MoneroSeed { mnemonic: String },
+}
+
 #[derive(Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum Wallet {
     #[cfg(feature = "bitcoin")]
     Bitcoin(bitcoin_wallet::BitcoinWallet),
+    #[cfg(feature = "ethereum")]
+    Ethereum(ethereum_wallet::EthereumWallet),
     #[cfg(feature = "monero")]
     Monero(monero_wallet::MoneroWallet),
 }
@@ -305,6 +465,8 @@ impl Wallet {
         match self {
             #[cfg(feature = "bitcoin")]
             Wallet::Bitcoin(w) => w.pubkey(),
+            #[cfg(feature = "ethereum")]
+            Wallet::Ethereum(w) => w.pubkey(),
             #[cfg(feature = "monero")]
             Wallet::Monero(w) => w.pubkey(),
         }
@@ -314,6 +476,8 @@ impl Wallet {
         match self {
             #[cfg(feature = "bitcoin")]
             Wallet::Bitcoin(w) => w.ticker(),
+            #[cfg(feature = "ethereum")]
+            Wallet::Ethereum(w) => w.ticker(),
             #[cfg(feature = "monero")]
             Wallet::Monero(w) => w.ticker(),
         }
@@ -323,6 +487,8 @@ impl Wallet {
         match self {
             #[cfg(feature = "bitcoin")]
             Wallet::Bitcoin(w) => w.network(),
+            #[cfg(feature = "ethereum")]
+            Wallet::Ethereum(w) => w.network(),
             #[cfg(feature = "monero")]
             Wallet::Monero(w) => w.network(),
         }
@@ -332,10 +498,23 @@ impl Wallet {
         match self {
             #[cfg(feature = "bitcoin")]
             Wallet::Bitcoin(w) => w.wallet_type(),
+            #[cfg(feature = "ethereum")]
+            Wallet::Ethereum(w) => w.wallet_type(),
             #[cfg(feature = "monero")]
             Wallet::Monero(w) => w.wallet_type(),
         }
     }
+
+    pub fn secret(&self) -> anyhow::Result<WalletSecret> {
+        match self {
+            #[cfg(feature = "bitcoin")]
+            Wallet::Bitcoin(w) => w.secret(),
+            #[cfg(feature = "ethereum")]
+            Wallet::Ethereum(w) => w.secret(),
+            #[cfg(feature = "monero")]
+            Wallet::Monero(w) => w.secret(),
+        }
+    }
 }
 
 #[cfg(feature = "bitcoin")]
@@ -356,3 +535,22 @@ impl Wallet {
         }
     }
 }
+
+#[cfg(feature = "ethereum")]
+impl Wallet {
+    pub fn is_ethereum(&self) -> bool {
+        match self {
+            Wallet::Ethereum(_) => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    pub fn into_ethereum(self) -> anyhow::Result<ethereum_wallet::EthereumWallet, Self> {
+        match self {
+            Wallet::Ethereum(w) => Ok(w),
+            #[allow(unreachable_patterns)]
+            _ => Err(self),
+        }
+    }
+}
@@ -1,11 +1,19 @@
-use std::{fmt, str::FromStr};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use anyhow::anyhow;
 use curve25519_dalek::scalar::Scalar;
 use monero::{
-    cryptonote,
+    cryptonote::{
+        self,
+        subaddress::{self, Index},
+    },
     util::{
         address::Address,
-        key::{PrivateKey, PublicKey},
+        key::{PrivateKey, PublicKey, ViewPair},
     },
 };
 use rand::{thread_rng, Rng};
@@ -13,7 +21,38 @@ use serde::{Deserialize, Serialize};
 
 use moonramp_core::{anyhow, bs58, curve25519_dalek, monero, rand, serde};
 
-use crate::{Network, Ticker, WalletType};
+use crate::{Network, Ticker, WalletSecret, WalletType};
+
+/// Meaning of the `Network` stored with a wallet. Version 0 blobs were written before
+/// `Network::Stagenet` existed and hold stagenet wallets as `Testnet`
+const NETWORK_VERSION: u32 = 1;
+
+/// Target block time since the v2 fork
+const SECONDS_PER_BLOCK: u64 = 120;
+/// Blocks the estimated restore height stays behind the chain tip, about a week
+const RESTORE_HEIGHT_MARGIN: u64 = 5040;
+
+/// Approximate chain height now, estimated like `wallet2::get_approximate_blockchain_height`
+/// from the height and time of the v2 fork
+fn approximate_height(network: &Network) -> u64 {
+    let (fork_height, fork_time, rolled_back) = match network {
+        Network::Mainnet => (1009827, 1458748658, 0),
+        Network::Testnet => (624634, 1448285909, 342100),
+        Network::Stagenet => (32000, 1520937818, 30000),
+        Network::Signet | Network::Regtest => return 0,
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(fork_time);
+    (fork_height + now.saturating_sub(fork_time) / SECONDS_PER_BLOCK).saturating_sub(rolled_back)
+}
+
+/// Height a view-only wallet starts scanning from, no transfer to a subaddress MoonRamp hands
+/// out can be older than the wallet itself
+fn restore_height(network: &Network) -> u64 {
+    approximate_height(network).saturating_sub(RESTORE_HEIGHT_MARGIN)
+}
 
 #[derive(Eq, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
@@ -22,6 +61,15 @@ pub struct MoneroHotWallet {
     //pub password: String,
     pub spend_key: [u8; 32],
     pub view_key: [u8; 32],
+    #[serde(default)]
+    pub account: u32,
+    #[serde(default)]
+    pub index: u32,
+    #[serde(default)]
+    pub network_version: u32,
+    /// Wallets stored without one scan from genesis
+    #[serde(default)]
+    pub restore_height: u64,
 }
 
 impl fmt::Debug for MoneroHotWallet {
@@ -51,6 +99,10 @@ impl From<PrivateKey> for MoneroHotWallet {
         MoneroHotWallet {
             spend_key: spend_key.to_bytes(),
             view_key: view_key.to_bytes(),
+            account: 0,
+            index: 0,
+            network_version: NETWORK_VERSION,
+            restore_height: 0,
         }
     }
 }
@@ -63,6 +115,97 @@ impl FromStr for MoneroHotWallet {
     }
 }
 
+/// English wordlist of the Monero Electrum style seed, every word is unique in its first
+/// `SEED_PREFIX_LEN` letters
+const SEED_WORDS: &str = include_str!("monero_english.txt");
+const SEED_WORD_COUNT: u32 = 1626;
+const SEED_PREFIX_LEN: usize = 3;
+
+fn seed_words() -> Vec<&'static str> {
+    SEED_WORDS.lines().collect()
+}
+
+fn seed_prefix(word: &str) -> String {
+    word.chars().take(SEED_PREFIX_LEN).collect()
+}
+
+/// Index of the checksum word, the CRC-32 of the concatenated word prefixes
+fn seed_checksum_index(words: &[&str]) -> usize {
+    let mut crc = 0xffff_ffffu32;
+    for byte in words
+        .iter()
+        .map(|w| seed_prefix(w))
+        .collect::<String>()
+        .bytes()
+    {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & 0u32.wrapping_sub(crc & 1));
+        }
+    }
+    (!crc) as usize % words.len()
+}
+
+impl MoneroHotWallet {
+    /// 25 word seed of the spend key as `monero-wallet-cli` shows it, every 4 bytes of the key
+    /// are encoded in 3 words followed by a checksum word
+    pub fn seed(&self) -> String {
+        let wordlist = seed_words();
+        let n = SEED_WORD_COUNT;
+        let mut words = Vec::with_capacity(25);
+        for chunk in self.spend_key.chunks(4) {
+            let x = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let w1 = x % n;
+            let w2 = (x / n + w1) % n;
+            let w3 = (x / n / n + w2) % n;
+            words.extend([w1, w2, w3].iter().map(|w| wordlist[*w as usize]));
+        }
+        words.push(words[seed_checksum_index(&words)]);
+        words.join(" ")
+    }
+
+    /// Restores the spend key from a 25 word seed, words may be shortened to their unique prefix
+    pub fn from_seed(seed: &str) -> anyhow::Result<MoneroHotWallet> {
+        let wordlist = seed_words();
+        let n = SEED_WORD_COUNT;
+        let seed = seed.to_lowercase();
+        let words = seed.split_whitespace().collect::<Vec<_>>();
+        if words.len() != 25 {
+            return Err(anyhow!("Monero seeds have 25 words, found {}", words.len()));
+        }
+        let indexes = words
+            .iter()
+            .map(|word| {
+                let prefix = seed_prefix(word);
+                wordlist
+                    .iter()
+                    .position(|w| seed_prefix(w) == prefix)
+                    .map(|i| i as u32)
+                    .ok_or(anyhow!("Invalid Monero seed word {}", word))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let checksum = seed_checksum_index(&words[..24]);
+        if seed_prefix(words[checksum]) != seed_prefix(words[24]) {
+            return Err(anyhow!("Invalid Monero seed checksum"));
+        }
+
+        let mut spend_key = [0u8; 32];
+        for (chunk, w) in spend_key.chunks_mut(4).zip(indexes[..24].chunks(3)) {
+            let (w1, w2, w3) = (w[0], w[1], w[2]);
+            let x = w1 as u64
+                + n as u64 * ((n - w1 + w2) % n) as u64
+                + n as u64 * n as u64 * ((n - w2 + w3) % n) as u64;
+            if x % n as u64 != w1 as u64 || x > u32::MAX as u64 {
+                return Err(anyhow!("Invalid Monero seed"));
+            }
+            chunk.copy_from_slice(&(x as u32).to_le_bytes());
+        }
+        let spend_scalar = Scalar::from_bytes_mod_order(spend_key);
+        Ok(MoneroHotWallet::from(PrivateKey::from_scalar(spend_scalar)))
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum MoneroColdWallet {
@@ -70,16 +213,60 @@ pub enum MoneroColdWallet {
         address: String,
         spend_pub_key: Vec<u8>,
         view_key: Vec<u8>,
+        #[serde(default)]
+        account: u32,
+        #[serde(default)]
+        index: u32,
+        #[serde(default)]
+        network_version: u32,
+        #[serde(default)]
+        restore_height: u64,
     },
 }
 
 #[derive(Debug, Eq, PartialEq, Deserialize, Serialize)]
-#[serde(crate = "moonramp_core::serde")]
+#[serde(crate = "moonramp_core::serde", from = "StoredMoneroWallet")]
 pub enum MoneroWallet {
     Hot(Network, MoneroHotWallet),
     Cold(Network, MoneroColdWallet),
 }
 
+/// A `MoneroWallet` as stored in an encrypted wallet blob, any version
+#[derive(Deserialize)]
+#[serde(crate = "moonramp_core::serde", rename = "MoneroWallet")]
+enum StoredMoneroWallet {
+    Hot(Network, MoneroHotWallet),
+    Cold(Network, MoneroColdWallet),
+}
+
+impl From<StoredMoneroWallet> for MoneroWallet {
+    fn from(stored: StoredMoneroWallet) -> MoneroWallet {
+        // The wallet is written back with the current version the next time it is saved
+        fn upgrade(network: Network, network_version: &mut u32) -> Network {
+            let network = match (*network_version, network) {
+                (0, Network::Testnet) => Network::Stagenet,
+                (_, network) => network,
+            };
+            *network_version = NETWORK_VERSION;
+            network
+        }
+        match stored {
+            StoredMoneroWallet::Hot(network, mut w) => {
+                let network = upgrade(network, &mut w.network_version);
+                MoneroWallet::Hot(network, w)
+            }
+            StoredMoneroWallet::Cold(network, mut w) => {
+                let network = match &mut w {
+                    MoneroColdWallet::ViewKey {
+                        network_version, ..
+                    } => upgrade(network, network_version),
+                };
+                MoneroWallet::Cold(network, w)
+            }
+        }
+    }
+}
+
 impl MoneroWallet {
     pub fn new_hot(network: Network) -> anyhow::Result<MoneroWallet> {
         let mut entropy = [0u8; 64];
@@ -87,7 +274,22 @@ impl MoneroWallet {
         let spend_scalar = Scalar::from_bytes_mod_order_wide(&entropy);
         let spend_key = PrivateKey::from_scalar(spend_scalar);
 
-        Ok(MoneroWallet::Hot(network, MoneroHotWallet::from(spend_key)))
+        let w = MoneroHotWallet {
+            restore_height: restore_height(&network),
+            ..MoneroHotWallet::from(spend_key)
+        };
+        Ok(MoneroWallet::Hot(network, w))
+    }
+
+    /// Restores a hot wallet from its 25 word seed, the view key is derived from the spend key
+    /// like `monero-wallet-cli --restore-deterministic-wallet` does. Invoices only pay
+    /// subaddresses handed out after the import so the wallet is scanned from the import height
+    pub fn import_hot(network: Network, seed: &str) -> anyhow::Result<MoneroWallet> {
+        let w = MoneroHotWallet {
+            restore_height: restore_height(&network),
+            ..MoneroHotWallet::from_seed(seed)?
+        };
+        Ok(MoneroWallet::Hot(network, w))
     }
 
     pub fn pubkey(&self) -> String {
@@ -124,9 +326,96 @@ impl MoneroWallet {
             MoneroWallet::Cold(_, _) => WalletType::Cold,
         }
     }
+
+    /// Height monero-wallet-rpc restores the view-only wallet from
+    pub fn restore_height(&self) -> u64 {
+        match self {
+            MoneroWallet::Hot(_, w) => w.restore_height,
+            MoneroWallet::Cold(_, MoneroColdWallet::ViewKey { restore_height, .. }) => {
+                *restore_height
+            }
+        }
+    }
+
+    /// 25 word seed of a hot wallet
+    pub fn secret(&self) -> anyhow::Result<WalletSecret> {
+        match self {
+            MoneroWallet::Hot(_, w) => Ok(WalletSecret::MoneroSeed { mnemonic: w.seed() }),
+            MoneroWallet::Cold(_, _) => Err(anyhow!("Cold wallets have no secret")),
+        }
+    }
 }
 
 impl MoneroWallet {
+    fn view_pair(&self) -> anyhow::Result<ViewPair> {
+        match self {
+            MoneroWallet::Hot(_, w) => {
+                let spend_key = PrivateKey::from_slice(&w.spend_key)?;
+                Ok(ViewPair {
+                    view: PrivateKey::from_slice(&w.view_key)?,
+                    spend: PublicKey::from_private_key(&spend_key),
+                })
+            }
+            MoneroWallet::Cold(
+                _,
+                MoneroColdWallet::ViewKey {
+                    spend_pub_key,
+                    view_key,
+                    ..
+                },
+            ) => Ok(ViewPair {
+                view: PrivateKey::from_slice(view_key)?,
+                spend: PublicKey::from_slice(spend_pub_key)?,
+            }),
+        }
+    }
+
+    /// Derives the next subaddress of the wallet account. `0/0` is the primary address so the
+    /// first subaddress handed out is `{account}/1`
+    pub fn next_subaddr_index(&mut self) -> anyhow::Result<Index> {
+        let (account, index) = match self {
+            MoneroWallet::Hot(_, w) => (w.account, &mut w.index),
+            MoneroWallet::Cold(_, MoneroColdWallet::ViewKey { account, index, .. }) => {
+                (*account, index)
+            }
+        };
+        *index = index
+            .checked_add(1)
+            .ok_or(anyhow!("Subaddress index exhausted"))?;
+        Ok(Index {
+            major: account,
+            minor: *index,
+        })
+    }
+
+    /// Index of the last subaddress handed out
+    pub fn last_subaddr_index(&self) -> Index {
+        let (major, minor) = match self {
+            MoneroWallet::Hot(_, w) => (w.account, w.index),
+            MoneroWallet::Cold(_, MoneroColdWallet::ViewKey { account, index, .. }) => {
+                (*account, *index)
+            }
+        };
+        Index { major, minor }
+    }
+
+    pub fn subaddr(&self, index: Index) -> anyhow::Result<(PublicKey, String)> {
+        let view_pair = self.view_pair()?;
+        let (_, spend_pub_key) = subaddress::get_public_keys(&view_pair, index);
+        let address = subaddress::get_subaddress(&view_pair, index, Some(self.network().into()));
+        Ok((spend_pub_key, address.to_string()))
+    }
+
+    pub fn next_addr(&mut self) -> anyhow::Result<(PublicKey, String)> {
+        let index = self.next_subaddr_index()?;
+        self.subaddr(index)
+    }
+
+    /// Hex encoded private view key, enough for a view-only wallet to see incoming transfers
+    pub fn view_key(&self) -> anyhow::Result<String> {
+        Ok(self.view_pair()?.view.to_string())
+    }
+
     pub fn addr(&self) -> String {
         match self {
             MoneroWallet::Hot(network, w) => {
@@ -158,6 +447,163 @@ fn test_hot_wallet() {
     assert_eq!(w.spend_key, expected_spend_key.to_bytes());
     assert_eq!(w.view_key, expected_view_key.to_bytes());
 
-    let mainnet_w = MoneroWallet::Hot(Network::Mainnet, w);
+    let mut mainnet_w = MoneroWallet::Hot(Network::Mainnet, w);
     assert_eq!(mainnet_w.addr(), "436cmaKNNvJQMBqWf3EaUCKJ7sJW4RkhJR1paum66s2ac8e8TrGYLwpiFsG66UAWkARupVhNkuiTweNehKn1x4Wa3zo7oFr");
+    assert_eq!(
+        mainnet_w.view_key().expect("Invalid ViewKey"),
+        "e065c2bb784345ff500807bea4eda8a9512d974f3e7695d120d73c54045f6704"
+    );
+
+    let (_, addr_1) = mainnet_w.next_addr().expect("Invalid Addr");
+    let (_, addr_2) = mainnet_w.next_addr().expect("Invalid Addr");
+    assert_ne!(addr_1, addr_2);
+    assert_ne!(addr_1, mainnet_w.addr());
+    assert_eq!(
+        mainnet_w
+            .subaddr(Index { major: 0, minor: 1 })
+            .expect("Invalid Addr")
+            .1,
+        addr_1
+    );
+    match Address::from_str(&addr_1)
+        .expect("Invalid Address")
+        .addr_type
+    {
+        monero::AddressType::SubAddress => {}
+        addr_type => panic!("Expected SubAddress found {:?}", addr_type),
+    }
+}
+
+#[test]
+fn test_cold_wallet() {
+    let view_key =
+        PrivateKey::from_str("77916d0cd56ed1920aef6ca56d8a41bac915b68e4c46a589e0956e27a7b77404")
+            .expect("Invalid ViewKey");
+    let spend_key =
+        PrivateKey::from_str("8163466f1883598e6dd14027b8da727057165da91485834314f5500a65846f09")
+            .expect("Invalid SpendKey");
+    let spend_pub_key = PublicKey::from_private_key(&spend_key);
+    let mut w = MoneroWallet::Cold(
+        Network::Mainnet,
+        MoneroColdWallet::ViewKey {
+            address: Address::standard(
+                monero::Network::Mainnet,
+                spend_pub_key,
+                PublicKey::from_private_key(&view_key),
+            )
+            .to_string(),
+            spend_pub_key: spend_pub_key.as_bytes().to_vec(),
+            view_key: view_key.as_bytes().to_vec(),
+            account: 2,
+            index: 17,
+            network_version: NETWORK_VERSION,
+            restore_height: 2750000,
+        },
+    );
+    assert_eq!(
+        w.next_addr().expect("Invalid Addr").1,
+        "89pMNxzcCo5LAPZDX4qaTeanA6ZiS3VRdUbeKHzbDZkD1Q3YsDDfmXbT2zyjLeHWuuN4vxKne8kNpjH3cMk7nmhwSALCxsd"
+    );
+    assert_eq!(
+        w.last_subaddr_index(),
+        Index {
+            major: 2,
+            minor: 18
+        }
+    );
+    assert_eq!(w.restore_height(), 2750000);
+    assert_eq!(
+        w.view_key().expect("Invalid ViewKey"),
+        "77916d0cd56ed1920aef6ca56d8a41bac915b68e4c46a589e0956e27a7b77404"
+    );
+}
+
+#[test]
+fn test_hot_wallet_import() {
+    let seed = "emit upcoming igloo orbit suture addicted boss pavements nouns oxygen sulking fleet cousin colony maximum awkward gigantic limits wildly violin ankle vinegar glide hiker maximum";
+    let w = MoneroWallet::import_hot(Network::Mainnet, seed).expect("Invalid MoneroWallet");
+    // Mainnet passed 3,000,000 in 2023
+    assert!(w.restore_height() > 3_000_000);
+    assert!(w.restore_height() < approximate_height(&Network::Mainnet));
+    assert_eq!(w.addr(), "436cmaKNNvJQMBqWf3EaUCKJ7sJW4RkhJR1paum66s2ac8e8TrGYLwpiFsG66UAWkARupVhNkuiTweNehKn1x4Wa3zo7oFr");
+    assert_eq!(
+        w.secret().expect("Invalid WalletSecret"),
+        WalletSecret::MoneroSeed {
+            mnemonic: seed.to_string()
+        }
+    );
+
+    let prefixes = seed
+        .split(' ')
+        .map(|word| word[..3].to_uppercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let restored =
+        MoneroWallet::import_hot(Network::Mainnet, &prefixes).expect("Invalid MoneroWallet");
+    assert_eq!(restored.addr(), w.addr());
+
+    let generated = MoneroWallet::new_hot(Network::Mainnet).expect("Invalid MoneroWallet");
+    let mnemonic = match generated.secret().expect("Invalid WalletSecret") {
+        WalletSecret::MoneroSeed { mnemonic } => mnemonic,
+        secret => panic!("Expected MoneroSeed found {:?}", secret),
+    };
+    let restored =
+        MoneroWallet::import_hot(Network::Mainnet, &mnemonic).expect("Invalid MoneroWallet");
+    assert_eq!(restored.addr(), generated.addr());
+    assert_eq!(
+        restored.view_key().expect("Invalid ViewKey"),
+        generated.view_key().expect("Invalid ViewKey")
+    );
+
+    let words = seed.split(' ').collect::<Vec<_>>();
+    assert!(MoneroWallet::import_hot(Network::Mainnet, &words[..24].join(" ")).is_err());
+    let bad_checksum = [&words[..24], &["emit"]].concat().join(" ");
+    assert!(MoneroWallet::import_hot(Network::Mainnet, &bad_checksum).is_err());
+    let bad_word = [&["bitcoin"], &words[1..]].concat().join(" ");
+    assert!(MoneroWallet::import_hot(Network::Mainnet, &bad_word).is_err());
+}
+
+#[test]
+fn test_seed_words() {
+    let words = seed_words();
+    assert_eq!(words.len(), SEED_WORD_COUNT as usize);
+    let mut prefixes = words.iter().map(|w| seed_prefix(w)).collect::<Vec<_>>();
+    prefixes.dedup();
+    assert_eq!(prefixes.len(), words.len());
+}
+
+#[test]
+fn test_legacy_network_blob() {
+    let spend_key =
+        PrivateKey::from_str("aa7c977f3f03ba300bd530f12839437b8fd0f95c10ea6128fb60e31ba0bd8409")
+            .expect("Invalid SpendKey");
+    let w = MoneroHotWallet::from(spend_key);
+    let stagenet_addr = Address::standard(
+        monero::Network::Stagenet,
+        PublicKey::from_private_key(&spend_key),
+        PublicKey::from_private_key(&PrivateKey::from_slice(&w.view_key).expect("Invalid ViewKey")),
+    )
+    .to_string();
+
+    // Written before Network::Stagenet existed, `Testnet` meant Monero stagenet
+    let legacy = format!(
+        r#"{{"Hot":["Testnet",{{"spend_key":{:?},"view_key":{:?},"account":0,"index":3}}]}}"#,
+        w.spend_key, w.view_key
+    );
+    let legacy_w: MoneroWallet =
+        moonramp_core::serde_json::from_str(&legacy).expect("Invalid MoneroWallet");
+    assert_eq!(legacy_w.network(), Network::Stagenet);
+    assert_eq!(legacy_w.addr(), stagenet_addr);
+
+    let blob = moonramp_core::serde_json::to_vec(&legacy_w).expect("Invalid MoneroWallet");
+    let saved_w: MoneroWallet =
+        moonramp_core::serde_json::from_slice(&blob).expect("Invalid MoneroWallet");
+    assert_eq!(saved_w, legacy_w);
+
+    let testnet_w = MoneroWallet::Hot(Network::Testnet, w);
+    let blob = moonramp_core::serde_json::to_vec(&testnet_w).expect("Invalid MoneroWallet");
+    let saved_w: MoneroWallet =
+        moonramp_core::serde_json::from_slice(&blob).expect("Invalid MoneroWallet");
+    assert_eq!(saved_w.network(), Network::Testnet);
+    assert_ne!(saved_w.addr(), stagenet_addr);
 }
@@ -1,11 +1,22 @@
 #[moonramp_lunar::program(DefaultSale)]
 mod program {
     use moonramp_lunar::{
-        gateway::{BitcoinGateway, BitcoinGatewayResponse},
-        moonramp_wallet::Wallet,
+        gateway::{
+            BitcoinCashGateway, BitcoinCashGatewayResponse, BitcoinGateway, BitcoinGatewayResponse,
+            EthereumGateway, LightningGateway, LightningInvoiceState, MoneroGateway,
+            MoneroViewKeys,
+        },
+        moonramp_core::Amount,
+        moonramp_wallet::{
+            eip681_erc20_transfer_uri, eip681_uri, to_cashaddr, Currency, Ticker, Wallet,
+        },
         EntryData, ExitData, LunarError, Program,
     };
 
+    /// Blocks of transfer logs scanned for ERC-20 invoices created without a `start_height`,
+    /// about a week of Ethereum mainnet blocks
+    const LEGACY_TRANSFER_LOG_BLOCKS: u64 = 50_400;
+
     pub struct DefaultSale {}
 
     impl Default for DefaultSale {
@@ -14,36 +25,304 @@ mod program {
         }
     }
 
+    fn base_units(currency: &Currency, amount: &Amount) -> Result<u128, LunarError> {
+        currency
+            .base_units(amount)
+            .map_err(|err| LunarError::Wallet(err.to_string()))
+    }
+
+    fn currency_amount(currency: &Currency, units: u128) -> Result<Amount, LunarError> {
+        currency
+            .amount(units)
+            .map_err(|err| LunarError::Wallet(err.to_string()))
+    }
+
+    fn erc20_decimals(currency: &Currency) -> Result<u32, LunarError> {
+        currency
+            .erc20_decimals()
+            .ok_or(LunarError::Wallet(format!("{:?} is not ERC-20", currency)))
+    }
+
     impl Program for DefaultSale {
         fn launch(self, entry_data: EntryData) -> Result<ExitData, LunarError> {
             match entry_data {
+                EntryData::Invoice {
+                    wallet,
+                    currency: Currency::BTCLN,
+                    amount,
+                    expires_in,
+                    ..
+                } => {
+                    let invoice = LightningGateway::new().create_invoice(
+                        base_units(&Currency::BTCLN, &amount)? as u64,
+                        "MoonRamp invoice".to_string(),
+                        expires_in.max(0) as u64,
+                    )?;
+                    Ok(ExitData::Invoice {
+                        pubkey: wallet.pubkey().to_string(),
+                        wallet,
+                        address: invoice.payment_request.clone(),
+                        uri: format!("lightning:{}", invoice.payment_request),
+                        payment_hash: Some(invoice.payment_hash),
+                        start_height: None,
+                        user_data: None,
+                    })
+                }
+                EntryData::Invoice {
+                    wallet: Wallet::Ethereum(mut ethereum_wallet),
+                    currency,
+                    amount,
+                    contract,
+                    ..
+                } => {
+                    let (pubkey, address) = ethereum_wallet
+                        .next_addr()
+                        .map_err(|err| LunarError::Wallet(err.to_string()))?;
+                    let (uri, start_height) = match contract {
+                        Some(contract) => {
+                            // Contracts are only valid for ERC-20 currencies
+                            erc20_decimals(&currency)?;
+                            (
+                                eip681_erc20_transfer_uri(
+                                    &contract,
+                                    &address,
+                                    base_units(&currency, &amount)?,
+                                ),
+                                Some(EthereumGateway::new().block_number()?),
+                            )
+                        }
+                        None => (eip681_uri(&address, base_units(&currency, &amount)?), None),
+                    };
+                    Ok(ExitData::Invoice {
+                        wallet: Wallet::Ethereum(ethereum_wallet),
+                        pubkey: pubkey.to_string(),
+                        address,
+                        uri,
+                        payment_hash: None,
+                        start_height,
+                        user_data: None,
+                    })
+                }
+                EntryData::Invoice {
+                    wallet: Wallet::Monero(mut monero_wallet),
+                    amount,
+                    ..
+                } => {
+                    let (pubkey, address) = monero_wallet
+                        .next_addr()
+                        .map_err(|err| LunarError::Wallet(err.to_string()))?;
+                    let uri = format!("monero:{}?tx_amount={}", address, amount);
+                    Ok(ExitData::Invoice {
+                        wallet: Wallet::Monero(monero_wallet),
+                        pubkey: pubkey.to_string(),
+                        address,
+                        uri,
+                        payment_hash: None,
+                        start_height: None,
+                        user_data: None,
+                    })
+                }
                 EntryData::Invoice { wallet, amount, .. } => {
                     let mut bitcoin_wallet = wallet
                         .into_bitcoin()
                         .map_err(|_| LunarError::Wallet("Wallet is not bitcoin".to_string()))?;
                     let (pubkey, address) = bitcoin_wallet
-                        .next_addr()
+                        .next_invoice_addr()
                         .map_err(|err| LunarError::Wallet(err.to_string()))?;
-                    let uri = format!("bitcoin:{};version=1.0&amount={}", address, amount);
+                    let uri = match bitcoin_wallet.ticker() {
+                        // CashAddr already carries its `bitcoincash:` scheme
+                        Ticker::BCH => format!("{}?amount={}", address, amount),
+                        _ => format!("bitcoin:{};version=1.0&amount={}", address, amount),
+                    };
                     Ok(ExitData::Invoice {
                         wallet: Wallet::Bitcoin(bitcoin_wallet),
-                        pubkey: pubkey.to_string(),
+                        pubkey,
                         address,
                         uri,
+                        payment_hash: None,
+                        start_height: None,
                         user_data: None,
                     })
                 }
                 EntryData::Sale {
+                    currency: Currency::BTCLN,
+                    payment_hash,
+                    ..
+                } => {
+                    let payment_hash = payment_hash.ok_or(LunarError::Wallet(
+                        "Lightning invoice has no payment hash".to_string(),
+                    ))?;
+                    let lightning_gateway = LightningGateway::new();
+                    loop {
+                        // Only settled HTLCs count, held (Accepted) payments can still fail
+                        let status = lightning_gateway.lookup_invoice(payment_hash.clone())?;
+                        let total_amount = currency_amount(&Currency::BTCLN, status.amount_paid_msat as u128)?;
+                        match status.state {
+                            LightningInvoiceState::Settled => {
+                                return Ok(ExitData::Sale {
+                                    funded: true,
+                                    amount: total_amount,
+                                    user_data: None,
+                                })
+                            }
+                            LightningInvoiceState::Canceled => {
+                                return Ok(ExitData::Sale {
+                                    funded: false,
+                                    amount: total_amount,
+                                    user_data: None,
+                                })
+                            }
+                            _ => {}
+                        }
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                    }
+                }
+                EntryData::Sale {
+                    wallet: Wallet::Ethereum(_),
+                    currency,
+                    address,
+                    amount,
+                    contract,
+                    start_height,
+                    confirmations,
+                    ..
+                } => {
+                    let ethereum_gateway = EthereumGateway::new();
+                    loop {
+                        let current_height = ethereum_gateway.block_number()?;
+                        // Only count value included at least `confirmations` blocks deep
+                        let confirmed_height =
+                            (current_height + 1).saturating_sub(confirmations.max(1));
+                        let total_amount = match &contract {
+                            Some(contract) => {
+                                let from_block = start_height.unwrap_or_else(|| {
+                                    current_height.saturating_sub(LEGACY_TRANSFER_LOG_BLOCKS)
+                                });
+                                let mut total_value = 0u128;
+                                for log in ethereum_gateway.transfer_logs(
+                                    contract.clone(),
+                                    address.clone(),
+                                    Some(from_block),
+                                )? {
+                                    if log.block_number <= confirmed_height {
+                                        total_value += log.value()?;
+                                    }
+                                }
+                                Amount::new(total_value, erc20_decimals(&currency)?)
+                                    .map_err(|err| LunarError::Wallet(err.to_string()))?
+                            }
+                            None => currency_amount(
+                                &currency,
+                                ethereum_gateway
+                                    .balance(address.clone(), Some(confirmed_height))?,
+                            )?,
+                        };
+                        if total_amount >= amount {
+                            return Ok(ExitData::Sale {
+                                funded: true,
+                                amount: total_amount,
+                                user_data: None,
+                            });
+                        }
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                    }
+                }
+                EntryData::Sale {
+                    wallet: Wallet::Monero(monero_wallet),
+                    currency,
+                    address,
+                    amount,
+                    confirmations,
+                    ..
+                } => {
+                    let last_index = monero_wallet.last_subaddr_index();
+                    let wallet = MoneroViewKeys {
+                        address: monero_wallet.addr(),
+                        view_key: monero_wallet
+                            .view_key()
+                            .map_err(|err| LunarError::Wallet(err.to_string()))?,
+                        restore_height: monero_wallet.restore_height(),
+                        account: last_index.major,
+                        index: last_index.minor,
+                    };
+                    let monero_gateway = MoneroGateway::new();
+                    loop {
+                        let total_amount: u64 = monero_gateway
+                            .incoming_transfers(wallet.clone(), address.clone())?
+                            .iter()
+                            .filter(|transfer| transfer.confirmations >= confirmations)
+                            .map(|transfer| transfer.amount)
+                            .sum();
+                        let total_amount = currency_amount(&currency, total_amount as u128)?;
+                        if total_amount >= amount {
+                            return Ok(ExitData::Sale {
+                                funded: true,
+                                amount: total_amount,
+                                user_data: None,
+                            });
+                        }
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                    }
+                }
+                EntryData::Sale {
+                    wallet: Wallet::Bitcoin(bitcoin_wallet),
+                    currency,
+                    address,
+                    amount,
+                    confirmations,
+                    ..
+                } if bitcoin_wallet.ticker() == Ticker::BCH => {
+                    // Accept legacy invoice addresses, the node is queried by CashAddr
+                    let address = to_cashaddr(&bitcoin_wallet.network(), &address)
+                        .map_err(|err| LunarError::Wallet(err.to_string()))?;
+                    let bitcoin_cash_gateway = BitcoinCashGateway::new();
+                    loop {
+                        match bitcoin_cash_gateway.scan_addr(address.clone())? {
+                            BitcoinCashGatewayResponse::ScanAddr {
+                                height,
+                                total_amount,
+                                unspents,
+                            } => {
+                                let confirmed = unspents.iter().all(|unspent| {
+                                    height.saturating_sub(unspent.height) >= confirmations
+                                });
+                                let total_amount = currency_amount(&currency, total_amount as u128)?;
+                                if total_amount >= amount && confirmed {
+                                    return Ok(ExitData::Sale {
+                                        funded: true,
+                                        amount: total_amount,
+                                        user_data: None,
+                                    });
+                                }
+                            }
+                        }
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                    }
+                }
+                EntryData::Sale {
+                    wallet,
+                    currency,
                     address,
+                    pubkey,
                     amount,
                     confirmations,
                     ..
                 } => {
+                    // Scan by the invoice key's descriptor (e.g. `tr(...)`, or the exact descriptor
+                    // recorded by descriptor wallets), falling back to the address for invoices
+                    // whose pubkey is not an extended key
+                    let scan_object = match &wallet {
+                        Wallet::Bitcoin(bitcoin_wallet) => bitcoin_wallet
+                            .descriptor(&pubkey)
+                            .unwrap_or_else(|_| format!("addr({})", address)),
+                        _ => format!("addr({})", address),
+                    };
                     let bitcoin_gateway = BitcoinGateway::new();
                     loop {
-                        match bitcoin_gateway.scan_tx_out(vec![format!("addr({})", address)])? {
+                        match bitcoin_gateway.scan_tx_out(vec![scan_object.clone()])? {
                             BitcoinGatewayResponse::ScanTxOut(scan_res) => {
-                                let total_amount = scan_res.total_amount.as_btc();
+                                let total_amount =
+                                    currency_amount(&currency, scan_res.total_amount.as_sat() as u128)?;
                                 if let Some(current_height) = scan_res.height {
                                     let mut confirmed = true;
                                     for unspent in &scan_res.unspents {
@@ -1,37 +1,42 @@
-use std::{os::raw::c_uchar, ptr};
+use std::os::raw::c_uchar;
 
 use serde::{Deserialize, Serialize};
 
 use moonramp_core::{serde, serde_json};
 
-use crate::LunarError;
+use crate::{lunar_ptr_len, LunarError};
 
 extern "C" {
-    fn bitcoin_cash_gateway_scan(
-        req_ptr: *mut c_uchar,
-        req_len: usize,
-        res_ptr: *mut c_uchar,
-        res_len_ptr: *mut usize,
-    );
+    fn bitcoin_cash_gateway(req_ptr: *mut c_uchar, req_len: usize) -> *mut c_uchar;
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum BitcoinCashGatewayRequest {
-    //ScanTxn { hash: String },
     ScanAddr { address: String },
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(crate = "moonramp_core::serde")]
 pub enum BitcoinCashGatewayResponse {
-    //ScanTxn { hash: String },
+    /// `total_amount` and unspent amounts are in satoshis
     ScanAddr {
-        total_amount: String,
-        txn_ids: Vec<String>,
+        height: u64,
+        total_amount: u64,
+        unspents: Vec<BitcoinCashUnspent>,
     },
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub struct BitcoinCashUnspent {
+    pub txid: String,
+    pub vout: u32,
+    pub amount: u64,
+    pub height: u64,
+}
+
+#[derive(Default)]
 pub struct BitcoinCashGateway {}
 
 impl BitcoinCashGateway {
@@ -45,18 +50,19 @@ impl BitcoinCashGateway {
             serde_json::to_vec(&req).map_err(|e| LunarError::Serde(e.to_string()))?;
         let req_len = req_json.len();
         let req_ptr = req_json.as_mut_ptr();
-        let res_ptr = ptr::null_mut();
-        let res_len_ptr = ptr::null_mut();
 
-        unsafe { bitcoin_gateway_scan(req_ptr as *mut c_uchar, req_len, res_ptr, res_len_ptr) };
+        let res_ptr = unsafe { bitcoin_cash_gateway(req_ptr as *mut c_uchar, req_len) };
 
-        if res_ptr == ptr::null_mut() || res_len_ptr == ptr::null_mut() {
+        if res_ptr.is_null() {
             Err(LunarError::Crash(
-                "Call to bitcoin_gateway_scan failed".to_string(),
+                "Call to bitcoin_cash_gateway failed".to_string(),
             ))
         } else {
-            let res_json = unsafe { Vec::from_raw_parts(res_ptr, *res_len_ptr, *res_len_ptr) };
-            Ok(serde_json::from_slice(&res_json).map_err(|e| LunarError::Serde(e.to_string()))?)
+            let res_json = unsafe {
+                let res_len = lunar_ptr_len(res_ptr as *mut c_uchar);
+                Vec::from_raw_parts(res_ptr, res_len, res_len)
+            };
+            serde_json::from_slice(&res_json).map_err(|e| LunarError::Serde(e.to_string()))
         }
     }
 }
@@ -0,0 +1,120 @@
+use std::os::raw::c_uchar;
+
+use serde::{Deserialize, Serialize};
+
+use moonramp_core::{serde, serde_json};
+
+use crate::{lunar_ptr_len, LunarError};
+
+extern "C" {
+    fn ethereum_gateway(req_ptr: *mut c_uchar, req_len: usize) -> *mut c_uchar;
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum EthereumGatewayRequest {
+    BlockNumber,
+    Balance {
+        address: String,
+        block: Option<u64>,
+    },
+    TransferLogs {
+        contract: String,
+        to: String,
+        from_block: Option<u64>,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum EthereumGatewayResponse {
+    BlockNumber(u64),
+    Balance(String),
+    TransferLogs(Vec<TransferLog>),
+}
+
+/// ERC-20 `Transfer(address,address,uint256)` event
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub struct TransferLog {
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub from: String,
+    pub to: String,
+    pub value: String,
+}
+
+impl TransferLog {
+    pub fn value(&self) -> Result<u128, LunarError> {
+        parse_quantity(&self.value)
+    }
+}
+
+/// Parses a hex encoded JSON-RPC quantity
+pub fn parse_quantity(quantity: &str) -> Result<u128, LunarError> {
+    let digits = quantity.trim_start_matches("0x").trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u128::from_str_radix(digits, 16).map_err(|e| LunarError::Serde(e.to_string()))
+}
+
+#[derive(Default)]
+pub struct EthereumGateway {}
+
+impl EthereumGateway {
+    pub fn new() -> Self {
+        EthereumGateway {}
+    }
+
+    pub fn block_number(&self) -> Result<u64, LunarError> {
+        match self.request(EthereumGatewayRequest::BlockNumber)? {
+            EthereumGatewayResponse::BlockNumber(block_number) => Ok(block_number),
+            res => Err(LunarError::Crash(format!("Unexpected response {:?}", res))),
+        }
+    }
+
+    pub fn balance(&self, address: String, block: Option<u64>) -> Result<u128, LunarError> {
+        match self.request(EthereumGatewayRequest::Balance { address, block })? {
+            EthereumGatewayResponse::Balance(balance) => parse_quantity(&balance),
+            res => Err(LunarError::Crash(format!("Unexpected response {:?}", res))),
+        }
+    }
+
+    pub fn transfer_logs(
+        &self,
+        contract: String,
+        to: String,
+        from_block: Option<u64>,
+    ) -> Result<Vec<TransferLog>, LunarError> {
+        match self.request(EthereumGatewayRequest::TransferLogs {
+            contract,
+            to,
+            from_block,
+        })? {
+            EthereumGatewayResponse::TransferLogs(logs) => Ok(logs),
+            res => Err(LunarError::Crash(format!("Unexpected response {:?}", res))),
+        }
+    }
+
+    fn request(&self, req: EthereumGatewayRequest) -> Result<EthereumGatewayResponse, LunarError> {
+        let mut req_json =
+            serde_json::to_vec(&req).map_err(|e| LunarError::Serde(e.to_string()))?;
+        let req_len = req_json.len();
+        let req_ptr = req_json.as_mut_ptr();
+
+        let res_ptr = unsafe { ethereum_gateway(req_ptr as *mut c_uchar, req_len) };
+
+        if res_ptr.is_null() {
+            Err(LunarError::Crash(
+                "Call to ethereum_gateway failed".to_string(),
+            ))
+        } else {
+            let res_json = unsafe {
+                let res_len = lunar_ptr_len(res_ptr as *mut c_uchar);
+                Vec::from_raw_parts(res_ptr, res_len, res_len)
+            };
+            Ok(serde_json::from_slice(&res_json).map_err(|e| LunarError::Serde(e.to_string()))?)
+        }
+    }
+}
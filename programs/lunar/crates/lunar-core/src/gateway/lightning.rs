@@ -0,0 +1,115 @@
+use std::os::raw::c_uchar;
+
+use serde::{Deserialize, Serialize};
+
+use moonramp_core::{serde, serde_json};
+
+use crate::{lunar_ptr_len, LunarError};
+
+extern "C" {
+    fn lightning_gateway(req_ptr: *mut c_uchar, req_len: usize) -> *mut c_uchar;
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum LightningGatewayRequest {
+    CreateInvoice {
+        amount_msat: u64,
+        memo: String,
+        expiry: u64,
+    },
+    LookupInvoice {
+        payment_hash: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum LightningGatewayResponse {
+    CreateInvoice(LightningInvoice),
+    LookupInvoice(LightningInvoiceStatus),
+}
+
+/// BOLT11 `payment_request` and its hex encoded `payment_hash`
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub struct LightningInvoice {
+    pub payment_hash: String,
+    pub payment_request: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum LightningInvoiceState {
+    Open,
+    /// HTLCs are held but not settled yet (hold invoices)
+    Accepted,
+    Settled,
+    Canceled,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub struct LightningInvoiceStatus {
+    pub state: LightningInvoiceState,
+    pub amount_paid_msat: u64,
+}
+
+#[derive(Default)]
+pub struct LightningGateway {}
+
+impl LightningGateway {
+    pub fn new() -> Self {
+        LightningGateway {}
+    }
+
+    pub fn create_invoice(
+        &self,
+        amount_msat: u64,
+        memo: String,
+        expiry: u64,
+    ) -> Result<LightningInvoice, LunarError> {
+        match self.request(LightningGatewayRequest::CreateInvoice {
+            amount_msat,
+            memo,
+            expiry,
+        })? {
+            LightningGatewayResponse::CreateInvoice(invoice) => Ok(invoice),
+            res => Err(LunarError::Crash(format!("Unexpected response {:?}", res))),
+        }
+    }
+
+    pub fn lookup_invoice(
+        &self,
+        payment_hash: String,
+    ) -> Result<LightningInvoiceStatus, LunarError> {
+        match self.request(LightningGatewayRequest::LookupInvoice { payment_hash })? {
+            LightningGatewayResponse::LookupInvoice(status) => Ok(status),
+            res => Err(LunarError::Crash(format!("Unexpected response {:?}", res))),
+        }
+    }
+
+    fn request(
+        &self,
+        req: LightningGatewayRequest,
+    ) -> Result<LightningGatewayResponse, LunarError> {
+        let mut req_json =
+            serde_json::to_vec(&req).map_err(|e| LunarError::Serde(e.to_string()))?;
+        let req_len = req_json.len();
+        let req_ptr = req_json.as_mut_ptr();
+
+        let res_ptr = unsafe { lightning_gateway(req_ptr as *mut c_uchar, req_len) };
+
+        if res_ptr.is_null() {
+            Err(LunarError::Crash(
+                "Call to lightning_gateway failed".to_string(),
+            ))
+        } else {
+            let res_json = unsafe {
+                let res_len = lunar_ptr_len(res_ptr as *mut c_uchar);
+                Vec::from_raw_parts(res_ptr, res_len, res_len)
+            };
+            serde_json::from_slice(&res_json).map_err(|e| LunarError::Serde(e.to_string()))
+        }
+    }
+}
@@ -1,3 +1,11 @@
 mod bitcoin;
+mod bitcoin_cash;
+mod ethereum;
+mod lightning;
+mod monero;
 
 pub use bitcoin::*;
+pub use bitcoin_cash::*;
+pub use ethereum::*;
+pub use lightning::*;
+pub use monero::*;
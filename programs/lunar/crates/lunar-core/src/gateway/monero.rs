@@ -0,0 +1,95 @@
+use std::os::raw::c_uchar;
+
+use serde::{Deserialize, Serialize};
+
+use moonramp_core::{serde, serde_json};
+
+use crate::{lunar_ptr_len, LunarError};
+
+extern "C" {
+    fn monero_gateway(req_ptr: *mut c_uchar, req_len: usize) -> *mut c_uchar;
+}
+
+/// View-only keys of the wallet an invoice subaddress belongs to
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub struct MoneroViewKeys {
+    /// Primary address
+    pub address: String,
+    /// Hex encoded private view key
+    pub view_key: String,
+    /// Height the view-only wallet is restored from
+    #[serde(default)]
+    pub restore_height: u64,
+    /// Account of the wallet's subaddresses
+    #[serde(default)]
+    pub account: u32,
+    /// Index of the last subaddress the wallet handed out
+    #[serde(default)]
+    pub index: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum MoneroGatewayRequest {
+    IncomingTransfers {
+        wallet: MoneroViewKeys,
+        address: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub enum MoneroGatewayResponse {
+    IncomingTransfers(Vec<MoneroTransfer>),
+}
+
+/// Incoming transfer to a subaddress, `amount` is in atomic units (piconero)
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "moonramp_core::serde")]
+pub struct MoneroTransfer {
+    pub txid: String,
+    pub amount: u64,
+    pub confirmations: u64,
+    pub height: u64,
+}
+
+#[derive(Default)]
+pub struct MoneroGateway {}
+
+impl MoneroGateway {
+    pub fn new() -> Self {
+        MoneroGateway {}
+    }
+
+    pub fn incoming_transfers(
+        &self,
+        wallet: MoneroViewKeys,
+        address: String,
+    ) -> Result<Vec<MoneroTransfer>, LunarError> {
+        match self.request(MoneroGatewayRequest::IncomingTransfers { wallet, address })? {
+            MoneroGatewayResponse::IncomingTransfers(transfers) => Ok(transfers),
+        }
+    }
+
+    fn request(&self, req: MoneroGatewayRequest) -> Result<MoneroGatewayResponse, LunarError> {
+        let mut req_json =
+            serde_json::to_vec(&req).map_err(|e| LunarError::Serde(e.to_string()))?;
+        let req_len = req_json.len();
+        let req_ptr = req_json.as_mut_ptr();
+
+        let res_ptr = unsafe { monero_gateway(req_ptr as *mut c_uchar, req_len) };
+
+        if res_ptr.is_null() {
+            Err(LunarError::Crash(
+                "Call to monero_gateway failed".to_string(),
+            ))
+        } else {
+            let res_json = unsafe {
+                let res_len = lunar_ptr_len(res_ptr as *mut c_uchar);
+                Vec::from_raw_parts(res_ptr, res_len, res_len)
+            };
+            serde_json::from_slice(&res_json).map_err(|e| LunarError::Serde(e.to_string()))
+        }
+    }
+}
@@ -11,7 +11,7 @@ pub use moonramp_wallet;
 
 pub use wee_alloc;
 
-use moonramp_core::{serde, serde_json};
+use moonramp_core::{serde, serde_json, Amount};
 use moonramp_wallet::{Currency, Wallet};
 
 pub mod gateway;
@@ -49,14 +49,24 @@ pub enum EntryData {
     Invoice {
         wallet: Wallet,
         currency: Currency,
-        amount: f64,
+        amount: Amount,
+        contract: Option<String>,
+        /// Seconds until the invoice expires
+        expires_in: i64,
         user_data: Option<Vec<u8>>,
     },
     Sale {
         wallet: Wallet,
         currency: Currency,
-        amount: f64,
+        amount: Amount,
         address: String,
+        pubkey: String,
+        contract: Option<String>,
+        /// Lightning payment hash, hex encoded
+        payment_hash: Option<String>,
+        /// Chain height recorded when the invoice was created, unset for invoices that predate it
+        #[serde(default)]
+        start_height: Option<u64>,
         confirmations: u64,
         user_data: Option<Vec<u8>>,
     },
@@ -70,11 +80,17 @@ pub enum ExitData {
         pubkey: String,
         address: String,
         uri: String,
+        /// Lightning payment hash, hex encoded
+        payment_hash: Option<String>,
+        /// Chain height the sale starts scanning from, ERC-20 transfer logs older than the
+        /// invoice can't pay it
+        #[serde(default)]
+        start_height: Option<u64>,
         user_data: Option<Vec<u8>>,
     },
     Sale {
         funded: bool,
-        amount: f64,
+        amount: Amount,
         user_data: Option<Vec<u8>>,
     },
 }
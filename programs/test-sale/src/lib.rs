@@ -18,11 +18,13 @@ mod program {
                     pubkey: "test_pubkey".to_string(),
                     address: "test_address".to_string(),
                     uri: "test_uri".to_string(),
+                    payment_hash: None,
+                    start_height: None,
                     user_data: None,
                 }),
-                EntryData::Sale { .. } => Ok(ExitData::Sale {
+                EntryData::Sale { amount, .. } => Ok(ExitData::Sale {
                     funded: true,
-                    amount: 0.00001000,
+                    amount,
                     user_data: None,
                 }),
                 #[allow(unreachable_patterns)]